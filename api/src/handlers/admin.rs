@@ -0,0 +1,76 @@
+use axum::{
+  extract::{Json, Path, State},
+  http::StatusCode,
+};
+use common::error::AppError;
+use serde_json::{json, Value};
+use shared::oauth::CreateOAuthClientSchema;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+use crate::{middleware::auth::AuthUser, AppState};
+
+pub async fn get_user_handler(
+  State(state): State<Arc<AppState>>,
+  auth_user: AuthUser,
+  Path(user_id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+  state.require_permission(&auth_user, "users:read").await?;
+
+  let user = state
+      .auth_service
+      .get_user(&user_id)
+      .await
+      .map_err(AppError::NotFound)?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "user": user
+  })))
+}
+
+pub async fn unlock_user_handler(
+  State(state): State<Arc<AppState>>,
+  auth_user: AuthUser,
+  Path(user_id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+  state.require_permission(&auth_user, "users:write").await?;
+
+  state.auth_service.unlock_account(&user_id).await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "Account unlocked"
+  })))
+}
+
+pub async fn create_oauth_client_handler(
+  State(state): State<Arc<AppState>>,
+  auth_user: AuthUser,
+  Json(body): Json<CreateOAuthClientSchema>,
+) -> Result<(StatusCode, Json<Value>), AppError> {
+  state.require_permission(&auth_user, "oauth_clients:write").await?;
+  body.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+  let client = state.auth_service.register_oauth_client(&body).await?;
+
+  // El secreto sólo se muestra esta vez
+  Ok((StatusCode::CREATED, Json(json!({
+      "status": "success",
+      "client": client
+  }))))
+}
+
+pub async fn list_oauth_clients_handler(
+  State(state): State<Arc<AppState>>,
+  auth_user: AuthUser,
+) -> Result<Json<Value>, AppError> {
+  state.require_permission(&auth_user, "oauth_clients:read").await?;
+
+  let clients = state.auth_service.list_oauth_clients().await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "clients": clients
+  })))
+}
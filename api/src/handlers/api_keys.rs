@@ -0,0 +1,64 @@
+use axum::{
+  extract::{Json, Path, State},
+  http::StatusCode,
+};
+use common::error::AppError;
+use serde_json::{json, Value};
+use shared::user::CreateApiKeySchema;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+use crate::{middleware::auth::SessionUser, AppState};
+
+pub async fn create_api_key_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+  Json(body): Json<CreateApiKeySchema>,
+) -> Result<(StatusCode, Json<Value>), AppError> {
+  body.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+  // Una clave sólo puede limitarse a permisos que ya tiene el usuario
+  let permissions = state.permissions_for(&auth_user).await?;
+  let unavailable: Vec<&str> = body
+      .scopes
+      .iter()
+      .filter(|scope| !permissions.contains(*scope))
+      .map(String::as_str)
+      .collect();
+  if !unavailable.is_empty() {
+      return Err(AppError::Validation(format!("Scopes not available to this user: {}", unavailable.join(", "))));
+  }
+
+  let api_key = state.auth_service.create_api_key(&auth_user.id, &body).await?;
+
+  // El secreto sólo se muestra esta vez
+  Ok((StatusCode::CREATED, Json(json!({
+      "status": "success",
+      "api_key": api_key
+  }))))
+}
+
+pub async fn list_api_keys_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+) -> Result<Json<Value>, AppError> {
+  let api_keys = state.auth_service.list_api_keys(&auth_user.id).await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "api_keys": api_keys
+  })))
+}
+
+pub async fn revoke_api_key_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+  Path(key_id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+  state.auth_service.revoke_api_key(&auth_user.id, &key_id).await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "API key revoked"
+  })))
+}
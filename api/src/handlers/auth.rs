@@ -4,15 +4,25 @@ use axum::{
 };
 use std::sync::Arc;
 use common::error::AppError;
-use shared::user::{CreateUserSchema, FilteredUser, LoginUserSchema};
+use shared::user::{
+    ApiKeyInfo, CreateApiKeySchema, CreateUserSchema, CreatedApiKey, FederatedLoginStart, FilteredUser, ForgotPasswordSchema, LinkTelegramSchema,
+    LoginUserSchema, LogoutSchema, MfaVerifySchema, PasskeyLoginSchema, PasskeyOptions, PasskeyRegistrationSchema,
+    RefreshTokenSchema, ResendVerificationSchema, ResetPasswordSchema, TelegramWebAppSchema, TotpEnrollment, VerifyEmailSchema,
+};
+use shared::oauth::{
+    AuthorizationRequestInfo, AuthorizeError, AuthorizeParams, CreateOAuthClientSchema, CreatedOAuthClient,
+    DeviceAuthorizationInfo, DeviceAuthorizationRequest, DeviceAuthorizationResponse, OAuthClientInfo, OAuthError, TokenRequest,
+    TokenResponse, UserInfo,
+};
 use crate::AppState;
-use common::jwt::verify_jwt;
+use common::jwt::{verify_jwt, Claims, API_KEY_TOKEN_USE, MFA_PENDING_TOKEN_USE};
+use common::telegram::TelegramLoginData;
 
 use serde_json::{json, Value};
 use validator::Validate;
 use axum::body::Body;
 use axum::middleware::Next;
-use axum::extract::{State,  Query};
+use axum::extract::{Extension, State};
 use axum::response::Response;
 use serde::Serialize;
 use uuid::Uuid;
@@ -20,22 +30,95 @@ use uuid::Uuid;
 // Definimos un trait para AuthService
 #[async_trait::async_trait]
 pub trait AuthService: Send + Sync {
-    async fn register_user(&self, user_data: &CreateUserSchema, telegram_id: Option<String>) -> Result<FilteredUser, String>;
-    async fn authenticate_by_email(&self, email: &str, password: &str) -> Result<shared::user::User, String>;
-    async fn authenticate_by_telegram(&self, telegram_id: &str) -> Result<shared::user::User, String>;
+    async fn register_user(&self, user_data: &CreateUserSchema) -> Result<FilteredUser, AppError>;
+    async fn authenticate_by_email(&self, email: &str, password: &str) -> Result<shared::user::User, AppError>;
+    /// Olvida los intentos de login fallidos del usuario y levanta el bloqueo
+    async fn unlock_account(&self, user_id: &Uuid) -> Result<(), AppError>;
+    async fn authenticate_by_telegram(&self, data: &TelegramLoginData) -> Result<shared::user::User, String>;
+    async fn authenticate_by_telegram_web_app(&self, init_data: &str) -> Result<shared::user::User, String>;
+    async fn link_telegram(&self, user_id: &Uuid, link: &LinkTelegramSchema) -> Result<String, AppError>;
+    async fn unlink_telegram(&self, user_id: &Uuid) -> Result<(), AppError>;
+    async fn verify_email(&self, token: &str) -> Result<(), AppError>;
+    async fn resend_verification_email(&self, email: &str) -> Result<(), AppError>;
+    async fn request_password_reset(&self, email: &str) -> Result<(), AppError>;
+    async fn reset_password(&self, token: &str, new_password: &str) -> Result<(), AppError>;
+    async fn change_password(&self, user_id: &Uuid, current_password: &str, new_password: &str) -> Result<(String, String), AppError>;
+    /// `token_version` actual del usuario; los tokens con otra versión se rechazan
+    async fn token_version(&self, user_id: &Uuid) -> Result<i32, AppError>;
+    /// Token `mfa_pending` si el usuario tiene 2FA activado, `None` si basta con la contraseña
+    async fn mfa_challenge(&self, user: &shared::user::User) -> Result<Option<String>, AppError>;
+    /// Comprueba el segundo factor gastando un intento del reto `challenge_id` (el `jti` del token `mfa_pending`).
+    /// Si `token_version` (el claim `ver`) no es la actual, se rechaza sin gastar nada.
+    async fn verify_mfa(
+        &self,
+        challenge_id: &str,
+        user_id: &Uuid,
+        token_version: i32,
+        code: Option<&str>,
+        recovery_code: Option<&str>,
+    ) -> Result<shared::user::User, AppError>;
+    async fn start_totp_enrollment(&self, user_id: &Uuid) -> Result<TotpEnrollment, AppError>;
+    async fn confirm_totp_enrollment(&self, user_id: &Uuid, code: &str) -> Result<Vec<String>, AppError>;
+    async fn disable_totp(&self, user_id: &Uuid, code: Option<&str>, recovery_code: Option<&str>) -> Result<(), AppError>;
+    async fn start_passkey_registration(&self, user_id: &Uuid) -> Result<PasskeyOptions, AppError>;
+    /// Guarda la passkey y devuelve su credential ID
+    async fn finish_passkey_registration(&self, user_id: &Uuid, registration: &PasskeyRegistrationSchema) -> Result<String, AppError>;
+    async fn start_passkey_login(&self) -> Result<PasskeyOptions, AppError>;
+    async fn finish_passkey_login(&self, login: &PasskeyLoginSchema) -> Result<shared::user::User, AppError>;
+    /// URL del proveedor OpenID Connect externo a la que enviar al usuario para iniciar sesión
+    async fn start_federated_login(&self) -> Result<FederatedLoginStart, AppError>;
+    /// Usuario local (vinculado o recién creado) de la identidad con la que vuelve del proveedor;
+    /// `state_binding` es el valor de la cookie que recibió el navegador al iniciar el login
+    async fn finish_federated_login(&self, code: &str, state: &str, state_binding: Option<&str>) -> Result<shared::user::User, AppError>;
+    async fn create_api_key(&self, user_id: &Uuid, request: &CreateApiKeySchema) -> Result<CreatedApiKey, AppError>;
+    async fn list_api_keys(&self, user_id: &Uuid) -> Result<Vec<ApiKeyInfo>, AppError>;
+    async fn revoke_api_key(&self, user_id: &Uuid, key_id: &Uuid) -> Result<(), AppError>;
+    /// Usuario dueño de una clave de API vigente
+    async fn authenticate_api_key(&self, key: &str) -> Result<(shared::user::User, ApiKeyInfo), AppError>;
+    async fn register_oauth_client(&self, request: &CreateOAuthClientSchema) -> Result<CreatedOAuthClient, AppError>;
+    async fn list_oauth_clients(&self) -> Result<Vec<OAuthClientInfo>, AppError>;
+    /// Valida la petición a `/oauth/authorize` y devuelve la URL de la pantalla de consentimiento
+    async fn start_oauth_authorization(&self, params: &AuthorizeParams) -> Result<String, AuthorizeError>;
+    async fn oauth_authorization_request(&self, request_id: &Uuid) -> Result<AuthorizationRequestInfo, AppError>;
+    /// Aprueba o deniega la solicitud y devuelve la URL con la que volver al cliente
+    async fn decide_oauth_authorization(&self, user_id: &Uuid, request_id: &Uuid, approve: bool) -> Result<String, AppError>;
+    /// Inicia el flujo de dispositivo (RFC 8628) con el `device_code` y el `user_code`
+    async fn start_device_authorization(&self, request: &DeviceAuthorizationRequest) -> Result<DeviceAuthorizationResponse, OAuthError>;
+    async fn device_authorization_request(&self, user_code: &str) -> Result<DeviceAuthorizationInfo, AppError>;
+    async fn decide_device_authorization(&self, user_id: &Uuid, user_code: &str, approve: bool) -> Result<(), AppError>;
+    async fn exchange_oauth_token(&self, request: &TokenRequest) -> Result<TokenResponse, OAuthError>;
+    async fn oauth_userinfo(&self, access_token: &str) -> Result<UserInfo, OAuthError>;
+    /// Comprueba que el cliente de un token de servicio sigue pudiendo usar `client_credentials`
+    async fn verify_service_client(&self, client_id: &str) -> Result<(), AppError>;
+    /// Documento de `/.well-known/openid-configuration`
+    fn openid_configuration(&self) -> Value;
     async fn generate_token(&self, user: &shared::user::User) -> Result<String, String>;
+    async fn issue_refresh_token(&self, user: &shared::user::User) -> Result<String, String>;
+    async fn refresh_token(&self, refresh_token: &str) -> Result<(String, String), String>;
+    /// Revoca la familia del refresh token si pertenece a `user_id`
+    async fn revoke_refresh_token(&self, user_id: &Uuid, refresh_token: &str) -> Result<(), AppError>;
     async fn get_user(&self, user_id: &Uuid) -> Result<FilteredUser, String>;
 }
 
 #[derive(Serialize)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
 }
-#[derive(serde::Deserialize, Debug)] // Añade Debug para facilitar el logging
-pub struct RegisterParams {
-    telegram_user_id: Option<String>,
+
+#[derive(Serialize)]
+pub struct MfaChallenge {
+    pub mfa_required: bool,
+    pub mfa_token: String,
 }
 
+/// Respuesta del login: sesión completa o reto del segundo factor
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum LoginOutcome {
+    Session(LoginResponse),
+    MfaRequired(MfaChallenge),
+}
 pub async fn auth_middleware(
   State(state): State<Arc<AppState>>,
   request: Request<Body>,
@@ -56,7 +139,7 @@ pub async fn auth_middleware(
   
   let token = auth_header.trim_start_matches("Bearer ").trim();
   
-  let _claims = verify_jwt(token, &state.jwt_secret)
+  let _claims = verify_jwt(token, &state.jwt_config)
       .map_err(|e| AppError::Auth(e.to_string()))?;
   
   // Continuar con la siguiente middleware/handler con el token validado
@@ -69,21 +152,18 @@ pub async fn auth_middleware(
 pub async fn register_handler(
  
   State(state): State<Arc<AppState>>,
-    Query(params): Query<RegisterParams>,
   Json(payload): Json<CreateUserSchema>,
    
 ) -> Result<Json<Value>, AppError> {
   // Validar entrada
   payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
 
-  let telegram_id = params.telegram_user_id;
-
-  // Registrar usuario
+  // Registrar usuario. La cuenta de Telegram se vincula después con una prueba firmada
+  // (POST /api/users/me/telegram), nunca con un ID sin verificar.
   let user = state
       .auth_service
-      .register_user(&payload, telegram_id)
-      .await
-      .map_err(|e| AppError::Auth(e.to_string()))?;
+      .register_user(&payload)
+      .await?;
   
   Ok(Json(json!({
       "status": "success",
@@ -95,28 +175,238 @@ pub async fn register_handler(
 pub async fn login_handler(
   State(app_state): State<Arc<AppState>>,
   Json(body): Json<LoginUserSchema>,
-) -> Result<Json<LoginResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<LoginOutcome>, (StatusCode, Json<serde_json::Value>)> {
   let user = if let Some(email) = body.email {
       // Autenticación con correo electrónico
       match app_state.auth_service.authenticate_by_email(&email, &body.password).await {
           Ok(user) => user,
+          // Sólo se llega aquí con la contraseña correcta, así que no revela nada a terceros
+          Err(AppError::Forbidden(_)) => return Err((StatusCode::FORBIDDEN, Json(json!({"error": "Email not verified"})))),
+          Err(AppError::AccountLocked { retry_after }) => {
+              return Err((StatusCode::LOCKED, Json(json!({"error": "Account temporarily locked", "retry_after": retry_after}))))
+          }
           Err(_) => return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid email or password"}))))
       }
-  } else if let Some(telegram_user_id) = body.telegram_user_id {
-      // Autenticación con Telegram
-      match app_state.auth_service.authenticate_by_telegram(&telegram_user_id).await {
+  } else if let Some(telegram) = body.telegram {
+      // Autenticación con los datos firmados del Telegram Login Widget
+      match app_state.auth_service.authenticate_by_telegram(&telegram).await {
           Ok(user) => user,
-          Err(_) => return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid Telegram user ID"}))))
+          Err(_) => return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid Telegram login data"}))))
       }
+  } else if body.telegram_user_id.is_some() {
+      // Un telegram_user_id sin firma no prueba nada: se exige el payload del Login Widget
+      return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "Signed Telegram login data is required"}))));
   } else {
-      return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "Email or Telegram user ID is required"}))));
+      return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "Email or Telegram login data is required"}))));
+  };
+
+  complete_login(&app_state, &user).await
+}
+
+pub async fn telegram_webapp_handler(
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<TelegramWebAppSchema>,
+) -> Result<Json<LoginOutcome>, (StatusCode, Json<serde_json::Value>)> {
+  // Login (o alta automática) con el initData firmado de la Mini App
+  let user = match app_state.auth_service.authenticate_by_telegram_web_app(&body.init_data).await {
+      Ok(user) => user,
+      Err(_) => return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid Telegram init data"}))))
+  };
+
+  complete_login(&app_state, &user).await
+}
+
+pub async fn mfa_verify_handler(
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<MfaVerifySchema>,
+) -> Result<Json<LoginResponse>, (StatusCode, Json<serde_json::Value>)> {
+  let invalid_token = || (StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid or expired MFA token"})));
+  let internal_error = || (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to verify MFA code"})));
+
+  // Sólo se aceptan tokens `mfa_pending` que no se hayan canjeado ya
+  let claims = verify_jwt(&body.mfa_token, &app_state.jwt_config).map_err(|_| invalid_token())?;
+  if claims.token_use.as_deref() != Some(MFA_PENDING_TOKEN_USE) {
+      return Err(invalid_token());
+  }
+  let revoked = app_state.revocation_store.is_revoked(&claims.jti).await.map_err(|_| internal_error())?;
+  if revoked {
+      return Err(invalid_token());
+  }
+  let user_id = Uuid::parse_str(&claims.sub).map_err(|_| invalid_token())?;
+
+  let user = match app_state
+      .auth_service
+      .verify_mfa(&claims.jti, &user_id, claims.ver.unwrap_or(0), body.code.as_deref(), body.recovery_code.as_deref())
+      .await
+  {
+      Ok(user) => user,
+      Err(AppError::Validation(_)) => return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid MFA code"})))),
+      // El reto agotó sus intentos o es anterior a un cambio de contraseña: hay que volver a iniciar sesión
+      Err(AppError::NotFound(_)) | Err(AppError::Auth(_)) => return Err(invalid_token()),
+      Err(_) => return Err(internal_error()),
   };
 
+  let expires_at = app_state.jwt_config.expires_at(&claims).ok_or_else(invalid_token)?;
+  app_state
+      .revocation_store
+      .revoke(&claims.jti, expires_at)
+      .await
+      .map_err(|_| internal_error())?;
+
+  issue_session(&app_state, &user).await
+}
+
+// Tras la contraseña (o Telegram o el proveedor externo) pide el segundo factor si el usuario lo tiene activado
+pub(crate) async fn complete_login(
+  app_state: &AppState,
+  user: &shared::user::User,
+) -> Result<Json<LoginOutcome>, (StatusCode, Json<serde_json::Value>)> {
+  let challenge = match app_state.auth_service.mfa_challenge(user).await {
+      Ok(challenge) => challenge,
+      Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to start login"}))))
+  };
+
+  match challenge {
+      Some(mfa_token) => Ok(Json(LoginOutcome::MfaRequired(MfaChallenge { mfa_required: true, mfa_token }))),
+      None => {
+          let Json(session) = issue_session(app_state, user).await?;
+          Ok(Json(LoginOutcome::Session(session)))
+      }
+  }
+}
+
+// Emite el par access token / refresh token de un usuario ya autenticado
+pub(crate) async fn issue_session(
+  app_state: &AppState,
+  user: &shared::user::User,
+) -> Result<Json<LoginResponse>, (StatusCode, Json<serde_json::Value>)> {
   // Generar token JWT
-  let token = match app_state.auth_service.generate_token(&user).await {
+  let token = match app_state.auth_service.generate_token(user).await {
       Ok(token) => token,
       Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to generate token"}))))
   };
 
-  Ok(Json(LoginResponse { token }))
+  let refresh_token = match app_state.auth_service.issue_refresh_token(user).await {
+      Ok(refresh_token) => refresh_token,
+      Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to generate refresh token"}))))
+  };
+
+  Ok(Json(LoginResponse { token, refresh_token }))
+}
+
+pub async fn verify_email_handler(
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<VerifyEmailSchema>,
+) -> Result<Json<Value>, AppError> {
+  app_state
+      .auth_service
+      .verify_email(&body.token)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "Email verified successfully"
+  })))
+}
+
+pub async fn resend_verification_handler(
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<ResendVerificationSchema>,
+) -> Result<Json<Value>, AppError> {
+  body.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+  // La respuesta es la misma exista o no el email
+  app_state
+      .auth_service
+      .resend_verification_email(&body.email)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "If the email is registered and not yet verified, a new verification link has been sent"
+  })))
+}
+
+pub async fn forgot_password_handler(
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<ForgotPasswordSchema>,
+) -> Result<Json<Value>, AppError> {
+  body.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+  // Misma respuesta exista o no el email, para no permitir enumerar cuentas
+  if let Err(e) = app_state.auth_service.request_password_reset(&body.email).await {
+      tracing::error!("Error al procesar la recuperación de contraseña: {}", e);
+  }
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "If the email is registered, a password reset link has been sent"
+  })))
+}
+
+pub async fn reset_password_handler(
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<ResetPasswordSchema>,
+) -> Result<Json<Value>, AppError> {
+  body.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+  app_state
+      .auth_service
+      .reset_password(&body.token, &body.new_password)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "Password has been reset"
+  })))
+}
+
+pub async fn refresh_handler(
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<RefreshTokenSchema>,
+) -> Result<Json<LoginResponse>, AppError> {
+  // Rotar el refresh token: el anterior queda invalidado
+  let (token, refresh_token) = app_state
+      .auth_service
+      .refresh_token(&body.refresh_token)
+      .await
+      .map_err(AppError::Auth)?;
+
+  Ok(Json(LoginResponse { token, refresh_token }))
+}
+
+pub async fn logout_handler(
+  State(app_state): State<Arc<AppState>>,
+  Extension(claims): Extension<Claims>,
+  body: Option<Json<LogoutSchema>>,
+) -> Result<Json<Value>, AppError> {
+  if claims.token_use.as_deref() == Some(API_KEY_TOKEN_USE) {
+      return Err(AppError::Validation("API keys are revoked with DELETE /api/users/me/api-keys/{id}".into()));
+  }
+
+  // Revocar el access token actual mientras se siga aceptando (expiración más el margen)
+  let expires_at = app_state
+      .jwt_config
+      .expires_at(&claims)
+      .ok_or_else(|| AppError::InvalidToken("Invalid expiration in token".into()))?;
+
+  app_state
+      .revocation_store
+      .revoke(&claims.jti, expires_at)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+
+  // Si el cliente envía su refresh token, se revoca también toda su familia
+  if let Some(Json(LogoutSchema { refresh_token: Some(refresh_token) })) = body {
+      let user_id = Uuid::parse_str(&claims.sub).map_err(|_| AppError::InvalidToken("Invalid user ID in token".into()))?;
+      app_state
+          .auth_service
+          .revoke_refresh_token(&user_id, &refresh_token)
+          .await?;
+  }
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "Logged out successfully"
+  })))
 }
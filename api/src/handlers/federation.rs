@@ -0,0 +1,64 @@
+use axum::{
+  extract::{Json, State},
+  http::{header, HeaderMap, StatusCode},
+};
+use common::error::AppError;
+use serde_json::{json, Value};
+use shared::user::FederatedCallbackSchema;
+use std::sync::Arc;
+use crate::{
+  handlers::auth::{complete_login, LoginOutcome},
+  AppState,
+};
+
+// Cookie con el hash del `state`: sólo el navegador que inició el login puede completarlo
+const STATE_COOKIE: &str = "oidc_state";
+
+pub async fn federated_login_handler(
+  State(state): State<Arc<AppState>>,
+) -> Result<([(header::HeaderName, String); 1], Json<Value>), AppError> {
+  let login = state.auth_service.start_federated_login().await?;
+  let cookie = format!(
+      "{}={}; Path=/api/auth/oidc; Max-Age={}; HttpOnly; Secure; SameSite=Lax",
+      STATE_COOKIE, login.state_binding, login.expires_in
+  );
+
+  Ok((
+      [(header::SET_COOKIE, cookie)],
+      Json(json!({
+          "status": "success",
+          "authorization_url": login.authorization_url
+      })),
+  ))
+}
+
+pub async fn federated_callback_handler(
+  State(app_state): State<Arc<AppState>>,
+  headers: HeaderMap,
+  Json(body): Json<FederatedCallbackSchema>,
+) -> Result<Json<LoginOutcome>, (StatusCode, Json<Value>)> {
+  // El proveedor sólo sustituye a la contraseña: el segundo factor local se sigue pidiendo
+  let state_binding = state_cookie(&headers);
+  let user = match app_state.auth_service.finish_federated_login(&body.code, &body.state, state_binding).await {
+      Ok(user) => user,
+      Err(AppError::Auth(_)) => return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid federated login"})))),
+      Err(AppError::Conflict(_)) => {
+          return Err((StatusCode::CONFLICT, Json(json!({"error": "Email belongs to another account and is not verified by the provider"}))))
+      }
+      Err(AppError::NotFound(_)) => {
+          return Err((StatusCode::NOT_FOUND, Json(json!({"error": "Federated login is not configured"}))))
+      }
+      Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to complete federated login"})))),
+  };
+
+  complete_login(&app_state, &user).await
+}
+
+fn state_cookie(headers: &HeaderMap) -> Option<&str> {
+  headers
+      .get_all(header::COOKIE)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .flat_map(|value| value.split(';'))
+      .find_map(|pair| pair.trim().strip_prefix(STATE_COOKIE)?.strip_prefix('='))
+}
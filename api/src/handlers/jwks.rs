@@ -0,0 +1,11 @@
+use axum::extract::{Json, State};
+use jsonwebtoken::jwk::JwkSet;
+use std::sync::Arc;
+use crate::AppState;
+
+/// Publica las claves públicas de verificación para que otros servicios validen nuestros tokens
+pub async fn jwks_handler(
+  State(state): State<Arc<AppState>>,
+) -> Json<JwkSet> {
+  Json(state.jwt_config.keys.jwks())
+}
@@ -1,41 +1,19 @@
-use axum::{
-  extract::{Json, State},
-  http::HeaderMap,
-};
+use axum::extract::{Json, State};
 use common::error::AppError;
 use serde_json::{json, Value};
 use std::sync::Arc;
-use uuid::Uuid;
-use common::jwt::verify_jwt;
-use crate::AppState;
+use shared::user::ChangePasswordSchema;
+use validator::Validate;
+use crate::{middleware::auth::{AuthUser, SessionUser}, AppState};
 
 pub async fn me_handler(
   State(state): State<Arc<AppState>>,
-  headers: HeaderMap,
+  auth_user: AuthUser,
 ) -> Result<Json<Value>, AppError> {
-  let authorization = headers
-      .get("Authorization")
-      .ok_or_else(|| AppError::Auth("Missing authorization header".into()))?;
-  
-  let auth_header = authorization
-      .to_str()
-      .map_err(|_| AppError::Auth("Invalid authorization header".into()))?;
-  
-  if !auth_header.starts_with("Bearer ") {
-      return Err(AppError::Auth("Invalid token format".into()));
-  }
-  
-  let token = auth_header.trim_start_matches("Bearer ").trim();
-  
-  let claims = verify_jwt(token, &state.jwt_secret)
-      .map_err(|e| AppError::Auth(e.to_string()))?;
-  
-  let user_id = Uuid::parse_str(&claims.sub)
-      .map_err(|_| AppError::Auth("Invalid user ID in token".into()))?;
-  
+  // auth_middleware ya validó el access token o la API key
   let user = state
       .auth_service
-      .get_user(&user_id)
+      .get_user(&auth_user.id)
       .await
       .map_err(|e| AppError::Auth(e.to_string()))?;
   
@@ -44,3 +22,24 @@ pub async fn me_handler(
       "user": user
   })))
 }
+
+pub async fn change_password_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+  Json(body): Json<ChangePasswordSchema>,
+) -> Result<Json<Value>, AppError> {
+  body.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+  // Todos los tokens anteriores quedan invalidados; se devuelve un par nuevo para esta sesión
+  let (token, refresh_token) = state
+      .auth_service
+      .change_password(&auth_user.id, &body.current_password, &body.new_password)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "Password changed successfully",
+      "token": token,
+      "refresh_token": refresh_token
+  })))
+}
@@ -0,0 +1,57 @@
+use axum::extract::{Json, State};
+use common::error::AppError;
+use serde_json::{json, Value};
+use shared::user::{MfaCodeSchema, TotpCodeSchema};
+use std::sync::Arc;
+use crate::{middleware::auth::SessionUser, AppState};
+
+pub async fn start_totp_enrollment_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+) -> Result<Json<Value>, AppError> {
+  // El secreto no se activa hasta confirmarlo con un primer código válido
+  let enrollment = state
+      .auth_service
+      .start_totp_enrollment(&auth_user.id)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "secret": enrollment.secret,
+      "otpauth_uri": enrollment.otpauth_uri
+  })))
+}
+
+pub async fn confirm_totp_enrollment_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+  Json(body): Json<TotpCodeSchema>,
+) -> Result<Json<Value>, AppError> {
+  let recovery_codes = state
+      .auth_service
+      .confirm_totp_enrollment(&auth_user.id, &body.code)
+      .await?;
+
+  // Los códigos de recuperación sólo se muestran esta vez
+  Ok(Json(json!({
+      "status": "success",
+      "message": "Two-factor authentication enabled",
+      "recovery_codes": recovery_codes
+  })))
+}
+
+pub async fn disable_totp_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+  Json(body): Json<MfaCodeSchema>,
+) -> Result<Json<Value>, AppError> {
+  state
+      .auth_service
+      .disable_totp(&auth_user.id, body.code.as_deref(), body.recovery_code.as_deref())
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "Two-factor authentication disabled"
+  })))
+}
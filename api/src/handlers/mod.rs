@@ -1,2 +1,10 @@
+pub mod admin;
+pub mod api_keys;
 pub mod auth;
-pub mod me;
\ No newline at end of file
+pub mod federation;
+pub mod jwks;
+pub mod me;
+pub mod mfa;
+pub mod oauth;
+pub mod telegram;
+pub mod webauthn;
@@ -0,0 +1,204 @@
+use axum::{
+  extract::{rejection::{FormRejection, QueryRejection}, Form, Json, Path, Query, State},
+  http::{header, HeaderMap},
+  response::{IntoResponse, Redirect, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::error::AppError;
+use serde_json::{json, Value};
+use shared::oauth::{AuthorizeError, AuthorizeParams, ConsentSchema, DeviceAuthorizationRequest, OAuthError, TokenRequest};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::{middleware::auth::SessionUser, AppState};
+
+/// Inicio del flujo authorization code: valida la petición del cliente y manda al
+/// navegador a la pantalla de consentimiento del frontend
+pub async fn authorize_handler(
+  State(state): State<Arc<AppState>>,
+  params: Result<Query<AuthorizeParams>, QueryRejection>,
+) -> Response {
+  let Ok(Query(params)) = params else {
+      return OAuthError::new("invalid_request", "client_id is required").into_response();
+  };
+
+  match state.auth_service.start_oauth_authorization(&params).await {
+      Ok(consent_url) => Redirect::to(&consent_url).into_response(),
+      Err(AuthorizeError::Redirect(url)) => Redirect::to(&url).into_response(),
+      Err(AuthorizeError::Direct(e)) => e.into_response(),
+  }
+}
+
+/// Datos de la solicitud para la pantalla de consentimiento
+pub async fn authorization_request_handler(
+  State(state): State<Arc<AppState>>,
+  Path(request_id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+  let request = state.auth_service.oauth_authorization_request(&request_id).await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "authorization_request": request
+  })))
+}
+
+pub async fn consent_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+  Path(request_id): Path<Uuid>,
+  Json(body): Json<ConsentSchema>,
+) -> Result<Json<Value>, AppError> {
+  let redirect_to = state
+      .auth_service
+      .decide_oauth_authorization(&auth_user.id, &request_id, body.approve)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "redirect_to": redirect_to
+  })))
+}
+
+/// Canje del código de autorización o token de servicio (`client_credentials`). El cliente se autentica con HTTP Basic
+/// (`client_secret_basic`), en el cuerpo (`client_secret_post`) o sólo con `client_id` si es público
+pub async fn token_handler(
+  State(state): State<Arc<AppState>>,
+  headers: HeaderMap,
+  body: Result<Form<TokenRequest>, FormRejection>,
+) -> Response {
+  let Ok(Form(mut body)) = body else {
+      return OAuthError::new("invalid_request", "grant_type is required").into_response();
+  };
+  if let Err(e) = merge_basic_credentials(&headers, &mut body.client_id, &mut body.client_secret) {
+      return e.into_response();
+  }
+
+  match state.auth_service.exchange_oauth_token(&body).await {
+      Ok(tokens) => (
+          [(header::CACHE_CONTROL, "no-store"), (header::PRAGMA, "no-cache")],
+          Json(tokens),
+      )
+          .into_response(),
+      Err(e) => e.into_response(),
+  }
+}
+
+/// Inicio del flujo de dispositivo (RFC 8628): el cliente se autentica como en `/oauth/token`
+pub async fn device_authorization_handler(
+  State(state): State<Arc<AppState>>,
+  headers: HeaderMap,
+  body: Result<Form<DeviceAuthorizationRequest>, FormRejection>,
+) -> Response {
+  let Ok(Form(mut body)) = body else {
+      return OAuthError::new("invalid_request", "Malformed request body").into_response();
+  };
+  if let Err(e) = merge_basic_credentials(&headers, &mut body.client_id, &mut body.client_secret) {
+      return e.into_response();
+  }
+
+  match state.auth_service.start_device_authorization(&body).await {
+      Ok(authorization) => ([(header::CACHE_CONTROL, "no-store")], Json(authorization)).into_response(),
+      Err(e) => e.into_response(),
+  }
+}
+
+/// Datos del dispositivo que espera aprobación, para la pantalla del frontend
+pub async fn device_request_handler(
+  State(state): State<Arc<AppState>>,
+  Path(user_code): Path<String>,
+) -> Result<Json<Value>, AppError> {
+  let request = state.auth_service.device_authorization_request(&user_code).await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "device_authorization": request
+  })))
+}
+
+pub async fn device_consent_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+  Path(user_code): Path<String>,
+  Json(body): Json<ConsentSchema>,
+) -> Result<Json<Value>, AppError> {
+  state
+      .auth_service
+      .decide_device_authorization(&auth_user.id, &user_code, body.approve)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": if body.approve { "Device approved" } else { "Device denied" }
+  })))
+}
+
+/// Claims del usuario para el access token emitido en `/oauth/token`
+pub async fn userinfo_handler(
+  State(state): State<Arc<AppState>>,
+  headers: HeaderMap,
+) -> Response {
+  let token = headers
+      .get(header::AUTHORIZATION)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.strip_prefix("Bearer "));
+  let Some(token) = token else {
+      return OAuthError::new("invalid_token", "Missing bearer token").into_response();
+  };
+
+  match state.auth_service.oauth_userinfo(token.trim()).await {
+      Ok(user_info) => ([(header::CACHE_CONTROL, "no-store")], Json(user_info)).into_response(),
+      Err(e) => e.into_response(),
+  }
+}
+
+pub async fn openid_configuration_handler(
+  State(state): State<Arc<AppState>>,
+) -> Json<Value> {
+  Json(state.auth_service.openid_configuration())
+}
+
+// Credenciales del cliente en HTTP Basic (`client_secret_basic`), si las hay, sobre las del cuerpo
+fn merge_basic_credentials(
+  headers: &HeaderMap,
+  client_id: &mut Option<String>,
+  client_secret: &mut Option<String>,
+) -> Result<(), OAuthError> {
+  let Some(credentials) = basic_credentials(headers) else {
+      return Ok(());
+  };
+  let (basic_id, basic_secret) = credentials
+      .ok_or_else(|| OAuthError::new("invalid_client", "Malformed Basic credentials"))?;
+  // Un único método de autenticación por petición
+  if client_secret.is_some() || client_id.as_ref().is_some_and(|id| *id != basic_id) {
+      return Err(OAuthError::new("invalid_request", "Use a single client authentication method"));
+  }
+  *client_id = Some(basic_id);
+  *client_secret = Some(basic_secret);
+  Ok(())
+}
+
+// `None` sin cabecera Basic; `Some(None)` si no se puede decodificar
+fn basic_credentials(headers: &HeaderMap) -> Option<Option<(String, String)>> {
+  let encoded = headers
+      .get(header::AUTHORIZATION)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.strip_prefix("Basic "))?;
+
+  let credentials = STANDARD
+      .decode(encoded.trim())
+      .ok()
+      .and_then(|decoded| String::from_utf8(decoded).ok())
+      .and_then(|decoded| {
+          decoded
+              .split_once(':')
+              .map(|(client_id, client_secret)| (form_decode(client_id), form_decode(client_secret)))
+      });
+  Some(credentials)
+}
+
+// El id y el secreto van codificados como application/x-www-form-urlencoded (RFC 6749 §2.3.1)
+fn form_decode(value: &str) -> String {
+  form_urlencoded::parse(format!("v={}", value).as_bytes())
+      .next()
+      .map(|(_, value)| value.into_owned())
+      .unwrap_or_default()
+}
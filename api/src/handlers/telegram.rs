@@ -0,0 +1,38 @@
+use axum::extract::{Json, State};
+use common::error::AppError;
+use serde_json::{json, Value};
+use shared::user::LinkTelegramSchema;
+use std::sync::Arc;
+use crate::{middleware::auth::SessionUser, AppState};
+
+pub async fn link_telegram_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+  Json(body): Json<LinkTelegramSchema>,
+) -> Result<Json<Value>, AppError> {
+  // La cuenta de Telegram sólo se vincula si sus datos vienen firmados por el bot
+  let telegram_user_id = state
+      .auth_service
+      .link_telegram(&auth_user.id, &body)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "telegram_user_id": telegram_user_id
+  })))
+}
+
+pub async fn unlink_telegram_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+) -> Result<Json<Value>, AppError> {
+  state
+      .auth_service
+      .unlink_telegram(&auth_user.id)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "message": "Telegram account unlinked"
+  })))
+}
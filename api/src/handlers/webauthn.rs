@@ -0,0 +1,68 @@
+use axum::{
+  extract::{Json, State},
+  http::StatusCode,
+};
+use common::error::AppError;
+use serde_json::{json, Value};
+use shared::user::{PasskeyLoginSchema, PasskeyOptions, PasskeyRegistrationSchema};
+use std::sync::Arc;
+use validator::Validate;
+use crate::{
+  handlers::auth::{issue_session, LoginResponse},
+  middleware::auth::SessionUser,
+  AppState,
+};
+
+pub async fn passkey_registration_options_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+) -> Result<Json<PasskeyOptions>, AppError> {
+  let options = state
+      .auth_service
+      .start_passkey_registration(&auth_user.id)
+      .await?;
+
+  Ok(Json(options))
+}
+
+pub async fn passkey_registration_handler(
+  State(state): State<Arc<AppState>>,
+  SessionUser(auth_user): SessionUser,
+  Json(body): Json<PasskeyRegistrationSchema>,
+) -> Result<Json<Value>, AppError> {
+  body.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+  let credential_id = state
+      .auth_service
+      .finish_passkey_registration(&auth_user.id, &body)
+      .await?;
+
+  Ok(Json(json!({
+      "status": "success",
+      "credential_id": credential_id
+  })))
+}
+
+pub async fn passkey_login_options_handler(
+  State(state): State<Arc<AppState>>,
+) -> Result<Json<PasskeyOptions>, AppError> {
+  let options = state.auth_service.start_passkey_login().await?;
+
+  Ok(Json(options))
+}
+
+pub async fn passkey_login_handler(
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<PasskeyLoginSchema>,
+) -> Result<Json<LoginResponse>, (StatusCode, Json<Value>)> {
+  // Una passkey con verificación de usuario ya es un segundo factor: no se pide TOTP
+  let user = match app_state.auth_service.finish_passkey_login(&body).await {
+      Ok(user) => user,
+      Err(AppError::Validation(_)) | Err(AppError::NotFound(_)) => {
+          return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid passkey"}))))
+      }
+      Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to verify passkey"})))),
+  };
+
+  issue_session(&app_state, &user).await
+}
@@ -1,5 +1,6 @@
 pub mod handlers;
 pub mod middleware;
+pub mod permissions;
 pub mod routes;
 pub mod state;
 
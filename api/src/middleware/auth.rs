@@ -1,39 +1,301 @@
 use axum::{
+  async_trait,
   body::Body,
-  extract::{Request, State},
+  extract::{FromRequestParts, Request, State},
+  http::{request::Parts, HeaderMap},
   middleware::Next,
   response::Response,
 };
+use chrono::Utc;
 use common::error::AppError;
-use crate::AppState;
-use std::sync::Arc;
+use crate::{permissions::PermissionCache, AppState};
+use std::{collections::HashSet, sync::Arc};
+use uuid::Uuid;
 
 //use shared::AppState;
-use common::jwt::verify_jwt;
+use common::jwt::{verify_jwt, Claims, API_KEY_TOKEN_USE, SERVICE_TOKEN_USE};
 
+/// Cabecera con la que los clientes pueden presentar su API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Esquema de `Authorization` para presentar una API key
+const API_KEY_SCHEME: &str = "ApiKey ";
+
+/// Usuario autenticado, construido a partir de los claims que deja `auth_middleware`.
+/// Sólo puede extraerse en rutas protegidas por ese middleware.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+  pub id: Uuid,
+  pub email: Option<String>,
+  pub role: String,
+  pub claims: Claims,
+  pub permissions: PermissionCache,
+  /// Permisos a los que se limita el acceso (`scope`); `None` si no hay límite
+  pub scopes: Option<HashSet<String>>,
+}
+
+impl AuthUser {
+  /// Falla con 403 si los claims son de un servicio (`client_credentials`): los endpoints
+  /// que actúan en nombre de un usuario no se pueden llamar como servicio.
+  pub fn from_claims(claims: Claims) -> Result<Self, AppError> {
+      if is_service_token(&claims) {
+          return Err(AppError::Forbidden("Service clients cannot access user endpoints".into()));
+      }
+      let id = Uuid::parse_str(&claims.sub)
+          .map_err(|_| AppError::Auth("Invalid user ID in token".into()))?;
+
+      Ok(Self {
+          id,
+          email: claims.email.clone(),
+          // Los tokens emitidos antes de incluir el rol se tratan como usuarios normales
+          role: claims.role.clone().unwrap_or_else(|| "user".to_string()),
+          scopes: claims.scope.as_deref().map(|scope| scope.split_whitespace().map(str::to_string).collect()),
+          claims,
+          permissions: PermissionCache::default(),
+      })
+  }
+
+  /// Si la petición se autenticó con una API key en lugar de un access token
+  pub fn is_api_key(&self) -> bool {
+      self.claims.token_use.as_deref() == Some(API_KEY_TOKEN_USE)
+  }
+
+  pub fn has_role(&self, role: &str) -> bool {
+      self.role == role
+  }
+
+  pub fn has_any_role<R: AsRef<str>>(&self, roles: &[R]) -> bool {
+      roles.iter().any(|role| self.has_role(role.as_ref()))
+  }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+  type Rejection = AppError;
+
+  async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+      let mut user = AuthUser::from_claims(request_claims(parts)?)?;
+      if let Some(permissions) = parts.extensions.get::<PermissionCache>() {
+          user.permissions = permissions.clone();
+      }
+      Ok(user)
+  }
+}
+
+/// Usuario autenticado con un access token de su sesión, no con una API key. Lo piden los
+/// endpoints que gestionan las credenciales o la cuenta: una clave filtrada no debe servir
+/// para cambiar la contraseña, añadir otro factor o método de login, gestionar las claves de
+/// API ni conceder acceso a clientes OAuth o dispositivos.
+#[derive(Debug, Clone)]
+pub struct SessionUser(pub AuthUser);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SessionUser {
+  type Rejection = AppError;
+
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+      let user = AuthUser::from_request_parts(parts, state).await?;
+      if user.is_api_key() {
+          return Err(AppError::Forbidden("API keys cannot manage account credentials".into()));
+      }
+      Ok(SessionUser(user))
+  }
+}
+
+/// Servicio autenticado con un token `client_credentials`. Sólo tiene los permisos de su `scope`.
+#[derive(Debug, Clone)]
+pub struct ServiceClient {
+  pub client_id: String,
+  pub scopes: HashSet<String>,
+  pub claims: Claims,
+}
+
+impl ServiceClient {
+  pub fn from_claims(claims: Claims) -> Result<Self, AppError> {
+      if !is_service_token(&claims) {
+          return Err(AppError::Forbidden("Only service clients can access this endpoint".into()));
+      }
+
+      Ok(Self {
+          client_id: claims.sub.clone(),
+          scopes: claims.scope.as_deref().unwrap_or_default().split_whitespace().map(str::to_string).collect(),
+          claims,
+      })
+  }
+
+  pub fn has_scope(&self, scope: &str) -> bool {
+      self.scopes.contains(scope)
+  }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ServiceClient {
+  type Rejection = AppError;
+
+  async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+      ServiceClient::from_claims(request_claims(parts)?)
+  }
+}
+
+/// Quien hace la petición, para los endpoints que admiten tanto usuarios (access token o
+/// API key) como servicios (`client_credentials`).
+#[derive(Debug, Clone)]
+pub enum Principal {
+  User(AuthUser),
+  Service(ServiceClient),
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+  type Rejection = AppError;
+
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+      let claims = request_claims(parts)?;
+      if is_service_token(&claims) {
+          Ok(Principal::Service(ServiceClient::from_claims(claims)?))
+      } else {
+          Ok(Principal::User(AuthUser::from_request_parts(parts, state).await?))
+      }
+  }
+}
+
+fn is_service_token(claims: &Claims) -> bool {
+  claims.token_use.as_deref() == Some(SERVICE_TOKEN_USE)
+}
+
+fn request_claims(parts: &Parts) -> Result<Claims, AppError> {
+  parts
+      .extensions
+      .get::<Claims>()
+      .cloned()
+      .ok_or_else(|| AppError::Auth("Missing authentication".into()))
+}
+
+/// API key de la petición: `Authorization: ApiKey <clave>` o la cabecera `X-API-Key`.
+pub fn api_key_from_headers(headers: &HeaderMap) -> Option<&str> {
+  let from_authorization = headers
+      .get("Authorization")
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.strip_prefix(API_KEY_SCHEME));
+
+  from_authorization
+      .or_else(|| headers.get(API_KEY_HEADER).and_then(|value| value.to_str().ok()))
+      .map(str::trim)
+      .filter(|key| !key.is_empty())
+}
+
+/// Acepta un access token (`Authorization: Bearer`), de usuario o de servicio, o una API key
+/// (`Authorization: ApiKey` o `X-API-Key`) y deja los claims y la caché de permisos para los
+/// handlers. `AuthUser`, `ServiceClient` y `Principal` distinguen después usuarios de servicios.
 pub async fn auth_middleware(
   State(state): State<Arc<AppState>>,
-  request: Request<Body>,
+  mut request: Request<Body>,
   next: Next,
 ) -> Result<Response, AppError> {
-  let authorization = request
-      .headers()
-      .get("Authorization")
-      .ok_or_else(|| AppError::Auth("Missing authorization header".into()))?;
-  
-  let auth_header = authorization
-      .to_str()
-      .map_err(|_| AppError::Auth("Invalid authorization header".into()))?;
-  
-  if !auth_header.starts_with("Bearer ") {
-      return Err(AppError::Auth("Invalid token format".into()));
-  }
-  
-  let token = auth_header.trim_start_matches("Bearer ").trim();
-  
-  let _claims = verify_jwt(token, &state.jwt_secret)
-      .map_err(|e| AppError::Auth(e.to_string()))?;
+  let claims = match request.headers().get("Authorization") {
+      Some(authorization) => {
+          let auth_header = authorization
+              .to_str()
+              .map_err(|_| AppError::Auth("Invalid authorization header".into()))?;
+
+          if let Some(token) = auth_header.strip_prefix("Bearer ") {
+              claims_from_access_token(&state, token.trim()).await?
+          } else if let Some(key) = api_key_from_headers(request.headers()) {
+              claims_from_api_key(&state, key).await?
+          } else {
+              return Err(AppError::Auth("Invalid token format".into()));
+          }
+      }
+      None => match api_key_from_headers(request.headers()) {
+          Some(key) => claims_from_api_key(&state, key).await?,
+          None => return Err(AppError::Auth("Missing authorization header".into())),
+      },
+  };
+
+  // Dejar los claims y la caché de permisos disponibles para los handlers
+  request.extensions_mut().insert(claims);
+  request.extensions_mut().insert(PermissionCache::default());
   
   // Continuar con la siguiente middleware/handler con el token validado
   Ok(next.run(request).await)
 }
+
+async fn claims_from_access_token(state: &AppState, token: &str) -> Result<Claims, AppError> {
+  let claims = verify_jwt(token, &state.jwt_config)
+      .map_err(|e| AppError::Auth(e.to_string()))?;
+
+  // Los tokens de uso restringido (p. ej. `mfa_pending`) no dan acceso a la API
+  if claims.token_use.is_some() && !is_service_token(&claims) {
+      return Err(AppError::Auth("Token not valid for this endpoint".into()));
+  }
+
+  // Rechazar tokens revocados (logout) aunque todavía no hayan expirado
+  let revoked = state
+      .revocation_store
+      .is_revoked(&claims.jti)
+      .await
+      .map_err(|e| AppError::Internal(e.to_string()))?;
+  if revoked {
+      return Err(AppError::Auth("Token has been revoked".into()));
+  }
+
+  // Los tokens de servicio valen mientras el cliente siga registrado con client_credentials
+  if is_service_token(&claims) {
+      state
+          .auth_service
+          .verify_service_client(&claims.sub)
+          .await
+          .map_err(|e| match e {
+              AppError::NotFound(_) => AppError::Auth("Service client no longer exists".into()),
+              other => other,
+          })?;
+      return Ok(claims);
+  }
+
+  // Rechazar tokens emitidos antes de un cambio de contraseña (o de usuarios eliminados).
+  // Los tokens sin `ver` son anteriores a esta comprobación y equivalen a la versión 0.
+  let user_id = Uuid::parse_str(&claims.sub)
+      .map_err(|_| AppError::Auth("Invalid user ID in token".into()))?;
+  let token_version = state
+      .auth_service
+      .token_version(&user_id)
+      .await
+      .map_err(|e| match e {
+          AppError::NotFound(_) => AppError::Auth("User no longer exists".into()),
+          other => other,
+      })?;
+  if claims.ver.unwrap_or(0) != token_version {
+      return Err(AppError::Auth("Token has been invalidated".into()));
+  }
+
+  Ok(claims)
+}
+
+// Claims equivalentes a los de un access token del dueño de la clave, limitados a sus
+// scopes. Las API keys no dependen de la contraseña: siguen valiendo tras cambiarla.
+async fn claims_from_api_key(state: &AppState, key: &str) -> Result<Claims, AppError> {
+  let (user, api_key) = state
+      .auth_service
+      .authenticate_api_key(key)
+      .await
+      .map_err(|e| match e {
+          AppError::NotFound(_) => AppError::Auth("Invalid API key".into()),
+          other => other,
+      })?;
+
+  let now = Utc::now().timestamp() as usize;
+  Ok(Claims {
+      sub: user.id.to_string(),
+      exp: api_key.expires_at.map_or(usize::MAX, |expires_at| expires_at.timestamp() as usize),
+      iat: now,
+      nbf: now,
+      jti: api_key.id.to_string(),
+      iss: None,
+      aud: None,
+      role: Some(user.role),
+      email: Some(user.email),
+      ver: Some(user.token_version),
+      token_use: Some(API_KEY_TOKEN_USE.to_string()),
+      scope: Some(api_key.scopes.join(" ")),
+  })
+}
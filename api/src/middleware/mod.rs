@@ -1,2 +1,4 @@
 pub mod auth;
-pub mod logging;
\ No newline at end of file
+pub mod logging;
+pub mod rate_limit;
+pub mod rbac;
\ No newline at end of file
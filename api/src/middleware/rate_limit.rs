@@ -0,0 +1,224 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use common::{config::AppConfig, error::AppError, jwt::parse_duration, utils::hash_token};
+use repository::{InMemoryRateLimitStore, RateLimitDecision, RateLimitPolicy, RateLimitStore};
+use serde_json::json;
+use std::{net::SocketAddr, sync::Arc};
+use tracing::{error, warn};
+
+use super::auth::api_key_from_headers;
+use crate::AppState;
+
+/// Tamaño máximo del cuerpo que se lee para extraer el email
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Qué identifica al cliente en una regla.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// IP del cliente (o la primera de `X-Forwarded-For` si se confía en el proxy)
+    Ip,
+    /// Campo `email` del cuerpo JSON
+    Email,
+    /// API key de `Authorization: ApiKey` o de la cabecera `X-API-Key`
+    ApiKey,
+}
+
+impl RateLimitKey {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitKey::Ip => "ip",
+            RateLimitKey::Email => "email",
+            RateLimitKey::ApiKey => "api_key",
+        }
+    }
+}
+
+/// Límite aplicado a una ruta concreta.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitRule {
+    pub route: String,
+    pub key: RateLimitKey,
+    pub policy: RateLimitPolicy,
+}
+
+impl RateLimitRule {
+    /// Interpreta una regla con el formato `ruta=clave:política:límite/periodo`,
+    /// por ejemplo `/api/auth/login=ip:sliding_window:20/1m`.
+    pub fn parse(entry: &str) -> Result<Self, AppError> {
+        let invalid = |reason: &str| AppError::Internal(format!("Invalid entry in RATE_LIMITS '{}': {}", entry, reason));
+
+        let (route, spec) = entry.split_once('=').ok_or_else(|| invalid("expected route=key:policy:limit/period"))?;
+        let mut parts = spec.split(':').map(str::trim);
+        let (Some(key), Some(policy), Some(rate), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid("expected key:policy:limit/period"));
+        };
+
+        let key = match key {
+            "ip" => RateLimitKey::Ip,
+            "email" => RateLimitKey::Email,
+            "api_key" => RateLimitKey::ApiKey,
+            _ => return Err(invalid("key must be ip, email or api_key")),
+        };
+        let (limit, period) = rate.split_once('/').ok_or_else(|| invalid("expected limit/period"))?;
+        let limit = limit.parse::<u32>().ok().filter(|limit| *limit > 0).ok_or_else(|| invalid("limit must be a positive integer"))?;
+        let period = parse_duration(period)
+            .ok()
+            .filter(|period| period.num_milliseconds() > 0)
+            .ok_or_else(|| invalid("invalid period"))?;
+        let policy = match policy {
+            "token_bucket" => RateLimitPolicy::TokenBucket { capacity: limit, period },
+            "sliding_window" => RateLimitPolicy::SlidingWindow { limit, window: period },
+            _ => return Err(invalid("policy must be token_bucket or sliding_window")),
+        };
+
+        Ok(Self { route: route.trim().to_string(), key, policy })
+    }
+
+    /// Interpreta una lista de reglas separadas por comas.
+    pub fn parse_list(rules: &str) -> Result<Vec<Self>, AppError> {
+        rules
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+}
+
+/// Reglas de límite de peticiones y almacén donde se lleva la cuenta.
+pub struct RateLimiter {
+    rules: Vec<RateLimitRule>,
+    store: Arc<dyn RateLimitStore>,
+    /// Tomar la IP de `X-Forwarded-For`; sólo es seguro detrás de un proxy que la reescriba
+    trust_proxy: bool,
+}
+
+impl RateLimiter {
+    pub fn new(rules: Vec<RateLimitRule>, store: Arc<dyn RateLimitStore>) -> Self {
+        Self { rules, store, trust_proxy: false }
+    }
+
+    /// Limitador sin reglas: deja pasar todas las peticiones.
+    pub fn disabled() -> Self {
+        Self::new(Vec::new(), Arc::new(InMemoryRateLimitStore::new()))
+    }
+
+    pub fn from_config(config: &AppConfig, store: Arc<dyn RateLimitStore>) -> Result<Self, AppError> {
+        let rules = RateLimitRule::parse_list(&config.rate_limits)?;
+        Ok(Self::new(rules, store).trust_proxy(config.rate_limit_trust_proxy))
+    }
+
+    pub fn trust_proxy(mut self, trust_proxy: bool) -> Self {
+        self.trust_proxy = trust_proxy;
+        self
+    }
+
+    pub fn rules(&self) -> &[RateLimitRule] {
+        &self.rules
+    }
+
+    fn client_ip(&self, request: &Request) -> Option<String> {
+        if self.trust_proxy {
+            let forwarded = request
+                .headers()
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(',').next())
+                .map(str::trim)
+                .filter(|ip| !ip.is_empty());
+            if let Some(ip) = forwarded {
+                return Some(ip.to_string());
+            }
+        }
+
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string())
+    }
+}
+
+/// Aplica las reglas de `AppState::rate_limiter` que coinciden con la ruta.
+///
+/// Si alguna se agota, responde 429 con `Retry-After`; en cualquier caso añade las
+/// cabeceras `RateLimit-Limit`, `RateLimit-Remaining` y `RateLimit-Reset` de la regla más
+/// restrictiva. Las reglas cuya clave no aparece en la petición (sin email en el cuerpo,
+/// sin API key) no se aplican. Si el almacén falla, la petición se deja pasar.
+pub async fn rate_limit_middleware(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let limiter = &state.rate_limiter;
+    let path = request.uri().path().to_string();
+    let rules: Vec<&RateLimitRule> = limiter.rules.iter().filter(|rule| rule.route == path).collect();
+    if rules.is_empty() {
+        return next.run(request).await;
+    }
+
+    // Leer el cuerpo sólo si alguna regla necesita el email, y reconstruir la petición
+    let (request, email) = if rules.iter().any(|rule| rule.key == RateLimitKey::Email) {
+        let (parts, body) = request.into_parts();
+        let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(_) => return AppError::Validation("Request body too large".into()).into_response(),
+        };
+        let email = serde_json::from_slice::<serde_json::Value>(&bytes)
+            .ok()
+            .and_then(|body| body.get("email").and_then(|email| email.as_str()).map(|email| email.trim().to_lowercase()))
+            .filter(|email| !email.is_empty());
+        (Request::from_parts(parts, Body::from(bytes)), email)
+    } else {
+        (request, None)
+    };
+
+    let mut decisions = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let client = match rule.key {
+            RateLimitKey::Ip => limiter.client_ip(&request),
+            RateLimitKey::Email => email.clone(),
+            RateLimitKey::ApiKey => api_key_from_headers(request.headers()).map(hash_token),
+        };
+        let Some(client) = client else { continue };
+
+        let key = format!("{}:{}:{}", rule.route, rule.key.as_str(), client);
+        match limiter.store.check(&key, &rule.policy).await {
+            Ok(decision) => decisions.push(decision),
+            Err(e) => error!("Error al comprobar el límite de peticiones de {}: {}", rule.route, e),
+        }
+    }
+
+    let Some(reported) = decisions.iter().min_by_key(|decision| (decision.allowed, decision.remaining)).cloned() else {
+        return next.run(request).await;
+    };
+
+    if let Some(retry_after) = decisions.iter().filter_map(|decision| decision.retry_after).max() {
+        warn!(path = %path, retry_after, "Límite de peticiones superado");
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(json!({ "error": "Too many requests", "retry_after": retry_after })),
+        )
+            .into_response();
+        insert_header(response.headers_mut(), "retry-after", retry_after);
+        insert_rate_limit_headers(response.headers_mut(), &reported);
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    insert_rate_limit_headers(response.headers_mut(), &reported);
+    response
+}
+
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    insert_header(headers, "ratelimit-limit", decision.limit);
+    insert_header(headers, "ratelimit-remaining", decision.remaining);
+    insert_header(headers, "ratelimit-reset", decision.reset_after);
+}
+
+fn insert_header(headers: &mut HeaderMap, name: &'static str, value: impl ToString) {
+    if let Ok(value) = HeaderValue::from_str(&value.to_string()) {
+        headers.insert(HeaderName::from_static(name), value);
+    }
+}
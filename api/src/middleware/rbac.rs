@@ -0,0 +1,102 @@
+use axum::{
+  extract::Request,
+  response::{IntoResponse, Response},
+};
+use common::{error::AppError, jwt::Claims};
+use std::{
+  convert::Infallible,
+  future::Future,
+  pin::Pin,
+  sync::Arc,
+  task::{Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::warn;
+
+use super::auth::AuthUser;
+
+/// Exige que el usuario autenticado tenga exactamente este rol.
+pub fn require_role(role: &str) -> RoleLayer {
+  require_any_role(&[role])
+}
+
+/// Exige que el usuario autenticado tenga alguno de estos roles.
+///
+/// Lee los claims que deja `auth_middleware`, por lo que ese middleware debe
+/// aplicarse por fuera (con `route_layer`, después de este):
+///
+/// ```ignore
+/// Router::new()
+///     .route("/users/:id", get(handler))
+///     .route_layer(require_role("admin"))
+///     .route_layer(middleware::from_fn_with_state(state, auth_middleware));
+/// ```
+pub fn require_any_role<R: AsRef<str>>(roles: &[R]) -> RoleLayer {
+  RoleLayer {
+      roles: roles.iter().map(|role| role.as_ref().to_string()).collect::<Vec<_>>().into(),
+  }
+}
+
+#[derive(Clone)]
+pub struct RoleLayer {
+  roles: Arc<[String]>,
+}
+
+impl<S> Layer<S> for RoleLayer {
+  type Service = RoleGuard<S>;
+
+  fn layer(&self, inner: S) -> Self::Service {
+      RoleGuard {
+          inner,
+          roles: self.roles.clone(),
+      }
+  }
+}
+
+#[derive(Clone)]
+pub struct RoleGuard<S> {
+  inner: S,
+  roles: Arc<[String]>,
+}
+
+impl<S> RoleGuard<S> {
+  fn authorize(&self, request: &Request) -> Result<(), AppError> {
+      let claims = request
+          .extensions()
+          .get::<Claims>()
+          .cloned()
+          .ok_or_else(|| AppError::Auth("Missing authentication".into()))?;
+      let user = AuthUser::from_claims(claims)?;
+
+      if user.has_any_role(&self.roles) {
+          Ok(())
+      } else {
+          warn!(user_id = %user.id, role = %user.role, "Acceso denegado por rol");
+          Err(AppError::Forbidden(format!(
+              "Requires one of the roles: {}",
+              self.roles.join(", ")
+          )))
+      }
+  }
+}
+
+impl<S> Service<Request> for RoleGuard<S>
+where
+  S: Service<Request, Response = Response, Error = Infallible>,
+  S::Future: Send + 'static,
+{
+  type Response = Response;
+  type Error = Infallible;
+  type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+  fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+      self.inner.poll_ready(cx)
+  }
+
+  fn call(&mut self, request: Request) -> Self::Future {
+      match self.authorize(&request) {
+          Ok(()) => Box::pin(self.inner.call(request)),
+          Err(error) => Box::pin(async move { Ok(error.into_response()) }),
+      }
+  }
+}
@@ -0,0 +1,78 @@
+use std::{collections::HashSet, sync::Arc};
+
+use common::error::AppError;
+use repository::PermissionRepository;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::{middleware::auth::{AuthUser, Principal}, AppState};
+
+/// Caché de los permisos resueltos del usuario. `auth_middleware` crea una por
+/// petición, de modo que la base de datos se consulta como mucho una vez por petición.
+#[derive(Debug, Clone, Default)]
+pub struct PermissionCache(Arc<OnceCell<Arc<HashSet<String>>>>);
+
+impl PermissionCache {
+  pub async fn get_or_load(
+      &self,
+      repository: &dyn PermissionRepository,
+      user_id: &Uuid,
+  ) -> Result<Arc<HashSet<String>>, AppError> {
+      self.0
+          .get_or_try_init(|| async {
+              let permissions = repository
+                  .find_permissions_for_user(user_id)
+                  .await
+                  .map_err(|e| AppError::Database(e.to_string()))?;
+              Ok(Arc::new(permissions.into_iter().collect()))
+          })
+          .await
+          .cloned()
+  }
+}
+
+impl AppState {
+  /// Permisos efectivos del usuario autenticado. Con una API key, sólo los de sus scopes.
+  pub async fn permissions_for(&self, user: &AuthUser) -> Result<Arc<HashSet<String>>, AppError> {
+      let permissions = user.permissions
+          .get_or_load(self.permission_repository.as_ref(), &user.id)
+          .await?;
+
+      match &user.scopes {
+          Some(scopes) => Ok(Arc::new(permissions.intersection(scopes).cloned().collect())),
+          None => Ok(permissions),
+      }
+  }
+
+  /// Comprueba si el usuario tiene un permiso, p. ej. `"users:write"`.
+  pub async fn has_permission(&self, user: &AuthUser, permission: &str) -> Result<bool, AppError> {
+      Ok(self.permissions_for(user).await?.contains(permission))
+  }
+
+  /// Igual que `has_permission`, pero responde 403 si falta el permiso.
+  pub async fn require_permission(&self, user: &AuthUser, permission: &str) -> Result<(), AppError> {
+      if self.has_permission(user, permission).await? {
+          Ok(())
+      } else {
+          Err(AppError::Forbidden(format!("Missing permission: {}", permission)))
+      }
+  }
+
+  /// Como `has_permission`, para usuarios o servicios: un servicio tiene exactamente los
+  /// permisos de su `scope`.
+  pub async fn principal_has_permission(&self, principal: &Principal, permission: &str) -> Result<bool, AppError> {
+      match principal {
+          Principal::User(user) => self.has_permission(user, permission).await,
+          Principal::Service(service) => Ok(service.has_scope(permission)),
+      }
+  }
+
+  /// Igual que `principal_has_permission`, pero responde 403 si falta el permiso.
+  pub async fn require_principal_permission(&self, principal: &Principal, permission: &str) -> Result<(), AppError> {
+      if self.principal_has_permission(principal, permission).await? {
+          Ok(())
+      } else {
+          Err(AppError::Forbidden(format!("Missing permission: {}", permission)))
+      }
+  }
+}
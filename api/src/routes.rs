@@ -1,33 +1,103 @@
 use axum::{
     middleware,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use std::sync::Arc;
 use crate::{
     handlers::{
-        auth::{login_handler, register_handler}, 
-        me::me_handler,
+        admin::{create_oauth_client_handler, get_user_handler, list_oauth_clients_handler, unlock_user_handler},
+        api_keys::{create_api_key_handler, list_api_keys_handler, revoke_api_key_handler},
+        federation::{federated_callback_handler, federated_login_handler},
+        auth::{
+            forgot_password_handler, login_handler, logout_handler, mfa_verify_handler, refresh_handler,
+            register_handler, resend_verification_handler, reset_password_handler, telegram_webapp_handler, verify_email_handler,
+        },
+        jwks::jwks_handler,
+        me::{change_password_handler, me_handler},
+        mfa::{confirm_totp_enrollment_handler, disable_totp_handler, start_totp_enrollment_handler},
+        oauth::{
+            authorization_request_handler, authorize_handler, consent_handler, device_authorization_handler,
+            device_consent_handler, device_request_handler, openid_configuration_handler, token_handler, userinfo_handler,
+        },
+        telegram::{link_telegram_handler, unlink_telegram_handler},
+        webauthn::{
+            passkey_login_handler, passkey_login_options_handler, passkey_registration_handler,
+            passkey_registration_options_handler,
+        },
     },
     middleware::{
         auth::auth_middleware,
         logging::logging_middleware,
+        rate_limit::rate_limit_middleware,
+        rbac::require_role,
     },
     AppState, 
 };
 
 pub fn create_router(app_state: Arc<AppState>) -> Router {
+    let session_routes = Router::new()
+        .route("/logout", post(logout_handler))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), auth_middleware));
+
     let auth_routes = Router::new()
         .route("/register", post(register_handler))
-        .route("/login", post(login_handler));
+        .route("/login", post(login_handler))
+        .route("/refresh", post(refresh_handler))
+        .route("/verify-email", post(verify_email_handler))
+        .route("/resend-verification", post(resend_verification_handler))
+        .route("/forgot-password", post(forgot_password_handler))
+        .route("/reset-password", post(reset_password_handler))
+        .route("/telegram/webapp", post(telegram_webapp_handler))
+        .route("/mfa/verify", post(mfa_verify_handler))
+        .route("/webauthn/login/options", post(passkey_login_options_handler))
+        .route("/webauthn/login", post(passkey_login_handler))
+        .route("/oidc/login", get(federated_login_handler))
+        .route("/oidc/callback", post(federated_callback_handler))
+        .merge(session_routes);
 
     let protected_routes = Router::new()
         .route("/me", get(me_handler))
+        .route("/me/password", put(change_password_handler))
+        .route("/me/telegram", post(link_telegram_handler).delete(unlink_telegram_handler))
+        .route("/me/mfa/totp", post(start_totp_enrollment_handler).delete(disable_totp_handler))
+        .route("/me/mfa/totp/confirm", post(confirm_totp_enrollment_handler))
+        .route("/me/webauthn/register/options", post(passkey_registration_options_handler))
+        .route("/me/webauthn/register", post(passkey_registration_handler))
+        .route("/me/api-keys", post(create_api_key_handler).get(list_api_keys_handler))
+        .route("/me/api-keys/:id", delete(revoke_api_key_handler))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), auth_middleware));
+
+    // El guard de rol va antes que auth_middleware para que éste se ejecute primero
+    let admin_routes = Router::new()
+        .route("/users/:id", get(get_user_handler))
+        .route("/users/:id/unlock", post(unlock_user_handler))
+        .route("/oauth/clients", post(create_oauth_client_handler).get(list_oauth_clients_handler))
+        .route_layer(require_role("admin"))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), auth_middleware));
+
+    // Protocolo OAuth 2.1 / OpenID Connect: lo consumen los clientes, no el frontend
+    let oauth_routes = Router::new()
+        .route("/authorize", get(authorize_handler))
+        .route("/token", post(token_handler))
+        .route("/device_authorization", post(device_authorization_handler))
+        .route("/userinfo", get(userinfo_handler).post(userinfo_handler));
+
+    // Pantallas de consentimiento y de aprobación de dispositivos del frontend, con la sesión del usuario
+    let consent_routes = Router::new()
+        .route("/authorizations/:id", get(authorization_request_handler).post(consent_handler))
+        .route("/device/:user_code", get(device_request_handler).post(device_consent_handler))
         .route_layer(middleware::from_fn_with_state(app_state.clone(), auth_middleware));
 
     Router::new()
+        .route("/.well-known/jwks.json", get(jwks_handler))
+        .route("/.well-known/openid-configuration", get(openid_configuration_handler))
+        .nest("/oauth", oauth_routes)
+        .nest("/api/oauth", consent_routes)
         .nest("/api/auth", auth_routes)
         .nest("/api/users", protected_routes)
+        .nest("/api/admin", admin_routes)
+        .layer(middleware::from_fn_with_state(app_state.clone(), rate_limit_middleware))
         .layer(middleware::from_fn(logging_middleware))
         .with_state(app_state)
 }
@@ -1,17 +1,36 @@
 use std::sync::Arc;
-use crate::handlers::auth::AuthService;
+use crate::{handlers::auth::AuthService, middleware::rate_limit::RateLimiter};
+use common::jwt::JwtConfig;
+use repository::{PermissionRepository, RevocationStore};
 
 // Definimos AppState sin genéricos para simplificar
 pub struct AppState {
     pub auth_service: Arc<dyn AuthService>,
-    pub jwt_secret: String,
+    pub jwt_config: Arc<JwtConfig>,
+    pub revocation_store: Arc<dyn RevocationStore>,
+    pub permission_repository: Arc<dyn PermissionRepository>,
+    pub rate_limiter: Arc<RateLimiter>,
 }
 
 impl AppState {
-    pub fn new(auth_service: Arc<dyn AuthService>, jwt_secret: String) -> Self {
+    pub fn new(
+        auth_service: Arc<dyn AuthService>,
+        jwt_config: Arc<JwtConfig>,
+        revocation_store: Arc<dyn RevocationStore>,
+        permission_repository: Arc<dyn PermissionRepository>,
+    ) -> Self {
         Self {
             auth_service,
-            jwt_secret,
+            jwt_config,
+            revocation_store,
+            permission_repository,
+            // Sin reglas hasta que se configure con `with_rate_limiter`
+            rate_limiter: Arc::new(RateLimiter::disabled()),
         }
     }
+
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Arc::new(rate_limiter);
+        self
+    }
 } 
\ No newline at end of file
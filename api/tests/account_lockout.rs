@@ -0,0 +1,38 @@
+mod support;
+
+use std::sync::Arc;
+
+use api::routes::create_router;
+use axum::http::StatusCode;
+use serde_json::json;
+use support::{
+    app_state_with, app_state_with_permissions, json_body, send_authorized_post, send_post, token_with_role,
+    StubAuthService, StubPermissionRepository,
+};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn locked_account_answers_423_with_retry_after() {
+    let auth_service = StubAuthService { locked_retry_after: Some(120), ..Default::default() };
+    let state = app_state_with(auth_service, Arc::new(StubPermissionRepository::default()));
+
+    let body = json!({ "email": "ana@example.com", "password": "secret123" });
+    let response = send_post(create_router(state), "/api/auth/login", body).await;
+
+    assert_eq!(response.status(), StatusCode::LOCKED);
+    assert_eq!(json_body(response).await["retry_after"], 120);
+}
+
+#[tokio::test]
+async fn unlocking_requires_users_write_permission() {
+    let uri = format!("/api/admin/users/{}/unlock", Uuid::new_v4());
+    let token = token_with_role("admin");
+
+    let read_only = app_state_with_permissions(Arc::new(StubPermissionRepository::with_permissions(&["users:read"])));
+    let response = send_authorized_post(create_router(read_only), &uri, &token).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let writer = app_state_with_permissions(Arc::new(StubPermissionRepository::with_permissions(&["users:write"])));
+    let response = send_authorized_post(create_router(writer), &uri, &token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
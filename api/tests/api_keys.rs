@@ -0,0 +1,209 @@
+mod support;
+
+use std::sync::Arc;
+
+use api::{middleware::auth::{auth_middleware, AuthUser}, routes::create_router, AppState};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Request, StatusCode},
+    middleware,
+    response::Response,
+    routing::get,
+    Router,
+};
+use common::error::AppError;
+use serde_json::json;
+use tower::ServiceExt;
+
+use support::{
+    app_state_with, json_body, token_with_role, StubAuthService, StubPermissionRepository, TEST_API_KEY,
+    TEST_API_KEY_USER_ID,
+};
+
+fn state_with_scopes(scopes: &[&str], permissions: &[&str]) -> Arc<AppState> {
+    let auth_service = StubAuthService {
+        api_key_scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+        ..Default::default()
+    };
+    app_state_with(auth_service, Arc::new(StubPermissionRepository::with_permissions(permissions)))
+}
+
+async fn send(router: Router, method: &str, uri: &str, header: (&str, &str), body: Option<serde_json::Value>) -> Response {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header.0, header.1)
+        .header("Content-Type", "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+    router.oneshot(request).await.unwrap()
+}
+
+async fn check_permissions(State(state): State<Arc<AppState>>, user: AuthUser) -> Result<String, AppError> {
+    let can_read = state.has_permission(&user, "users:read").await?;
+    let can_write = state.has_permission(&user, "users:write").await?;
+    Ok(format!("{} {}", can_read, can_write))
+}
+
+#[tokio::test]
+async fn api_key_authenticates_with_either_header() {
+    let authorization = format!("ApiKey {}", TEST_API_KEY);
+    for header in [("Authorization", authorization.as_str()), ("X-API-Key", TEST_API_KEY)] {
+        let router = create_router(state_with_scopes(&[], &[]));
+        let response = send(router, "GET", "/api/users/me", header, None).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{}", header.0);
+        assert_eq!(json_body(response).await["user"]["id"], TEST_API_KEY_USER_ID.to_string());
+    }
+}
+
+#[tokio::test]
+async fn unknown_api_key_is_rejected() {
+    let router = create_router(state_with_scopes(&[], &[]));
+    let response = send(router, "GET", "/api/users/me", ("X-API-Key", "ak_0123456789ab_wrong"), None).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn scopes_limit_the_owner_permissions() {
+    let state = state_with_scopes(&["users:read"], &["users:read", "users:write"]);
+    let router = Router::new()
+        .route("/check", get(check_permissions))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware))
+        .with_state(state);
+
+    let response = send(router.clone(), "GET", "/check", ("X-API-Key", TEST_API_KEY), None).await;
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..], b"true false");
+
+    // Un access token conserva todos los permisos del usuario
+    let authorization = format!("Bearer {}", token_with_role("user"));
+    let response = send(router, "GET", "/check", ("Authorization", &authorization), None).await;
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..], b"true true");
+}
+
+#[tokio::test]
+async fn keys_are_created_only_from_a_session_and_within_the_user_permissions() {
+    let authorization = format!("Bearer {}", token_with_role("user"));
+    let router = || create_router(state_with_scopes(&["users:read"], &["users:read"]));
+
+    let response = send(
+        router(),
+        "POST",
+        "/api/users/me/api-keys",
+        ("Authorization", &authorization),
+        Some(json!({"name": "cron", "scopes": ["users:read"], "expires_in": "90d"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(response).await["api_key"]["key"], TEST_API_KEY);
+
+    let response = send(
+        router(),
+        "POST",
+        "/api/users/me/api-keys",
+        ("Authorization", &authorization),
+        Some(json!({"name": "cron", "scopes": ["users:write"]})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = send(
+        router(),
+        "POST",
+        "/api/users/me/api-keys",
+        ("X-API-Key", TEST_API_KEY),
+        Some(json!({"name": "cron", "scopes": []})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn api_key_cannot_log_out() {
+    let router = create_router(state_with_scopes(&[], &[]));
+    let response = send(router, "POST", "/api/auth/logout", ("X-API-Key", TEST_API_KEY), None).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+// Las rutas que gestionan credenciales o la cuenta sólo se pueden usar con la sesión del usuario
+async fn api_key_is_forbidden(method: &str, uri: &str) {
+    let router = create_router(state_with_scopes(&[], &[]));
+    let response = send(router, method, uri, ("X-API-Key", TEST_API_KEY), Some(json!({}))).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+}
+
+#[tokio::test]
+async fn api_key_cannot_start_passkey_registration() {
+    api_key_is_forbidden("POST", "/api/users/me/webauthn/register/options").await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_register_a_passkey() {
+    api_key_is_forbidden("POST", "/api/users/me/webauthn/register").await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_start_totp_enrollment() {
+    api_key_is_forbidden("POST", "/api/users/me/mfa/totp").await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_confirm_totp_enrollment() {
+    api_key_is_forbidden("POST", "/api/users/me/mfa/totp/confirm").await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_disable_totp() {
+    api_key_is_forbidden("DELETE", "/api/users/me/mfa/totp").await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_link_telegram() {
+    api_key_is_forbidden("POST", "/api/users/me/telegram").await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_unlink_telegram() {
+    api_key_is_forbidden("DELETE", "/api/users/me/telegram").await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_change_the_password() {
+    api_key_is_forbidden("PUT", "/api/users/me/password").await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_list_api_keys() {
+    api_key_is_forbidden("GET", "/api/users/me/api-keys").await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_revoke_api_keys() {
+    api_key_is_forbidden("DELETE", &format!("/api/users/me/api-keys/{}", uuid::Uuid::new_v4())).await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_grant_oauth_consent() {
+    api_key_is_forbidden("POST", &format!("/api/oauth/authorizations/{}", uuid::Uuid::new_v4())).await;
+}
+
+#[tokio::test]
+async fn api_key_cannot_approve_a_device() {
+    api_key_is_forbidden("POST", "/api/oauth/device/ABCD-EFGH").await;
+}
+
+#[tokio::test]
+async fn session_can_still_manage_account_credentials() {
+    let authorization = format!("Bearer {}", token_with_role("user"));
+    let router = create_router(state_with_scopes(&[], &[]));
+    let response = send(router, "POST", "/api/users/me/mfa/totp", ("Authorization", &authorization), None).await;
+
+    // El stub no implementa la inscripción: basta con que la petición llegue al handler
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
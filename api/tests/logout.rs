@@ -0,0 +1,36 @@
+mod support;
+
+use api::routes::create_router;
+use axum::http::StatusCode;
+use chrono::Utc;
+use common::jwt::{sign_jwt, Claims};
+use support::{app_state, jwt_config, send_authorized_post, send_get};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn token_revoked_within_the_leeway_stays_revoked() {
+    // Caducado hace 30 s: `verify_jwt` lo sigue aceptando con el margen de 60 s
+    let now = Utc::now().timestamp();
+    let claims = Claims {
+        sub: Uuid::new_v4().to_string(),
+        exp: (now - 30) as usize,
+        iat: (now - 330) as usize,
+        nbf: (now - 330) as usize,
+        jti: Uuid::new_v4().to_string(),
+        iss: None,
+        aud: None,
+        role: Some("user".into()),
+        email: Some("user@example.com".into()),
+        ver: None,
+        token_use: None,
+        scope: None,
+    };
+    let token = sign_jwt(&claims, &jwt_config()).unwrap();
+    let router = create_router(app_state());
+    assert_eq!(send_get(router.clone(), "/api/users/me", Some(&token)).await.status(), StatusCode::OK);
+
+    let response = send_authorized_post(router.clone(), "/api/auth/logout", &token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(send_get(router, "/api/users/me", Some(&token)).await.status(), StatusCode::UNAUTHORIZED);
+}
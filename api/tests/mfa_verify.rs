@@ -0,0 +1,103 @@
+mod support;
+
+use std::sync::Arc;
+
+use api::{routes::create_router, AppState};
+use axum::http::StatusCode;
+use chrono::Utc;
+use serde_json::json;
+use support::{
+    app_state_with, json_body, mfa_token_for, send_get, send_post, signed_init_data, token_with_version,
+    StubAuthService, StubPermissionRepository, TEST_BOT_TOKEN, TEST_MFA_CODE,
+};
+use uuid::Uuid;
+
+fn mfa_state() -> Arc<AppState> {
+    let auth_service = StubAuthService { mfa_enabled: true, ..Default::default() };
+    app_state_with(auth_service, Arc::new(StubPermissionRepository::default()))
+}
+
+#[tokio::test]
+async fn login_of_user_with_mfa_returns_a_challenge_instead_of_a_session() {
+    let user = r#"{"id":42,"first_name":"Ada"}"#;
+    let init_data = signed_init_data(&[("auth_date", &Utc::now().timestamp().to_string()), ("user", user)], TEST_BOT_TOKEN);
+
+    let response = send_post(create_router(mfa_state()), "/api/auth/telegram/webapp", json!({ "init_data": init_data })).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["mfa_required"], true);
+    assert!(body["mfa_token"].is_string());
+    assert!(body.get("token").is_none());
+}
+
+#[tokio::test]
+async fn mfa_token_is_exchanged_once_for_a_full_session() {
+    let state = mfa_state();
+    let mfa_token = mfa_token_for(&Uuid::new_v4(), Some(0));
+    let request = json!({ "mfa_token": mfa_token, "code": TEST_MFA_CODE });
+
+    let response = send_post(create_router(state.clone()), "/api/auth/mfa/verify", request.clone()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    let token = body["token"].as_str().unwrap().to_string();
+    assert!(body["refresh_token"].is_string());
+    assert_eq!(send_get(create_router(state.clone()), "/api/users/me", Some(&token)).await.status(), StatusCode::OK);
+
+    // El reto ya se canjeó
+    let replay = send_post(create_router(state), "/api/auth/mfa/verify", request).await;
+    assert_eq!(replay.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn wrong_code_is_rejected() {
+    let mfa_token = mfa_token_for(&Uuid::new_v4(), Some(0));
+
+    let response = send_post(
+        create_router(mfa_state()),
+        "/api/auth/mfa/verify",
+        json!({ "mfa_token": mfa_token, "code": "000000" }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn mfa_pending_token_does_not_grant_api_access() {
+    let mfa_token = mfa_token_for(&Uuid::new_v4(), Some(0));
+
+    let response = send_get(create_router(mfa_state()), "/api/users/me", Some(&mfa_token)).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn access_token_cannot_be_used_as_mfa_token() {
+    let access_token = token_with_version("user", Some(0));
+
+    let response = send_post(
+        create_router(mfa_state()),
+        "/api/auth/mfa/verify",
+        json!({ "mfa_token": access_token, "code": TEST_MFA_CODE }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn challenge_from_before_a_password_change_is_rejected() {
+    let auth_service = StubAuthService { mfa_enabled: true, token_version: 1, ..Default::default() };
+    let state = app_state_with(auth_service, Arc::new(StubPermissionRepository::default()));
+    let mfa_token = mfa_token_for(&Uuid::new_v4(), Some(0));
+
+    let response = send_post(
+        create_router(state),
+        "/api/auth/mfa/verify",
+        json!({ "mfa_token": mfa_token, "code": TEST_MFA_CODE }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,59 @@
+mod support;
+
+use std::sync::Arc;
+
+use api::{middleware::auth::{auth_middleware, AuthUser}, routes::create_router, AppState};
+use axum::{extract::State, http::StatusCode, middleware, routing::get, Router};
+use common::error::AppError;
+use uuid::Uuid;
+
+use support::{app_state_with_permissions, send_get, token_with_role, StubPermissionRepository};
+
+async fn check_twice(
+    State(state): State<Arc<AppState>>,
+    user: AuthUser,
+) -> Result<String, AppError> {
+    let can_read = state.has_permission(&user, "users:read").await?;
+    let can_write = state.has_permission(&user, "users:write").await?;
+    Ok(format!("{} {}", can_read, can_write))
+}
+
+fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/check", get(check_twice))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware))
+        .with_state(state)
+}
+
+#[tokio::test]
+async fn has_permission_reports_granted_and_missing_permissions() {
+    let permissions = Arc::new(StubPermissionRepository::with_permissions(&["users:read"]));
+    let response = send_get(router(app_state_with_permissions(permissions)), "/check", Some(&token_with_role("user"))).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..], b"true false");
+}
+
+#[tokio::test]
+async fn permissions_are_resolved_once_per_request() {
+    let permissions = Arc::new(StubPermissionRepository::with_permissions(&["users:read"]));
+    let state = app_state_with_permissions(permissions.clone());
+    let token = token_with_role("user");
+
+    send_get(router(state.clone()), "/check", Some(&token)).await;
+    assert_eq!(permissions.lookups(), 1);
+
+    send_get(router(state), "/check", Some(&token)).await;
+    assert_eq!(permissions.lookups(), 2);
+}
+
+#[tokio::test]
+async fn admin_route_requires_users_read_permission() {
+    let uri = format!("/api/admin/users/{}", Uuid::new_v4());
+    let permissions = Arc::new(StubPermissionRepository::with_permissions(&[]));
+
+    let response = send_get(create_router(app_state_with_permissions(permissions)), &uri, Some(&token_with_role("admin"))).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,153 @@
+mod support;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use api::{
+    middleware::rate_limit::{RateLimitKey, RateLimitRule, RateLimiter},
+    routes::create_router,
+};
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, Response, StatusCode},
+    Router,
+};
+use chrono::{Duration, Utc};
+use repository::{InMemoryRateLimitStore, RateLimitPolicy};
+use serde_json::json;
+use support::{app_state, json_body, send_get, send_post};
+use tower::ServiceExt;
+
+fn router_with(rules: &str) -> Router {
+    let state = Arc::try_unwrap(app_state()).ok().unwrap();
+    let limiter = RateLimiter::new(RateLimitRule::parse_list(rules).unwrap(), Arc::new(InMemoryRateLimitStore::new()));
+    create_router(Arc::new(state.with_rate_limiter(limiter)))
+}
+
+async fn login_from(router: Router, ip: [u8; 4], email: &str) -> Response<Body> {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri("/api/auth/login")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "email": email, "password": "secret123" }).to_string()))
+        .unwrap();
+    request.extensions_mut().insert(ConnectInfo(SocketAddr::from((ip, 4000))));
+    router.oneshot(request).await.unwrap()
+}
+
+fn header(response: &Response<Body>, name: &str) -> Option<String> {
+    response.headers().get(name).map(|value| value.to_str().unwrap().to_string())
+}
+
+#[test]
+fn parses_rules_from_config() {
+    let rules = RateLimitRule::parse_list("/api/auth/login=ip:sliding_window:20/1m, /api/keys=api_key:token_bucket:5/1h").unwrap();
+
+    assert_eq!(rules[0].route, "/api/auth/login");
+    assert_eq!(rules[0].key, RateLimitKey::Ip);
+    assert_eq!(rules[0].policy, RateLimitPolicy::SlidingWindow { limit: 20, window: Duration::minutes(1) });
+    assert_eq!(rules[1].key, RateLimitKey::ApiKey);
+    assert_eq!(rules[1].policy, RateLimitPolicy::TokenBucket { capacity: 5, period: Duration::hours(1) });
+
+    assert!(RateLimitRule::parse("/api/auth/login=user:sliding_window:20/1m").is_err());
+    assert!(RateLimitRule::parse("/api/auth/login=ip:fixed_window:20/1m").is_err());
+    assert!(RateLimitRule::parse("/api/auth/login=ip:token_bucket:0/1m").is_err());
+    assert!(RateLimitRule::parse("/api/auth/login").is_err());
+}
+
+#[test]
+fn token_bucket_refills_over_time() {
+    let policy = RateLimitPolicy::TokenBucket { capacity: 2, period: Duration::seconds(10) };
+    let now = Utc::now();
+
+    let (state, first) = policy.apply(None, now);
+    let (state, second) = policy.apply(Some(&state), now);
+    let (state, third) = policy.apply(Some(&state), now);
+    assert!(first.allowed && second.allowed && !third.allowed);
+    assert_eq!(second.remaining, 0);
+    // Una ficha cada 5 s
+    assert_eq!(third.retry_after, Some(5));
+
+    let (_, after_refill) = policy.apply(Some(&state), now + Duration::seconds(5));
+    assert!(after_refill.allowed);
+}
+
+#[test]
+fn sliding_window_weighs_the_previous_window() {
+    let policy = RateLimitPolicy::SlidingWindow { limit: 4, window: Duration::seconds(60) };
+    let start = Utc::now();
+
+    let mut state = None;
+    for _ in 0..4 {
+        let (next, decision) = policy.apply(state.as_ref(), start);
+        assert!(decision.allowed);
+        state = Some(next);
+    }
+    let (_, rejected) = policy.apply(state.as_ref(), start + Duration::seconds(30));
+    assert!(!rejected.allowed);
+
+    // A mitad de la ventana siguiente las 4 anteriores pesan 2: caben 2 más
+    for _ in 0..2 {
+        let (next, decision) = policy.apply(state.as_ref(), start + Duration::seconds(90));
+        assert!(decision.allowed);
+        state = Some(next);
+    }
+    let (_, decision) = policy.apply(state.as_ref(), start + Duration::seconds(90));
+    assert!(!decision.allowed);
+    assert!(decision.retry_after.unwrap() <= 30);
+}
+
+#[tokio::test]
+async fn throttled_requests_get_429_with_headers() {
+    let router = router_with("/api/auth/login=ip:sliding_window:2/1m");
+
+    let response = login_from(router.clone(), [10, 0, 0, 1], "ana@example.com").await;
+    assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&response, "ratelimit-limit").as_deref(), Some("2"));
+    assert_eq!(header(&response, "ratelimit-remaining").as_deref(), Some("1"));
+
+    login_from(router.clone(), [10, 0, 0, 1], "ana@example.com").await;
+    let response = login_from(router.clone(), [10, 0, 0, 1], "ana@example.com").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&response, "ratelimit-remaining").as_deref(), Some("0"));
+    let retry_after: i64 = header(&response, "retry-after").unwrap().parse().unwrap();
+    // Las 2 peticiones siguen pesando en la ventana siguiente hasta su mitad
+    assert!(retry_after > 60 && retry_after <= 90);
+    assert_eq!(json_body(response).await["retry_after"], retry_after);
+
+    // Otra IP tiene su propio contador
+    let response = login_from(router, [10, 0, 0, 2], "ana@example.com").await;
+    assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn email_rules_are_keyed_by_the_body() {
+    let router = router_with("/api/auth/login=email:token_bucket:1/1h");
+
+    let response = login_from(router.clone(), [10, 0, 0, 1], "ana@example.com").await;
+    assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    // Desde otra IP y con otras mayúsculas sigue siendo la misma cuenta
+    let response = login_from(router.clone(), [10, 0, 0, 2], " ANA@example.com").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let response = login_from(router, [10, 0, 0, 1], "luis@example.com").await;
+    assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn api_key_rules_skip_requests_without_a_key() {
+    let router = router_with("/.well-known/jwks.json=api_key:token_bucket:1/1h");
+    let with_key = || Request::builder().uri("/.well-known/jwks.json").header("X-API-Key", "key-1").body(Body::empty()).unwrap();
+
+    assert_eq!(router.clone().oneshot(with_key()).await.unwrap().status(), StatusCode::OK);
+    assert_eq!(router.clone().oneshot(with_key()).await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(send_get(router, "/.well-known/jwks.json", None).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unlimited_routes_are_untouched() {
+    let router = router_with("/api/auth/login=ip:sliding_window:1/1m");
+
+    let response = send_post(router, "/api/auth/register", json!({})).await;
+    assert!(response.headers().get("ratelimit-limit").is_none());
+}
@@ -0,0 +1,88 @@
+mod support;
+
+use api::{
+    middleware::{
+        auth::{auth_middleware, AuthUser},
+        rbac::{require_any_role, require_role},
+    },
+    routes::create_router,
+};
+use axum::{http::StatusCode, middleware, routing::get, Router};
+use uuid::Uuid;
+
+use support::{app_state, json_body, send_get, token_with_role};
+
+async fn whoami(user: AuthUser) -> String {
+    user.role
+}
+
+fn guarded_router() -> Router {
+    let state = app_state();
+
+    let admin = Router::new()
+        .route("/", get(whoami))
+        .route_layer(require_role("admin"))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
+
+    let staff = Router::new()
+        .route("/", get(whoami))
+        .route_layer(require_any_role(&["admin", "support"]))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
+
+    Router::new()
+        .nest("/admin", admin)
+        .nest("/staff", staff)
+        .with_state(state)
+}
+
+#[tokio::test]
+async fn require_role_allows_matching_role() {
+    let response = send_get(guarded_router(), "/admin", Some(&token_with_role("admin"))).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..], b"admin");
+}
+
+#[tokio::test]
+async fn require_role_denies_other_roles_with_403() {
+    let response = send_get(guarded_router(), "/admin", Some(&token_with_role("user"))).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = json_body(response).await;
+    assert_eq!(body["error"], "Requires one of the roles: admin");
+}
+
+#[tokio::test]
+async fn require_any_role_allows_each_listed_role() {
+    for role in ["admin", "support"] {
+        let response = send_get(guarded_router(), "/staff", Some(&token_with_role(role))).await;
+        assert_eq!(response.status(), StatusCode::OK, "role {}", role);
+    }
+}
+
+#[tokio::test]
+async fn require_any_role_denies_unlisted_role() {
+    let response = send_get(guarded_router(), "/staff", Some(&token_with_role("user"))).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn guarded_route_without_token_is_401() {
+    let response = send_get(guarded_router(), "/admin", None).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn admin_router_enforces_admin_role() {
+    let uri = format!("/api/admin/users/{}", Uuid::new_v4());
+
+    let denied = send_get(create_router(app_state()), &uri, Some(&token_with_role("user"))).await;
+    assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+
+    let allowed = send_get(create_router(app_state()), &uri, Some(&token_with_role("admin"))).await;
+    assert_eq!(allowed.status(), StatusCode::OK);
+    assert_eq!(json_body(allowed).await["status"], "success");
+}
@@ -0,0 +1,92 @@
+mod support;
+
+use std::sync::Arc;
+
+use api::{
+    middleware::auth::{auth_middleware, Principal, ServiceClient},
+    routes::create_router,
+    AppState,
+};
+use axum::{extract::State, http::StatusCode, middleware, routing::get, Router};
+use common::error::AppError;
+
+use support::{
+    app_state, app_state_with_permissions, json_body, send_get, service_token, token_with_role, StubPermissionRepository,
+    TEST_SERVICE_CLIENT_ID,
+};
+
+async fn whoami(State(state): State<Arc<AppState>>, principal: Principal) -> Result<String, AppError> {
+    let can_read = state.principal_has_permission(&principal, "users:read").await?;
+    Ok(match principal {
+        Principal::User(user) => format!("user {} {}", user.id, can_read),
+        Principal::Service(service) => format!("service {} {}", service.client_id, can_read),
+    })
+}
+
+async fn service_only(service: ServiceClient) -> String {
+    service.client_id
+}
+
+fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/whoami", get(whoami))
+        .route("/internal", get(service_only))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware))
+        .with_state(state)
+}
+
+async fn text(response: axum::response::Response) -> String {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn middleware_tells_services_apart_from_users() {
+    let state = app_state();
+
+    let token = service_token(TEST_SERVICE_CLIENT_ID, "users:read");
+    let response = send_get(router(state.clone()), "/whoami", Some(&token)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(text(response).await, format!("service {} true", TEST_SERVICE_CLIENT_ID));
+
+    let response = send_get(router(state.clone()), "/internal", Some(&token)).await;
+    assert_eq!(text(response).await, TEST_SERVICE_CLIENT_ID);
+
+    let user_token = token_with_role("user");
+    let response = send_get(router(state.clone()), "/whoami", Some(&user_token)).await;
+    assert!(text(response).await.starts_with("user "));
+
+    let response = send_get(router(state), "/internal", Some(&user_token)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn services_only_have_the_permissions_of_their_scope() {
+    // Los permisos del repositorio son de usuarios; no se aplican al servicio
+    let state = app_state_with_permissions(Arc::new(StubPermissionRepository::with_permissions(&["users:read"])));
+
+    let token = service_token(TEST_SERVICE_CLIENT_ID, "billing:write");
+    let response = send_get(router(state.clone()), "/whoami", Some(&token)).await;
+    assert_eq!(text(response).await, format!("service {} false", TEST_SERVICE_CLIENT_ID));
+}
+
+#[tokio::test]
+async fn service_tokens_cannot_reach_user_endpoints() {
+    let token = service_token(TEST_SERVICE_CLIENT_ID, "users:read");
+
+    let response = send_get(create_router(app_state()), "/api/users/me", Some(&token)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Tampoco pasan los guards de rol: un servicio no tiene rol
+    let response = send_get(create_router(app_state()), "/api/admin/users/00000000-0000-0000-0000-000000000001", Some(&token)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn tokens_of_unregistered_clients_are_rejected() {
+    let token = service_token("deleted-service", "users:read");
+    let response = send_get(router(app_state()), "/whoami", Some(&token)).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(json_body(response).await["error"], "Service client no longer exists");
+}
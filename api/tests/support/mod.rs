@@ -0,0 +1,476 @@
+#![allow(dead_code)]
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use api::{handlers::auth::AuthService, AppState};
+use async_trait::async_trait;
+use axum::{body::Body, http::Request, response::Response, Router};
+use chrono::{Duration, Utc};
+use common::{
+    error::AppError,
+    jwt::{generate_jwt, CustomClaims, JwtConfig, MFA_PENDING_TOKEN_USE, SERVICE_TOKEN_USE},
+    keys::JwtKeys,
+    telegram::{verify_web_app_init_data, web_app_secret_key, TelegramLoginData},
+    utils::generate_secure_token,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use repository::{InMemoryRevocationStore, PermissionRepository};
+use shared::oauth::{
+    AuthorizationRequestInfo, AuthorizeError, AuthorizeParams, CreateOAuthClientSchema, CreatedOAuthClient,
+    DeviceAuthorizationInfo, DeviceAuthorizationRequest, DeviceAuthorizationResponse, OAuthClientInfo, OAuthError, TokenRequest,
+    TokenResponse, UserInfo,
+};
+use shared::user::{
+    ApiKeyInfo, CreateApiKeySchema, CreateUserSchema, CreatedApiKey, FederatedLoginStart, FilteredUser, LinkTelegramSchema, PasskeyLoginSchema,
+    PasskeyOptions, PasskeyRegistrationSchema, TotpEnrollment, User,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+pub const TEST_SECRET: &str = "test-secret";
+pub const TEST_BOT_TOKEN: &str = "123456:TEST-bot-token";
+pub const TEST_MFA_CODE: &str = "123456";
+pub const TEST_API_KEY: &str = "ak_0123456789ab_test-secret";
+pub const TEST_API_KEY_USER_ID: Uuid = Uuid::from_u128(0x5e55_10e5);
+pub const TEST_SERVICE_CLIENT_ID: &str = "billing-service";
+
+/// AuthService mínimo para pruebas: responde a `get_user`, emite tokens y acepta
+/// el initData de Mini App firmado con `TEST_BOT_TOKEN`.
+/// `token_version` es la versión que devuelve para cualquier usuario; con `mfa_enabled`
+/// el login pide segundo factor y sólo se acepta `TEST_MFA_CODE`. Con `locked_retry_after`
+/// el login por email responde como una cuenta bloqueada. Sólo se acepta la API key
+/// `TEST_API_KEY`, de `TEST_API_KEY_USER_ID` y limitada a `api_key_scopes`; el único
+/// cliente de servicio registrado es `TEST_SERVICE_CLIENT_ID`.
+#[derive(Default)]
+pub struct StubAuthService {
+    pub token_version: i32,
+    pub mfa_enabled: bool,
+    pub locked_retry_after: Option<i64>,
+    pub api_key_scopes: Vec<String>,
+}
+
+fn test_api_key_info(scopes: Vec<String>) -> ApiKeyInfo {
+    ApiKeyInfo {
+        id: Uuid::new_v4(),
+        name: "cron".into(),
+        prefix: "ak_0123456789ab".into(),
+        scopes,
+        expires_at: None,
+        last_used_at: None,
+        created_at: Utc::now(),
+    }
+}
+
+#[async_trait]
+impl AuthService for StubAuthService {
+    async fn register_user(&self, _user_data: &CreateUserSchema) -> Result<FilteredUser, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn authenticate_by_email(&self, _email: &str, _password: &str) -> Result<User, AppError> {
+        match self.locked_retry_after {
+            Some(retry_after) => Err(AppError::AccountLocked { retry_after }),
+            None => Err(AppError::Internal("not implemented".into())),
+        }
+    }
+
+    async fn unlock_account(&self, _user_id: &Uuid) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn authenticate_by_telegram(&self, _data: &TelegramLoginData) -> Result<User, String> {
+        Err("not implemented".into())
+    }
+
+    async fn authenticate_by_telegram_web_app(&self, init_data: &str) -> Result<User, String> {
+        let data = verify_web_app_init_data(init_data, TEST_BOT_TOKEN, Duration::days(1)).map_err(|e| e.to_string())?;
+        let telegram_user = data.user.ok_or("missing user")?;
+        Ok(User {
+            id: Uuid::new_v4(),
+            email: format!("telegram-{}@users.telegram.invalid", telegram_user.id),
+            password: String::new(),
+            telegram_user_id: Some(telegram_user.id.to_string()),
+            name: telegram_user.display_name(),
+            role: "user".into(),
+            email_verified_at: None,
+            token_version: 0,
+            created_at: Some(Utc::now()),
+            updated_at: Some(Utc::now()),
+        })
+    }
+
+    async fn link_telegram(&self, _user_id: &Uuid, _link: &LinkTelegramSchema) -> Result<String, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn unlink_telegram(&self, _user_id: &Uuid) -> Result<(), AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn verify_email(&self, _token: &str) -> Result<(), AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn resend_verification_email(&self, _email: &str) -> Result<(), AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn request_password_reset(&self, _email: &str) -> Result<(), AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn reset_password(&self, _token: &str, _new_password: &str) -> Result<(), AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn change_password(&self, _user_id: &Uuid, _current_password: &str, _new_password: &str) -> Result<(String, String), AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn token_version(&self, _user_id: &Uuid) -> Result<i32, AppError> {
+        Ok(self.token_version)
+    }
+
+    async fn mfa_challenge(&self, user: &User) -> Result<Option<String>, AppError> {
+        if !self.mfa_enabled {
+            return Ok(None);
+        }
+        Ok(Some(mfa_token_for(&user.id, Some(user.token_version))))
+    }
+
+    async fn verify_mfa(
+        &self,
+        _challenge_id: &str,
+        user_id: &Uuid,
+        token_version: i32,
+        code: Option<&str>,
+        _recovery_code: Option<&str>,
+    ) -> Result<User, AppError> {
+        if token_version != self.token_version {
+            return Err(AppError::Auth("stale challenge".into()));
+        }
+        if code != Some(TEST_MFA_CODE) {
+            return Err(AppError::Validation("invalid code".into()));
+        }
+        Ok(User {
+            id: *user_id,
+            email: "someone@example.com".into(),
+            password: String::new(),
+            telegram_user_id: None,
+            name: Some("Someone".into()),
+            role: "user".into(),
+            email_verified_at: None,
+            token_version: self.token_version,
+            created_at: Some(Utc::now()),
+            updated_at: Some(Utc::now()),
+        })
+    }
+
+    async fn start_totp_enrollment(&self, _user_id: &Uuid) -> Result<TotpEnrollment, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn confirm_totp_enrollment(&self, _user_id: &Uuid, _code: &str) -> Result<Vec<String>, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn disable_totp(&self, _user_id: &Uuid, _code: Option<&str>, _recovery_code: Option<&str>) -> Result<(), AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn start_passkey_registration(&self, _user_id: &Uuid) -> Result<PasskeyOptions, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn finish_passkey_registration(&self, _user_id: &Uuid, _registration: &PasskeyRegistrationSchema) -> Result<String, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn start_passkey_login(&self) -> Result<PasskeyOptions, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn finish_passkey_login(&self, _login: &PasskeyLoginSchema) -> Result<User, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn start_federated_login(&self) -> Result<FederatedLoginStart, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn finish_federated_login(&self, _code: &str, _state: &str, _state_binding: Option<&str>) -> Result<User, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn create_api_key(&self, _user_id: &Uuid, request: &CreateApiKeySchema) -> Result<CreatedApiKey, AppError> {
+        Ok(CreatedApiKey { key: TEST_API_KEY.into(), info: test_api_key_info(request.scopes.clone()) })
+    }
+
+    async fn list_api_keys(&self, _user_id: &Uuid) -> Result<Vec<ApiKeyInfo>, AppError> {
+        Ok(vec![test_api_key_info(self.api_key_scopes.clone())])
+    }
+
+    async fn revoke_api_key(&self, _user_id: &Uuid, _key_id: &Uuid) -> Result<(), AppError> {
+        Err(AppError::NotFound("API key not found".into()))
+    }
+
+    async fn authenticate_api_key(&self, key: &str) -> Result<(User, ApiKeyInfo), AppError> {
+        if key != TEST_API_KEY {
+            return Err(AppError::Auth("Invalid API key".into()));
+        }
+        let user = User {
+            id: TEST_API_KEY_USER_ID,
+            email: "service@example.com".into(),
+            password: String::new(),
+            telegram_user_id: None,
+            name: None,
+            role: "user".into(),
+            email_verified_at: None,
+            token_version: self.token_version,
+            created_at: Some(Utc::now()),
+            updated_at: Some(Utc::now()),
+        };
+        Ok((user, test_api_key_info(self.api_key_scopes.clone())))
+    }
+
+    async fn register_oauth_client(&self, _request: &CreateOAuthClientSchema) -> Result<CreatedOAuthClient, AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn list_oauth_clients(&self) -> Result<Vec<OAuthClientInfo>, AppError> {
+        Ok(vec![])
+    }
+
+    async fn start_oauth_authorization(&self, _params: &AuthorizeParams) -> Result<String, AuthorizeError> {
+        Err(AuthorizeError::Direct(OAuthError::new("invalid_request", "Unknown client_id")))
+    }
+
+    async fn oauth_authorization_request(&self, _request_id: &Uuid) -> Result<AuthorizationRequestInfo, AppError> {
+        Err(AppError::NotFound("not implemented".into()))
+    }
+
+    async fn decide_oauth_authorization(&self, _user_id: &Uuid, _request_id: &Uuid, _approve: bool) -> Result<String, AppError> {
+        Err(AppError::NotFound("not implemented".into()))
+    }
+
+    async fn start_device_authorization(&self, _request: &DeviceAuthorizationRequest) -> Result<DeviceAuthorizationResponse, OAuthError> {
+        Err(OAuthError::new("invalid_client", "Client authentication failed"))
+    }
+
+    async fn device_authorization_request(&self, _user_code: &str) -> Result<DeviceAuthorizationInfo, AppError> {
+        Err(AppError::NotFound("not implemented".into()))
+    }
+
+    async fn decide_device_authorization(&self, _user_id: &Uuid, _user_code: &str, _approve: bool) -> Result<(), AppError> {
+        Err(AppError::NotFound("not implemented".into()))
+    }
+
+    async fn exchange_oauth_token(&self, _request: &TokenRequest) -> Result<TokenResponse, OAuthError> {
+        Err(OAuthError::new("invalid_client", "Client authentication failed"))
+    }
+
+    async fn oauth_userinfo(&self, _access_token: &str) -> Result<UserInfo, OAuthError> {
+        Err(OAuthError::new("invalid_token", "not implemented"))
+    }
+
+    async fn verify_service_client(&self, client_id: &str) -> Result<(), AppError> {
+        if client_id == TEST_SERVICE_CLIENT_ID {
+            Ok(())
+        } else {
+            Err(AppError::NotFound("Service client not found".into()))
+        }
+    }
+
+    fn openid_configuration(&self) -> serde_json::Value {
+        serde_json::json!({})
+    }
+
+    async fn generate_token(&self, user: &User) -> Result<String, String> {
+        let custom_claims = CustomClaims {
+            role: Some(user.role.clone()),
+            email: Some(user.email.clone()),
+            token_version: Some(user.token_version),
+            token_use: None,
+            scope: None,
+        };
+        generate_jwt(&user.id.to_string(), custom_claims, &jwt_config(), "5m").map_err(|e| e.to_string())
+    }
+
+    async fn issue_refresh_token(&self, _user: &User) -> Result<String, String> {
+        Ok(generate_secure_token(32))
+    }
+
+    async fn refresh_token(&self, _refresh_token: &str) -> Result<(String, String), String> {
+        Err("not implemented".into())
+    }
+
+    async fn revoke_refresh_token(&self, _user_id: &Uuid, _refresh_token: &str) -> Result<(), AppError> {
+        Err(AppError::Internal("not implemented".into()))
+    }
+
+    async fn get_user(&self, user_id: &Uuid) -> Result<FilteredUser, String> {
+        Ok(FilteredUser {
+            id: *user_id,
+            email: "someone@example.com".into(),
+            name: Some("Someone".into()),
+            role: "user".into(),
+            email_verified_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        })
+    }
+}
+
+/// Permisos fijos en memoria; cuenta las consultas para comprobar la caché por petición.
+#[derive(Default)]
+pub struct StubPermissionRepository {
+    pub lookups: AtomicUsize,
+    pub permissions: Vec<String>,
+}
+
+impl StubPermissionRepository {
+    pub fn with_permissions(permissions: &[&str]) -> Self {
+        Self {
+            lookups: AtomicUsize::new(0),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    pub fn lookups(&self) -> usize {
+        self.lookups.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl PermissionRepository for StubPermissionRepository {
+    async fn find_permissions_for_user(&self, _user_id: &Uuid) -> anyhow::Result<Vec<String>> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        Ok(self.permissions.clone())
+    }
+
+    async fn find_roles_for_user(&self, _user_id: &Uuid) -> anyhow::Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    async fn assign_role_to_user(&self, _user_id: &Uuid, _role_name: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn remove_role_from_user(&self, _user_id: &Uuid, _role_name: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn jwt_config() -> JwtConfig {
+    JwtConfig::new(JwtKeys::hmac(TEST_SECRET))
+}
+
+pub fn app_state() -> Arc<AppState> {
+    app_state_with_permissions(Arc::new(StubPermissionRepository::with_permissions(&["users:read"])))
+}
+
+pub fn app_state_with_permissions(permission_repository: Arc<StubPermissionRepository>) -> Arc<AppState> {
+    app_state_with(StubAuthService::default(), permission_repository)
+}
+
+pub fn app_state_with(auth_service: StubAuthService, permission_repository: Arc<StubPermissionRepository>) -> Arc<AppState> {
+    Arc::new(AppState::new(
+        Arc::new(auth_service),
+        Arc::new(jwt_config()),
+        Arc::new(InMemoryRevocationStore::new()),
+        permission_repository,
+    ))
+}
+
+pub fn token_with_role(role: &str) -> String {
+    token_with_version(role, None)
+}
+
+pub fn token_with_version(role: &str, token_version: Option<i32>) -> String {
+    let custom_claims = CustomClaims {
+        role: Some(role.to_string()),
+        email: Some(format!("{}@example.com", role)),
+        token_version,
+        token_use: None,
+        scope: None,
+    };
+    generate_jwt(&Uuid::new_v4().to_string(), custom_claims, &jwt_config(), "5m").unwrap()
+}
+
+/// Token `client_credentials` como el que emite `/oauth/token` a un servicio.
+pub fn service_token(client_id: &str, scope: &str) -> String {
+    let custom_claims = CustomClaims {
+        token_use: Some(SERVICE_TOKEN_USE.to_string()),
+        scope: Some(scope.to_string()),
+        ..Default::default()
+    };
+    generate_jwt(client_id, custom_claims, &jwt_config(), "5m").unwrap()
+}
+
+/// Token `mfa_pending` como el que emite el login de un usuario con 2FA.
+pub fn mfa_token_for(user_id: &Uuid, token_version: Option<i32>) -> String {
+    let custom_claims = CustomClaims {
+        token_version,
+        token_use: Some(MFA_PENDING_TOKEN_USE.to_string()),
+        ..Default::default()
+    };
+    generate_jwt(&user_id.to_string(), custom_claims, &jwt_config(), "5m").unwrap()
+}
+
+/// Construye un initData como el de Telegram, firmado con `bot_token`.
+pub fn signed_init_data(fields: &[(&str, &str)], bot_token: &str) -> String {
+    let mut sorted = fields.to_vec();
+    sorted.sort_by_key(|(key, _)| *key);
+    let data_check_string = sorted
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(&web_app_secret_key(bot_token)).unwrap();
+    mac.update(data_check_string.as_bytes());
+    let hash = hex::encode(mac.finalize().into_bytes());
+
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in fields {
+        serializer.append_pair(key, value);
+    }
+    serializer.append_pair("hash", &hash).finish()
+}
+
+pub async fn send_post(router: Router, uri: &str, body: serde_json::Value) -> Response {
+    let request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    router.oneshot(request).await.unwrap()
+}
+
+pub async fn send_authorized_post(router: Router, uri: &str, token: &str) -> Response {
+    let request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("Authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    router.oneshot(request).await.unwrap()
+}
+
+pub async fn send_get(router: Router, uri: &str, token: Option<&str>) -> Response {
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    router.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+pub async fn json_body(response: Response) -> serde_json::Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
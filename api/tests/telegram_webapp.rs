@@ -0,0 +1,74 @@
+mod support;
+
+use api::routes::create_router;
+use axum::http::StatusCode;
+use chrono::Utc;
+use common::jwt::verify_jwt;
+use serde_json::json;
+use support::{app_state, json_body, jwt_config, send_post, signed_init_data, TEST_BOT_TOKEN};
+
+const USER_JSON: &str = r#"{"id":987654321,"first_name":"Ana","last_name":"Pérez","username":"ana","language_code":"es"}"#;
+
+fn init_data_fields(auth_date: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("query_id", "AAHdF6IQAAAAAN0XohDhrOrc".to_string()),
+        ("user", USER_JSON.to_string()),
+        ("auth_date", auth_date.to_string()),
+    ]
+}
+
+fn sign(fields: &[(&'static str, String)], bot_token: &str) -> String {
+    let fields: Vec<(&str, &str)> = fields.iter().map(|(key, value)| (*key, value.as_str())).collect();
+    signed_init_data(&fields, bot_token)
+}
+
+#[tokio::test]
+async fn valid_init_data_logs_in_the_telegram_user() {
+    let init_data = sign(&init_data_fields(&Utc::now().timestamp().to_string()), TEST_BOT_TOKEN);
+
+    let response = send_post(create_router(app_state()), "/api/auth/telegram/webapp", json!({ "init_data": init_data })).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert!(body["refresh_token"].is_string());
+    let claims = verify_jwt(body["token"].as_str().unwrap(), &jwt_config()).unwrap();
+    assert_eq!(claims.email.as_deref(), Some("telegram-987654321@users.telegram.invalid"));
+}
+
+#[tokio::test]
+async fn init_data_signed_with_another_bot_token_is_rejected() {
+    let init_data = sign(&init_data_fields(&Utc::now().timestamp().to_string()), "654321:OTHER-bot-token");
+
+    let response = send_post(create_router(app_state()), "/api/auth/telegram/webapp", json!({ "init_data": init_data })).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn tampered_user_is_rejected() {
+    let init_data = sign(&init_data_fields(&Utc::now().timestamp().to_string()), TEST_BOT_TOKEN)
+        .replace("987654321", "111111111");
+
+    let response = send_post(create_router(app_state()), "/api/auth/telegram/webapp", json!({ "init_data": init_data })).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn stale_init_data_is_rejected() {
+    let two_days_ago = Utc::now().timestamp() - 2 * 24 * 60 * 60;
+    let init_data = sign(&init_data_fields(&two_days_ago.to_string()), TEST_BOT_TOKEN);
+
+    let response = send_post(create_router(app_state()), "/api/auth/telegram/webapp", json!({ "init_data": init_data })).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn init_data_without_user_is_rejected() {
+    let init_data = signed_init_data(&[("auth_date", &Utc::now().timestamp().to_string())], TEST_BOT_TOKEN);
+
+    let response = send_post(create_router(app_state()), "/api/auth/telegram/webapp", json!({ "init_data": init_data })).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
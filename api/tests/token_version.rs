@@ -0,0 +1,38 @@
+mod support;
+
+use std::sync::Arc;
+
+use api::routes::create_router;
+use axum::http::StatusCode;
+use support::{app_state_with, send_get, token_with_version, StubAuthService, StubPermissionRepository};
+
+fn router_with_token_version(token_version: i32) -> axum::Router {
+    let auth_service = StubAuthService { token_version, ..Default::default() };
+    create_router(app_state_with(auth_service, Arc::new(StubPermissionRepository::default())))
+}
+
+#[tokio::test]
+async fn token_with_the_current_version_is_accepted() {
+    let token = token_with_version("user", Some(3));
+
+    let response = send_get(router_with_token_version(3), "/api/users/me", Some(&token)).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn token_issued_before_a_password_change_is_rejected() {
+    let token = token_with_version("user", Some(2));
+
+    let response = send_get(router_with_token_version(3), "/api/users/me", Some(&token)).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn token_without_version_counts_as_version_zero() {
+    let token = token_with_version("user", None);
+
+    assert_eq!(send_get(router_with_token_version(0), "/api/users/me", Some(&token)).await.status(), StatusCode::OK);
+    assert_eq!(send_get(router_with_token_version(1), "/api/users/me", Some(&token)).await.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,114 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use memmap2::Mmap;
+use sha1::{Digest, Sha1};
+
+/// Lista local de contraseñas filtradas con el formato de Have I Been Pwned, sin acceso a red.
+///
+/// Admite las dos formas en que se descarga el corpus de HIBP:
+/// - un fichero con una línea `SHA1:RECUENTO` por contraseña, ordenado por hash;
+/// - un directorio con un fichero por prefijo de 5 caracteres (`ABCDE` o `ABCDE.txt`)
+///   cuyas líneas son `SUFIJO:RECUENTO`, como las respuestas de la API de rangos.
+///
+/// Los ficheros se proyectan en memoria y se buscan por bisección, así que la consulta
+/// no depende del tamaño del corpus ni lo carga entero.
+pub struct BreachedPasswordList {
+    path: PathBuf,
+    source: Source,
+}
+
+enum Source {
+    SortedFile(Mmap),
+    RangeDirectory,
+}
+
+impl fmt::Debug for BreachedPasswordList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BreachedPasswordList").field("path", &self.path).finish()
+    }
+}
+
+const PREFIX_LEN: usize = 5;
+
+impl BreachedPasswordList {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let source = if path.is_dir() {
+            Source::RangeDirectory
+        } else {
+            Source::SortedFile(map_file(&path)?)
+        };
+        Ok(Self { path, source })
+    }
+
+    /// Veces que la contraseña aparece en el corpus (0 si no aparece).
+    pub fn occurrences(&self, password: &str) -> io::Result<u64> {
+        let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+
+        match &self.source {
+            Source::SortedFile(map) => Ok(search(map, hash.as_bytes())),
+            Source::RangeDirectory => {
+                let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+                let file = [self.path.join(prefix), self.path.join(format!("{}.txt", prefix))]
+                    .into_iter()
+                    .find(|candidate| candidate.is_file());
+                match file {
+                    Some(file) => Ok(search(&map_file(&file)?, suffix.as_bytes())),
+                    // Sin fichero para el prefijo no hay ninguna contraseña con ese hash
+                    None => Ok(0),
+                }
+            }
+        }
+    }
+
+    pub fn contains(&self, password: &str) -> io::Result<bool> {
+        Ok(self.occurrences(password)? > 0)
+    }
+}
+
+fn map_file(path: &Path) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    // SAFETY: el corpus es de sólo lectura; si otro proceso lo trunca mientras está
+    // proyectado, la lectura puede fallar, como con cualquier fichero proyectado.
+    unsafe { Mmap::map(&file) }
+}
+
+// Bisección sobre bytes: se localiza el inicio de la línea que contiene el punto medio
+// y se compara su hash (sin distinguir mayúsculas, por si el fichero está en minúsculas)
+fn search(data: &[u8], key: &[u8]) -> u64 {
+    let (mut low, mut high) = (0, data.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let start = data[..mid].iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1).max(low);
+        let end = data[start..].iter().position(|b| *b == b'\n').map_or(data.len(), |i| start + i);
+        let line = &data[start..end];
+        let hash = line.split(|b| *b == b':').next().unwrap_or_default();
+
+        match compare_ignore_case(hash, key) {
+            Ordering::Less => low = end + 1,
+            Ordering::Greater => high = start,
+            Ordering::Equal => return parse_count(line),
+        }
+    }
+    0
+}
+
+fn compare_ignore_case(a: &[u8], b: &[u8]) -> Ordering {
+    a.iter().map(u8::to_ascii_uppercase).cmp(b.iter().map(u8::to_ascii_uppercase))
+}
+
+// Sin recuento (o ilegible) la contraseña cuenta como una aparición
+fn parse_count(line: &[u8]) -> u64 {
+    line.split(|b| *b == b':')
+        .nth(1)
+        .and_then(|count| std::str::from_utf8(count).ok())
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(1)
+        .max(1)
+}
@@ -1,5 +1,8 @@
+use common::error::AppError;
 use thiserror::Error;
 
+use crate::password_policy::PasswordViolation;
+
 #[derive(Debug, Error)]
 pub enum AuthError {
     #[error("Invalid credentials")]
@@ -15,4 +18,121 @@ pub enum AuthError {
     InvalidToken(String),
     #[error("Token expirado")]
     TokenExpired,
+    #[error("Refresh token inválido")]
+    InvalidRefreshToken,
+    #[error("Refresh token reutilizado; la sesión fue revocada")]
+    RefreshTokenReused,
+    #[error("El refresh token pertenece a otro usuario")]
+    RefreshTokenNotOwned,
+    #[error("Datos de Telegram inválidos: {0}")]
+    InvalidTelegramData(String),
+    #[error("La autenticación por Telegram no está configurada")]
+    TelegramNotConfigured,
+    #[error("La cuenta de Telegram ya está vinculada a otro usuario")]
+    TelegramAlreadyLinked,
+    #[error("El usuario ya tiene otra cuenta de Telegram vinculada")]
+    TelegramLinkExists,
+    #[error("El usuario no tiene ninguna cuenta de Telegram vinculada")]
+    TelegramNotLinked,
+    #[error("Telegram es el único método de acceso del usuario")]
+    TelegramOnlyLoginMethod,
+    #[error("Cuenta bloqueada temporalmente; reintentar en {retry_after} s")]
+    AccountLocked { retry_after: i64 },
+    #[error("Usuario no encontrado")]
+    UserNotFound,
+    #[error("El email no está verificado")]
+    EmailNotVerified,
+    #[error("Token de verificación inválido o expirado")]
+    InvalidVerificationToken,
+    #[error("Token de recuperación inválido o expirado")]
+    InvalidResetToken,
+    #[error("La contraseña actual no es correcta")]
+    InvalidCurrentPassword,
+    #[error("La contraseña nueva debe ser distinta de la actual")]
+    PasswordUnchanged,
+    #[error("La contraseña no cumple la política: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    WeakPassword(Vec<PasswordViolation>),
+    #[error("La autenticación en dos pasos ya está activada")]
+    MfaAlreadyEnabled,
+    #[error("La autenticación en dos pasos no está activada")]
+    MfaNotEnrolled,
+    #[error("Código de verificación inválido")]
+    InvalidMfaCode,
+    #[error("Demasiados códigos inválidos; hay que volver a iniciar sesión")]
+    MfaChallengeExhausted,
+    #[error("Passkey inválida: {0}")]
+    InvalidPasskey(String),
+    #[error("La passkey ya está registrada")]
+    PasskeyAlreadyRegistered,
+    #[error("Clave de API inválida, revocada o caducada")]
+    InvalidApiKey,
+    #[error("Clave de API no encontrada")]
+    ApiKeyNotFound,
+    #[error("Validez de la clave de API no válida: {0}")]
+    InvalidApiKeyExpiry(String),
+    #[error("Cliente OAuth inválido: {0}")]
+    InvalidOAuthClient(String),
+    #[error("Solicitud de autorización no encontrada o caducada")]
+    OAuthRequestNotFound,
+    #[error("Cliente de servicio no encontrado")]
+    ServiceClientNotFound,
+    #[error("El login con un proveedor externo no está configurado")]
+    FederatedLoginNotConfigured,
+    #[error("Login con el proveedor externo inválido: {0}")]
+    InvalidFederatedLogin(String),
+    #[error("El email del proveedor no está verificado y ya pertenece a otra cuenta")]
+    FederatedEmailConflict,
+    #[error("Error al comunicar con el proveedor externo: {0}")]
+    FederatedProviderError(String),
+    #[error("Error al enviar el correo: {0}")]
+    EmailDeliveryError(String),
+    #[error("Error de base de datos: {0}")]
+    DatabaseError(String),
+}
+
+impl From<AuthError> for AppError {
+    fn from(err: AuthError) -> Self {
+        let message = err.to_string();
+        match err {
+            AuthError::TelegramAlreadyLinked
+            | AuthError::TelegramLinkExists
+            | AuthError::TelegramOnlyLoginMethod
+            | AuthError::MfaAlreadyEnabled
+            | AuthError::PasskeyAlreadyRegistered
+            | AuthError::FederatedEmailConflict => AppError::Conflict(message),
+            AuthError::TelegramNotLinked | AuthError::UserNotFound | AuthError::MfaNotEnrolled | AuthError::ApiKeyNotFound
+            | AuthError::OAuthRequestNotFound | AuthError::ServiceClientNotFound | AuthError::FederatedLoginNotConfigured => {
+                AppError::NotFound(message)
+            }
+            AuthError::InvalidTelegramData(_)
+            | AuthError::InvalidVerificationToken
+            | AuthError::InvalidResetToken
+            | AuthError::InvalidCurrentPassword
+            | AuthError::PasswordUnchanged
+            | AuthError::InvalidMfaCode
+            | AuthError::InvalidPasskey(_)
+            | AuthError::InvalidApiKeyExpiry(_)
+            | AuthError::InvalidOAuthClient(_) => AppError::Validation(message),
+            AuthError::EmailNotVerified | AuthError::RefreshTokenNotOwned => AppError::Forbidden(message),
+            AuthError::AccountLocked { retry_after } => AppError::AccountLocked { retry_after },
+            AuthError::WeakPassword(violations) => {
+                AppError::PasswordPolicy(violations.iter().map(ToString::to_string).collect())
+            }
+            AuthError::InvalidCredentials
+            | AuthError::InvalidToken(_)
+            | AuthError::TokenExpired
+            | AuthError::InvalidRefreshToken
+            | AuthError::RefreshTokenReused
+            | AuthError::InvalidApiKey
+            | AuthError::MfaChallengeExhausted
+            | AuthError::InvalidFederatedLogin(_) => AppError::Auth(message),
+            AuthError::DatabaseError(_) => AppError::Database(message),
+            AuthError::PasswordHashError(_)
+            | AuthError::PasswordVerifyError(_)
+            | AuthError::TokenGenerationError(_)
+            | AuthError::TelegramNotConfigured
+            | AuthError::EmailDeliveryError(_)
+            | AuthError::FederatedProviderError(_) => AppError::Internal(message),
+        }
+    }
 }
@@ -0,0 +1,307 @@
+//! Cliente OpenID Connect para iniciar sesión con un proveedor externo (IdP corporativo):
+//! discovery, URL de autorización con `state`, `nonce` y PKCE, canje del código y validación
+//! del ID token con el JWKS del proveedor. Qué usuario local le corresponde vive en `AuthService`.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{config::AppConfig, error::AppError};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use tracing::{info, warn};
+
+use crate::{
+    error::AuthError,
+    oauth::{self, PKCE_METHOD_S256, SCOPE_OPENID},
+    totp::constant_time_eq,
+};
+
+const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+// Tiempo máximo de cada llamada al proveedor
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+// Margen para la diferencia de reloj con el proveedor al comprobar `exp`
+const ID_TOKEN_LEEWAY_SECS: u64 = 60;
+
+/// Proveedor externo tomado de las variables `OIDC_*`
+#[derive(Debug, Clone)]
+pub struct FederatedLoginSettings {
+    /// URL de `/.well-known/openid-configuration`; el `issuer` del proveedor debe coincidir con ella
+    pub discovery_url: String,
+    pub client_id: String,
+    /// Sin secreto el cliente es público y se protege sólo con PKCE
+    pub client_secret: Option<String>,
+    /// Página del frontend a la que vuelve el usuario con `code` y `state`
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    /// Tiempo para completar el login en el proveedor
+    pub login_expires_in: String,
+}
+
+impl FederatedLoginSettings {
+    /// `None` si no hay proveedor configurado (`OIDC_DISCOVERY_URL` vacío).
+    pub fn from_config(config: &AppConfig) -> Result<Option<Self>, AppError> {
+        let Some(discovery_url) = non_empty(&config.oidc_discovery_url) else {
+            return Ok(None);
+        };
+        let client_id = non_empty(&config.oidc_client_id)
+            .ok_or_else(|| AppError::Internal("OIDC_CLIENT_ID is required when OIDC_DISCOVERY_URL is set".into()))?;
+
+        let scopes = oauth::parse_scope(&config.oidc_scopes);
+        if !scopes.iter().any(|scope| scope == SCOPE_OPENID) {
+            return Err(AppError::Internal("OIDC_SCOPES must include openid".into()));
+        }
+
+        let redirect_uri = non_empty(&config.oidc_redirect_uri)
+            .unwrap_or_else(|| format!("{}/auth/oidc/callback", config.frontend_url.trim_end_matches('/')));
+
+        Ok(Some(Self {
+            discovery_url,
+            client_id,
+            client_secret: non_empty(&config.oidc_client_secret),
+            redirect_uri,
+            scopes,
+            login_expires_in: config.oidc_login_expires_in.clone(),
+        }))
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+/// Metadatos de la discovery que necesita el flujo
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// Claims del ID token del proveedor que se usan para localizar o crear al usuario
+#[derive(Debug, Clone, Deserialize)]
+pub struct FederatedClaims {
+    pub iss: String,
+    pub sub: String,
+    #[serde(default)]
+    pub azp: Option<String>,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+// Algunos proveedores envían `email_verified` como cadena ("true")
+fn bool_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match BoolOrString::deserialize(deserializer)? {
+        BoolOrString::Bool(value) => value,
+        BoolOrString::String(value) => value.eq_ignore_ascii_case("true"),
+    })
+}
+
+#[derive(Deserialize)]
+struct ProviderTokenResponse {
+    id_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProviderErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Cliente del proveedor. La discovery se pide en el primer login y se conserva; el JWKS
+/// se vuelve a pedir cuando llega un ID token firmado con una clave que no se conoce.
+pub struct OidcClient {
+    settings: FederatedLoginSettings,
+    http: reqwest::Client,
+    metadata: Mutex<Option<Arc<ProviderMetadata>>>,
+    jwks: Mutex<Option<Arc<JwkSet>>>,
+}
+
+impl OidcClient {
+    pub fn new(settings: FederatedLoginSettings) -> Self {
+        Self {
+            settings,
+            http: reqwest::Client::new(),
+            metadata: Mutex::new(None),
+            jwks: Mutex::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> &FederatedLoginSettings {
+        &self.settings
+    }
+
+    /// URL del proveedor a la que se envía al usuario, con PKCE S256.
+    pub async fn authorization_url(&self, state: &str, nonce: &str, code_verifier: &str) -> Result<String, AuthError> {
+        let metadata = self.metadata().await?;
+        let query = form_urlencoded::Serializer::new(String::new())
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.settings.client_id)
+            .append_pair("redirect_uri", &self.settings.redirect_uri)
+            .append_pair("scope", &self.settings.scopes.join(" "))
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", &oauth::code_challenge_s256(code_verifier))
+            .append_pair("code_challenge_method", PKCE_METHOD_S256)
+            .finish();
+        let separator = if metadata.authorization_endpoint.contains('?') { '&' } else { '?' };
+        Ok(format!("{}{}{}", metadata.authorization_endpoint, separator, query))
+    }
+
+    /// Canjea el código en el token endpoint y devuelve el ID token, aún sin validar.
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<String, AuthError> {
+        let metadata = self.metadata().await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.settings.redirect_uri),
+            ("code_verifier", code_verifier),
+        ];
+        let mut request = self.http.post(&metadata.token_endpoint).timeout(PROVIDER_TIMEOUT);
+        match &self.settings.client_secret {
+            Some(secret) => request = request.basic_auth(&self.settings.client_id, Some(secret)),
+            None => form.push(("client_id", &self.settings.client_id)),
+        }
+
+        let response = request.form(&form).send().await.map_err(provider_error)?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response.json::<ProviderErrorResponse>().await.ok();
+            warn!("El proveedor OIDC rechazó el canje del código ({}): {:?}", status, error.as_ref().map(|e| &e.error));
+            return Err(match error {
+                // El código caducó, ya se usó o no corresponde al verificador
+                Some(error) if error.error == "invalid_grant" => {
+                    AuthError::InvalidFederatedLogin(error.error_description.unwrap_or(error.error))
+                }
+                _ => AuthError::FederatedProviderError(format!("token endpoint respondió {}", status)),
+            });
+        }
+
+        let tokens: ProviderTokenResponse = response.json().await.map_err(provider_error)?;
+        tokens
+            .id_token
+            .ok_or_else(|| AuthError::FederatedProviderError("la respuesta del token endpoint no incluye id_token".into()))
+    }
+
+    /// Valida firma, `iss`, `aud`, `azp`, `exp` y `nonce` del ID token.
+    pub async fn verify_id_token(&self, id_token: &str, nonce: &str) -> Result<FederatedClaims, AuthError> {
+        let invalid = |reason: String| AuthError::InvalidFederatedLogin(reason);
+        let metadata = self.metadata().await?;
+        let header = decode_header(id_token).map_err(|e| invalid(e.to_string()))?;
+        // Un ID token firmado con HMAC usaría el secreto del cliente como clave; sólo se aceptan claves públicas
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(invalid(format!("algoritmo no admitido: {:?}", header.alg)));
+        }
+
+        let jwk = match self.find_key(header.kid.as_deref(), false).await? {
+            Some(jwk) => jwk,
+            // Clave nueva del proveedor (rotación): se vuelve a pedir el JWKS una vez
+            None => self
+                .find_key(header.kid.as_deref(), true)
+                .await?
+                .ok_or_else(|| invalid("ID token firmado con una clave desconocida".into()))?,
+        };
+        let key = DecodingKey::from_jwk(&jwk).map_err(|e| invalid(e.to_string()))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = ID_TOKEN_LEEWAY_SECS;
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.settings.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = decode::<FederatedClaims>(id_token, &key, &validation)
+            .map_err(|e| invalid(e.to_string()))?
+            .claims;
+
+        if claims.azp.as_deref().is_some_and(|azp| azp != self.settings.client_id) {
+            return Err(invalid("azp no corresponde a este cliente".into()));
+        }
+        let nonce_matches = claims
+            .nonce
+            .as_deref()
+            .is_some_and(|received| constant_time_eq(received.as_bytes(), nonce.as_bytes()));
+        if !nonce_matches {
+            return Err(invalid("nonce no coincide".into()));
+        }
+
+        Ok(claims)
+    }
+
+    async fn metadata(&self) -> Result<Arc<ProviderMetadata>, AuthError> {
+        if let Some(metadata) = self.metadata.lock().unwrap().clone() {
+            return Ok(metadata);
+        }
+
+        let metadata: ProviderMetadata = self.get_json(&self.settings.discovery_url).await?;
+        // OpenID Connect Discovery §4.3: el issuer es la URL de la que cuelga la discovery
+        let expected_issuer = self.settings.discovery_url.trim_end_matches(DISCOVERY_PATH).trim_end_matches('/');
+        if metadata.issuer.trim_end_matches('/') != expected_issuer {
+            return Err(AuthError::FederatedProviderError(format!(
+                "el issuer de la discovery ({}) no corresponde a {}",
+                metadata.issuer, self.settings.discovery_url
+            )));
+        }
+        info!("Discovery del proveedor OIDC {} cargada", metadata.issuer);
+
+        let metadata = Arc::new(metadata);
+        *self.metadata.lock().unwrap() = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    // Sin `kid` en el token sólo vale un JWKS de una única clave
+    async fn find_key(&self, kid: Option<&str>, refresh: bool) -> Result<Option<Jwk>, AuthError> {
+        let cached = if refresh { None } else { self.jwks.lock().unwrap().clone() };
+        let jwks = match cached {
+            Some(jwks) => jwks,
+            None => {
+                let metadata = self.metadata().await?;
+                let jwks: Arc<JwkSet> = Arc::new(self.get_json(&metadata.jwks_uri).await?);
+                *self.jwks.lock().unwrap() = Some(jwks.clone());
+                jwks
+            }
+        };
+
+        Ok(match kid {
+            Some(kid) => jwks.find(kid).cloned(),
+            None if jwks.keys.len() == 1 => jwks.keys.first().cloned(),
+            None => None,
+        })
+    }
+
+    async fn get_json<R: DeserializeOwned>(&self, url: &str) -> Result<R, AuthError> {
+        self.http
+            .get(url)
+            .timeout(PROVIDER_TIMEOUT)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)
+    }
+}
+
+fn provider_error(error: reqwest::Error) -> AuthError {
+    warn!("Error al comunicar con el proveedor OIDC: {}", error);
+    AuthError::FederatedProviderError(error.to_string())
+}
@@ -1,4 +1,10 @@
+pub mod breached_passwords;
 pub mod error;
+pub mod federation;
+pub mod oauth;
 // pub mod jwt;
 pub mod password;
-pub mod service;
\ No newline at end of file
+pub mod password_policy;
+pub mod service;
+pub mod totp;
+pub mod webauthn;
@@ -0,0 +1,167 @@
+//! Piezas del proveedor OAuth 2.1 / OpenID Connect que no dependen del almacenamiento:
+//! scopes, PKCE (sólo S256), validación de `redirect_uri`, respuestas de redirección
+//! y claims del ID token. El flujo completo vive en `AuthService`.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::Rng;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use shared::{oauth::UserInfo, user::User};
+
+use crate::totp::constant_time_eq;
+
+pub const SCOPE_OPENID: &str = "openid";
+pub const SCOPE_PROFILE: &str = "profile";
+pub const SCOPE_EMAIL: &str = "email";
+
+/// Scopes que entiende el proveedor; `openid` es el que pide el ID token
+pub const SUPPORTED_SCOPES: [&str; 3] = [SCOPE_OPENID, SCOPE_PROFILE, SCOPE_EMAIL];
+
+/// Flujo con el usuario delante: consentimiento, código y PKCE
+pub const GRANT_AUTHORIZATION_CODE: &str = "authorization_code";
+/// Token del propio cliente para llamadas entre servicios, sin usuario
+pub const GRANT_CLIENT_CREDENTIALS: &str = "client_credentials";
+/// Flujo de dispositivo (RFC 8628): el usuario aprueba desde otro navegador
+pub const GRANT_DEVICE_CODE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Grants que se pueden asignar a un cliente
+pub const SUPPORTED_GRANTS: [&str; 3] = [GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS, GRANT_DEVICE_CODE];
+
+// Consonantes sin vocales ni caracteres ambiguos (RFC 8628 §6.1): 20^8 combinaciones
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const USER_CODE_LEN: usize = 8;
+
+/// Único método PKCE admitido; OAuth 2.1 desaconseja `plain`
+pub const PKCE_METHOD_S256: &str = "S256";
+
+// Longitud en base64url de un SHA-256
+const CODE_CHALLENGE_LEN: usize = 43;
+// Límites del code_verifier (RFC 7636 §4.1)
+const CODE_VERIFIER_MIN_LEN: usize = 43;
+const CODE_VERIFIER_MAX_LEN: usize = 128;
+
+/// Claims del ID token: los del usuario según los scopes más los de la sesión
+#[derive(Debug, Serialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    /// Momento en que el usuario aprobó la autorización
+    pub auth_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub user: UserInfo,
+}
+
+/// Separa los scopes de un parámetro `scope`, sin repetidos y en el orden recibido.
+pub fn parse_scope(scope: &str) -> Vec<String> {
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in scope.split_whitespace() {
+        if !scopes.iter().any(|existing| existing == scope) {
+            scopes.push(scope.to_string());
+        }
+    }
+    scopes
+}
+
+/// Los scopes de un servicio son permisos con la forma `recurso:acción` (`users:read`).
+pub fn is_valid_service_scope(scope: &str) -> bool {
+    scope.split_once(':').is_some_and(|(resource, action)| {
+        [resource, action].iter().all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+        })
+    })
+}
+
+/// Código que el usuario teclea para aprobar un dispositivo, ya normalizado (`BCDFGHJK`).
+pub fn generate_user_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..USER_CODE_LEN)
+        .map(|_| USER_CODE_ALPHABET[rng.gen_range(0..USER_CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Forma de mostrar el user_code: `BCDF-GHJK`.
+pub fn format_user_code(user_code: &str) -> String {
+    let (first, second) = user_code.split_at(user_code.len() / 2);
+    format!("{}-{}", first, second)
+}
+
+/// Se ignoran mayúsculas, guiones y espacios al comparar el user_code que teclea el usuario.
+pub fn normalize_user_code(user_code: &str) -> String {
+    user_code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Un challenge S256 es un SHA-256 en base64url sin relleno.
+pub fn is_valid_code_challenge(code_challenge: &str) -> bool {
+    code_challenge.len() == CODE_CHALLENGE_LEN
+        && code_challenge.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Comprueba que `BASE64URL(SHA256(code_verifier))` coincide con el challenge guardado.
+pub fn verify_pkce(code_verifier: &str, code_challenge: &str) -> bool {
+    let well_formed = (CODE_VERIFIER_MIN_LEN..=CODE_VERIFIER_MAX_LEN).contains(&code_verifier.len())
+        && code_verifier
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~'));
+    if !well_formed {
+        return false;
+    }
+
+    constant_time_eq(code_challenge_s256(code_verifier).as_bytes(), code_challenge.as_bytes())
+}
+
+/// `BASE64URL(SHA256(code_verifier))`: el challenge que se envía al pedir el código.
+pub fn code_challenge_s256(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// URIs de redirección que se aceptan al registrar un cliente: `https`, `http` sólo en
+/// loopback (apps nativas y desarrollo) o un esquema privado con forma de dominio invertido
+/// (`com.example.app:/callback`). Nunca con fragmento.
+pub fn is_valid_redirect_uri(uri: &str) -> bool {
+    if uri.contains('#') || uri.chars().any(char::is_whitespace) {
+        return false;
+    }
+    let Some((scheme, rest)) = uri.split_once(':') else {
+        return false;
+    };
+
+    match scheme {
+        "https" => rest.len() > 2 && rest.starts_with("//"),
+        "http" => ["//localhost", "//127.0.0.1", "//[::1]"].iter().any(|host| {
+            rest.strip_prefix(host)
+                .is_some_and(|after| after.is_empty() || after.starts_with([':', '/', '?']))
+        }),
+        _ => scheme.contains('.') && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+')),
+    }
+}
+
+/// Añade parámetros a la query de la `redirect_uri`, conservando los que ya tuviera.
+pub fn redirect_with(redirect_uri: &str, params: &[(&str, &str)]) -> String {
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in params {
+        serializer.append_pair(key, value);
+    }
+    let separator = if redirect_uri.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", redirect_uri, separator, serializer.finish())
+}
+
+/// Claims del usuario que corresponden a los scopes concedidos.
+pub fn user_info(user: &User, scopes: &[String]) -> UserInfo {
+    let granted = |scope: &str| scopes.iter().any(|granted| granted == scope);
+    let email = granted(SCOPE_EMAIL);
+
+    UserInfo {
+        sub: user.id.to_string(),
+        name: user.name.clone().filter(|_| granted(SCOPE_PROFILE)),
+        email: email.then(|| user.email.clone()),
+        email_verified: email.then_some(user.email_verified_at.is_some()),
+    }
+}
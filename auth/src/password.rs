@@ -1,59 +1,50 @@
 use anyhow::Result;
-use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
-};
+use common::password::{self, Argon2Params, HashAlgorithm};
 use tracing::{debug, error, info};
 
 use crate::error::AuthError;
 
-pub fn hash_password(password: &str) -> Result<String, AuthError> {
+pub fn hash_password(password: &str, params: &Argon2Params) -> Result<String, AuthError> {
     info!("Iniciando proceso de hash de contraseña");
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    
-    debug!("Generando hash con Argon2");
-    let password_hash = match argon2.hash_password(password.as_bytes(), &salt) {
+
+    debug!("Generando hash con Argon2id");
+    let password_hash = match password::hash_password(password, params) {
         Ok(hash) => {
             debug!("Hash generado correctamente");
-            hash.to_string()
+            hash
         },
         Err(e) => {
             error!("Error al generar hash: {}", e);
             return Err(AuthError::PasswordHashError(e.to_string()));
         }
     };
-    
+
     Ok(password_hash)
 }
 
+/// Verifica la contraseña contra un hash Argon2, bcrypt, scrypt o PBKDF2.
 pub fn verify_password(provided_password: &str, stored_hash: &str) -> Result<bool, AuthError> {
     info!("Verificando contraseña");
-    debug!("Hash almacenado: {}", stored_hash);
-    
-    let parsed_hash = match PasswordHash::new(stored_hash) {
-        Ok(hash) => {
-            debug!("Hash parseado correctamente");
-            hash
-        },
+    debug!("Algoritmo del hash almacenado: {:?}", HashAlgorithm::identify(stored_hash));
+
+    let is_valid = match password::verify_password(provided_password, stored_hash) {
+        Ok(is_valid) => is_valid,
         Err(e) => {
-            error!("Error al parsear hash: {}", e);
+            error!("Error al verificar el hash: {}", e);
             return Err(AuthError::PasswordVerifyError(e.to_string()));
         }
     };
-    
-    debug!("Verificando contraseña con Argon2");
-    let result = Argon2::default().verify_password(provided_password.as_bytes(), &parsed_hash);
-    
-    let is_valid = result.is_ok();
+
     if is_valid {
         info!("Verificación de contraseña exitosa");
     } else {
         info!("Verificación de contraseña fallida");
-        if let Err(e) = result {
-            debug!("Error de verificación: {}", e);
-        }
     }
-    
+
     Ok(is_valid)
 }
+
+/// Indica si el hash almacenado debe regenerarse con los parámetros actuales.
+pub fn needs_rehash(stored_hash: &str, params: &Argon2Params) -> bool {
+    password::needs_rehash(stored_hash, params)
+}
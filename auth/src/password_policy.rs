@@ -0,0 +1,361 @@
+use std::{fmt, sync::Arc};
+
+use common::{config::AppConfig, error::AppError};
+use tracing::warn;
+
+use crate::breached_passwords::BreachedPasswordList;
+
+/// Clases de caracteres que la política puede exigir.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+impl CharacterClass {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "lowercase" => Some(CharacterClass::Lowercase),
+            "uppercase" => Some(CharacterClass::Uppercase),
+            "digit" => Some(CharacterClass::Digit),
+            "symbol" => Some(CharacterClass::Symbol),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharacterClass::Lowercase => c.is_lowercase(),
+            CharacterClass::Uppercase => c.is_uppercase(),
+            CharacterClass::Digit => c.is_numeric(),
+            CharacterClass::Symbol => !c.is_alphanumeric(),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            CharacterClass::Lowercase => "a lowercase letter",
+            CharacterClass::Uppercase => "an uppercase letter",
+            CharacterClass::Digit => "a digit",
+            CharacterClass::Symbol => "a symbol",
+        }
+    }
+}
+
+/// Regla de la política que una contraseña no cumple. Su `Display` es el mensaje que
+/// se devuelve al cliente.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordViolation {
+    TooShort { min: usize },
+    TooLong { max: usize },
+    MissingClass(CharacterClass),
+    ContainsUserInfo,
+    TooWeak { score: u8, min: u8 },
+    Breached,
+}
+
+impl fmt::Display for PasswordViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordViolation::TooShort { min } => write!(f, "Password must be at least {} characters", min),
+            PasswordViolation::TooLong { max } => write!(f, "Password must be at most {} characters", max),
+            PasswordViolation::MissingClass(class) => write!(f, "Password must contain {}", class.description()),
+            PasswordViolation::ContainsUserInfo => write!(f, "Password must not contain your email or name"),
+            PasswordViolation::TooWeak { score, min } => {
+                write!(f, "Password is too easy to guess (strength {}/4, at least {} required)", score, min)
+            }
+            PasswordViolation::Breached => write!(f, "Password has appeared in a data breach"),
+        }
+    }
+}
+
+/// Reglas que deben cumplir las contraseñas nuevas (registro, restablecimiento y cambio).
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub required_classes: Vec<CharacterClass>,
+    /// Rechazar contraseñas que contienen el email (la parte local) o el nombre del usuario
+    pub forbid_user_info: bool,
+    /// Puntuación mínima de `strength_score` (0-4); con 0 no se comprueba
+    pub min_strength: u8,
+    /// Corpus local de contraseñas filtradas; sin él no se comprueba
+    pub breached_passwords: Option<Arc<BreachedPasswordList>>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            required_classes: Vec::new(),
+            forbid_user_info: true,
+            min_strength: 2,
+            breached_passwords: None,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn from_config(config: &AppConfig) -> Result<Self, AppError> {
+        let required_classes = config
+            .password_required_classes
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                CharacterClass::parse(name)
+                    .ok_or_else(|| AppError::Internal(format!("Invalid entry in PASSWORD_REQUIRED_CLASSES: {}", name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if config.password_min_length > config.password_max_length {
+            return Err(AppError::Internal("PASSWORD_MIN_LENGTH must not exceed PASSWORD_MAX_LENGTH".into()));
+        }
+        if config.password_min_strength > 4 {
+            return Err(AppError::Internal("PASSWORD_MIN_STRENGTH must be between 0 and 4".into()));
+        }
+
+        let breached_passwords = match config.password_breach_list_path.as_deref().filter(|path| !path.is_empty()) {
+            Some(path) => Some(Arc::new(BreachedPasswordList::open(path).map_err(|e| {
+                AppError::Internal(format!("Cannot open PASSWORD_BREACH_LIST_PATH '{}': {}", path, e))
+            })?)),
+            None => None,
+        };
+
+        Ok(Self {
+            min_length: config.password_min_length,
+            max_length: config.password_max_length,
+            required_classes,
+            forbid_user_info: config.password_forbid_user_info,
+            min_strength: config.password_min_strength,
+            breached_passwords,
+        })
+    }
+
+    /// Comprueba todas las reglas y devuelve las que no se cumplen.
+    /// `user_inputs` son el email y el nombre del usuario.
+    pub fn check(&self, password: &str, user_inputs: &[&str]) -> Result<(), Vec<PasswordViolation>> {
+        let mut violations = Vec::new();
+
+        let length = password.chars().count();
+        if length < self.min_length {
+            violations.push(PasswordViolation::TooShort { min: self.min_length });
+        }
+        if length > self.max_length {
+            violations.push(PasswordViolation::TooLong { max: self.max_length });
+        }
+        for class in &self.required_classes {
+            if !password.chars().any(|c| class.matches(c)) {
+                violations.push(PasswordViolation::MissingClass(*class));
+            }
+        }
+
+        let user_words = user_words(user_inputs);
+        if self.forbid_user_info {
+            let lowered = password.to_lowercase();
+            if user_words.iter().any(|word| lowered.contains(word.as_str())) {
+                violations.push(PasswordViolation::ContainsUserInfo);
+            }
+        }
+        if self.min_strength > 0 {
+            let score = score_with_words(password, &user_words);
+            if score < self.min_strength {
+                violations.push(PasswordViolation::TooWeak { score, min: self.min_strength });
+            }
+        }
+        if let Some(breached_passwords) = &self.breached_passwords {
+            match breached_passwords.contains(password) {
+                Ok(true) => violations.push(PasswordViolation::Breached),
+                Ok(false) => {}
+                // Un corpus ilegible no debe impedir registrarse ni cambiar la contraseña
+                Err(e) => warn!("Error al consultar la lista de contraseñas filtradas: {}", e),
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+// Contraseñas y palabras frecuentes, de más a menos comunes
+const COMMON_WORDS: &[&str] = &[
+    "password", "qwerty", "letmein", "welcome", "admin", "secret", "dragon", "monkey", "football", "iloveyou",
+    "login", "master", "sunshine", "princess", "shadow", "baseball", "superman", "trustno", "starwars", "hello",
+    "freedom", "whatever", "charlie", "michael", "jordan", "hunter", "ranger", "buster", "soccer", "harley",
+    "batman", "jennifer", "thomas", "computer", "summer", "winter", "spring", "autumn", "love", "pass",
+    "access", "default", "changeme", "user", "root", "test", "guest", "flower", "orange", "banana",
+    "apple", "cookie", "chocolate", "pepper", "ginger", "tigger", "pokemon", "matrix", "ninja", "mustang",
+    "maggie", "killer", "samsung", "google", "internet", "service", "secure", "system", "server", "london",
+    "paris", "berlin", "madrid", "barcelona", "amor", "hola", "contraseña", "clave", "secreto", "usuario",
+    "correct", "horse", "battery", "staple", "family", "friend", "money", "angel", "purple", "silver",
+];
+
+// Filas del teclado para detectar recorridos como "qwerty" o "asdf"
+const KEYBOARD_ROWS: &[&str] = &["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+// Las contraseñas más largas no ganan nada con el análisis y lo encarecen
+const MAX_ANALYZED_CHARS: usize = 100;
+
+/// Estima la robustez de la contraseña al estilo de zxcvbn: de 0 (trivial) a 4 (muy robusta).
+///
+/// Divide la contraseña en los fragmentos que antes adivinaría un atacante (palabras
+/// frecuentes o del propio usuario, también con sustituciones l33t, repeticiones, secuencias
+/// y recorridos de teclado) y puntúa según el orden de magnitud del número de intentos.
+pub fn strength_score(password: &str, user_inputs: &[&str]) -> u8 {
+    score_with_words(password, &user_words(user_inputs))
+}
+
+fn score_with_words(password: &str, user_words: &[String]) -> u8 {
+    let chars: Vec<char> = password.chars().take(MAX_ANALYZED_CHARS).collect();
+    let log_guesses = estimate_log10_guesses(&chars, user_words);
+
+    match log_guesses {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    }
+}
+
+// Segmentación de coste mínimo: best[i] es el log10 de intentos para adivinar chars[..i]
+fn estimate_log10_guesses(chars: &[char], user_words: &[String]) -> f64 {
+    let mut best = vec![f64::INFINITY; chars.len() + 1];
+    best[0] = 0.0;
+    for end in 1..=chars.len() {
+        for start in 0..end {
+            let cost = best[start] + segment_log10_guesses(&chars[start..end], user_words);
+            if cost < best[end] {
+                best[end] = cost;
+            }
+        }
+    }
+    best[chars.len()]
+}
+
+fn segment_log10_guesses(segment: &[char], user_words: &[String]) -> f64 {
+    // Fuerza bruta: 10 intentos por carácter, como zxcvbn
+    let mut guesses = 10f64.powi(segment.len() as i32);
+
+    if let Some(word_guesses) = dictionary_guesses(segment, user_words) {
+        guesses = guesses.min(word_guesses);
+    }
+    if segment.len() >= 3 {
+        if segment.iter().all(|c| *c == segment[0]) {
+            guesses = guesses.min(cardinality(segment[0]) * segment.len() as f64);
+        }
+        if let Some(sequence_guesses) = sequence_guesses(segment) {
+            guesses = guesses.min(sequence_guesses);
+        }
+        if is_keyboard_walk(segment) {
+            guesses = guesses.min(40.0 * segment.len() as f64);
+        }
+    }
+
+    guesses.max(10.0).log10()
+}
+
+fn dictionary_guesses(segment: &[char], user_words: &[String]) -> Option<f64> {
+    if segment.len() < 3 {
+        return None;
+    }
+    let lowered: String = segment.iter().flat_map(|c| c.to_lowercase()).collect();
+    let (unleeted, substitutions) = unleet(&lowered);
+
+    let rank = if user_words.iter().any(|word| *word == lowered || *word == unleeted) {
+        1
+    } else {
+        COMMON_WORDS
+            .iter()
+            .position(|word| *word == lowered || *word == unleeted)
+            .map(|position| position + 2)?
+    };
+
+    Some(rank as f64 * uppercase_variations(segment) * 2f64.powi(substitutions.min(4) as i32))
+}
+
+// Deshace las sustituciones l33t habituales y cuenta cuántas había
+fn unleet(word: &str) -> (String, usize) {
+    let mut substitutions = 0;
+    let unleeted = word
+        .chars()
+        .map(|c| {
+            let plain = match c {
+                '4' | '@' => 'a',
+                '3' => 'e',
+                '1' | '!' => 'i',
+                '0' => 'o',
+                '5' | '$' => 's',
+                '7' => 't',
+                other => other,
+            };
+            if plain != c {
+                substitutions += 1;
+            }
+            plain
+        })
+        .collect();
+    (unleeted, substitutions)
+}
+
+fn uppercase_variations(segment: &[char]) -> f64 {
+    let upper = segment.iter().filter(|c| c.is_uppercase()).count();
+    let lower = segment.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        1.0
+    } else if lower == 0 || (upper == 1 && segment[0].is_uppercase()) {
+        // Todo en mayúsculas o sólo la inicial: lo primero que se prueba
+        2.0
+    } else {
+        2f64.powi(upper.min(lower) as i32 + 1)
+    }
+}
+
+fn sequence_guesses(segment: &[char]) -> Option<f64> {
+    let delta = segment[1] as i64 - segment[0] as i64;
+    if delta.abs() != 1 || segment.windows(2).any(|pair| pair[1] as i64 - pair[0] as i64 != delta) {
+        return None;
+    }
+
+    let base = if matches!(segment[0], 'a' | 'A' | 'z' | 'Z' | '0' | '1' | '9') { 4.0 } else { cardinality(segment[0]) };
+    let direction = if delta > 0 { 1.0 } else { 2.0 };
+    Some(base * direction * segment.len() as f64)
+}
+
+fn is_keyboard_walk(segment: &[char]) -> bool {
+    let lowered: String = segment.iter().flat_map(|c| c.to_lowercase()).collect();
+    let reversed: String = lowered.chars().rev().collect();
+    KEYBOARD_ROWS.iter().any(|row| row.contains(&lowered) || row.contains(&reversed))
+}
+
+fn cardinality(c: char) -> f64 {
+    if c.is_ascii_digit() {
+        10.0
+    } else if c.is_alphabetic() {
+        26.0
+    } else {
+        33.0
+    }
+}
+
+// Palabras del usuario que no deberían aparecer en su contraseña: la parte local del
+// email, el nombre completo y cada una de sus partes de al menos 3 caracteres
+fn user_words(user_inputs: &[&str]) -> Vec<String> {
+    let mut words = Vec::new();
+    for input in user_inputs {
+        let lowered = input.trim().to_lowercase();
+        let local = lowered.split('@').next().unwrap_or_default();
+        words.push(local.to_string());
+        words.extend(local.split(|c: char| !c.is_alphanumeric()).map(str::to_string));
+    }
+    words.retain(|word| word.chars().count() >= 3);
+    words.sort();
+    words.dedup();
+    words
+}
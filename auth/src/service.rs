@@ -1,7 +1,31 @@
 use anyhow::Result;
-use common::jwt::generate_jwt;
-use shared::user::{CreateUserSchema, FilteredUser, LoginUserSchema, User};
-use repository::UserRepository;
+use chrono::Utc;
+use common::config::AppConfig;
+use common::error::AppError;
+use common::jwt::{
+    generate_jwt, parse_duration, sign_jwt, verify_jwt, CustomClaims, JwtConfig, MFA_PENDING_TOKEN_USE, OAUTH_ACCESS_TOKEN_USE,
+    SERVICE_TOKEN_USE,
+};
+use common::mailer::{EmailMessage, Mailer};
+use common::password::Argon2Params;
+use common::telegram::{verify_login_widget, verify_web_app_init_data, TelegramLoginData, TelegramWebAppUser};
+use common::utils::{generate_secure_token, hash_token};
+use shared::oauth::{
+    AuthorizationRequestInfo, AuthorizeError, AuthorizeParams, CreateOAuthClientSchema, CreatedOAuthClient,
+    DeviceAuthorizationInfo, DeviceAuthorizationRequest, DeviceAuthorizationResponse, OAuthClientInfo, OAuthError, TokenRequest,
+    TokenResponse, UserInfo,
+};
+use shared::user::{
+    ApiKeyInfo, CreateApiKeySchema, CreateUserSchema, CreatedApiKey, FederatedLoginStart, FilteredUser, LinkTelegramSchema, LoginUserSchema,
+    PasskeyLoginSchema, PasskeyOptions, PasskeyRegistrationSchema, TotpEnrollment, User,
+};
+use repository::{
+    ApiKey, ApiKeyRepository, DeviceAuthorization, EmailVerificationRepository, FederationRepository, LoginAttemptRepository, LoginAttempts, MfaRepository,
+    NewDeviceAuthorization, NewOAuthAuthorization, NewOAuthClient, OAuthAuthorization, OAuthClient, OAuthRepository, PasswordResetRepository, RefreshToken, RefreshTokenRepository, TotpCredential, UserRepository, WebAuthnChallenge, WebAuthnRepository,
+    DEVICE_STATUS_APPROVED, DEVICE_STATUS_DENIED, DEVICE_STATUS_PENDING, IDENTITY_PROVIDER_TELEGRAM,
+};
+use serde_json::json;
+use std::sync::Arc;
 use uuid::Uuid;
 use serde::Serialize;
 use async_trait::async_trait;
@@ -9,66 +33,1870 @@ use tracing::{info, error, debug, warn};
 
 use crate::{
     error::AuthError,
-    password::{hash_password, verify_password},
+    federation::{FederatedClaims, FederatedLoginSettings, OidcClient},
+    oauth::{self, IdTokenClaims},
+    password::{hash_password, needs_rehash, verify_password},
+    password_policy::PasswordPolicy,
+    totp::{self, constant_time_eq},
+    webauthn::{self, RelyingParty, COSE_ALG_ES256},
 };
 
-#[derive(Serialize)]
-pub struct LoginResponse {
-    pub token: String,
-}
+// Dominio de los emails asignados a usuarios creados desde Telegram
+const TELEGRAM_PLACEHOLDER_EMAIL_DOMAIN: &str = "@users.telegram.invalid";
+
+// Códigos de recuperación que se entregan al activar el TOTP
+const RECOVERY_CODE_COUNT: usize = 10;
+
+// Ceremonias WebAuthn con las que se guardan los challenges
+const PASSKEY_REGISTRATION: &str = "registration";
+const PASSKEY_AUTHENTICATION: &str = "authentication";
+
+// Las claves de API tienen la forma `ak_<prefijo>_<secreto>`; el prefijo se guarda en claro
+const API_KEY_PREFIX: &str = "ak_";
+const API_KEY_PREFIX_BYTES: usize = 6;
+const API_KEY_SECRET_BYTES: usize = 32;
+
+// Identificadores y secretos del proveedor OAuth
+const OAUTH_CLIENT_ID_BYTES: usize = 16;
+const OAUTH_CLIENT_SECRET_BYTES: usize = 32;
+const OAUTH_CODE_BYTES: usize = 32;
+// Segundos que se añaden al intervalo del dispositivo con cada `slow_down`
+const DEVICE_SLOW_DOWN_SECS: i32 = 5;
+
+#[derive(Serialize)]
+pub struct LoginResponse {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+/// Resultado de `login_user`: sesión completa o reto del segundo factor
+pub enum LoginOutcome {
+    Authenticated(FilteredUser, LoginResponse),
+    /// El usuario tiene 2FA: `mfa_token` sólo sirve para canjearlo en `/api/auth/mfa/verify`
+    MfaRequired { mfa_token: String },
+}
+
+/// Parámetros del servicio de autenticación tomados de `AppConfig`
+#[derive(Debug, Clone)]
+pub struct AuthSettings {
+    pub jwt_expires_in: String,
+    pub refresh_token_expires_in: String,
+    pub telegram_bot_token: Option<String>,
+    pub telegram_auth_max_age: String,
+    pub frontend_url: String,
+    pub email_verification_required: bool,
+    pub email_verification_expires_in: String,
+    pub email_verification_resend_interval: String,
+    pub password_reset_expires_in: String,
+    pub totp_issuer: String,
+    pub mfa_token_expires_in: String,
+    /// Códigos que se pueden probar con un mismo token `mfa_pending` antes de invalidarlo
+    pub mfa_max_attempts: u32,
+    pub webauthn_rp_id: String,
+    pub webauthn_rp_name: String,
+    pub webauthn_origin: String,
+    pub webauthn_challenge_expires_in: String,
+    /// Fallos seguidos que bloquean la cuenta; con 0 no se limita el número de intentos
+    pub login_max_failed_attempts: u32,
+    pub login_backoff_base: String,
+    pub login_lockout_duration: String,
+    pub password_policy: PasswordPolicy,
+    /// Coste de Argon2id de los hashes nuevos; los más débiles se regeneran al hacer login
+    pub argon2_params: Argon2Params,
+    /// URL pública del proveedor OpenID Connect (claim `iss` y base de la discovery)
+    pub oauth_issuer: String,
+    pub oauth_consent_expires_in: String,
+    pub oauth_code_expires_in: String,
+    /// Validez del device_code y del user_code del flujo de dispositivo (RFC 8628)
+    pub oauth_device_code_expires_in: String,
+    /// Intervalo mínimo entre consultas del dispositivo a `/oauth/token`
+    pub oauth_device_poll_interval: String,
+    /// Proveedor OpenID Connect externo para iniciar sesión; `None` si no está configurado
+    pub federated_login: Option<FederatedLoginSettings>,
+}
+
+impl AuthSettings {
+    pub fn from_config(config: &AppConfig) -> Result<Self, AppError> {
+        Ok(Self {
+            jwt_expires_in: config.jwt_expires_in.clone(),
+            refresh_token_expires_in: config.refresh_token_expires_in.clone(),
+            telegram_bot_token: config.telegram_bot_token.clone().filter(|token| !token.is_empty()),
+            telegram_auth_max_age: config.telegram_auth_max_age.clone(),
+            frontend_url: config.frontend_url.trim_end_matches('/').to_string(),
+            email_verification_required: config.email_verification_required,
+            email_verification_expires_in: config.email_verification_expires_in.clone(),
+            email_verification_resend_interval: config.email_verification_resend_interval.clone(),
+            password_reset_expires_in: config.password_reset_expires_in.clone(),
+            totp_issuer: config.totp_issuer.clone(),
+            mfa_token_expires_in: config.mfa_token_expires_in.clone(),
+            mfa_max_attempts: config.mfa_max_attempts,
+            webauthn_rp_id: config.webauthn_rp_id.clone(),
+            webauthn_rp_name: config.webauthn_rp_name.clone(),
+            // Sin origen explícito se usa el del frontend
+            webauthn_origin: config
+                .webauthn_origin
+                .clone()
+                .filter(|origin| !origin.is_empty())
+                .unwrap_or_else(|| config.frontend_url.clone())
+                .trim_end_matches('/')
+                .to_string(),
+            webauthn_challenge_expires_in: config.webauthn_challenge_expires_in.clone(),
+            login_max_failed_attempts: config.login_max_failed_attempts,
+            login_backoff_base: config.login_backoff_base.clone(),
+            login_lockout_duration: config.login_lockout_duration.clone(),
+            password_policy: PasswordPolicy::from_config(config)?,
+            argon2_params: Argon2Params::from_config(config).map_err(|e| AppError::Internal(e.to_string()))?,
+            oauth_issuer: config.oauth_issuer.trim_end_matches('/').to_string(),
+            oauth_consent_expires_in: config.oauth_consent_expires_in.clone(),
+            oauth_code_expires_in: config.oauth_code_expires_in.clone(),
+            oauth_device_code_expires_in: config.oauth_device_code_expires_in.clone(),
+            oauth_device_poll_interval: config.oauth_device_poll_interval.clone(),
+            federated_login: FederatedLoginSettings::from_config(config)?,
+        })
+    }
+}
+
+/// Repositorios de tokens y credenciales que usa el servicio además del de usuarios
+#[derive(Clone)]
+pub struct AuthStores {
+    pub refresh_tokens: Arc<dyn RefreshTokenRepository>,
+    pub email_verifications: Arc<dyn EmailVerificationRepository>,
+    pub password_resets: Arc<dyn PasswordResetRepository>,
+    pub mfa: Arc<dyn MfaRepository>,
+    pub webauthn: Arc<dyn WebAuthnRepository>,
+    pub login_attempts: Arc<dyn LoginAttemptRepository>,
+    pub api_keys: Arc<dyn ApiKeyRepository>,
+    pub oauth: Arc<dyn OAuthRepository>,
+    pub federation: Arc<dyn FederationRepository>,
+}
+
+pub struct AuthService<T: UserRepository> {
+    user_repository: T,
+    refresh_token_repository: Arc<dyn RefreshTokenRepository>,
+    email_verification_repository: Arc<dyn EmailVerificationRepository>,
+    password_reset_repository: Arc<dyn PasswordResetRepository>,
+    mfa_repository: Arc<dyn MfaRepository>,
+    webauthn_repository: Arc<dyn WebAuthnRepository>,
+    login_attempt_repository: Arc<dyn LoginAttemptRepository>,
+    api_key_repository: Arc<dyn ApiKeyRepository>,
+    oauth_repository: Arc<dyn OAuthRepository>,
+    federation_repository: Arc<dyn FederationRepository>,
+    federated_provider: Option<OidcClient>,
+    mailer: Arc<dyn Mailer>,
+    jwt_config: Arc<JwtConfig>,
+    settings: AuthSettings,
+}
+
+impl<T: UserRepository> AuthService<T> {
+    pub fn new(
+        user_repository: T,
+        stores: AuthStores,
+        mailer: Arc<dyn Mailer>,
+        jwt_config: Arc<JwtConfig>,
+        settings: AuthSettings,
+    ) -> Self {
+        info!("Inicializando servicio de autenticación");
+        Self {
+            user_repository,
+            refresh_token_repository: stores.refresh_tokens,
+            email_verification_repository: stores.email_verifications,
+            password_reset_repository: stores.password_resets,
+            mfa_repository: stores.mfa,
+            webauthn_repository: stores.webauthn,
+            login_attempt_repository: stores.login_attempts,
+            api_key_repository: stores.api_keys,
+            oauth_repository: stores.oauth,
+            federation_repository: stores.federation,
+            federated_provider: settings.federated_login.clone().map(OidcClient::new),
+            mailer,
+            jwt_config,
+            settings,
+        }
+    }
+
+    pub async fn authenticate_by_email(&self, email: &str, password: &str) -> Result<User, AuthError> {
+        info!("Intentando autenticar usuario con email: {}", email);
+        
+        let user = match self.user_repository.find_user_by_email(email).await {
+            Ok(user) => {
+                info!("Usuario encontrado en la base de datos: {}", email);
+                user
+            },
+            Err(e) => {
+                error!("Error al buscar usuario por email: {}, error: {}", email, e);
+                return Err(AuthError::InvalidCredentials);
+            }
+        };
+
+        // Mientras dure la espera no se comprueba la contraseña ni se cuenta el intento
+        let attempts = self.find_login_attempts(&user.id).await?;
+        if let Some(retry_after) = attempts.as_ref().and_then(|attempts| self.login_retry_after(attempts)) {
+            warn!("Login rechazado, cuenta bloqueada temporalmente: {} ({} s)", email, retry_after);
+            return Err(AuthError::AccountLocked { retry_after });
+        }
+        let reserved = self.reserve_login_attempt(&user, attempts.as_ref()).await?;
+
+        debug!("Verificando contraseña para usuario: {}", email);
+        let is_valid = match self.verify_password(&user.password, password) {
+            Ok(valid) => {
+                if valid {
+                    info!("Contraseña válida para usuario: {}", email);
+                } else {
+                    warn!("Contraseña inválida para usuario: {}", email);
+                }
+                valid
+            },
+            Err(e) => {
+                error!("Error al verificar contraseña: {}", e);
+                return Err(AuthError::PasswordVerifyError(e.to_string()));
+            }
+        };
+        
+        // El intento ya se contó como fallo al reservarlo
+        if !is_valid {
+            if let Some(attempts) = reserved.filter(|attempts| attempts.failed_count as u32 >= self.settings.login_max_failed_attempts) {
+                warn!("Cuenta bloqueada tras {} intentos fallidos: {}", attempts.failed_count, email);
+            }
+            return Err(AuthError::InvalidCredentials);
+        }
+        if reserved.is_some() {
+            self.reset_login_attempts(&user.id).await?;
+        }
+        let user = self.upgrade_password_hash(user, password).await;
+
+        if self.settings.email_verification_required && user.email_verified_at.is_none() {
+            warn!("Login bloqueado, email sin verificar: {}", email);
+            return Err(AuthError::EmailNotVerified);
+        }
+
+        info!("Autenticación exitosa para usuario: {}", email);
+        Ok(user)
+    }
+
+    // Tras verificar la contraseña se regenera el hash si usa otro algoritmo o un coste menor
+    // que el configurado. No cambia `token_version`, así que las sesiones siguen valiendo; si
+    // falla, el login sigue adelante con el hash anterior.
+    async fn upgrade_password_hash(&self, user: User, password: &str) -> User {
+        if !needs_rehash(&user.password, &self.settings.argon2_params) {
+            return user;
+        }
+
+        let upgraded = match hash_password(password, &self.settings.argon2_params) {
+            Ok(hash) => self.user_repository.update_password_hash(&user.id, &hash).await,
+            Err(e) => Err(e.into()),
+        };
+        match upgraded {
+            Ok(upgraded) => {
+                info!("Hash de contraseña actualizado para usuario: {}", upgraded.email);
+                upgraded
+            }
+            Err(e) => {
+                warn!("No se pudo actualizar el hash de contraseña de {}: {}", user.email, e);
+                user
+            }
+        }
+    }
+
+    /// Desbloquea la cuenta olvidando sus intentos de login fallidos (acción de administrador).
+    pub async fn unlock_account(&self, user_id: &Uuid) -> Result<(), AuthError> {
+        let user = self.find_existing_user(user_id).await?;
+        self.reset_login_attempts(user_id).await?;
+        info!("Cuenta desbloqueada: {}", user.email);
+        Ok(())
+    }
+
+    async fn find_login_attempts(&self, user_id: &Uuid) -> Result<Option<LoginAttempts>, AuthError> {
+        if self.settings.login_max_failed_attempts == 0 {
+            return Ok(None);
+        }
+        self.login_attempt_repository
+            .find_login_attempts(user_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))
+    }
+
+    // Cuenta el intento como fallo antes de comprobar la contraseña (un login correcto lo
+    // olvida después), así que las peticiones simultáneas no pueden colarse todas tras la
+    // misma comprobación del bloqueo: sólo una reserva el intento y las demás se rechazan.
+    // Devuelve el recuento con el intento incluido, o `None` si el bloqueo está desactivado.
+    async fn reserve_login_attempt(&self, user: &User, seen: Option<&LoginAttempts>) -> Result<Option<LoginAttempts>, AuthError> {
+        if self.settings.login_max_failed_attempts == 0 {
+            return Ok(None);
+        }
+        let reserved = self
+            .login_attempt_repository
+            .reserve_login_attempt(&user.id, seen.map_or(0, |attempts| attempts.failed_count))
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        let Some(attempts) = reserved else {
+            warn!("Login rechazado, otro intento simultáneo en curso: {}", user.email);
+            let retry_after = self
+                .find_login_attempts(&user.id)
+                .await?
+                .and_then(|attempts| self.login_retry_after(&attempts))
+                .unwrap_or(1);
+            return Err(AuthError::AccountLocked { retry_after });
+        };
+        Ok(Some(attempts))
+    }
+
+    async fn reset_login_attempts(&self, user_id: &Uuid) -> Result<(), AuthError> {
+        self.login_attempt_repository
+            .reset_login_attempts(user_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))
+    }
+
+    /// Segundos que faltan para poder reintentar el login, o `None` si ya se puede.
+    fn login_retry_after(&self, attempts: &LoginAttempts) -> Option<i64> {
+        let remaining = attempts.last_failed_at + self.login_delay(attempts.failed_count) - Utc::now();
+        let remaining_ms = remaining.num_milliseconds();
+        // Se redondea hacia arriba para no invitar a reintentar un instante antes de tiempo
+        (remaining_ms > 0).then(|| (remaining_ms + 999) / 1000)
+    }
+
+    // Espera tras `failed_count` fallos: la base se duplica con cada fallo y al llegar
+    // al máximo de intentos pasa a ser el bloqueo completo
+    fn login_delay(&self, failed_count: i32) -> chrono::Duration {
+        // Una duración mal configurada no debe impedir el login: se trata como 0
+        let lockout = parse_duration(&self.settings.login_lockout_duration).unwrap_or_default();
+        if failed_count as u32 >= self.settings.login_max_failed_attempts {
+            return lockout;
+        }
+        let base = parse_duration(&self.settings.login_backoff_base).unwrap_or_default();
+        let factor = 1i64 << (failed_count - 1).clamp(0, 30);
+        chrono::Duration::milliseconds(base.num_milliseconds().saturating_mul(factor)).min(lockout)
+    }
+
+    /// Autentica con los datos firmados del Telegram Login Widget y busca al usuario vinculado.
+    pub async fn authenticate_by_telegram(&self, data: &TelegramLoginData) -> Result<User, AuthError> {
+        info!("Intentando autenticar usuario con Telegram ID: {}", data.id);
+        self.verify_telegram_login(data)?;
+
+        match self.user_repository.find_by_telegram_user_id(&data.id.to_string()).await {
+            Ok(user) => {
+                info!("Autenticación por Telegram exitosa para usuario: {}", user.email);
+                Ok(user)
+            },
+            Err(e) => {
+                warn!("No hay usuario vinculado al Telegram ID {}: {}", data.id, e);
+                Err(AuthError::InvalidCredentials)
+            }
+        }
+    }
+
+    /// Autentica con el `initData` de la Mini App. Si el Telegram ID no está vinculado
+    /// a ningún usuario se crea uno nuevo.
+    pub async fn authenticate_by_telegram_web_app(&self, init_data: &str) -> Result<User, AuthError> {
+        let telegram_user = self.verify_telegram_web_app(init_data)?;
+        info!("Intentando autenticar usuario de Mini App con Telegram ID: {}", telegram_user.id);
+
+        match self.find_user_by_identity(IDENTITY_PROVIDER_TELEGRAM, &telegram_user.id.to_string()).await? {
+            Some(user) => {
+                info!("Autenticación por Mini App exitosa para usuario: {}", user.email);
+                Ok(user)
+            },
+            None => self.provision_telegram_user(&telegram_user).await,
+        }
+    }
+
+    /// Vincula al usuario la cuenta de Telegram probada por los datos firmados
+    /// (Login Widget o `initData`). Devuelve el Telegram ID vinculado.
+    pub async fn link_telegram(&self, user_id: &Uuid, link: &LinkTelegramSchema) -> Result<String, AuthError> {
+        let telegram_id = match (&link.telegram, &link.init_data) {
+            (Some(data), _) => {
+                self.verify_telegram_login(data)?;
+                data.id.to_string()
+            },
+            (None, Some(init_data)) => self.verify_telegram_web_app(init_data)?.id.to_string(),
+            (None, None) => {
+                return Err(AuthError::InvalidTelegramData("Se requieren los datos firmados de Telegram".into()));
+            }
+        };
+        info!("Vinculando Telegram ID {} al usuario {}", telegram_id, user_id);
+
+        let user = self.find_existing_user(user_id).await?;
+        match user.telegram_user_id.as_deref() {
+            Some(current) if current == telegram_id => return Ok(telegram_id),
+            Some(_) => return Err(AuthError::TelegramLinkExists),
+            None => {}
+        }
+
+        if let Some(owner) = self.find_user_by_identity(IDENTITY_PROVIDER_TELEGRAM, &telegram_id).await? {
+            warn!("Telegram ID {} ya vinculado al usuario {}", telegram_id, owner.id);
+            return Err(AuthError::TelegramAlreadyLinked);
+        }
+
+        // El índice único cubre la carrera entre dos vinculaciones simultáneas
+        self.user_repository
+            .link_identity(user_id, IDENTITY_PROVIDER_TELEGRAM, &telegram_id, json!({}))
+            .await
+            .map_err(|e| match e.downcast_ref::<sqlx::Error>() {
+                Some(sqlx::Error::Database(db)) if db.is_unique_violation() => AuthError::TelegramAlreadyLinked,
+                _ => AuthError::DatabaseError(e.to_string()),
+            })?;
+
+        info!("Telegram ID {} vinculado al usuario {}", telegram_id, user_id);
+        Ok(telegram_id)
+    }
+
+    /// Desvincula la cuenta de Telegram del usuario.
+    pub async fn unlink_telegram(&self, user_id: &Uuid) -> Result<(), AuthError> {
+        let user = self.find_existing_user(user_id).await?;
+        if user.telegram_user_id.is_none() {
+            return Err(AuthError::TelegramNotLinked);
+        }
+        // Los usuarios creados desde la Mini App no tienen otra forma de iniciar sesión
+        if user.email.ends_with(TELEGRAM_PLACEHOLDER_EMAIL_DOMAIN) {
+            return Err(AuthError::TelegramOnlyLoginMethod);
+        }
+
+        self.user_repository
+            .unlink_identity(user_id, IDENTITY_PROVIDER_TELEGRAM)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        info!("Cuenta de Telegram desvinculada del usuario {}", user_id);
+        Ok(())
+    }
+
+    fn telegram_settings(&self) -> Result<(&str, chrono::Duration), AuthError> {
+        let bot_token = self.settings.telegram_bot_token.as_deref().ok_or_else(|| {
+            error!("TELEGRAM_BOT_TOKEN no configurado");
+            AuthError::TelegramNotConfigured
+        })?;
+        let max_age = parse_duration(&self.settings.telegram_auth_max_age)
+            .map_err(|e| AuthError::InvalidTelegramData(e.to_string()))?;
+        Ok((bot_token, max_age))
+    }
+
+    fn verify_telegram_login(&self, data: &TelegramLoginData) -> Result<(), AuthError> {
+        let (bot_token, max_age) = self.telegram_settings()?;
+        verify_login_widget(data, bot_token, max_age).map_err(|e| {
+            warn!("Datos de Telegram rechazados para ID {}: {}", data.id, e);
+            AuthError::InvalidTelegramData(e.to_string())
+        })
+    }
+
+    fn verify_telegram_web_app(&self, init_data: &str) -> Result<TelegramWebAppUser, AuthError> {
+        let (bot_token, max_age) = self.telegram_settings()?;
+        let data = verify_web_app_init_data(init_data, bot_token, max_age).map_err(|e| {
+            warn!("initData de Telegram rechazado: {}", e);
+            AuthError::InvalidTelegramData(e.to_string())
+        })?;
+        data.user
+            .ok_or_else(|| AuthError::InvalidTelegramData("initData sin usuario".into()))
+    }
+
+    // `None` si ningún usuario tiene vinculada esa cuenta del proveedor
+    async fn find_user_by_identity(&self, provider: &str, subject: &str) -> Result<Option<User>, AuthError> {
+        match self.user_repository.find_by_identity(provider, subject).await {
+            Ok(user) => Ok(Some(user)),
+            Err(e) if is_row_not_found(&e) => Ok(None),
+            Err(e) => {
+                error!("Error al buscar usuario por la identidad {} de {}: {}", subject, provider, e);
+                Err(AuthError::DatabaseError(e.to_string()))
+            }
+        }
+    }
+
+    async fn find_existing_user(&self, user_id: &Uuid) -> Result<User, AuthError> {
+        self.user_repository.find_user_by_id(user_id).await.map_err(|e| {
+            if is_row_not_found(&e) {
+                AuthError::UserNotFound
+            } else {
+                AuthError::DatabaseError(e.to_string())
+            }
+        })
+    }
+
+    // Los usuarios creados desde Telegram no tienen email ni contraseña propios: se usa un
+    // email no entregable y una contraseña aleatoria que nadie conoce.
+    async fn provision_telegram_user(&self, telegram_user: &TelegramWebAppUser) -> Result<User, AuthError> {
+        info!("Creando usuario para Telegram ID: {}", telegram_user.id);
+        let user_data = CreateUserSchema {
+            email: format!("telegram-{}{}", telegram_user.id, TELEGRAM_PLACEHOLDER_EMAIL_DOMAIN),
+            password: generate_secure_token(32),
+            name: telegram_user.display_name(),
+        };
+        let hashed_password = hash_password(&user_data.password, &self.settings.argon2_params)?;
+
+        self.user_repository
+            .create_user(&user_data, &hashed_password, Some(telegram_user.id.to_string()))
+            .await
+            .map_err(|e| {
+                error!("Error al crear usuario de Telegram {}: {}", telegram_user.id, e);
+                AuthError::DatabaseError(e.to_string())
+            })
+    }
+
+    /// Prepara el login con el proveedor OpenID Connect externo y devuelve la URL a la que
+    /// enviar al usuario. El `state`, el `nonce` y el verificador PKCE quedan guardados hasta
+    /// que vuelva; el hash del `state` lo guarda además el navegador para la callback.
+    pub async fn start_federated_login(&self) -> Result<FederatedLoginStart, AuthError> {
+        let provider = self.federated_provider()?;
+        let expires_in = parse_duration(&provider.settings().login_expires_in)
+            .map_err(|e| AuthError::TokenGenerationError(e.to_string()))?;
+        let state = generate_secure_token(32);
+        let nonce = generate_secure_token(16);
+        let code_verifier = generate_secure_token(32);
+
+        // La URL va primero: si el proveedor no responde no queda ninguna solicitud huérfana
+        let authorization_url = provider.authorization_url(&state, &nonce, &code_verifier).await?;
+        let state_hash = hash_token(&state);
+        self.federation_repository
+            .create_login_request(&state_hash, &nonce, &code_verifier, Utc::now() + expires_in)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        Ok(FederatedLoginStart { authorization_url, state_binding: state_hash, expires_in: expires_in.num_seconds() })
+    }
+
+    /// Completa el login con el `code` y el `state` con los que vuelve el usuario: canjea el
+    /// código, valida el ID token y devuelve el usuario local vinculado (o uno nuevo).
+    /// `state_binding` es el valor que recibió el navegador al iniciar el login; sin él, un
+    /// atacante podría hacer que la víctima complete su propio login (login CSRF).
+    pub async fn finish_federated_login(&self, code: &str, state: &str, state_binding: Option<&str>) -> Result<User, AuthError> {
+        let provider = self.federated_provider()?;
+        let state_hash = hash_token(state);
+        // Antes de consumir la solicitud, para que un intento ajeno no la invalide
+        if !state_binding.is_some_and(|binding| constant_time_eq(binding.as_bytes(), state_hash.as_bytes())) {
+            return Err(AuthError::InvalidFederatedLogin("el state no se inició en este navegador".into()));
+        }
+        let request = self
+            .federation_repository
+            .consume_login_request(&state_hash)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| AuthError::InvalidFederatedLogin("state desconocido, ya usado o caducado".into()))?;
+
+        let id_token = provider.exchange_code(code, &request.code_verifier).await?;
+        let claims = provider.verify_id_token(&id_token, &request.nonce).await?;
+        info!("Login federado de {} en {}", claims.sub, claims.iss);
+
+        self.federated_user(&claims).await
+    }
+
+    fn federated_provider(&self) -> Result<&OidcClient, AuthError> {
+        self.federated_provider.as_ref().ok_or(AuthError::FederatedLoginNotConfigured)
+    }
+
+    // Usuario de la identidad ya vinculada; si no la hay, se vincula la cuenta con el mismo
+    // email (sólo si el proveedor lo da por verificado) o se crea una nueva.
+    async fn federated_user(&self, claims: &FederatedClaims) -> Result<User, AuthError> {
+        if let Some(user) = self.find_user_by_identity(&claims.iss, &claims.sub).await? {
+            return Ok(user);
+        }
+
+        let email = claims
+            .email
+            .as_deref()
+            .filter(|email| !email.is_empty())
+            .ok_or_else(|| AuthError::InvalidFederatedLogin("el ID token no incluye el email".into()))?;
+        let user = match self.user_repository.find_user_by_email(email).await {
+            Ok(user) if claims.email_verified => user,
+            // Un email sin verificar no prueba que la cuenta local sea de la misma persona
+            Ok(user) => {
+                warn!("Email {} del proveedor sin verificar; no se vincula al usuario {}", email, user.id);
+                return Err(AuthError::FederatedEmailConflict);
+            }
+            Err(e) if is_row_not_found(&e) => self.provision_federated_user(email, claims).await?,
+            Err(e) => return Err(AuthError::DatabaseError(e.to_string())),
+        };
+
+        self.user_repository
+            .link_identity(&user.id, &claims.iss, &claims.sub, json!({ "email": email }))
+            .await
+            .map_err(|e| match e.downcast_ref::<sqlx::Error>() {
+                // El usuario ya tiene vinculada otra cuenta de este proveedor
+                Some(sqlx::Error::Database(db)) if db.is_unique_violation() => AuthError::FederatedEmailConflict,
+                _ => AuthError::DatabaseError(e.to_string()),
+            })?;
+        info!("Identidad {} de {} vinculada al usuario {}", claims.sub, claims.iss, user.id);
+        Ok(user)
+    }
+
+    // Como en Telegram, el usuario no tiene contraseña propia: se le asigna una aleatoria que nadie conoce.
+    async fn provision_federated_user(&self, email: &str, claims: &FederatedClaims) -> Result<User, AuthError> {
+        info!("Creando usuario para la identidad {} de {}", claims.sub, claims.iss);
+        let user_data = CreateUserSchema {
+            email: email.to_string(),
+            password: generate_secure_token(32),
+            name: claims.name.clone(),
+        };
+        let hashed_password = hash_password(&user_data.password, &self.settings.argon2_params)?;
+
+        let user = self
+            .user_repository
+            .create_user(&user_data, &hashed_password, None)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        if !claims.email_verified {
+            return Ok(user);
+        }
+        self.user_repository
+            .mark_email_verified(&user.id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))
+    }
+
+    /// Consume un token de verificación y marca el email del usuario como verificado.
+    pub async fn verify_email(&self, token: &str) -> Result<(), AuthError> {
+        let stored = self
+            .email_verification_repository
+            .consume_verification_token(&hash_token(token))
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::InvalidVerificationToken)?;
+
+        let user = self
+            .user_repository
+            .mark_email_verified(&stored.user_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        // Los demás enlaces enviados dejan de servir
+        self.email_verification_repository
+            .invalidate_verification_tokens(&user.id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        info!("Email verificado para usuario: {}", user.email);
+        Ok(())
+    }
+
+    /// Reenvía el correo de verificación. No revela si el email existe: los emails
+    /// desconocidos, ya verificados o con un envío demasiado reciente se ignoran.
+    pub async fn resend_verification_email(&self, email: &str) -> Result<(), AuthError> {
+        let user = match self.user_repository.find_user_by_email(email).await {
+            Ok(user) => user,
+            Err(e) if is_row_not_found(&e) => {
+                debug!("Reenvío de verificación para email desconocido: {}", email);
+                return Ok(());
+            },
+            Err(e) => return Err(AuthError::DatabaseError(e.to_string())),
+        };
+        if user.email_verified_at.is_some() {
+            debug!("Reenvío de verificación para email ya verificado: {}", email);
+            return Ok(());
+        }
+
+        let resend_interval = parse_duration(&self.settings.email_verification_resend_interval)
+            .map_err(|e| AuthError::TokenGenerationError(e.to_string()))?;
+        let latest = self
+            .email_verification_repository
+            .find_latest_verification_token(&user.id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        if let Some(latest) = latest {
+            if latest.created_at + resend_interval > Utc::now() {
+                warn!("Reenvío de verificación limitado para: {}", email);
+                return Ok(());
+            }
+        }
+
+        self.email_verification_repository
+            .invalidate_verification_tokens(&user.id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        self.send_verification_email(&user).await
+    }
+
+    async fn send_verification_email(&self, user: &User) -> Result<(), AuthError> {
+        let expires_in = parse_duration(&self.settings.email_verification_expires_in)
+            .map_err(|e| AuthError::TokenGenerationError(e.to_string()))?;
+        let token = generate_secure_token(32);
+
+        self.email_verification_repository
+            .create_verification_token(&user.id, &hash_token(&token), Utc::now() + expires_in)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        let link = format!("{}/verify-email?token={}", self.settings.frontend_url, token);
+        self.mailer
+            .send(EmailMessage {
+                to: user.email.clone(),
+                subject: "Verifica tu email".into(),
+                body: format!("Para verificar tu email abre el siguiente enlace:\n\n{}\n\nEl enlace caduca en {}.", link, self.settings.email_verification_expires_in),
+            })
+            .await
+            .map_err(|e| AuthError::EmailDeliveryError(e.to_string()))?;
+
+        debug!("Correo de verificación enviado a {}", user.email);
+        Ok(())
+    }
+
+    /// Envía un enlace de recuperación de contraseña. No revela si el email existe:
+    /// para emails desconocidos simplemente no se envía nada.
+    pub async fn request_password_reset(&self, email: &str) -> Result<(), AuthError> {
+        let user = match self.user_repository.find_user_by_email(email).await {
+            Ok(user) => user,
+            Err(e) if is_row_not_found(&e) => {
+                debug!("Recuperación de contraseña para email desconocido: {}", email);
+                return Ok(());
+            },
+            Err(e) => return Err(AuthError::DatabaseError(e.to_string())),
+        };
+
+        let expires_in = parse_duration(&self.settings.password_reset_expires_in)
+            .map_err(|e| AuthError::TokenGenerationError(e.to_string()))?;
+        let token = generate_secure_token(32);
+
+        // Sólo el último enlace enviado es válido
+        self.password_reset_repository
+            .invalidate_reset_tokens(&user.id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        self.password_reset_repository
+            .create_reset_token(&user.id, &hash_token(&token), Utc::now() + expires_in)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        let link = format!("{}/reset-password?token={}", self.settings.frontend_url, token);
+        self.mailer
+            .send(EmailMessage {
+                to: user.email.clone(),
+                subject: "Recupera tu contraseña".into(),
+                body: format!("Para elegir una contraseña nueva abre el siguiente enlace:\n\n{}\n\nEl enlace caduca en {}. Si no lo has pedido, ignora este correo.", link, self.settings.password_reset_expires_in),
+            })
+            .await
+            .map_err(|e| AuthError::EmailDeliveryError(e.to_string()))?;
+
+        info!("Enlace de recuperación de contraseña enviado a {}", user.email);
+        Ok(())
+    }
+
+    /// Consume un token de recuperación, guarda la contraseña nueva y cierra las sesiones abiertas.
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<(), AuthError> {
+        let token_hash = hash_token(token);
+
+        // La política se comprueba antes de consumir el token para que una contraseña
+        // rechazada no obligue a pedir otro enlace
+        let pending = self
+            .password_reset_repository
+            .find_reset_token(&token_hash)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::InvalidResetToken)?;
+        let user = self.find_existing_user(&pending.user_id).await?;
+        self.check_password_policy(new_password, &user.email, user.name.as_deref())?;
+
+        let stored = self
+            .password_reset_repository
+            .consume_reset_token(&token_hash)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::InvalidResetToken)?;
+
+        let hashed_password = hash_password(new_password, &self.settings.argon2_params)?;
+        let user = self
+            .user_repository
+            .update_password(&stored.user_id, &hashed_password)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        self.password_reset_repository
+            .invalidate_reset_tokens(&user.id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        self.refresh_token_repository
+            .revoke_user_refresh_tokens(&user.id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        info!("Contraseña restablecida para usuario: {}", user.email);
+        Ok(())
+    }
+
+    /// Cambia la contraseña del usuario autenticado. Incrementa `token_version` y revoca los
+    /// refresh tokens, así que todas las sesiones anteriores (incluida la actual) dejan de
+    /// valer; devuelve un par de tokens nuevo para la sesión que hizo el cambio.
+    pub async fn change_password(&self, user_id: &Uuid, current_password: &str, new_password: &str) -> Result<(String, String), AuthError> {
+        let user = self.find_existing_user(user_id).await?;
+
+        let is_valid = self
+            .verify_password(&user.password, current_password)
+            .map_err(|e| AuthError::PasswordVerifyError(e.to_string()))?;
+        if !is_valid {
+            warn!("Contraseña actual incorrecta al cambiar la contraseña de: {}", user.email);
+            return Err(AuthError::InvalidCurrentPassword);
+        }
+        if current_password == new_password {
+            return Err(AuthError::PasswordUnchanged);
+        }
+        self.check_password_policy(new_password, &user.email, user.name.as_deref())?;
+
+        let hashed_password = hash_password(new_password, &self.settings.argon2_params)?;
+        let user = self
+            .user_repository
+            .update_password(user_id, &hashed_password)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        self.refresh_token_repository
+            .revoke_user_refresh_tokens(user_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        let token = self.generate_access_token(&user)?;
+        let (_, refresh_token) = self.issue_refresh_token(user_id, None).await?;
+
+        info!("Contraseña cambiada para usuario: {} (token_version {})", user.email, user.token_version);
+        Ok((token, refresh_token))
+    }
+
+    /// `token_version` actual del usuario, con la que se comparan los tokens recibidos.
+    pub async fn current_token_version(&self, user_id: &Uuid) -> Result<i32, AuthError> {
+        Ok(self.find_existing_user(user_id).await?.token_version)
+    }
+
+    /// Genera un secreto TOTP pendiente de confirmar. Repetirlo antes de confirmar
+    /// sustituye el secreto anterior.
+    pub async fn start_totp_enrollment(&self, user_id: &Uuid) -> Result<TotpEnrollment, AuthError> {
+        let user = self.find_existing_user(user_id).await?;
+        if self.find_totp(user_id).await?.is_some_and(|credential| credential.enabled_at.is_some()) {
+            return Err(AuthError::MfaAlreadyEnabled);
+        }
+
+        let secret = totp::generate_secret();
+        // No devuelve fila si otra petición activó el TOTP mientras tanto
+        self.mfa_repository
+            .save_pending_totp(user_id, &secret)
+            .await
+            .map_err(|e| {
+                if is_row_not_found(&e) {
+                    AuthError::MfaAlreadyEnabled
+                } else {
+                    AuthError::DatabaseError(e.to_string())
+                }
+            })?;
+
+        info!("Inscripción TOTP iniciada para usuario: {}", user.email);
+        let otpauth_uri = totp::otpauth_uri(&secret, &self.settings.totp_issuer, &user.email);
+        Ok(TotpEnrollment { secret, otpauth_uri })
+    }
+
+    /// Activa el TOTP con el primer código válido y devuelve los códigos de recuperación
+    /// en claro; sólo se guarda su hash, así que no se pueden volver a consultar.
+    pub async fn confirm_totp_enrollment(&self, user_id: &Uuid, code: &str) -> Result<Vec<String>, AuthError> {
+        let credential = self.find_totp(user_id).await?.ok_or(AuthError::MfaNotEnrolled)?;
+        if credential.enabled_at.is_some() {
+            return Err(AuthError::MfaAlreadyEnabled);
+        }
+        let step = totp::verify_code(&credential.secret, code, Utc::now().timestamp())
+            .ok_or(AuthError::InvalidMfaCode)?;
+
+        let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect();
+        let code_hashes: Vec<String> = recovery_codes.iter().map(|code| hash_recovery_code(code)).collect();
+        let enabled = self
+            .mfa_repository
+            .enable_totp(user_id, step, &code_hashes)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        if !enabled {
+            return Err(AuthError::MfaAlreadyEnabled);
+        }
+
+        info!("TOTP activado para usuario {}", user_id);
+        Ok(recovery_codes)
+    }
+
+    /// Desactiva el 2FA tras comprobar un código TOTP o de recuperación.
+    pub async fn disable_totp(&self, user_id: &Uuid, code: Option<&str>, recovery_code: Option<&str>) -> Result<(), AuthError> {
+        let credential = self.find_enabled_totp(user_id).await?;
+        self.verify_second_factor(&credential, code, recovery_code).await?;
+
+        self.mfa_repository
+            .delete_mfa(user_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        info!("TOTP desactivado para usuario {}", user_id);
+        Ok(())
+    }
+
+    /// Si el usuario tiene 2FA activado, devuelve el token `mfa_pending` de corta duración
+    /// que sustituye a la sesión hasta que se presente el segundo factor.
+    pub async fn mfa_challenge(&self, user: &User) -> Result<Option<String>, AuthError> {
+        let enabled = self
+            .find_totp(&user.id)
+            .await?
+            .is_some_and(|credential| credential.enabled_at.is_some());
+        if !enabled {
+            return Ok(None);
+        }
+
+        let custom_claims = CustomClaims {
+            role: None,
+            email: None,
+            token_version: Some(user.token_version),
+            token_use: Some(MFA_PENDING_TOKEN_USE.to_string()),
+            scope: None,
+        };
+        let mfa_token = generate_jwt(&user.id.to_string(), custom_claims, &self.jwt_config, &self.settings.mfa_token_expires_in)
+            .map_err(|e| AuthError::TokenGenerationError(e.to_string()))?;
+
+        info!("Segundo factor requerido para usuario: {}", user.email);
+        Ok(Some(mfa_token))
+    }
+
+    /// Completa el login de un usuario con 2FA comprobando el segundo factor. Cada código
+    /// probado gasta uno de los `mfa_max_attempts` intentos del reto `challenge_id` (el `jti`
+    /// del token `mfa_pending`); `token_version` es el claim `ver` de ese token.
+    pub async fn verify_mfa(
+        &self,
+        challenge_id: &str,
+        user_id: &Uuid,
+        token_version: i32,
+        code: Option<&str>,
+        recovery_code: Option<&str>,
+    ) -> Result<User, AuthError> {
+        // Un cambio de contraseña posterior al login invalida el reto: se comprueba antes de
+        // gastar un intento o un código de recuperación
+        let user = self.find_existing_user(user_id).await?;
+        if user.token_version != token_version {
+            return Err(AuthError::InvalidToken("reto MFA anterior al último cambio de contraseña".into()));
+        }
+        let credential = self.find_enabled_totp(user_id).await?;
+
+        // El reto se acepta hasta su expiración más el margen de `verify_jwt`
+        let expires_at = Utc::now()
+            + parse_duration(&self.settings.mfa_token_expires_in).unwrap_or_default()
+            + chrono::Duration::seconds(self.jwt_config.leeway as i64);
+        let max_attempts = i32::try_from(self.settings.mfa_max_attempts).unwrap_or(i32::MAX);
+        // El intento se reserva antes de comprobar el código, así que las peticiones
+        // concurrentes no pueden superar el límite
+        let reserved = self
+            .mfa_repository
+            .reserve_challenge_attempt(challenge_id, user_id, max_attempts, expires_at)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        if !reserved {
+            warn!("Reto MFA {} del usuario {} sin intentos restantes", challenge_id, user_id);
+            return Err(AuthError::MfaChallengeExhausted);
+        }
+        self.verify_second_factor(&credential, code, recovery_code).await?;
+
+        info!("Segundo factor verificado para usuario: {}", user.email);
+        Ok(user)
+    }
+
+    async fn find_totp(&self, user_id: &Uuid) -> Result<Option<TotpCredential>, AuthError> {
+        self.mfa_repository
+            .find_totp(user_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))
+    }
+
+    async fn find_enabled_totp(&self, user_id: &Uuid) -> Result<TotpCredential, AuthError> {
+        self.find_totp(user_id)
+            .await?
+            .filter(|credential| credential.enabled_at.is_some())
+            .ok_or(AuthError::MfaNotEnrolled)
+    }
+
+    // Un código TOTP sólo vale una vez (se guarda el último paso usado); un código de
+    // recuperación se marca como usado al consumirlo.
+    async fn verify_second_factor(
+        &self,
+        credential: &TotpCredential,
+        code: Option<&str>,
+        recovery_code: Option<&str>,
+    ) -> Result<(), AuthError> {
+        let user_id = &credential.user_id;
+        match (code, recovery_code) {
+            (Some(code), _) => {
+                let step = totp::verify_code(&credential.secret, code, Utc::now().timestamp())
+                    .ok_or(AuthError::InvalidMfaCode)?;
+                let fresh = self
+                    .mfa_repository
+                    .record_totp_step(user_id, step)
+                    .await
+                    .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+                if !fresh {
+                    warn!("Código TOTP reutilizado por usuario {}", user_id);
+                    return Err(AuthError::InvalidMfaCode);
+                }
+            },
+            (None, Some(recovery_code)) => {
+                let consumed = self
+                    .mfa_repository
+                    .consume_recovery_code(user_id, &hash_recovery_code(recovery_code))
+                    .await
+                    .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+                if !consumed {
+                    warn!("Código de recuperación inválido para usuario {}", user_id);
+                    return Err(AuthError::InvalidMfaCode);
+                }
+                info!("Código de recuperación usado por usuario {}", user_id);
+            },
+            (None, None) => return Err(AuthError::InvalidMfaCode),
+        }
+        Ok(())
+    }
+
+    /// Opciones para `navigator.credentials.create()`. El challenge se guarda en el servidor
+    /// y sólo vale para una ceremonia de registro de este usuario.
+    pub async fn start_passkey_registration(&self, user_id: &Uuid) -> Result<PasskeyOptions, AuthError> {
+        let user = self.find_existing_user(user_id).await?;
+        let existing = self
+            .webauthn_repository
+            .find_credentials_for_user(user_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        let (challenge, timeout) = self.create_passkey_challenge(Some(user_id), PASSKEY_REGISTRATION).await?;
+
+        let public_key = json!({
+            "challenge": challenge.challenge,
+            "rp": { "id": self.settings.webauthn_rp_id, "name": self.settings.webauthn_rp_name },
+            "user": {
+                "id": webauthn::encode_b64url(user.id.as_bytes()),
+                "name": user.email,
+                "displayName": user.name.clone().unwrap_or_else(|| user.email.clone()),
+            },
+            "pubKeyCredParams": [{ "type": "public-key", "alg": COSE_ALG_ES256 }],
+            "timeout": timeout,
+            "attestation": "none",
+            "excludeCredentials": existing
+                .iter()
+                .map(|credential| json!({ "type": "public-key", "id": credential.credential_id }))
+                .collect::<Vec<_>>(),
+            // Credencial residente (passkey) para poder iniciar sesión sin indicar el usuario
+            "authenticatorSelection": {
+                "residentKey": "required",
+                "requireResidentKey": true,
+                "userVerification": "required",
+            },
+        });
+
+        Ok(PasskeyOptions { challenge_id: challenge.id, public_key })
+    }
+
+    /// Verifica la respuesta del autenticador y guarda la passkey. Devuelve su credential ID.
+    pub async fn finish_passkey_registration(&self, user_id: &Uuid, registration: &PasskeyRegistrationSchema) -> Result<String, AuthError> {
+        let challenge = self.consume_passkey_challenge(&registration.challenge_id, PASSKEY_REGISTRATION).await?;
+        if challenge.user_id != Some(*user_id) {
+            warn!("Challenge de registro WebAuthn de otro usuario usado por {}", user_id);
+            return Err(AuthError::InvalidPasskey("Challenge inválido o caducado".into()));
+        }
+
+        let response = &registration.credential.response;
+        let registered = webauthn::verify_registration(
+            &self.relying_party(),
+            &challenge.challenge,
+            &webauthn::decode_b64url(&response.client_data_json)?,
+            &webauthn::decode_b64url(&response.attestation_object)?,
+        )?;
+        let credential_id = webauthn::encode_b64url(&registered.credential_id);
+        if credential_id != registration.credential.id.trim_end_matches('=') {
+            return Err(AuthError::InvalidPasskey("El credential ID no coincide".into()));
+        }
+
+        self.webauthn_repository
+            .create_credential(
+                user_id,
+                &credential_id,
+                &registered.public_key,
+                i64::from(registered.sign_count),
+                registration.name.as_deref(),
+            )
+            .await
+            .map_err(|e| match e.downcast_ref::<sqlx::Error>() {
+                Some(sqlx::Error::Database(db)) if db.is_unique_violation() => AuthError::PasskeyAlreadyRegistered,
+                _ => AuthError::DatabaseError(e.to_string()),
+            })?;
+
+        info!("Passkey registrada para usuario {}", user_id);
+        Ok(credential_id)
+    }
+
+    /// Opciones para `navigator.credentials.get()`. No se indica el usuario: el autenticador
+    /// ofrece sus passkeys y la credencial elegida identifica al usuario.
+    pub async fn start_passkey_login(&self) -> Result<PasskeyOptions, AuthError> {
+        let (challenge, timeout) = self.create_passkey_challenge(None, PASSKEY_AUTHENTICATION).await?;
+
+        let public_key = json!({
+            "challenge": challenge.challenge,
+            "rpId": self.settings.webauthn_rp_id,
+            "timeout": timeout,
+            "userVerification": "required",
+            "allowCredentials": [],
+        });
+
+        Ok(PasskeyOptions { challenge_id: challenge.id, public_key })
+    }
+
+    /// Verifica la firma del autenticador con la passkey guardada y devuelve su usuario.
+    pub async fn finish_passkey_login(&self, login: &PasskeyLoginSchema) -> Result<User, AuthError> {
+        let challenge = self.consume_passkey_challenge(&login.challenge_id, PASSKEY_AUTHENTICATION).await?;
+
+        let credential = self
+            .webauthn_repository
+            .find_credential(login.credential.id.trim_end_matches('='))
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| AuthError::InvalidPasskey("Passkey no registrada".into()))?;
+
+        let response = &login.credential.response;
+        if let Some(user_handle) = &response.user_handle {
+            if webauthn::decode_b64url(user_handle)? != credential.user_id.as_bytes() {
+                return Err(AuthError::InvalidPasskey("El user handle no coincide".into()));
+            }
+        }
+
+        let sign_count = webauthn::verify_assertion(
+            &self.relying_party(),
+            &challenge.challenge,
+            &webauthn::decode_b64url(&response.client_data_json)?,
+            &webauthn::decode_b64url(&response.authenticator_data)?,
+            &webauthn::decode_b64url(&response.signature)?,
+            &credential.public_key,
+        )?;
+
+        // Un contador que no avanza indica un autenticador clonado; las passkeys
+        // sincronizadas envían siempre 0 y no se pueden comprobar. El repositorio sólo
+        // lo guarda si avanza, así que tampoco pasan dos aserciones simultáneas.
+        let advanced = self
+            .webauthn_repository
+            .update_sign_count(&credential.id, i64::from(sign_count))
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        if !advanced {
+            warn!("Contador de firmas no válido para la passkey {} del usuario {}", credential.id, credential.user_id);
+            return Err(AuthError::InvalidPasskey("Contador de firmas no válido".into()));
+        }
+
+        let user = self.find_existing_user(&credential.user_id).await?;
+        info!("Login con passkey para usuario: {}", user.email);
+        Ok(user)
+    }
+
+    fn relying_party(&self) -> RelyingParty<'_> {
+        RelyingParty {
+            id: &self.settings.webauthn_rp_id,
+            origin: &self.settings.webauthn_origin,
+        }
+    }
+
+    // Devuelve el challenge guardado y su validez en milisegundos (el `timeout` de WebAuthn)
+    async fn create_passkey_challenge(&self, user_id: Option<&Uuid>, ceremony: &str) -> Result<(WebAuthnChallenge, i64), AuthError> {
+        let expires_in = parse_duration(&self.settings.webauthn_challenge_expires_in)
+            .map_err(|e| AuthError::TokenGenerationError(e.to_string()))?;
+        let challenge = self
+            .webauthn_repository
+            .create_challenge(user_id, ceremony, &webauthn::generate_challenge(), Utc::now() + expires_in)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        Ok((challenge, expires_in.num_milliseconds()))
+    }
+
+    async fn consume_passkey_challenge(&self, challenge_id: &Uuid, ceremony: &str) -> Result<WebAuthnChallenge, AuthError> {
+        self.webauthn_repository
+            .consume_challenge(challenge_id, ceremony)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| AuthError::InvalidPasskey("Challenge inválido o caducado".into()))
+    }
+
+    /// Crea una clave de API y la devuelve en claro junto con sus datos; sólo se guarda su hash,
+    /// así que no se puede volver a consultar.
+    pub async fn create_api_key(&self, user_id: &Uuid, request: &CreateApiKeySchema) -> Result<(ApiKey, String), AuthError> {
+        let user = self.find_existing_user(user_id).await?;
+        let expires_at = match &request.expires_in {
+            Some(expires_in) => {
+                let expires_in = parse_duration(expires_in)
+                    .ok()
+                    .filter(|expires_in| *expires_in > chrono::Duration::zero())
+                    .ok_or_else(|| AuthError::InvalidApiKeyExpiry(expires_in.clone()))?;
+                Some(Utc::now() + expires_in)
+            },
+            None => None,
+        };
+        let mut scopes = request.scopes.clone();
+        scopes.sort();
+        scopes.dedup();
+
+        let prefix = format!("{}{}", API_KEY_PREFIX, generate_secure_token(API_KEY_PREFIX_BYTES));
+        let key = format!("{}_{}", prefix, generate_secure_token(API_KEY_SECRET_BYTES));
+        let api_key = self
+            .api_key_repository
+            .create_api_key(user_id, request.name.trim(), &prefix, &hash_token(&key), &scopes, expires_at)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        info!("Clave de API {} creada para usuario: {}", api_key.prefix, user.email);
+        Ok((api_key, key))
+    }
+
+    /// Claves de API vigentes o caducadas del usuario; las revocadas no se muestran.
+    pub async fn list_api_keys(&self, user_id: &Uuid) -> Result<Vec<ApiKey>, AuthError> {
+        self.api_key_repository
+            .find_api_keys_for_user(user_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn revoke_api_key(&self, user_id: &Uuid, key_id: &Uuid) -> Result<(), AuthError> {
+        let revoked = self
+            .api_key_repository
+            .revoke_api_key(user_id, key_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        if !revoked {
+            return Err(AuthError::ApiKeyNotFound);
+        }
+
+        info!("Clave de API {} revocada por usuario {}", key_id, user_id);
+        Ok(())
+    }
+
+    /// Comprueba la clave de API presentada por un cliente y devuelve su usuario y la clave.
+    pub async fn authenticate_api_key(&self, key: &str) -> Result<(User, ApiKey), AuthError> {
+        let prefix = key
+            .trim()
+            .rsplit_once('_')
+            .map(|(prefix, _)| prefix)
+            .filter(|prefix| prefix.starts_with(API_KEY_PREFIX))
+            .ok_or(AuthError::InvalidApiKey)?;
+        let api_key = self
+            .api_key_repository
+            .find_api_key_by_prefix(prefix)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::InvalidApiKey)?;
+
+        if !constant_time_eq(hash_token(key.trim()).as_bytes(), api_key.key_hash.as_bytes()) {
+            warn!("Secreto incorrecto para la clave de API {}", api_key.prefix);
+            return Err(AuthError::InvalidApiKey);
+        }
+        if api_key.revoked_at.is_some() || api_key.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            warn!("Clave de API revocada o caducada: {}", api_key.prefix);
+            return Err(AuthError::InvalidApiKey);
+        }
+
+        // No registrar el uso no debe impedir la petición
+        if let Err(e) = self.api_key_repository.touch_api_key(&api_key.id).await {
+            warn!("No se pudo registrar el uso de la clave de API {}: {}", api_key.prefix, e);
+        }
+
+        let user = self.find_existing_user(&api_key.user_id).await?;
+        debug!("Clave de API {} autenticada para usuario: {}", api_key.prefix, user.email);
+        Ok((user, api_key))
+    }
+
+    /// Registra un cliente OAuth. Los confidenciales reciben un secreto que sólo se devuelve
+    /// ahora; de él se guarda el hash.
+    pub async fn register_oauth_client(&self, request: &CreateOAuthClientSchema) -> Result<(OAuthClient, Option<String>), AuthError> {
+        let mut grant_types = request.grant_types.clone();
+        grant_types.sort();
+        grant_types.dedup();
+        let grants = |grant: &str| grant_types.iter().any(|granted| granted == grant);
+        if grant_types.is_empty() {
+            return Err(AuthError::InvalidOAuthClient("el cliente necesita al menos un grant".into()));
+        }
+        if let Some(grant) = grant_types
+            .iter()
+            .find(|grant| !oauth::SUPPORTED_GRANTS.contains(&grant.as_str()))
+        {
+            return Err(AuthError::InvalidOAuthClient(format!("grant no admitido: {}", grant)));
+        }
+        // Sin usuario delante, el servicio sólo se puede autenticar con su secreto
+        if request.public && grants(oauth::GRANT_CLIENT_CREDENTIALS) {
+            return Err(AuthError::InvalidOAuthClient("client_credentials requiere un cliente confidencial".into()));
+        }
+        // El dispositivo recibe una sesión completa del usuario, no un token limitado a sus scopes
+        if grants(oauth::GRANT_DEVICE_CODE) && !request.first_party {
+            return Err(AuthError::InvalidOAuthClient("el grant de dispositivo requiere un cliente propio (first_party)".into()));
+        }
+        if grants(oauth::GRANT_AUTHORIZATION_CODE) && request.redirect_uris.is_empty() {
+            return Err(AuthError::InvalidOAuthClient("authorization_code requiere al menos una redirect_uri".into()));
+        }
+        if let Some(uri) = request.redirect_uris.iter().find(|uri| !oauth::is_valid_redirect_uri(uri)) {
+            return Err(AuthError::InvalidOAuthClient(format!("redirect_uri no admitida: {}", uri)));
+        }
+
+        // Scopes de OpenID Connect para los flujos con usuario y permisos para el servicio
+        let user_grants = grants(oauth::GRANT_AUTHORIZATION_CODE) || grants(oauth::GRANT_DEVICE_CODE);
+        let mut scopes = oauth::parse_scope(&request.scopes.join(" "));
+        if scopes.is_empty() && user_grants {
+            scopes = oauth::SUPPORTED_SCOPES.iter().map(|scope| scope.to_string()).collect();
+        }
+        let invalid_scope = |scope: &String| {
+            if oauth::SUPPORTED_SCOPES.contains(&scope.as_str()) {
+                !user_grants
+            } else {
+                !grants(oauth::GRANT_CLIENT_CREDENTIALS) || !oauth::is_valid_service_scope(scope)
+            }
+        };
+        if let Some(scope) = scopes.iter().find(|scope| invalid_scope(scope)) {
+            return Err(AuthError::InvalidOAuthClient(format!("scope no admitido: {}", scope)));
+        }
+        let mut redirect_uris = request.redirect_uris.clone();
+        redirect_uris.dedup();
+
+        let client_secret = (!request.public).then(|| generate_secure_token(OAUTH_CLIENT_SECRET_BYTES));
+        let client = self
+            .oauth_repository
+            .create_client(&NewOAuthClient {
+                client_id: generate_secure_token(OAUTH_CLIENT_ID_BYTES),
+                client_secret_hash: client_secret.as_deref().map(hash_token),
+                name: request.name.trim().to_string(),
+                redirect_uris,
+                scopes,
+                grant_types,
+                first_party: request.first_party,
+            })
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        info!("Cliente OAuth {} registrado: {}", client.client_id, client.name);
+        Ok((client, client_secret))
+    }
+
+    pub async fn list_oauth_clients(&self) -> Result<Vec<OAuthClient>, AuthError> {
+        self.oauth_repository
+            .find_clients()
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))
+    }
+
+    /// Valida una petición a `/oauth/authorize` y la guarda a la espera del consentimiento.
+    /// Devuelve la URL de la pantalla de consentimiento del frontend.
+    pub async fn start_oauth_authorization(&self, params: &AuthorizeParams) -> Result<String, AuthorizeError> {
+        let client = self
+            .oauth_repository
+            .find_client(&params.client_id)
+            .await
+            .map_err(|e| AuthorizeError::Direct(oauth_server_error(e)))?
+            .ok_or_else(|| AuthorizeError::Direct(OAuthError::new("invalid_request", "Unknown client_id")))?;
+        if !has_grant(&client, oauth::GRANT_AUTHORIZATION_CODE) {
+            return Err(AuthorizeError::Direct(OAuthError::new(
+                "unauthorized_client",
+                "The client is not allowed to use the authorization_code grant",
+            )));
+        }
+
+        // Sin una redirect_uri registrada no se puede devolver nada al cliente
+        let redirect_uri = match &params.redirect_uri {
+            Some(uri) if client.redirect_uris.contains(uri) => uri.clone(),
+            None if client.redirect_uris.len() == 1 => client.redirect_uris[0].clone(),
+            _ => {
+                warn!("redirect_uri no registrada para el cliente OAuth {}", client.client_id);
+                return Err(AuthorizeError::Direct(OAuthError::new(
+                    "invalid_request",
+                    "redirect_uri is not registered for this client",
+                )));
+            }
+        };
+        let reject = |error: &str, description: &str| {
+            AuthorizeError::Redirect(self.authorization_response(
+                &redirect_uri,
+                params.state.as_deref(),
+                &[("error", error), ("error_description", description)],
+            ))
+        };
+
+        match params.response_type.as_deref() {
+            Some("code") => {},
+            Some(_) => return Err(reject("unsupported_response_type", "Only response_type=code is supported")),
+            None => return Err(reject("invalid_request", "response_type is required")),
+        }
+        // PKCE es obligatorio para todos los clientes, también los confidenciales
+        let code_challenge = match (params.code_challenge.as_deref(), params.code_challenge_method.as_deref()) {
+            (None, _) => return Err(reject("invalid_request", "code_challenge is required")),
+            (Some(_), method) if method != Some(oauth::PKCE_METHOD_S256) => {
+                return Err(reject("invalid_request", "code_challenge_method must be S256"))
+            },
+            (Some(challenge), _) if !oauth::is_valid_code_challenge(challenge) => {
+                return Err(reject("invalid_request", "Malformed code_challenge"))
+            },
+            (Some(challenge), _) => challenge.to_string(),
+        };
+
+        let scopes = user_scopes(&client, params.scope.as_deref())
+            .map_err(|scope| reject("invalid_scope", &format!("Scope not allowed for this client: {}", scope)))?;
+
+        let expires_in = parse_duration(&self.settings.oauth_consent_expires_in)
+            .map_err(|e| reject("server_error", &oauth_server_error(e).error_description))?;
+        let authorization = self
+            .oauth_repository
+            .create_authorization(&NewOAuthAuthorization {
+                client_id: client.client_id.clone(),
+                redirect_uri: redirect_uri.clone(),
+                scopes,
+                state: params.state.clone(),
+                nonce: params.nonce.clone(),
+                code_challenge,
+                expires_at: Utc::now() + expires_in,
+            })
+            .await
+            .map_err(|e| reject("server_error", &oauth_server_error(e).error_description))?;
+
+        debug!("Solicitud de autorización {} creada para el cliente OAuth {}", authorization.id, client.client_id);
+        Ok(format!("{}/oauth/consent?request_id={}", self.settings.frontend_url, authorization.id))
+    }
+
+    /// Solicitud pendiente y su cliente, para mostrar la pantalla de consentimiento.
+    pub async fn oauth_authorization_request(&self, request_id: &Uuid) -> Result<(OAuthAuthorization, OAuthClient), AuthError> {
+        let authorization = self
+            .oauth_repository
+            .find_pending_authorization(request_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::OAuthRequestNotFound)?;
+        let client = self
+            .oauth_repository
+            .find_client(&authorization.client_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::OAuthRequestNotFound)?;
+        Ok((authorization, client))
+    }
+
+    /// Aprueba o deniega una solicitud pendiente en nombre del usuario. Devuelve la URL de
+    /// vuelta al cliente con el código de autorización o con `error=access_denied`.
+    pub async fn decide_oauth_authorization(&self, user_id: &Uuid, request_id: &Uuid, approve: bool) -> Result<String, AuthError> {
+        let user = self.find_existing_user(user_id).await?;
+
+        if !approve {
+            let authorization = self
+                .oauth_repository
+                .delete_pending_authorization(request_id)
+                .await
+                .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+                .ok_or(AuthError::OAuthRequestNotFound)?;
+            info!("Usuario {} deniega la autorización al cliente OAuth {}", user.email, authorization.client_id);
+            return Ok(self.authorization_response(
+                &authorization.redirect_uri,
+                authorization.state.as_deref(),
+                &[("error", "access_denied"), ("error_description", "The user denied the request")],
+            ));
+        }
+
+        let code = generate_secure_token(OAUTH_CODE_BYTES);
+        let expires_in = parse_duration(&self.settings.oauth_code_expires_in)
+            .map_err(|e| AuthError::TokenGenerationError(e.to_string()))?;
+        let authorization = self
+            .oauth_repository
+            .approve_authorization(request_id, user_id, &hash_token(&code), Utc::now() + expires_in)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::OAuthRequestNotFound)?;
+
+        info!("Usuario {} autoriza al cliente OAuth {} ({})", user.email, authorization.client_id, authorization.scopes.join(" "));
+        Ok(self.authorization_response(&authorization.redirect_uri, authorization.state.as_deref(), &[("code", &code)]))
+    }
+
+    /// Inicia el flujo de dispositivo (RFC 8628): devuelve el `device_code` con el que consultará
+    /// el dispositivo y el `user_code` que el usuario aprueba desde otro navegador.
+    pub async fn start_device_authorization(&self, request: &DeviceAuthorizationRequest) -> Result<DeviceAuthorizationResponse, OAuthError> {
+        let client = self
+            .authenticate_oauth_client(request.client_id.as_deref(), request.client_secret.as_deref())
+            .await?;
+        if !can_use_device_flow(&client) {
+            return Err(OAuthError::new("unauthorized_client", "The client is not allowed to use the device_code grant"));
+        }
+        let scopes = user_scopes(&client, request.scope.as_deref())
+            .map_err(|scope| OAuthError::new("invalid_scope", format!("Scope not allowed for this client: {}", scope)))?;
+
+        let expires_in = parse_duration(&self.settings.oauth_device_code_expires_in).map_err(oauth_server_error)?;
+        let interval = parse_duration(&self.settings.oauth_device_poll_interval).map_err(oauth_server_error)?;
+        let device_code = generate_secure_token(OAUTH_CODE_BYTES);
+        let user_code = oauth::generate_user_code();
+        self.oauth_repository
+            .create_device_authorization(&NewDeviceAuthorization {
+                client_id: client.client_id.clone(),
+                device_code_hash: hash_token(&device_code),
+                user_code: user_code.clone(),
+                scopes,
+                interval_secs: interval.num_seconds() as i32,
+                expires_at: Utc::now() + expires_in,
+            })
+            .await
+            .map_err(oauth_server_error)?;
+
+        info!("Autorización de dispositivo iniciada para el cliente OAuth {}", client.client_id);
+        let user_code = oauth::format_user_code(&user_code);
+        let verification_uri = format!("{}/oauth/device", self.settings.frontend_url);
+        Ok(DeviceAuthorizationResponse {
+            device_code,
+            verification_uri_complete: format!("{}?user_code={}", verification_uri, user_code),
+            user_code,
+            verification_uri,
+            expires_in: expires_in.num_seconds(),
+            interval: interval.num_seconds(),
+        })
+    }
+
+    /// Autorización de dispositivo pendiente y su cliente, para la pantalla de aprobación.
+    pub async fn device_authorization_request(&self, user_code: &str) -> Result<(DeviceAuthorization, OAuthClient), AuthError> {
+        let authorization = self
+            .oauth_repository
+            .find_pending_device_authorization(&oauth::normalize_user_code(user_code))
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::OAuthRequestNotFound)?;
+        let client = self
+            .oauth_repository
+            .find_client(&authorization.client_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::OAuthRequestNotFound)?;
+
+        Ok((authorization, client))
+    }
+
+    /// Aprueba o deniega el `user_code` en nombre del usuario; el dispositivo lo sabrá en su
+    /// siguiente consulta a `/oauth/token`.
+    pub async fn decide_device_authorization(&self, user_id: &Uuid, user_code: &str, approve: bool) -> Result<(), AuthError> {
+        let status = if approve { DEVICE_STATUS_APPROVED } else { DEVICE_STATUS_DENIED };
+        let authorization = self
+            .oauth_repository
+            .decide_device_authorization(&oauth::normalize_user_code(user_code), user_id, status)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::OAuthRequestNotFound)?;
+
+        info!("Autorización de dispositivo del cliente OAuth {}: {} por el usuario {}", authorization.client_id, status, user_id);
+        Ok(())
+    }
+
+    /// Atiende `/oauth/token`: autentica al cliente y emite los tokens según el grant
+    /// (código con PKCE, token de servicio o consulta del dispositivo).
+    pub async fn exchange_oauth_token(&self, request: &TokenRequest) -> Result<TokenResponse, OAuthError> {
+        let grant_type = request.grant_type.as_str();
+        if !oauth::SUPPORTED_GRANTS.contains(&grant_type) {
+            return Err(OAuthError::new("unsupported_grant_type", format!("Unsupported grant_type: {}", grant_type)));
+        }
+        let client = self
+            .authenticate_oauth_client(request.client_id.as_deref(), request.client_secret.as_deref())
+            .await?;
+        if !has_grant(&client, grant_type) {
+            warn!("El cliente OAuth {} no tiene el grant {}", client.client_id, grant_type);
+            return Err(OAuthError::new("unauthorized_client", format!("The client is not allowed to use the {} grant", grant_type)));
+        }
+
+        match grant_type {
+            oauth::GRANT_CLIENT_CREDENTIALS => self.issue_service_token(&client, request.scope.as_deref()),
+            oauth::GRANT_DEVICE_CODE => self.exchange_device_code(&client, request).await,
+            _ => self.exchange_authorization_code(&client, request).await,
+        }
+    }
+
+    // Canje del código: el código es de un solo uso y debe corresponder al cliente y al PKCE
+    async fn exchange_authorization_code(&self, client: &OAuthClient, request: &TokenRequest) -> Result<TokenResponse, OAuthError> {
+        let code = request
+            .code
+            .as_deref()
+            .filter(|code| !code.is_empty())
+            .ok_or_else(|| OAuthError::new("invalid_request", "code is required"))?;
+        let code_verifier = request
+            .code_verifier
+            .as_deref()
+            .ok_or_else(|| OAuthError::new("invalid_request", "code_verifier is required"))?;
+
+        // El código se borra aunque falle alguna comprobación: no admite un segundo intento
+        let authorization = self
+            .oauth_repository
+            .consume_authorization_code(&hash_token(code))
+            .await
+            .map_err(oauth_server_error)?
+            .ok_or_else(|| OAuthError::new("invalid_grant", "Invalid or expired authorization code"))?;
+        if authorization.client_id != client.client_id {
+            warn!("Código de autorización del cliente {} presentado por {}", authorization.client_id, client.client_id);
+            return Err(OAuthError::new("invalid_grant", "Invalid or expired authorization code"));
+        }
+        if request.redirect_uri.as_ref().is_some_and(|uri| *uri != authorization.redirect_uri) {
+            return Err(OAuthError::new("invalid_grant", "redirect_uri does not match the authorization request"));
+        }
+        if !oauth::verify_pkce(code_verifier, &authorization.code_challenge) {
+            warn!("code_verifier incorrecto para el cliente OAuth {}", client.client_id);
+            return Err(OAuthError::new("invalid_grant", "PKCE verification failed"));
+        }
+
+        let user_id = authorization
+            .user_id
+            .ok_or_else(|| oauth_server_error("código de autorización sin usuario"))?;
+        let user = self.oauth_token_user(&user_id).await?;
+
+        let scope = authorization.scopes.join(" ");
+        let custom_claims = CustomClaims {
+            token_version: Some(user.token_version),
+            token_use: Some(OAUTH_ACCESS_TOKEN_USE.to_string()),
+            scope: Some(scope.clone()),
+            ..Default::default()
+        };
+        let access_token = generate_jwt(&user.id.to_string(), custom_claims, &self.jwt_config, &self.settings.jwt_expires_in)
+            .map_err(oauth_server_error)?;
+        let expires_in = parse_duration(&self.settings.jwt_expires_in).map_err(oauth_server_error)?;
+        let id_token = self.id_token(client, &user, &authorization.scopes, authorization.approved_at, authorization.nonce.clone())?;
+
+        info!("Tokens OAuth emitidos al cliente {} para usuario {}", client.client_id, user.email);
+        Ok(TokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: expires_in.num_seconds(),
+            scope: Some(scope),
+            refresh_token: None,
+            id_token,
+        })
+    }
+
+    // Consulta del dispositivo: `authorization_pending` hasta que el usuario decide y
+    // `slow_down` si consulta antes del intervalo. Aprobada, emite una sesión como la del
+    // login (access y refresh token) y, con `openid`, el ID token. Por eso sólo la pueden
+    // usar clientes propios, y la respuesta no lleva `scope`: los scopes aprobados sólo
+    // deciden el contenido del ID token.
+    async fn exchange_device_code(&self, client: &OAuthClient, request: &TokenRequest) -> Result<TokenResponse, OAuthError> {
+        if !can_use_device_flow(client) {
+            warn!("El cliente OAuth {} no es propio y no puede usar el flujo de dispositivo", client.client_id);
+            return Err(OAuthError::new("unauthorized_client", "The client is not allowed to use the device_code grant"));
+        }
+        let device_code = request
+            .device_code
+            .as_deref()
+            .filter(|device_code| !device_code.is_empty())
+            .ok_or_else(|| OAuthError::new("invalid_request", "device_code is required"))?;
+        let authorization = self
+            .oauth_repository
+            .find_device_authorization(&hash_token(device_code))
+            .await
+            .map_err(oauth_server_error)?
+            .filter(|authorization| authorization.client_id == client.client_id)
+            .ok_or_else(|| OAuthError::new("invalid_grant", "Invalid device_code"))?;
+
+        let now = Utc::now();
+        if authorization.expires_at <= now {
+            self.oauth_repository
+                .delete_device_authorization(&authorization.id, &authorization.status)
+                .await
+                .map_err(oauth_server_error)?;
+            return Err(OAuthError::new("expired_token", "The device_code has expired"));
+        }
+
+        let too_fast = authorization
+            .last_polled_at
+            .is_some_and(|last_polled_at| now < last_polled_at + chrono::Duration::seconds(authorization.interval_secs.into()));
+        // Cada slow_down alarga el intervalo para las siguientes consultas (RFC 8628 §3.5)
+        let interval_secs = if too_fast { authorization.interval_secs + DEVICE_SLOW_DOWN_SECS } else { authorization.interval_secs };
+        self.oauth_repository
+            .record_device_poll(&authorization.id, interval_secs)
+            .await
+            .map_err(oauth_server_error)?;
+        if too_fast {
+            return Err(OAuthError::new("slow_down", format!("Poll at most every {} seconds", interval_secs)));
+        }
+
+        match authorization.status.as_str() {
+            DEVICE_STATUS_PENDING => return Err(OAuthError::new("authorization_pending", "The user has not approved the request yet")),
+            DEVICE_STATUS_DENIED => {
+                self.oauth_repository
+                    .delete_device_authorization(&authorization.id, DEVICE_STATUS_DENIED)
+                    .await
+                    .map_err(oauth_server_error)?;
+                return Err(OAuthError::new("access_denied", "The user denied the request"));
+            },
+            _ => {},
+        }
+        // El borrado garantiza que la autorización aprobada sólo se canjea una vez
+        let authorization = self
+            .oauth_repository
+            .delete_device_authorization(&authorization.id, DEVICE_STATUS_APPROVED)
+            .await
+            .map_err(oauth_server_error)?
+            .ok_or_else(|| OAuthError::new("invalid_grant", "Invalid device_code"))?;
+
+        let user_id = authorization
+            .user_id
+            .ok_or_else(|| oauth_server_error("autorización de dispositivo sin usuario"))?;
+        let user = self.oauth_token_user(&user_id).await?;
+        let access_token = self.generate_access_token(&user).map_err(oauth_server_error)?;
+        let (_, refresh_token) = self.issue_refresh_token(&user.id, None).await.map_err(oauth_server_error)?;
+        let expires_in = parse_duration(&self.settings.jwt_expires_in).map_err(oauth_server_error)?;
+        let id_token = self.id_token(client, &user, &authorization.scopes, authorization.approved_at, None)?;
 
-pub struct AuthService<T: UserRepository> {
-    user_repository: T,
-    jwt_secret: String,
-    jwt_expires_in: String,
-}
+        info!("Sesión de dispositivo emitida al cliente {} para usuario {}", client.client_id, user.email);
+        Ok(TokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: expires_in.num_seconds(),
+            scope: None,
+            refresh_token: Some(refresh_token),
+            id_token,
+        })
+    }
 
-impl<T: UserRepository> AuthService<T> {
-    pub fn new(user_repository: T, jwt_secret: String, jwt_expires_in: String) -> Self {
-        info!("Inicializando servicio de autenticación");
-        Self {
-            user_repository,
-            jwt_secret,
-            jwt_expires_in,
+    // Usuario de un código o una autorización de dispositivo; si ya no existe, el grant no vale
+    async fn oauth_token_user(&self, user_id: &Uuid) -> Result<User, OAuthError> {
+        match self.find_existing_user(user_id).await {
+            Ok(user) => Ok(user),
+            Err(AuthError::UserNotFound) => Err(OAuthError::new("invalid_grant", "The user no longer exists")),
+            Err(e) => Err(oauth_server_error(e)),
         }
     }
 
-    pub async fn authenticate_by_email(&self, email: &str, password: &str) -> Result<User, AuthError> {
-        info!("Intentando autenticar usuario con email: {}", email);
-        
-        let user = match self.user_repository.find_user_by_email(email).await {
-            Ok(user) => {
-                info!("Usuario encontrado en la base de datos: {}", email);
-                user
-            },
-            Err(e) => {
-                error!("Error al buscar usuario por email: {}, error: {}", email, e);
-                return Err(AuthError::InvalidCredentials);
-            }
+    // ID token para el cliente, sólo si se concedió el scope `openid`
+    fn id_token(
+        &self,
+        client: &OAuthClient,
+        user: &User,
+        scopes: &[String],
+        auth_time: Option<chrono::DateTime<Utc>>,
+        nonce: Option<String>,
+    ) -> Result<Option<String>, OAuthError> {
+        if !scopes.iter().any(|scope| scope == oauth::SCOPE_OPENID) {
+            return Ok(None);
+        }
+
+        let expires_in = parse_duration(&self.settings.jwt_expires_in).map_err(oauth_server_error)?;
+        let now = Utc::now();
+        let claims = IdTokenClaims {
+            iss: self.settings.oauth_issuer.clone(),
+            aud: client.client_id.clone(),
+            exp: (now + expires_in).timestamp(),
+            iat: now.timestamp(),
+            auth_time: auth_time.unwrap_or(now).timestamp(),
+            nonce,
+            user: oauth::user_info(user, scopes),
         };
+        Ok(Some(sign_jwt(&claims, &self.jwt_config).map_err(oauth_server_error)?))
+    }
 
-        debug!("Verificando contraseña para usuario: {}", email);
-        let is_valid = match self.verify_password(&user.password, password) {
-            Ok(valid) => {
-                if valid {
-                    info!("Contraseña válida para usuario: {}", email);
-                } else {
-                    warn!("Contraseña inválida para usuario: {}", email);
+    // Token del propio servicio: `sub` es el client_id y `scope` los permisos concedidos.
+    // No hay refresh token; el servicio pide otro con sus credenciales cuando caduca.
+    fn issue_service_token(&self, client: &OAuthClient, scope: Option<&str>) -> Result<TokenResponse, OAuthError> {
+        let service_scopes = client
+            .scopes
+            .iter()
+            .filter(|scope| !oauth::SUPPORTED_SCOPES.contains(&scope.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        let scopes = match scope.map(oauth::parse_scope).filter(|scopes| !scopes.is_empty()) {
+            Some(requested) => {
+                if let Some(scope) = requested.iter().find(|scope| !service_scopes.contains(scope)) {
+                    return Err(OAuthError::new("invalid_scope", format!("Scope not allowed for this client: {}", scope)));
                 }
-                valid
+                requested
             },
-            Err(e) => {
-                error!("Error al verificar contraseña: {}", e);
-                return Err(AuthError::PasswordVerifyError(e.to_string()));
+            None => service_scopes,
+        };
+
+        let scope = scopes.join(" ");
+        let custom_claims = CustomClaims {
+            token_use: Some(SERVICE_TOKEN_USE.to_string()),
+            scope: Some(scope.clone()),
+            ..Default::default()
+        };
+        let access_token = generate_jwt(&client.client_id, custom_claims, &self.jwt_config, &self.settings.jwt_expires_in)
+            .map_err(oauth_server_error)?;
+        let expires_in = parse_duration(&self.settings.jwt_expires_in).map_err(oauth_server_error)?;
+
+        info!("Token de servicio emitido al cliente OAuth {} con scope '{}'", client.client_id, scope);
+        Ok(TokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: expires_in.num_seconds(),
+            scope: Some(scope),
+            refresh_token: None,
+            id_token: None,
+        })
+    }
+
+    /// Comprueba que el cliente de un token de servicio sigue registrado con el grant
+    /// `client_credentials`; si no, sus tokens dejan de aceptarse.
+    pub async fn verify_service_client(&self, client_id: &str) -> Result<(), AuthError> {
+        let client = self
+            .oauth_repository
+            .find_client(client_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        match client {
+            Some(client) if has_grant(&client, oauth::GRANT_CLIENT_CREDENTIALS) => Ok(()),
+            _ => Err(AuthError::ServiceClientNotFound),
+        }
+    }
+
+    /// Autentica al cliente en `/oauth/token`: los confidenciales con su secreto y los
+    /// públicos sólo con `client_id` (sin secreto).
+    pub async fn authenticate_oauth_client(&self, client_id: Option<&str>, client_secret: Option<&str>) -> Result<OAuthClient, OAuthError> {
+        let invalid_client = || OAuthError::new("invalid_client", "Client authentication failed");
+        let client_id = client_id.filter(|client_id| !client_id.is_empty()).ok_or_else(invalid_client)?;
+        let client = self
+            .oauth_repository
+            .find_client(client_id)
+            .await
+            .map_err(oauth_server_error)?
+            .ok_or_else(invalid_client)?;
+
+        match (&client.client_secret_hash, client_secret) {
+            (Some(secret_hash), Some(secret)) if constant_time_eq(hash_token(secret).as_bytes(), secret_hash.as_bytes()) => Ok(client),
+            (None, None) => Ok(client),
+            _ => {
+                warn!("Autenticación fallida del cliente OAuth {}", client.client_id);
+                Err(invalid_client())
             }
+        }
+    }
+
+    /// Claims del usuario para `/oauth/userinfo` según los scopes del access token.
+    pub async fn oauth_userinfo(&self, access_token: &str) -> Result<UserInfo, OAuthError> {
+        let invalid_token = |description: &str| OAuthError::new("invalid_token", description);
+        let claims = verify_jwt(access_token, &self.jwt_config).map_err(|e| invalid_token(&e.to_string()))?;
+        // Los tokens de sesión del servicio no sirven aquí, ni los de OAuth en la API
+        if claims.token_use.as_deref() != Some(OAUTH_ACCESS_TOKEN_USE) {
+            return Err(invalid_token("Not an OAuth access token"));
+        }
+        let scopes = oauth::parse_scope(claims.scope.as_deref().unwrap_or_default());
+        if !scopes.iter().any(|scope| scope == oauth::SCOPE_OPENID) {
+            return Err(OAuthError::new("insufficient_scope", "The openid scope is required"));
+        }
+
+        let user_id = Uuid::parse_str(&claims.sub).map_err(|_| invalid_token("Invalid subject"))?;
+        let user = match self.find_existing_user(&user_id).await {
+            Ok(user) => user,
+            Err(AuthError::UserNotFound) => return Err(invalid_token("Unknown user")),
+            Err(e) => return Err(oauth_server_error(e)),
         };
-        
-        if !is_valid {
-            return Err(AuthError::InvalidCredentials);
+        // Igual que los tokens de sesión, dejan de valer al cambiar la contraseña
+        if claims.ver != Some(user.token_version) {
+            return Err(invalid_token("Token has been revoked"));
         }
 
-        info!("Autenticación exitosa para usuario: {}", email);
-        Ok(user)
+        Ok(oauth::user_info(&user, &scopes))
+    }
+
+    /// Documento de discovery de OpenID Connect (`/.well-known/openid-configuration`).
+    pub fn openid_configuration(&self) -> serde_json::Value {
+        let issuer = &self.settings.oauth_issuer;
+        json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/oauth/authorize", issuer),
+            "token_endpoint": format!("{}/oauth/token", issuer),
+            "userinfo_endpoint": format!("{}/oauth/userinfo", issuer),
+            "jwks_uri": format!("{}/.well-known/jwks.json", issuer),
+            "response_types_supported": ["code"],
+            "response_modes_supported": ["query"],
+            "device_authorization_endpoint": format!("{}/oauth/device_authorization", issuer),
+            "grant_types_supported": oauth::SUPPORTED_GRANTS,
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": [self.jwt_config.keys.signing_key().algorithm],
+            "scopes_supported": oauth::SUPPORTED_SCOPES,
+            "claims_supported": ["sub", "iss", "aud", "exp", "iat", "auth_time", "nonce", "name", "email", "email_verified"],
+            "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+            "code_challenge_methods_supported": [oauth::PKCE_METHOD_S256],
+            "authorization_response_iss_parameter_supported": true,
+        })
+    }
+
+    // Respuesta de autorización en la redirect_uri del cliente; `iss` (RFC 9207) permite
+    // al cliente comprobar qué proveedor responde
+    fn authorization_response(&self, redirect_uri: &str, state: Option<&str>, params: &[(&str, &str)]) -> String {
+        let mut params = params.to_vec();
+        if let Some(state) = state {
+            params.push(("state", state));
+        }
+        params.push(("iss", &self.settings.oauth_issuer));
+        oauth::redirect_with(redirect_uri, &params)
     }
 
     fn verify_password(&self, stored_password: &str, provided_password: &str) -> Result<bool> {
@@ -77,10 +1905,18 @@ impl<T: UserRepository> AuthService<T> {
         Ok(is_valid)
     }
 
-    pub async fn register_user(&self, user_data: &CreateUserSchema, telegram_user_id: Option<String>) -> Result<FilteredUser> {
+    /// Aplica la política de contraseñas teniendo en cuenta el email y el nombre del usuario.
+    pub fn check_password_policy(&self, password: &str, email: &str, name: Option<&str>) -> Result<(), AuthError> {
+        let mut user_inputs = vec![email];
+        user_inputs.extend(name);
+        self.settings.password_policy.check(password, &user_inputs).map_err(AuthError::WeakPassword)
+    }
+
+    pub async fn register_user(&self, user_data: &CreateUserSchema) -> Result<FilteredUser> {
         info!("Registrando nuevo usuario con email: {}", user_data.email);
+        self.check_password_policy(&user_data.password, &user_data.email, user_data.name.as_deref())?;
         
-        let hashed_password = match hash_password(&user_data.password) {
+        let hashed_password = match hash_password(&user_data.password, &self.settings.argon2_params) {
             Ok(hash) => {
                 debug!("Contraseña hasheada correctamente");
                 hash
@@ -91,7 +1927,7 @@ impl<T: UserRepository> AuthService<T> {
             }
         };
 
-        let user = match self.user_repository.create_user(user_data, &hashed_password, telegram_user_id).await {
+        let user = match self.user_repository.create_user(user_data, &hashed_password, None).await {
             Ok(user) => {
                 info!("Usuario creado correctamente: {}", user.email);
                 user
@@ -102,27 +1938,39 @@ impl<T: UserRepository> AuthService<T> {
             }
         };
         
+        // El registro no falla si el correo no sale: el usuario puede pedir un reenvío
+        if let Err(e) = self.send_verification_email(&user).await {
+            error!("Error al enviar el correo de verificación a {}: {}", user.email, e);
+        }
+
         Ok(filter_user_response(user))
     }
 
-    pub async fn login_user(&self, credentials: &LoginUserSchema) -> Result<(FilteredUser, String)> {
+    pub async fn login_user(&self, credentials: &LoginUserSchema) -> Result<LoginOutcome> {
         info!("Iniciando proceso de login");
         
         let user = if let Some(email) = &credentials.email {
             info!("Autenticando por email: {}", email);
             self.authenticate_by_email(email, &credentials.password).await?
+        } else if let Some(telegram) = &credentials.telegram {
+            info!("Autenticando por Telegram: {}", telegram.id);
+            self.authenticate_by_telegram(telegram).await?
         } else {
             if let Some(telegram_id) = &credentials.telegram_user_id {
-                warn!("Intento de autenticación por Telegram (no implementada): {}", telegram_id);
-                return Err(anyhow::anyhow!("Autenticación por Telegram no implementada"));
+                warn!("Intento de login con telegram_user_id sin firmar: {}", telegram_id);
+                return Err(AuthError::InvalidTelegramData("Se requieren los datos firmados del Login Widget".into()).into());
             } else {
-                error!("No se proporcionó email ni telegram_user_id");
-                return Err(anyhow::anyhow!("Se requiere email o telegram_user_id"));
+                error!("No se proporcionó email ni datos de Telegram");
+                return Err(anyhow::anyhow!("Se requiere email o datos de Telegram"));
             }
         };
 
+        if let Some(mfa_token) = self.mfa_challenge(&user).await? {
+            return Ok(LoginOutcome::MfaRequired { mfa_token });
+        }
+
         info!("Generando token JWT para usuario: {}", user.email);
-        let token = match generate_jwt(&user.id.to_string(), &self.jwt_secret, &self.jwt_expires_in) {
+        let token = match self.generate_access_token(&user) {
             Ok(token) => {
                 debug!("Token JWT generado correctamente");
                 token
@@ -133,8 +1981,118 @@ impl<T: UserRepository> AuthService<T> {
             }
         };
         
+        let (_, refresh_token) = self.issue_refresh_token(&user.id, None).await?;
+
         info!("Login exitoso para usuario: {}", user.email);
-        Ok((filter_user_response(user), token))
+        Ok(LoginOutcome::Authenticated(filter_user_response(user), LoginResponse { token, refresh_token }))
+    }
+
+    /// Genera el access token del usuario incluyendo su rol, email y `token_version` como claims.
+    pub fn generate_access_token(&self, user: &User) -> Result<String, AuthError> {
+        let custom_claims = CustomClaims {
+            role: Some(user.role.clone()),
+            email: Some(user.email.clone()),
+            token_version: Some(user.token_version),
+            token_use: None,
+            scope: None,
+        };
+        generate_jwt(&user.id.to_string(), custom_claims, &self.jwt_config, &self.settings.jwt_expires_in)
+            .map_err(|e| AuthError::TokenGenerationError(e.to_string()))
+    }
+
+    /// Emite un refresh token nuevo. Sin `family_id` se abre una familia nueva (login);
+    /// al rotar se reutiliza la familia del token consumido.
+    pub async fn issue_refresh_token(&self, user_id: &Uuid, family_id: Option<Uuid>) -> Result<(RefreshToken, String), AuthError> {
+        let expires_in = parse_duration(&self.settings.refresh_token_expires_in)
+            .map_err(|e| AuthError::TokenGenerationError(e.to_string()))?;
+        let family_id = family_id.unwrap_or_else(Uuid::new_v4);
+        let token = generate_secure_token(32);
+
+        let record = self
+            .refresh_token_repository
+            .create_refresh_token(user_id, &family_id, &hash_token(&token), Utc::now() + expires_in)
+            .await
+            .map_err(|e| {
+                error!("Error al guardar refresh token: {}", e);
+                AuthError::DatabaseError(e.to_string())
+            })?;
+
+        debug!("Refresh token emitido para usuario {} (familia {})", user_id, family_id);
+        Ok((record, token))
+    }
+
+    /// Consume un refresh token y devuelve un par nuevo (access token, refresh token).
+    /// Si el token ya había sido usado se revoca toda su familia.
+    pub async fn refresh_session(&self, refresh_token: &str) -> Result<(String, String), AuthError> {
+        let stored = self
+            .refresh_token_repository
+            .find_refresh_token_by_hash(&hash_token(refresh_token))
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::InvalidRefreshToken)?;
+
+        if stored.revoked_at.is_some() {
+            warn!("Reutilización de refresh token detectada, revocando familia {}", stored.family_id);
+            self.revoke_refresh_token_family(&stored.family_id).await?;
+            return Err(AuthError::RefreshTokenReused);
+        }
+
+        if stored.expires_at <= Utc::now() {
+            info!("Refresh token expirado para usuario {}", stored.user_id);
+            return Err(AuthError::TokenExpired);
+        }
+
+        let user = self
+            .user_repository
+            .find_user_by_id(&stored.user_id)
+            .await
+            .map_err(|_| AuthError::InvalidRefreshToken)?;
+
+        let (new_record, new_refresh_token) = self.issue_refresh_token(&user.id, Some(stored.family_id)).await?;
+
+        // Si otra petición consumió el mismo token en paralelo, se trata como reutilización
+        let consumed = self
+            .refresh_token_repository
+            .revoke_refresh_token(&stored.id, Some(&new_record.id))
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        if !consumed {
+            warn!("Refresh token consumido de forma concurrente, revocando familia {}", stored.family_id);
+            self.revoke_refresh_token_family(&stored.family_id).await?;
+            return Err(AuthError::RefreshTokenReused);
+        }
+
+        let token = self.generate_access_token(&user)?;
+
+        info!("Sesión renovada para usuario: {}", user.email);
+        Ok((token, new_refresh_token))
+    }
+
+    /// Revoca la familia completa del refresh token indicado (logout). El token
+    /// tiene que pertenecer a `user_id`, el usuario que cierra la sesión.
+    pub async fn revoke_refresh_token(&self, user_id: &Uuid, refresh_token: &str) -> Result<(), AuthError> {
+        let stored = self
+            .refresh_token_repository
+            .find_refresh_token_by_hash(&hash_token(refresh_token))
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+            .ok_or(AuthError::InvalidRefreshToken)?;
+
+        if stored.user_id != *user_id {
+            warn!("El usuario {} intentó revocar un refresh token de otro usuario", user_id);
+            return Err(AuthError::RefreshTokenNotOwned);
+        }
+
+        info!("Revocando familia de refresh tokens {}", stored.family_id);
+        self.revoke_refresh_token_family(&stored.family_id).await
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: &Uuid) -> Result<(), AuthError> {
+        self.refresh_token_repository
+            .revoke_refresh_token_family(family_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+        Ok(())
     }
 
     pub async fn get_user(&self, user_id: &Uuid) -> Result<FilteredUser> {
@@ -158,7 +2116,7 @@ impl<T: UserRepository> AuthService<T> {
 // Implementación del trait api::handlers::auth::AuthService para AuthService<T>
 #[async_trait]
 impl<T: UserRepository + Send + Sync + 'static> api::handlers::auth::AuthService for AuthService<T> {
-    async fn register_user(&self, user_data: &shared::user::CreateUserSchema, telegram_id: Option<String>) -> Result<shared::user::FilteredUser, String> {
+    async fn register_user(&self, user_data: &shared::user::CreateUserSchema) -> Result<shared::user::FilteredUser, AppError> {
         info!("Delegando registro de usuario a la implementación interna");
         
         // Convertir de shared::user::CreateUserSchema a models::CreateUserSchema
@@ -166,18 +2124,21 @@ impl<T: UserRepository + Send + Sync + 'static> api::handlers::auth::AuthService
             email: user_data.email.clone(),
             name: user_data.name.clone(),
             password: user_data.password.clone(),
-            role: user_data.role.clone(),
         };
 
         // Ejecutamos el future y manejamos el resultado
-        let filtered_user = match self.register_user(&models_user_data, telegram_id).await {
+        let filtered_user = match self.register_user(&models_user_data).await {
             Ok(user) => {
                 info!("Usuario registrado correctamente: {}", user.email);
                 user
             },
             Err(e) => {
                 error!("Error en registro de usuario: {}", e);
-                return Err(e.to_string());
+                // Los rechazos de la política de contraseñas llevan el detalle de cada regla
+                return Err(match e.downcast::<AuthError>() {
+                    Ok(auth_error) => auth_error.into(),
+                    Err(e) => AppError::Auth(e.to_string()),
+                });
             }
         };
         
@@ -187,48 +2148,305 @@ impl<T: UserRepository + Send + Sync + 'static> api::handlers::auth::AuthService
             email: filtered_user.email,
             name: filtered_user.name,
             role: filtered_user.role,
+            email_verified_at: filtered_user.email_verified_at,
             created_at: filtered_user.created_at,
             updated_at: filtered_user.updated_at,
         })
     }
 
-    async fn authenticate_by_email(&self, email: &str, password: &str) -> Result<shared::user::User, String> {
+    async fn authenticate_by_email(&self, email: &str, password: &str) -> Result<shared::user::User, AppError> {
         info!("Delegando autenticación por email a la implementación interna: {}", email);
-        
-        // Ejecutamos el future y manejamos el resultado
-        let user = match self.authenticate_by_email(email, password).await {
-            Ok(user) => {
-                info!("Autenticación exitosa para: {}", email);
-                user
-            },
-            Err(e) => {
-                error!("Error en autenticación por email: {}, error: {}", email, e);
-                return Err(e.to_string());
+        self.authenticate_by_email(email, password).await.map_err(|e| {
+            error!("Error en autenticación por email: {}, error: {}", email, e);
+            e.into()
+        })
+    }
+
+    async fn unlock_account(&self, user_id: &Uuid) -> Result<(), AppError> {
+        self.unlock_account(user_id).await.map_err(|e| {
+            error!("Error al desbloquear la cuenta {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn authenticate_by_telegram(&self, data: &TelegramLoginData) -> Result<shared::user::User, String> {
+        info!("Delegando autenticación por Telegram a la implementación interna: {}", data.id);
+        self.authenticate_by_telegram(data).await.map_err(|e| {
+            error!("Error en autenticación por Telegram: {}, error: {}", data.id, e);
+            e.to_string()
+        })
+    }
+
+    async fn authenticate_by_telegram_web_app(&self, init_data: &str) -> Result<shared::user::User, String> {
+        info!("Delegando autenticación por Mini App a la implementación interna");
+        self.authenticate_by_telegram_web_app(init_data).await.map_err(|e| {
+            error!("Error en autenticación por Mini App: {}", e);
+            e.to_string()
+        })
+    }
+
+    async fn link_telegram(&self, user_id: &Uuid, link: &shared::user::LinkTelegramSchema) -> Result<String, AppError> {
+        self.link_telegram(user_id, link).await.map_err(|e| {
+            warn!("Error al vincular Telegram al usuario {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn unlink_telegram(&self, user_id: &Uuid) -> Result<(), AppError> {
+        self.unlink_telegram(user_id).await.map_err(|e| {
+            warn!("Error al desvincular Telegram del usuario {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn verify_email(&self, token: &str) -> Result<(), AppError> {
+        self.verify_email(token).await.map_err(|e| {
+            warn!("Error al verificar email: {}", e);
+            e.into()
+        })
+    }
+
+    async fn resend_verification_email(&self, email: &str) -> Result<(), AppError> {
+        self.resend_verification_email(email).await.map_err(|e| {
+            error!("Error al reenviar verificación a {}: {}", email, e);
+            e.into()
+        })
+    }
+
+    async fn request_password_reset(&self, email: &str) -> Result<(), AppError> {
+        self.request_password_reset(email).await.map_err(|e| {
+            error!("Error al solicitar recuperación de contraseña para {}: {}", email, e);
+            e.into()
+        })
+    }
+
+    async fn reset_password(&self, token: &str, new_password: &str) -> Result<(), AppError> {
+        self.reset_password(token, new_password).await.map_err(|e| {
+            warn!("Error al restablecer contraseña: {}", e);
+            e.into()
+        })
+    }
+
+    async fn change_password(&self, user_id: &Uuid, current_password: &str, new_password: &str) -> Result<(String, String), AppError> {
+        self.change_password(user_id, current_password, new_password).await.map_err(|e| {
+            warn!("Error al cambiar la contraseña del usuario {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn token_version(&self, user_id: &Uuid) -> Result<i32, AppError> {
+        self.current_token_version(user_id).await.map_err(|e| e.into())
+    }
+
+    async fn mfa_challenge(&self, user: &shared::user::User) -> Result<Option<String>, AppError> {
+        self.mfa_challenge(user).await.map_err(|e| {
+            error!("Error al preparar el reto MFA del usuario {}: {}", user.id, e);
+            e.into()
+        })
+    }
+
+    async fn verify_mfa(
+        &self,
+        challenge_id: &str,
+        user_id: &Uuid,
+        token_version: i32,
+        code: Option<&str>,
+        recovery_code: Option<&str>,
+    ) -> Result<shared::user::User, AppError> {
+        self.verify_mfa(challenge_id, user_id, token_version, code, recovery_code).await.map_err(|e| {
+            warn!("Segundo factor rechazado para el usuario {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn start_totp_enrollment(&self, user_id: &Uuid) -> Result<TotpEnrollment, AppError> {
+        self.start_totp_enrollment(user_id).await.map_err(|e| {
+            warn!("Error al iniciar la inscripción TOTP del usuario {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn confirm_totp_enrollment(&self, user_id: &Uuid, code: &str) -> Result<Vec<String>, AppError> {
+        self.confirm_totp_enrollment(user_id, code).await.map_err(|e| {
+            warn!("Error al confirmar el TOTP del usuario {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn disable_totp(&self, user_id: &Uuid, code: Option<&str>, recovery_code: Option<&str>) -> Result<(), AppError> {
+        self.disable_totp(user_id, code, recovery_code).await.map_err(|e| {
+            warn!("Error al desactivar el TOTP del usuario {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn start_passkey_registration(&self, user_id: &Uuid) -> Result<PasskeyOptions, AppError> {
+        self.start_passkey_registration(user_id).await.map_err(|e| {
+            error!("Error al iniciar el registro de passkey del usuario {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn finish_passkey_registration(&self, user_id: &Uuid, registration: &PasskeyRegistrationSchema) -> Result<String, AppError> {
+        self.finish_passkey_registration(user_id, registration).await.map_err(|e| {
+            warn!("Registro de passkey rechazado para el usuario {}: {}", user_id, e);
+            e.into()
+        })
+    }
+
+    async fn start_passkey_login(&self) -> Result<PasskeyOptions, AppError> {
+        self.start_passkey_login().await.map_err(|e| {
+            error!("Error al iniciar el login con passkey: {}", e);
+            e.into()
+        })
+    }
+
+    async fn finish_passkey_login(&self, login: &PasskeyLoginSchema) -> Result<shared::user::User, AppError> {
+        self.finish_passkey_login(login).await.map_err(|e| {
+            warn!("Login con passkey rechazado: {}", e);
+            e.into()
+        })
+    }
+
+    async fn start_federated_login(&self) -> Result<FederatedLoginStart, AppError> {
+        self.start_federated_login().await.map_err(|e| {
+            error!("No se pudo iniciar el login federado: {}", e);
+            e.into()
+        })
+    }
+
+    async fn finish_federated_login(&self, code: &str, state: &str, state_binding: Option<&str>) -> Result<shared::user::User, AppError> {
+        self.finish_federated_login(code, state, state_binding).await.map_err(|e| {
+            warn!("Login federado rechazado: {}", e);
+            e.into()
+        })
+    }
+
+    async fn create_api_key(&self, user_id: &Uuid, request: &CreateApiKeySchema) -> Result<CreatedApiKey, AppError> {
+        let (api_key, key) = self.create_api_key(user_id, request).await.map_err(|e| {
+            warn!("Error al crear una clave de API para el usuario {}: {}", user_id, e);
+            AppError::from(e)
+        })?;
+
+        Ok(CreatedApiKey { key, info: api_key_info(api_key) })
+    }
+
+    async fn list_api_keys(&self, user_id: &Uuid) -> Result<Vec<ApiKeyInfo>, AppError> {
+        let api_keys = self.list_api_keys(user_id).await.map_err(|e| {
+            error!("Error al listar las claves de API del usuario {}: {}", user_id, e);
+            AppError::from(e)
+        })?;
+
+        Ok(api_keys.into_iter().map(api_key_info).collect())
+    }
+
+    async fn revoke_api_key(&self, user_id: &Uuid, key_id: &Uuid) -> Result<(), AppError> {
+        self.revoke_api_key(user_id, key_id).await.map_err(|e| {
+            warn!("Error al revocar la clave de API {} del usuario {}: {}", key_id, user_id, e);
+            e.into()
+        })
+    }
+
+    async fn authenticate_api_key(&self, key: &str) -> Result<(shared::user::User, ApiKeyInfo), AppError> {
+        let (user, api_key) = self.authenticate_api_key(key).await.map_err(AppError::from)?;
+
+        Ok((user, api_key_info(api_key)))
+    }
+
+    async fn register_oauth_client(&self, request: &CreateOAuthClientSchema) -> Result<CreatedOAuthClient, AppError> {
+        let (client, client_secret) = self.register_oauth_client(request).await.map_err(|e| {
+            warn!("Error al registrar el cliente OAuth {}: {}", request.name, e);
+            AppError::from(e)
+        })?;
+
+        Ok(CreatedOAuthClient { client_secret, info: oauth_client_info(client) })
+    }
+
+    async fn list_oauth_clients(&self) -> Result<Vec<OAuthClientInfo>, AppError> {
+        let clients = self.list_oauth_clients().await.map_err(|e| {
+            error!("Error al listar los clientes OAuth: {}", e);
+            AppError::from(e)
+        })?;
+
+        Ok(clients.into_iter().map(oauth_client_info).collect())
+    }
+
+    async fn start_oauth_authorization(&self, params: &AuthorizeParams) -> Result<String, AuthorizeError> {
+        self.start_oauth_authorization(params).await
+    }
+
+    async fn oauth_authorization_request(&self, request_id: &Uuid) -> Result<AuthorizationRequestInfo, AppError> {
+        let (authorization, client) = self.oauth_authorization_request(request_id).await.map_err(AppError::from)?;
+
+        Ok(AuthorizationRequestInfo {
+            id: authorization.id,
+            client_id: client.client_id,
+            client_name: client.name,
+            scopes: authorization.scopes,
+            redirect_uri: authorization.redirect_uri,
+            expires_at: authorization.expires_at,
+        })
+    }
+
+    async fn decide_oauth_authorization(&self, user_id: &Uuid, request_id: &Uuid, approve: bool) -> Result<String, AppError> {
+        self.decide_oauth_authorization(user_id, request_id, approve).await.map_err(|e| {
+            warn!("Error al resolver la autorización OAuth {}: {}", request_id, e);
+            e.into()
+        })
+    }
+
+    async fn start_device_authorization(&self, request: &DeviceAuthorizationRequest) -> Result<DeviceAuthorizationResponse, OAuthError> {
+        self.start_device_authorization(request).await.map_err(|e| {
+            warn!("Autorización de dispositivo rechazada: {}", e);
+            e
+        })
+    }
+
+    async fn device_authorization_request(&self, user_code: &str) -> Result<DeviceAuthorizationInfo, AppError> {
+        let (authorization, client) = self.device_authorization_request(user_code).await.map_err(AppError::from)?;
+
+        Ok(DeviceAuthorizationInfo {
+            user_code: oauth::format_user_code(&authorization.user_code),
+            client_id: client.client_id,
+            client_name: client.name,
+            scopes: authorization.scopes,
+            expires_at: authorization.expires_at,
+        })
+    }
+
+    async fn decide_device_authorization(&self, user_id: &Uuid, user_code: &str, approve: bool) -> Result<(), AppError> {
+        self.decide_device_authorization(user_id, user_code, approve).await.map_err(|e| {
+            warn!("Error al resolver la autorización de dispositivo: {}", e);
+            e.into()
+        })
+    }
+
+    async fn exchange_oauth_token(&self, request: &TokenRequest) -> Result<TokenResponse, OAuthError> {
+        self.exchange_oauth_token(request).await.map_err(|e| {
+            // Las consultas del dispositivo mientras espera no son fallos
+            if !matches!(e.error.as_str(), "authorization_pending" | "slow_down") {
+                warn!("Canje de token OAuth rechazado: {}", e);
             }
-        };
-        
-        // Convertimos el modelo::User a shared::user::User
-        Ok(shared::user::User {
-            id: user.id,
-            email: user.email,
-            password: user.password,
-            name: user.name,
-            role: user.role,
-            telegram_user_id: None,
-            created_at: user.created_at,
-            updated_at: user.updated_at,
+            e
         })
     }
 
-    async fn authenticate_by_telegram(&self, telegram_id: &str) -> Result<shared::user::User, String> {
-        warn!("Intento de autenticación por Telegram (no implementada): {}", telegram_id);
-        Err("Autenticación por Telegram no implementada".to_string())
+    async fn oauth_userinfo(&self, access_token: &str) -> Result<UserInfo, OAuthError> {
+        self.oauth_userinfo(access_token).await
+    }
+
+    async fn verify_service_client(&self, client_id: &str) -> Result<(), AppError> {
+        Ok(self.verify_service_client(client_id).await?)
+    }
+
+    fn openid_configuration(&self) -> serde_json::Value {
+        self.openid_configuration()
     }
 
     async fn generate_token(&self, user: &shared::user::User) -> Result<String, String> {
         info!("Generando token JWT para usuario: {}", user.email);
         
-        match generate_jwt(&user.id.to_string(), &self.jwt_secret, &self.jwt_expires_in) {
+        match self.generate_access_token(user) {
             Ok(token) => {
                 debug!("Token JWT generado correctamente");
                 Ok(token)
@@ -240,6 +2458,28 @@ impl<T: UserRepository + Send + Sync + 'static> api::handlers::auth::AuthService
         }
     }
 
+    async fn issue_refresh_token(&self, user: &shared::user::User) -> Result<String, String> {
+        match self.issue_refresh_token(&user.id, None).await {
+            Ok((_, token)) => Ok(token),
+            Err(e) => {
+                error!("Error al emitir refresh token: {}", e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    async fn refresh_token(&self, refresh_token: &str) -> Result<(String, String), String> {
+        info!("Delegando rotación de refresh token a la implementación interna");
+        self.refresh_session(refresh_token).await.map_err(|e| {
+            warn!("Error al renovar sesión: {}", e);
+            e.to_string()
+        })
+    }
+
+    async fn revoke_refresh_token(&self, user_id: &Uuid, refresh_token: &str) -> Result<(), AppError> {
+        self.revoke_refresh_token(user_id, refresh_token).await.map_err(AppError::from)
+    }
+
     async fn get_user(&self, user_id: &Uuid) -> Result<shared::user::FilteredUser, String> {
         info!("Delegando búsqueda de usuario por ID a la implementación interna: {}", user_id);
         
@@ -261,6 +2501,7 @@ impl<T: UserRepository + Send + Sync + 'static> api::handlers::auth::AuthService
             email: filtered_user.email,
             name: filtered_user.name,
             role: filtered_user.role,
+            email_verified_at: filtered_user.email_verified_at,
             created_at: filtered_user.created_at,
             updated_at: filtered_user.updated_at,
         })
@@ -273,7 +2514,82 @@ fn filter_user_response(user: User) -> FilteredUser {
         email: user.email,
         name: user.name,
         role: user.role,
+        email_verified_at: user.email_verified_at,
         created_at: user.created_at.unwrap_or_default(),
         updated_at: user.updated_at.unwrap_or_default(),
     }
-}
\ No newline at end of file
+}
+
+fn api_key_info(api_key: ApiKey) -> ApiKeyInfo {
+    ApiKeyInfo {
+        id: api_key.id,
+        name: api_key.name,
+        prefix: api_key.prefix,
+        scopes: api_key.scopes,
+        expires_at: api_key.expires_at,
+        last_used_at: api_key.last_used_at,
+        created_at: api_key.created_at,
+    }
+}
+
+fn oauth_client_info(client: OAuthClient) -> OAuthClientInfo {
+    OAuthClientInfo {
+        public: client.client_secret_hash.is_none(),
+        client_id: client.client_id,
+        name: client.name,
+        redirect_uris: client.redirect_uris,
+        scopes: client.scopes,
+        grant_types: client.grant_types,
+        first_party: client.first_party,
+        created_at: client.created_at,
+    }
+}
+
+// Scopes de OpenID Connect que pide un flujo con usuario (por defecto `openid`); los
+// permisos de servicio del cliente no se conceden en nombre de un usuario.
+// Devuelve el primer scope no permitido como error.
+fn user_scopes(client: &OAuthClient, scope: Option<&str>) -> Result<Vec<String>, String> {
+    let mut scopes = oauth::parse_scope(scope.unwrap_or_default());
+    if scopes.is_empty() {
+        scopes.push(oauth::SCOPE_OPENID.to_string());
+    }
+    let allowed = |scope: &String| client.scopes.contains(scope) && oauth::SUPPORTED_SCOPES.contains(&scope.as_str());
+    match scopes.iter().find(|scope| !allowed(scope)) {
+        Some(scope) => Err(scope.clone()),
+        None => Ok(scopes),
+    }
+}
+
+fn has_grant(client: &OAuthClient, grant_type: &str) -> bool {
+    client.grant_types.iter().any(|grant| grant == grant_type)
+}
+
+fn can_use_device_flow(client: &OAuthClient) -> bool {
+    client.first_party && has_grant(client, oauth::GRANT_DEVICE_CODE)
+}
+
+// Los fallos internos no se detallan al cliente OAuth
+fn oauth_server_error(e: impl std::fmt::Display) -> OAuthError {
+    error!("Error interno del proveedor OAuth: {}", e);
+    OAuthError::new("server_error", "Internal server error")
+}
+
+// Formato xxxx-xxxx-xxxx, fácil de copiar a mano
+fn generate_recovery_code() -> String {
+    let raw = generate_secure_token(6);
+    format!("{}-{}-{}", &raw[0..4], &raw[4..8], &raw[8..12])
+}
+
+// Se ignoran mayúsculas, guiones y espacios al comparar códigos de recuperación
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hash_token(&normalized)
+}
+
+fn is_row_not_found(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<sqlx::Error>(), Some(sqlx::Error::RowNotFound))
+}
@@ -0,0 +1,78 @@
+//! TOTP (RFC 6238): HMAC-SHA1, 6 dígitos y pasos de 30 segundos, lo que esperan
+//! Google Authenticator, 1Password, Authy y compañía.
+
+use base32::Alphabet;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+type HmacSha1 = Hmac<Sha1>;
+
+pub const TOTP_STEP_SECS: i64 = 30;
+pub const TOTP_DIGITS: u32 = 6;
+const SECRET_BYTES: usize = 20;
+// Pasos aceptados a cada lado del actual para tolerar relojes desincronizados
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+
+const BASE32: Alphabet = Alphabet::Rfc4648 { padding: false };
+
+/// Genera un secreto aleatorio de 160 bits codificado en base32 (sin relleno)
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base32::encode(BASE32, &bytes)
+}
+
+/// URI `otpauth://` que las apps de autenticación importan desde un código QR
+pub fn otpauth_uri(secret: &str, issuer: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret,
+        percent_encode(issuer),
+        TOTP_DIGITS,
+        TOTP_STEP_SECS,
+    )
+}
+
+/// Código válido para el instante `timestamp` (segundos Unix)
+pub fn generate_code(secret: &str, timestamp: i64) -> Option<String> {
+    let key = base32::decode(BASE32, secret)?;
+    Some(code_for_step(&key, timestamp.div_euclid(TOTP_STEP_SECS)))
+}
+
+/// Comprueba un código y devuelve el paso de tiempo con el que coincide,
+/// para que el llamador pueda rechazar su reutilización.
+pub fn verify_code(secret: &str, code: &str, timestamp: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let key = base32::decode(BASE32, secret)?;
+    let current_step = timestamp.div_euclid(TOTP_STEP_SECS);
+
+    (-ALLOWED_DRIFT_STEPS..=ALLOWED_DRIFT_STEPS)
+        .map(|drift| current_step + drift)
+        .find(|step| constant_time_eq(code_for_step(&key, *step).as_bytes(), code.as_bytes()))
+}
+
+// HOTP (RFC 4226) con truncado dinámico
+fn code_for_step(key: &[u8], step: i64) -> String {
+    let mut mac = HmacSha1::new_from_slice(key).expect("HMAC acepta claves de cualquier tamaño");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+    format!("{:0width$}", binary % 10u32.pow(TOTP_DIGITS), width = TOTP_DIGITS as usize)
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn percent_encode(value: &str) -> String {
+    // byte_serialize codifica los espacios como `+`, que en la ruta de la URI no es válido
+    form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>().replace('+', "%20")
+}
@@ -0,0 +1,254 @@
+//! Verificación de las ceremonias WebAuthn (passkeys): registro con
+//! `navigator.credentials.create()` y login con `navigator.credentials.get()`.
+//! Sólo se admiten claves ES256 (P-256), las que generan los autenticadores de
+//! plataforma, y la atestación no se verifica porque se pide `attestation: "none"`.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use minicbor::Decoder;
+use p256::ecdsa::{signature::Verifier, DerSignature, VerifyingKey};
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::error::AuthError;
+
+/// Identificador COSE de ECDSA con P-256 y SHA-256
+pub const COSE_ALG_ES256: i64 = -7;
+const COSE_KTY_EC2: i64 = 2;
+const COSE_CRV_P256: i64 = 1;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// rpIdHash (32) + flags (1) + signCount (4)
+const AUTH_DATA_MIN_LEN: usize = 37;
+const AAGUID_LEN: usize = 16;
+const CHALLENGE_BYTES: usize = 32;
+
+/// Relying Party frente al que se verifican las respuestas del autenticador
+pub struct RelyingParty<'a> {
+    pub id: &'a str,
+    pub origin: &'a str,
+}
+
+/// Credencial extraída de una respuesta de registro válida
+pub struct RegisteredCredential {
+    pub credential_id: Vec<u8>,
+    /// Clave pública en formato COSE, tal como la entrega el autenticador
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectedClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+    #[serde(default)]
+    cross_origin: bool,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    // (credential ID, clave pública COSE), sólo presentes en el registro
+    attested_credential: Option<(&'a [u8], &'a [u8])>,
+}
+
+/// Challenge aleatorio de 256 bits en base64url
+pub fn generate_challenge() -> String {
+    let mut bytes = [0u8; CHALLENGE_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    encode_b64url(&bytes)
+}
+
+pub fn encode_b64url(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn decode_b64url(value: &str) -> Result<Vec<u8>, AuthError> {
+    // Algunos clientes añaden el relleno aunque base64url no lo use
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| invalid("Codificación base64url inválida"))
+}
+
+/// Verifica la respuesta de `navigator.credentials.create()` y devuelve la credencial a guardar.
+pub fn verify_registration(
+    rp: &RelyingParty,
+    expected_challenge: &str,
+    client_data_json: &[u8],
+    attestation_object: &[u8],
+) -> Result<RegisteredCredential, AuthError> {
+    verify_client_data(rp, "webauthn.create", expected_challenge, client_data_json)?;
+
+    let auth_data = parse_authenticator_data(authenticator_data_from_attestation(attestation_object)?)?;
+    verify_authenticator_data(rp, &auth_data)?;
+
+    let (credential_id, public_key) = auth_data
+        .attested_credential
+        .ok_or_else(|| invalid("La respuesta no incluye la credencial"))?;
+    // Rechaza ya en el registro las claves que no se podrían verificar después
+    verifying_key_from_cose(public_key)?;
+
+    Ok(RegisteredCredential {
+        credential_id: credential_id.to_vec(),
+        public_key: public_key.to_vec(),
+        sign_count: auth_data.sign_count,
+    })
+}
+
+/// Verifica la respuesta de `navigator.credentials.get()` con la clave pública guardada
+/// y devuelve el contador de firmas que informa el autenticador.
+pub fn verify_assertion(
+    rp: &RelyingParty,
+    expected_challenge: &str,
+    client_data_json: &[u8],
+    authenticator_data: &[u8],
+    signature: &[u8],
+    public_key: &[u8],
+) -> Result<u32, AuthError> {
+    verify_client_data(rp, "webauthn.get", expected_challenge, client_data_json)?;
+
+    let auth_data = parse_authenticator_data(authenticator_data)?;
+    verify_authenticator_data(rp, &auth_data)?;
+
+    // La firma cubre authenticatorData || SHA-256(clientDataJSON)
+    let mut signed_data = authenticator_data.to_vec();
+    signed_data.extend_from_slice(&Sha256::digest(client_data_json));
+    let signature = DerSignature::from_bytes(signature).map_err(|_| invalid("Firma mal formada"))?;
+    verifying_key_from_cose(public_key)?
+        .verify(&signed_data, &signature)
+        .map_err(|_| invalid("Firma inválida"))?;
+
+    Ok(auth_data.sign_count)
+}
+
+fn verify_client_data(
+    rp: &RelyingParty,
+    ceremony_type: &str,
+    expected_challenge: &str,
+    client_data_json: &[u8],
+) -> Result<(), AuthError> {
+    let client_data: CollectedClientData =
+        serde_json::from_slice(client_data_json).map_err(|_| invalid("clientDataJSON mal formado"))?;
+
+    if client_data.ceremony_type != ceremony_type {
+        return Err(invalid("Tipo de ceremonia incorrecto"));
+    }
+    if client_data.challenge != expected_challenge {
+        return Err(invalid("El challenge no coincide"));
+    }
+    if client_data.origin != rp.origin || client_data.cross_origin {
+        return Err(invalid("Origen no permitido"));
+    }
+    Ok(())
+}
+
+fn verify_authenticator_data(rp: &RelyingParty, auth_data: &AuthenticatorData) -> Result<(), AuthError> {
+    if auth_data.rp_id_hash != &Sha256::digest(rp.id.as_bytes())[..] {
+        return Err(invalid("La credencial pertenece a otro RP ID"));
+    }
+    if auth_data.flags & FLAG_USER_PRESENT == 0 {
+        return Err(invalid("El autenticador no confirmó la presencia del usuario"));
+    }
+    // Las passkeys sustituyen a la contraseña, así que se exige verificación (PIN, biometría)
+    if auth_data.flags & FLAG_USER_VERIFIED == 0 {
+        return Err(invalid("El autenticador no verificó al usuario"));
+    }
+    Ok(())
+}
+
+// El objeto de atestación es un mapa CBOR {fmt, attStmt, authData}
+fn authenticator_data_from_attestation(attestation_object: &[u8]) -> Result<&[u8], AuthError> {
+    let mut decoder = Decoder::new(attestation_object);
+    let entries = decoder.map().map_err(cbor_error)?.ok_or_else(|| invalid("Mapa CBOR de longitud indefinida"))?;
+
+    let mut auth_data = None;
+    for _ in 0..entries {
+        match decoder.str().map_err(cbor_error)? {
+            "authData" => auth_data = Some(decoder.bytes().map_err(cbor_error)?),
+            _ => decoder.skip().map_err(cbor_error)?,
+        }
+    }
+    auth_data.ok_or_else(|| invalid("Falta authData en el objeto de atestación"))
+}
+
+fn parse_authenticator_data(bytes: &[u8]) -> Result<AuthenticatorData<'_>, AuthError> {
+    if bytes.len() < AUTH_DATA_MIN_LEN {
+        return Err(invalid("authenticatorData demasiado corto"));
+    }
+    let flags = bytes[32];
+    let sign_count = u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]);
+
+    let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+        let rest = &bytes[AUTH_DATA_MIN_LEN..];
+        let id_start = AAGUID_LEN + 2;
+        if rest.len() < id_start {
+            return Err(invalid("Datos de la credencial incompletos"));
+        }
+        let id_len = u16::from_be_bytes([rest[AAGUID_LEN], rest[AAGUID_LEN + 1]]) as usize;
+        let credential_id = rest
+            .get(id_start..id_start + id_len)
+            .ok_or_else(|| invalid("Credential ID incompleto"))?;
+
+        // La clave COSE va seguida de las extensiones, así que hay que medirla decodificándola
+        let key_bytes = &rest[id_start + id_len..];
+        let mut decoder = Decoder::new(key_bytes);
+        decoder.skip().map_err(cbor_error)?;
+        Some((credential_id, &key_bytes[..decoder.position()]))
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        rp_id_hash: &bytes[..32],
+        flags,
+        sign_count,
+        attested_credential,
+    })
+}
+
+fn verifying_key_from_cose(cose_key: &[u8]) -> Result<VerifyingKey, AuthError> {
+    let mut decoder = Decoder::new(cose_key);
+    let entries = decoder.map().map_err(cbor_error)?.ok_or_else(|| invalid("Mapa CBOR de longitud indefinida"))?;
+
+    let (mut kty, mut alg, mut crv, mut x, mut y) = (None, None, None, None, None);
+    for _ in 0..entries {
+        match decoder.i64().map_err(cbor_error)? {
+            1 => kty = Some(decoder.i64().map_err(cbor_error)?),
+            3 => alg = Some(decoder.i64().map_err(cbor_error)?),
+            -1 => crv = Some(decoder.i64().map_err(cbor_error)?),
+            -2 => x = Some(decoder.bytes().map_err(cbor_error)?),
+            -3 => y = Some(decoder.bytes().map_err(cbor_error)?),
+            _ => decoder.skip().map_err(cbor_error)?,
+        }
+    }
+
+    if kty != Some(COSE_KTY_EC2) || alg != Some(COSE_ALG_ES256) || crv != Some(COSE_CRV_P256) {
+        return Err(invalid("Algoritmo de clave no soportado (sólo ES256)"));
+    }
+    let (x, y) = x.zip(y).ok_or_else(|| invalid("Clave pública incompleta"))?;
+    if x.len() != 32 || y.len() != 32 {
+        return Err(invalid("Clave pública incompleta"));
+    }
+
+    // Punto SEC1 sin comprimir: 0x04 || x || y
+    let mut point = Vec::with_capacity(65);
+    point.push(0x04);
+    point.extend_from_slice(x);
+    point.extend_from_slice(y);
+    VerifyingKey::from_sec1_bytes(&point).map_err(|_| invalid("Clave pública inválida"))
+}
+
+fn cbor_error(_: minicbor::decode::Error) -> AuthError {
+    invalid("CBOR mal formado")
+}
+
+fn invalid(message: &str) -> AuthError {
+    AuthError::InvalidPasskey(message.to_string())
+}
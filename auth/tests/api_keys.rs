@@ -0,0 +1,92 @@
+mod support;
+
+use auth::error::AuthError;
+use chrono::{Duration, Utc};
+use shared::user::CreateApiKeySchema;
+use support::TestHarness;
+use uuid::Uuid;
+
+fn request(scopes: &[&str], expires_in: Option<&str>) -> CreateApiKeySchema {
+    CreateApiKeySchema {
+        name: "cron".into(),
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+        expires_in: expires_in.map(str::to_string),
+    }
+}
+
+#[tokio::test]
+async fn created_key_authenticates_its_owner() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+
+    let (api_key, key) = service
+        .create_api_key(&user.id, &request(&["users:read", "users:read"], Some("30d")))
+        .await
+        .unwrap();
+    assert!(key.starts_with(&format!("{}_", api_key.prefix)));
+    assert!(api_key.prefix.starts_with("ak_"));
+    // Sólo se guarda el hash
+    assert!(!api_key.key_hash.contains(&key[api_key.prefix.len() + 1..]));
+    assert_eq!(api_key.scopes, vec!["users:read".to_string()]);
+    assert!(api_key.expires_at.unwrap() > Utc::now() + Duration::days(29));
+
+    let (authenticated, used_key) = service.authenticate_api_key(&key).await.unwrap();
+    assert_eq!(authenticated.id, user.id);
+    assert_eq!(used_key.id, api_key.id);
+    assert!(harness.api_keys.get(&api_key.id).last_used_at.is_some());
+}
+
+#[tokio::test]
+async fn wrong_revoked_or_expired_keys_are_rejected() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+    let (api_key, key) = service.create_api_key(&user.id, &request(&[], None)).await.unwrap();
+
+    let tampered = format!("{}_{}", api_key.prefix, "0".repeat(64));
+    for candidate in [tampered.as_str(), "ak_missing_secret", "not-an-api-key", ""] {
+        assert!(matches!(service.authenticate_api_key(candidate).await, Err(AuthError::InvalidApiKey)), "{}", candidate);
+    }
+
+    harness.api_keys.keys.lock().unwrap()[0].expires_at = Some(Utc::now() - Duration::seconds(1));
+    assert!(matches!(service.authenticate_api_key(&key).await, Err(AuthError::InvalidApiKey)));
+
+    harness.api_keys.keys.lock().unwrap()[0].expires_at = None;
+    service.authenticate_api_key(&key).await.unwrap();
+    service.revoke_api_key(&user.id, &api_key.id).await.unwrap();
+    assert!(matches!(service.authenticate_api_key(&key).await, Err(AuthError::InvalidApiKey)));
+}
+
+#[tokio::test]
+async fn users_list_and_revoke_only_their_own_keys() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let ana = harness.users.insert("ana@example.com", None);
+    let luis = harness.users.insert("luis@example.com", None);
+
+    let (first, _) = service.create_api_key(&ana.id, &request(&[], None)).await.unwrap();
+    let (second, _) = service.create_api_key(&ana.id, &request(&[], None)).await.unwrap();
+    service.create_api_key(&luis.id, &request(&[], None)).await.unwrap();
+
+    let result = service.revoke_api_key(&luis.id, &first.id).await;
+    assert!(matches!(result, Err(AuthError::ApiKeyNotFound)));
+    service.revoke_api_key(&ana.id, &first.id).await.unwrap();
+    assert!(matches!(service.revoke_api_key(&ana.id, &first.id).await, Err(AuthError::ApiKeyNotFound)));
+
+    let listed: Vec<Uuid> = service.list_api_keys(&ana.id).await.unwrap().iter().map(|key| key.id).collect();
+    assert_eq!(listed, vec![second.id]);
+}
+
+#[tokio::test]
+async fn invalid_expiry_is_rejected() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+
+    for expires_in in ["0d", "soon", "-5m"] {
+        let result = service.create_api_key(&user.id, &request(&[], Some(expires_in))).await;
+        assert!(matches!(result, Err(AuthError::InvalidApiKeyExpiry(_))), "{}", expires_in);
+    }
+    assert!(harness.api_keys.keys.lock().unwrap().is_empty());
+}
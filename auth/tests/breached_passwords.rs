@@ -0,0 +1,113 @@
+mod support;
+
+use std::{fs, path::PathBuf, sync::Arc};
+
+use auth::{
+    breached_passwords::BreachedPasswordList,
+    error::AuthError,
+    password_policy::{PasswordPolicy, PasswordViolation},
+};
+use sha1::{Digest, Sha1};
+use shared::user::CreateUserSchema;
+use support::{test_settings, AuthSettings, TestHarness};
+use uuid::Uuid;
+
+const BREACHED: &[(&str, u64)] = &[("Winter-Orchid-42", 3), ("P@ssw0rd!", 120_000), ("hunter2", 17)];
+
+fn sha1_hex(password: &str) -> String {
+    hex::encode_upper(Sha1::digest(password.as_bytes()))
+}
+
+// Corpus con las contraseñas de BREACHED entre cientos de hashes de relleno, ordenado por hash
+fn corpus_lines() -> Vec<(String, u64)> {
+    let mut lines: Vec<(String, u64)> = (0..500).map(|i| (sha1_hex(&format!("filler-{}", i)), i + 1)).collect();
+    lines.extend(BREACHED.iter().map(|(password, count)| (sha1_hex(password), *count)));
+    lines.sort();
+    lines
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("breached-{}-{}", name, Uuid::new_v4()))
+}
+
+fn sorted_file() -> PathBuf {
+    let path = temp_path("sorted");
+    let contents: String = corpus_lines().iter().map(|(hash, count)| format!("{}:{}\r\n", hash, count)).collect();
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+// Un fichero por prefijo de 5 caracteres, con el sufijo de cada hash en minúsculas
+fn range_directory() -> PathBuf {
+    let dir = temp_path("ranges");
+    fs::create_dir(&dir).unwrap();
+    for (hash, count) in corpus_lines() {
+        let (prefix, suffix) = hash.split_at(5);
+        let file = dir.join(format!("{}.txt", prefix));
+        let mut contents = fs::read_to_string(&file).unwrap_or_default();
+        contents.push_str(&format!("{}:{}\n", suffix.to_lowercase(), count));
+        fs::write(&file, contents).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn finds_passwords_in_a_sorted_hash_file() {
+    let path = sorted_file();
+    let list = BreachedPasswordList::open(&path).unwrap();
+
+    for (password, count) in BREACHED {
+        assert_eq!(list.occurrences(password).unwrap(), *count, "{}", password);
+    }
+    assert_eq!(list.occurrences("filler-0").unwrap(), 1);
+    assert_eq!(list.occurrences("filler-499").unwrap(), 500);
+    assert!(!list.contains("Lantern5-meadow-copper").unwrap());
+    assert!(!list.contains("").unwrap());
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn finds_passwords_in_a_range_directory() {
+    let dir = range_directory();
+    let list = BreachedPasswordList::open(&dir).unwrap();
+
+    for (password, count) in BREACHED {
+        assert_eq!(list.occurrences(password).unwrap(), *count, "{}", password);
+    }
+    assert!(!list.contains("Lantern5-meadow-copper").unwrap());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn policy_rejects_breached_passwords() {
+    let path = sorted_file();
+    let settings = AuthSettings {
+        password_policy: PasswordPolicy {
+            min_strength: 0,
+            breached_passwords: Some(Arc::new(BreachedPasswordList::open(&path).unwrap())),
+            ..PasswordPolicy::default()
+        },
+        ..test_settings()
+    };
+    let harness = TestHarness::default();
+    let service = harness.auth_service_with(settings);
+
+    let user = |password: &str| CreateUserSchema {
+        email: "ana@example.com".into(),
+        password: password.into(),
+        name: None,
+    };
+    let error = service.register_user(&user("Winter-Orchid-42")).await.unwrap_err();
+    assert!(matches!(
+        error.downcast::<AuthError>(),
+        Ok(AuthError::WeakPassword(violations)) if violations == vec![PasswordViolation::Breached]
+    ));
+
+    let user_id = service.register_user(&user("Lantern5-meadow-copper")).await.unwrap().id;
+    let result = service.change_password(&user_id, "Lantern5-meadow-copper", "P@ssw0rd!").await;
+    assert!(matches!(result, Err(AuthError::WeakPassword(violations)) if violations == vec![PasswordViolation::Breached]));
+
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,56 @@
+mod support;
+
+use auth::error::AuthError;
+use common::jwt::{verify_jwt, JwtConfig};
+use common::keys::JwtKeys;
+use shared::user::CreateUserSchema;
+use support::TestHarness;
+
+async fn registered(harness: &TestHarness) -> uuid::Uuid {
+    let user = CreateUserSchema {
+        email: "ana@example.com".into(),
+        password: "old-secret".into(),
+        name: None,
+    };
+    harness.auth_service().register_user(&user).await.unwrap().id
+}
+
+#[tokio::test]
+async fn change_password_bumps_token_version_and_closes_sessions() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+    assert_eq!(service.current_token_version(&user_id).await.unwrap(), 0);
+
+    let (token, _refresh_token) = service.change_password(&user_id, "old-secret", "new-secret").await.unwrap();
+
+    assert_eq!(service.current_token_version(&user_id).await.unwrap(), 1);
+    assert_eq!(*harness.refresh_tokens.revoked_users.lock().unwrap(), vec![user_id]);
+    // El token nuevo lleva la versión actual
+    let claims = verify_jwt(&token, &JwtConfig::new(JwtKeys::hmac("test-secret"))).unwrap();
+    assert_eq!(claims.ver, Some(1));
+    assert!(service.authenticate_by_email("ana@example.com", "new-secret").await.is_ok());
+}
+
+#[tokio::test]
+async fn wrong_current_password_is_rejected() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+
+    let result = service.change_password(&user_id, "wrong", "new-secret").await;
+
+    assert!(matches!(result, Err(AuthError::InvalidCurrentPassword)));
+    assert_eq!(service.current_token_version(&user_id).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn new_password_must_differ_from_the_current_one() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+
+    let result = service.change_password(&user_id, "old-secret", "old-secret").await;
+
+    assert!(matches!(result, Err(AuthError::PasswordUnchanged)));
+}
@@ -0,0 +1,167 @@
+mod support;
+
+use auth::{
+    error::AuthError,
+    oauth::{GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS},
+};
+use common::{
+    jwt::{verify_jwt, JwtConfig, SERVICE_TOKEN_USE},
+    keys::JwtKeys,
+};
+use shared::oauth::{AuthorizeError, AuthorizeParams, CreateOAuthClientSchema, TokenRequest};
+use support::TestHarness;
+
+fn service_client(scopes: &[&str]) -> CreateOAuthClientSchema {
+    CreateOAuthClientSchema {
+        name: "Facturación".into(),
+        redirect_uris: vec![],
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+        public: false,
+        grant_types: vec![GRANT_CLIENT_CREDENTIALS.into()],
+        first_party: false,
+    }
+}
+
+fn token_request(client_id: &str, client_secret: &str, scope: Option<&str>) -> TokenRequest {
+    TokenRequest {
+        grant_type: GRANT_CLIENT_CREDENTIALS.into(),
+        scope: scope.map(str::to_string),
+        client_id: Some(client_id.to_string()),
+        client_secret: Some(client_secret.to_string()),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn service_token_has_the_client_as_subject_and_its_scopes() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let (client, secret) = service.register_oauth_client(&service_client(&["users:read", "reports:write"])).await.unwrap();
+    let secret = secret.unwrap();
+
+    let tokens = service.exchange_oauth_token(&token_request(&client.client_id, &secret, None)).await.unwrap();
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope.as_deref(), Some("users:read reports:write"));
+    assert!(tokens.id_token.is_none());
+
+    let claims = verify_jwt(&tokens.access_token, &JwtConfig::new(JwtKeys::hmac("test-secret"))).unwrap();
+    assert_eq!(claims.sub, client.client_id);
+    assert_eq!(claims.token_use.as_deref(), Some(SERVICE_TOKEN_USE));
+    assert_eq!(claims.scope.as_deref(), Some("users:read reports:write"));
+    // Sin datos de usuario
+    assert!(claims.role.is_none() && claims.email.is_none() && claims.ver.is_none());
+
+    service.verify_service_client(&client.client_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn requested_scope_narrows_the_token_within_the_client_scopes() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let (client, secret) = service.register_oauth_client(&service_client(&["users:read", "reports:write"])).await.unwrap();
+    let secret = secret.unwrap();
+
+    let tokens = service
+        .exchange_oauth_token(&token_request(&client.client_id, &secret, Some("reports:write")))
+        .await
+        .unwrap();
+    assert_eq!(tokens.scope.as_deref(), Some("reports:write"));
+
+    for scope in ["users:write", "openid", "users:read users:write"] {
+        let error = service
+            .exchange_oauth_token(&token_request(&client.client_id, &secret, Some(scope)))
+            .await
+            .unwrap_err();
+        assert_eq!(error.error, "invalid_scope", "{}", scope);
+    }
+}
+
+#[tokio::test]
+async fn clients_need_their_secret_and_the_grant() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let (client, secret) = service.register_oauth_client(&service_client(&["users:read"])).await.unwrap();
+
+    let error = service.exchange_oauth_token(&token_request(&client.client_id, "wrong", None)).await.unwrap_err();
+    assert_eq!(error.error, "invalid_client");
+
+    let request = TokenRequest { grant_type: "password".into(), ..token_request(&client.client_id, secret.as_deref().unwrap(), None) };
+    let error = service.exchange_oauth_token(&request).await.unwrap_err();
+    assert_eq!(error.error, "unsupported_grant_type");
+
+    // Un cliente del flujo con usuario no puede pedir tokens de servicio
+    let (web_client, web_secret) = service
+        .register_oauth_client(&CreateOAuthClientSchema {
+            name: "Foro".into(),
+            redirect_uris: vec!["https://foro.example.com/callback".into()],
+            scopes: vec![],
+            public: false,
+            grant_types: vec![GRANT_AUTHORIZATION_CODE.into()],
+            first_party: false,
+        })
+        .await
+        .unwrap();
+    let error = service
+        .exchange_oauth_token(&token_request(&web_client.client_id, &web_secret.unwrap(), None))
+        .await
+        .unwrap_err();
+    assert_eq!(error.error, "unauthorized_client");
+    assert!(matches!(service.verify_service_client(&web_client.client_id).await, Err(AuthError::ServiceClientNotFound)));
+    assert!(matches!(service.verify_service_client("unknown").await, Err(AuthError::ServiceClientNotFound)));
+
+    // Ni un servicio puede iniciar el flujo con usuario
+    let result = service
+        .start_oauth_authorization(&AuthorizeParams {
+            response_type: Some("code".into()),
+            client_id: client.client_id.clone(),
+            redirect_uri: None,
+            scope: None,
+            state: None,
+            nonce: None,
+            code_challenge: None,
+            code_challenge_method: None,
+        })
+        .await;
+    assert!(matches!(result, Err(AuthorizeError::Direct(error)) if error.error == "unauthorized_client"));
+}
+
+#[tokio::test]
+async fn registration_checks_grants_and_scopes() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+
+    let invalid = [
+        // Un servicio siempre es confidencial
+        CreateOAuthClientSchema { public: true, ..service_client(&["users:read"]) },
+        CreateOAuthClientSchema { grant_types: vec![], ..service_client(&["users:read"]) },
+        CreateOAuthClientSchema { grant_types: vec!["implicit".into()], ..service_client(&["users:read"]) },
+        service_client(&["openid"]),
+        service_client(&["users"]),
+        service_client(&["users:read write"]),
+        // Los permisos sólo se conceden con client_credentials
+        CreateOAuthClientSchema {
+            redirect_uris: vec!["https://foro.example.com/callback".into()],
+            grant_types: vec![GRANT_AUTHORIZATION_CODE.into()],
+            ..service_client(&["users:read"])
+        },
+        // El flujo con usuario necesita dónde volver
+        CreateOAuthClientSchema { grant_types: vec![GRANT_AUTHORIZATION_CODE.into()], ..service_client(&[]) },
+    ];
+    for request in invalid {
+        let result = service.register_oauth_client(&request).await;
+        assert!(matches!(result, Err(AuthError::InvalidOAuthClient(_))), "{:?}", request);
+    }
+
+    // Un mismo cliente puede usar los dos grants
+    let (client, secret) = service
+        .register_oauth_client(&CreateOAuthClientSchema {
+            redirect_uris: vec!["https://foro.example.com/callback".into()],
+            grant_types: vec![GRANT_CLIENT_CREDENTIALS.into(), GRANT_AUTHORIZATION_CODE.into()],
+            ..service_client(&["openid", "users:read"])
+        })
+        .await
+        .unwrap();
+    assert!(secret.is_some());
+    assert_eq!(client.scopes, vec!["openid".to_string(), "users:read".to_string()]);
+    assert_eq!(client.grant_types.len(), 2);
+}
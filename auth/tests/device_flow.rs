@@ -0,0 +1,237 @@
+mod support;
+
+use auth::{error::AuthError, oauth::GRANT_DEVICE_CODE};
+use chrono::{Duration, Utc};
+use common::{
+    jwt::{verify_jwt, JwtConfig},
+    keys::JwtKeys,
+};
+use shared::oauth::{CreateOAuthClientSchema, DeviceAuthorizationRequest, OAuthError, TokenRequest, TokenResponse};
+use support::{AuthService, InMemoryUserRepository, TestHarness};
+
+struct Device<'a> {
+    service: &'a AuthService<InMemoryUserRepository>,
+    client_id: String,
+}
+
+impl Device<'_> {
+    async fn register(service: &AuthService<InMemoryUserRepository>) -> Device<'_> {
+        let (client, _) = service
+            .register_oauth_client(&CreateOAuthClientSchema {
+                name: "CLI interna".into(),
+                redirect_uris: vec![],
+                scopes: vec![],
+                public: true,
+                grant_types: vec![GRANT_DEVICE_CODE.into()],
+                first_party: true,
+            })
+            .await
+            .unwrap();
+        Device { service, client_id: client.client_id }
+    }
+
+    /// Devuelve el device_code y el user_code tal como se muestra
+    async fn start(&self) -> (String, String) {
+        let authorization = self
+            .service
+            .start_device_authorization(&DeviceAuthorizationRequest {
+                client_id: Some(self.client_id.clone()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        (authorization.device_code, authorization.user_code)
+    }
+
+    async fn poll(&self, device_code: &str) -> Result<TokenResponse, OAuthError> {
+        self.service
+            .exchange_oauth_token(&TokenRequest {
+                grant_type: GRANT_DEVICE_CODE.into(),
+                device_code: Some(device_code.to_string()),
+                client_id: Some(self.client_id.clone()),
+                ..Default::default()
+            })
+            .await
+    }
+}
+
+/// Simula que ha pasado el intervalo desde la última consulta del dispositivo
+fn wait_interval(harness: &TestHarness) {
+    for authorization in harness.oauth.device_authorizations.lock().unwrap().iter_mut() {
+        authorization.last_polled_at = authorization
+            .last_polled_at
+            .map(|last_polled_at| last_polled_at - Duration::seconds(authorization.interval_secs.into()));
+    }
+}
+
+#[tokio::test]
+async fn approved_device_receives_a_session_once() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+    let device = Device::register(&service).await;
+
+    let authorization = service
+        .start_device_authorization(&DeviceAuthorizationRequest {
+            client_id: Some(device.client_id.clone()),
+            scope: Some("openid email".into()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(authorization.user_code.len(), 9);
+    assert_eq!(&authorization.user_code[4..5], "-");
+    assert_eq!(authorization.verification_uri, "http://localhost:3000/oauth/device");
+    assert_eq!(
+        authorization.verification_uri_complete,
+        format!("http://localhost:3000/oauth/device?user_code={}", authorization.user_code)
+    );
+    assert_eq!(authorization.expires_in, 600);
+    assert_eq!(authorization.interval, 5);
+
+    assert_eq!(device.poll(&authorization.device_code).await.unwrap_err().error, "authorization_pending");
+
+    // El usuario puede teclear el código en minúsculas y sin guion
+    let typed = authorization.user_code.replace('-', "").to_lowercase();
+    let (pending, client) = service.device_authorization_request(&typed).await.unwrap();
+    assert_eq!(client.name, "CLI interna");
+    assert_eq!(pending.scopes, vec!["openid".to_string(), "email".to_string()]);
+    service.decide_device_authorization(&user.id, &typed, true).await.unwrap();
+
+    wait_interval(&harness);
+    let tokens = device.poll(&authorization.device_code).await.unwrap();
+    // No es un token limitado a los scopes aprobados: éstos sólo deciden el ID token
+    assert_eq!(tokens.scope, None);
+    assert!(tokens.refresh_token.is_some());
+    assert!(tokens.id_token.is_some());
+    // Una sesión como la del login, emitida por generate_jwt
+    let claims = verify_jwt(&tokens.access_token, &JwtConfig::new(JwtKeys::hmac("test-secret"))).unwrap();
+    assert_eq!(claims.sub, user.id.to_string());
+    assert_eq!(claims.email.as_deref(), Some("ana@example.com"));
+    assert!(claims.token_use.is_none());
+
+    wait_interval(&harness);
+    assert_eq!(device.poll(&authorization.device_code).await.unwrap_err().error, "invalid_grant");
+}
+
+#[tokio::test]
+async fn polling_faster_than_the_interval_slows_the_device_down() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let device = Device::register(&service).await;
+    let (device_code, _) = device.start().await;
+
+    assert_eq!(device.poll(&device_code).await.unwrap_err().error, "authorization_pending");
+    assert_eq!(device.poll(&device_code).await.unwrap_err().error, "slow_down");
+    assert_eq!(harness.oauth.device_authorizations.lock().unwrap()[0].interval_secs, 10);
+    assert_eq!(device.poll(&device_code).await.unwrap_err().error, "slow_down");
+    assert_eq!(harness.oauth.device_authorizations.lock().unwrap()[0].interval_secs, 15);
+
+    wait_interval(&harness);
+    assert_eq!(device.poll(&device_code).await.unwrap_err().error, "authorization_pending");
+}
+
+#[tokio::test]
+async fn denied_and_expired_requests_end_the_flow() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+    let device = Device::register(&service).await;
+
+    let (device_code, user_code) = device.start().await;
+    service.decide_device_authorization(&user.id, &user_code, false).await.unwrap();
+    assert_eq!(device.poll(&device_code).await.unwrap_err().error, "access_denied");
+    wait_interval(&harness);
+    assert_eq!(device.poll(&device_code).await.unwrap_err().error, "invalid_grant");
+
+    let (device_code, user_code) = device.start().await;
+    for authorization in harness.oauth.device_authorizations.lock().unwrap().iter_mut() {
+        authorization.expires_at = Utc::now() - Duration::seconds(1);
+    }
+    assert!(matches!(
+        service.decide_device_authorization(&user.id, &user_code, true).await,
+        Err(AuthError::OAuthRequestNotFound)
+    ));
+    assert_eq!(device.poll(&device_code).await.unwrap_err().error, "expired_token");
+    assert!(harness.oauth.device_authorizations.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn codes_are_bound_to_their_client_and_decided_once() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+    let device = Device::register(&service).await;
+    let other = Device::register(&service).await;
+
+    let (device_code, user_code) = device.start().await;
+    assert_eq!(other.poll(&device_code).await.unwrap_err().error, "invalid_grant");
+    assert_eq!(device.poll("unknown-device-code").await.unwrap_err().error, "invalid_grant");
+
+    service.decide_device_authorization(&user.id, &user_code, true).await.unwrap();
+    assert!(matches!(
+        service.decide_device_authorization(&user.id, &user_code, false).await,
+        Err(AuthError::OAuthRequestNotFound)
+    ));
+    assert!(matches!(service.device_authorization_request("BCDF-GHJK").await, Err(AuthError::OAuthRequestNotFound)));
+
+    // Un cliente sin el grant no puede iniciar el flujo
+    let (web_client, _) = service
+        .register_oauth_client(&CreateOAuthClientSchema {
+            name: "Foro".into(),
+            redirect_uris: vec!["https://foro.example.com/callback".into()],
+            scopes: vec![],
+            public: true,
+            grant_types: vec!["authorization_code".into()],
+            first_party: false,
+        })
+        .await
+        .unwrap();
+    let error = service
+        .start_device_authorization(&DeviceAuthorizationRequest {
+            client_id: Some(web_client.client_id),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.error, "unauthorized_client");
+
+    let error = service
+        .start_device_authorization(&DeviceAuthorizationRequest {
+            client_id: Some(device.client_id.clone()),
+            scope: Some("users:read".into()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.error, "invalid_scope");
+}
+
+#[tokio::test]
+async fn only_first_party_clients_can_use_the_device_flow() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+
+    // Emite una sesión completa del usuario: un cliente de terceros no puede registrarse con el grant
+    let result = service
+        .register_oauth_client(&CreateOAuthClientSchema {
+            name: "CLI de terceros".into(),
+            redirect_uris: vec![],
+            scopes: vec![],
+            public: true,
+            grant_types: vec![GRANT_DEVICE_CODE.into()],
+            first_party: false,
+        })
+        .await;
+    assert!(matches!(result, Err(AuthError::InvalidOAuthClient(_))));
+
+    // Si el cliente deja de ser propio, una autorización ya aprobada no se canjea
+    let device = Device::register(&service).await;
+    let (device_code, user_code) = device.start().await;
+    service.decide_device_authorization(&user.id, &user_code, true).await.unwrap();
+    for client in harness.oauth.clients.lock().unwrap().iter_mut() {
+        client.first_party = false;
+    }
+    assert_eq!(device.poll(&device_code).await.unwrap_err().error, "unauthorized_client");
+}
@@ -0,0 +1,121 @@
+mod support;
+
+use auth::error::AuthError;
+use chrono::{Duration, Utc};
+use shared::user::CreateUserSchema;
+use support::{test_settings, TestHarness};
+
+fn new_user(email: &str) -> CreateUserSchema {
+    CreateUserSchema {
+        email: email.to_string(),
+        password: "secret123".into(),
+        name: Some("Ana".into()),
+    }
+}
+
+#[tokio::test]
+async fn registration_sends_a_verification_link() {
+    let harness = TestHarness::default();
+
+    let user = harness.auth_service().register_user(&new_user("ana@example.com")).await.unwrap();
+
+    assert!(user.email_verified_at.is_none());
+    assert_eq!(harness.mailer.sent_count(), 1);
+    assert_eq!(harness.mailer.sent.lock().unwrap()[0].to, "ana@example.com");
+    // Sólo se guarda el hash del token
+    let token = harness.mailer.last_token();
+    assert!(harness.email_verifications.tokens.lock().unwrap().iter().all(|t| t.token_hash != token));
+}
+
+#[tokio::test]
+async fn verification_token_marks_the_email_as_verified() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = service.register_user(&new_user("ana@example.com")).await.unwrap();
+
+    service.verify_email(&harness.mailer.last_token()).await.unwrap();
+
+    assert!(harness.users.get(&user.id).email_verified_at.is_some());
+}
+
+#[tokio::test]
+async fn verification_token_is_single_use() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    service.register_user(&new_user("ana@example.com")).await.unwrap();
+    let token = harness.mailer.last_token();
+
+    service.verify_email(&token).await.unwrap();
+    let second = service.verify_email(&token).await;
+
+    assert!(matches!(second, Err(AuthError::InvalidVerificationToken)));
+}
+
+#[tokio::test]
+async fn expired_verification_token_is_rejected() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = service.register_user(&new_user("ana@example.com")).await.unwrap();
+    for token in harness.email_verifications.tokens.lock().unwrap().iter_mut() {
+        token.expires_at = Utc::now() - Duration::minutes(1);
+    }
+
+    let result = service.verify_email(&harness.mailer.last_token()).await;
+
+    assert!(matches!(result, Err(AuthError::InvalidVerificationToken)));
+    assert!(harness.users.get(&user.id).email_verified_at.is_none());
+}
+
+#[tokio::test]
+async fn unverified_login_is_blocked_when_verification_is_required() {
+    let harness = TestHarness::default();
+    let settings = support::AuthSettings { email_verification_required: true, ..test_settings() };
+    let service = harness.auth_service_with(settings);
+    service.register_user(&new_user("ana@example.com")).await.unwrap();
+
+    let before = service.authenticate_by_email("ana@example.com", "secret123").await;
+    service.verify_email(&harness.mailer.last_token()).await.unwrap();
+    let after = service.authenticate_by_email("ana@example.com", "secret123").await;
+
+    assert!(matches!(before, Err(AuthError::EmailNotVerified)));
+    assert!(after.is_ok());
+}
+
+#[tokio::test]
+async fn unverified_login_is_allowed_by_default() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    service.register_user(&new_user("ana@example.com")).await.unwrap();
+
+    assert!(service.authenticate_by_email("ana@example.com", "secret123").await.is_ok());
+}
+
+#[tokio::test]
+async fn resend_is_throttled_and_invalidates_previous_links() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    service.register_user(&new_user("ana@example.com")).await.unwrap();
+    let first_token = harness.mailer.last_token();
+
+    // Dentro del intervalo mínimo no se envía nada
+    service.resend_verification_email("ana@example.com").await.unwrap();
+    assert_eq!(harness.mailer.sent_count(), 1);
+
+    for token in harness.email_verifications.tokens.lock().unwrap().iter_mut() {
+        token.created_at = Utc::now() - Duration::minutes(5);
+    }
+    service.resend_verification_email("ana@example.com").await.unwrap();
+    assert_eq!(harness.mailer.sent_count(), 2);
+
+    assert!(matches!(service.verify_email(&first_token).await, Err(AuthError::InvalidVerificationToken)));
+    service.verify_email(&harness.mailer.last_token()).await.unwrap();
+}
+
+#[tokio::test]
+async fn resend_for_unknown_email_succeeds_silently() {
+    let harness = TestHarness::default();
+
+    harness.auth_service().resend_verification_email("nobody@example.com").await.unwrap();
+
+    assert_eq!(harness.mailer.sent_count(), 0);
+}
@@ -0,0 +1,118 @@
+mod support;
+
+use auth::error::AuthError;
+use chrono::Duration;
+use shared::user::CreateUserSchema;
+use support::{test_settings, TestHarness};
+use uuid::Uuid;
+
+// `test_settings`: bloqueo de 15 min al tercer fallo, espera base de 1 s
+
+async fn registered(harness: &TestHarness) -> Uuid {
+    let user = CreateUserSchema {
+        email: "ana@example.com".into(),
+        password: "secret123".into(),
+        name: None,
+    };
+    harness.auth_service().register_user(&user).await.unwrap().id
+}
+
+async fn login(harness: &TestHarness, password: &str) -> Result<(), AuthError> {
+    harness.auth_service().authenticate_by_email("ana@example.com", password).await.map(|_| ())
+}
+
+// Falla el login y deja pasar la espera que provoca
+async fn fail_and_wait(harness: &TestHarness, user_id: &Uuid) {
+    assert!(matches!(login(harness, "wrong").await, Err(AuthError::InvalidCredentials)));
+    harness.login_attempts.rewind(user_id, Duration::seconds(10));
+}
+
+#[tokio::test]
+async fn failures_impose_an_exponential_delay() {
+    let harness = TestHarness::default();
+    let user_id = registered(&harness).await;
+
+    assert!(matches!(login(&harness, "wrong").await, Err(AuthError::InvalidCredentials)));
+    // Ni la contraseña correcta pasa durante la espera, y el intento no cuenta
+    assert!(matches!(login(&harness, "secret123").await, Err(AuthError::AccountLocked { retry_after: 1 })));
+    assert_eq!(harness.login_attempts.failed_count(&user_id), 1);
+
+    harness.login_attempts.rewind(&user_id, Duration::seconds(1));
+    assert!(matches!(login(&harness, "wrong").await, Err(AuthError::InvalidCredentials)));
+    assert!(matches!(login(&harness, "wrong").await, Err(AuthError::AccountLocked { retry_after: 2 })));
+}
+
+#[tokio::test]
+async fn reaching_the_threshold_locks_the_account() {
+    let harness = TestHarness::default();
+    let user_id = registered(&harness).await;
+
+    for _ in 0..3 {
+        fail_and_wait(&harness, &user_id).await;
+    }
+
+    // Tras los 10 s simulados quedan 890 s de los 15 min de bloqueo
+    assert!(matches!(login(&harness, "secret123").await, Err(AuthError::AccountLocked { retry_after: 890 })));
+
+    harness.login_attempts.rewind(&user_id, Duration::minutes(15));
+    assert!(login(&harness, "secret123").await.is_ok());
+}
+
+#[tokio::test]
+async fn successful_login_resets_the_counter() {
+    let harness = TestHarness::default();
+    let user_id = registered(&harness).await;
+
+    fail_and_wait(&harness, &user_id).await;
+    fail_and_wait(&harness, &user_id).await;
+    assert!(login(&harness, "secret123").await.is_ok());
+    assert_eq!(harness.login_attempts.failed_count(&user_id), 0);
+
+    // El contador vuelve a empezar: un fallo más no bloquea la cuenta
+    fail_and_wait(&harness, &user_id).await;
+    assert!(login(&harness, "secret123").await.is_ok());
+}
+
+#[tokio::test]
+async fn admin_unlock_lifts_the_lockout() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+
+    for _ in 0..3 {
+        fail_and_wait(&harness, &user_id).await;
+    }
+    assert!(matches!(login(&harness, "secret123").await, Err(AuthError::AccountLocked { .. })));
+
+    service.unlock_account(&user_id).await.unwrap();
+    assert!(login(&harness, "secret123").await.is_ok());
+    assert!(matches!(service.unlock_account(&Uuid::new_v4()).await, Err(AuthError::UserNotFound)));
+}
+
+#[tokio::test]
+async fn zero_threshold_disables_the_lockout() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service_with(support::AuthSettings { login_max_failed_attempts: 0, ..test_settings() });
+    registered(&harness).await;
+
+    for _ in 0..5 {
+        let result = service.authenticate_by_email("ana@example.com", "wrong").await;
+        assert!(matches!(result, Err(AuthError::InvalidCredentials)));
+    }
+    assert!(service.authenticate_by_email("ana@example.com", "secret123").await.is_ok());
+    assert!(harness.login_attempts.attempts.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn concurrent_guesses_cannot_share_one_lockout_check() {
+    let harness = TestHarness::default();
+    let user_id = registered(&harness).await;
+
+    // Las tres peticiones leen el recuento antes de que ninguna cuente su fallo
+    let (first, second, third) = tokio::join!(login(&harness, "wrong"), login(&harness, "wrong"), login(&harness, "wrong"));
+    let results = [first, second, third];
+    let guessed = results.iter().filter(|result| matches!(result, Err(AuthError::InvalidCredentials))).count();
+    assert_eq!(guessed, 1);
+    assert!(results.iter().all(|result| matches!(result, Err(AuthError::InvalidCredentials | AuthError::AccountLocked { .. }))));
+    assert_eq!(harness.login_attempts.failed_count(&user_id), 1);
+}
@@ -0,0 +1,190 @@
+mod support;
+
+use auth::error::AuthError;
+use auth::service::LoginOutcome;
+use auth::totp::{generate_code, TOTP_STEP_SECS};
+use chrono::Utc;
+use common::jwt::{verify_jwt, JwtConfig, MFA_PENDING_TOKEN_USE};
+use common::keys::JwtKeys;
+use shared::user::{CreateUserSchema, LoginUserSchema};
+use support::TestHarness;
+use uuid::Uuid;
+
+async fn registered(harness: &TestHarness) -> Uuid {
+    let user = CreateUserSchema {
+        email: "ana@example.com".into(),
+        password: "secret123".into(),
+        name: None,
+    };
+    harness.auth_service().register_user(&user).await.unwrap().id
+}
+
+// Activa el TOTP y devuelve el secreto y los códigos de recuperación
+async fn enrolled(harness: &TestHarness, user_id: &Uuid) -> (String, Vec<String>) {
+    let service = harness.auth_service();
+    let enrollment = service.start_totp_enrollment(user_id).await.unwrap();
+    let code = generate_code(&enrollment.secret, Utc::now().timestamp()).unwrap();
+    let recovery_codes = service.confirm_totp_enrollment(user_id, &code).await.unwrap();
+    (enrollment.secret, recovery_codes)
+}
+
+// Código del paso siguiente: el del paso actual ya se gastó al confirmar
+fn next_code(secret: &str) -> String {
+    generate_code(secret, Utc::now().timestamp() + TOTP_STEP_SECS).unwrap()
+}
+
+// Cada login con 2FA es un reto nuevo, con su propio `jti`
+fn challenge() -> String {
+    Uuid::new_v4().to_string()
+}
+
+fn credentials() -> LoginUserSchema {
+    LoginUserSchema {
+        email: Some("ana@example.com".into()),
+        telegram_user_id: None,
+        telegram: None,
+        password: "secret123".into(),
+    }
+}
+
+#[tokio::test]
+async fn enrollment_requires_a_valid_first_code() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+
+    let enrollment = service.start_totp_enrollment(&user_id).await.unwrap();
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+
+    let result = service.confirm_totp_enrollment(&user_id, "000000").await;
+    assert!(matches!(result, Err(AuthError::InvalidMfaCode)));
+    // Sin confirmar, el login sigue sin pedir segundo factor
+    assert!(matches!(service.login_user(&credentials()).await.unwrap(), LoginOutcome::Authenticated(..)));
+
+    let code = generate_code(&enrollment.secret, Utc::now().timestamp()).unwrap();
+    let recovery_codes = service.confirm_totp_enrollment(&user_id, &code).await.unwrap();
+    assert_eq!(recovery_codes.len(), 10);
+    assert!(matches!(service.start_totp_enrollment(&user_id).await, Err(AuthError::MfaAlreadyEnabled)));
+}
+
+#[tokio::test]
+async fn recovery_codes_are_stored_hashed() {
+    let harness = TestHarness::default();
+    let user_id = registered(&harness).await;
+
+    let (_, recovery_codes) = enrolled(&harness, &user_id).await;
+
+    let stored = harness.mfa.recovery_codes.lock().unwrap();
+    assert_eq!(stored.len(), recovery_codes.len());
+    assert!(stored.iter().all(|stored| !recovery_codes.contains(&stored.code_hash)));
+}
+
+#[tokio::test]
+async fn login_with_mfa_returns_a_pending_token_and_verify_completes_it() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+    let (secret, _) = enrolled(&harness, &user_id).await;
+
+    let mfa_token = match service.login_user(&credentials()).await.unwrap() {
+        LoginOutcome::MfaRequired { mfa_token } => mfa_token,
+        LoginOutcome::Authenticated(..) => panic!("se esperaba el reto MFA"),
+    };
+    let claims = verify_jwt(&mfa_token, &JwtConfig::new(JwtKeys::hmac("test-secret"))).unwrap();
+    assert_eq!(claims.token_use.as_deref(), Some(MFA_PENDING_TOKEN_USE));
+    assert_eq!(claims.role, None);
+
+    let code = next_code(&secret);
+    let user = service.verify_mfa(&claims.jti, &user_id, 0, Some(&code), None).await.unwrap();
+    assert_eq!(user.id, user_id);
+    // El mismo código no vale dos veces
+    assert!(matches!(service.verify_mfa(&challenge(), &user_id, 0, Some(&code), None).await, Err(AuthError::InvalidMfaCode)));
+}
+
+#[tokio::test]
+async fn recovery_code_works_only_once() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+    let (_, recovery_codes) = enrolled(&harness, &user_id).await;
+
+    // Se aceptan en mayúsculas y sin guiones
+    let typed = recovery_codes[0].replace('-', "").to_uppercase();
+    assert!(service.verify_mfa(&challenge(), &user_id, 0, None, Some(&typed)).await.is_ok());
+    assert!(matches!(
+        service.verify_mfa(&challenge(), &user_id, 0, None, Some(&recovery_codes[0])).await,
+        Err(AuthError::InvalidMfaCode)
+    ));
+    assert!(service.verify_mfa(&challenge(), &user_id, 0, None, Some(&recovery_codes[1])).await.is_ok());
+}
+
+#[tokio::test]
+async fn disabling_requires_a_second_factor() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+    let (secret, _) = enrolled(&harness, &user_id).await;
+
+    assert!(matches!(service.disable_totp(&user_id, None, None).await, Err(AuthError::InvalidMfaCode)));
+    service.disable_totp(&user_id, Some(&next_code(&secret)), None).await.unwrap();
+
+    assert!(harness.mfa.recovery_codes.lock().unwrap().is_empty());
+    assert!(matches!(service.login_user(&credentials()).await.unwrap(), LoginOutcome::Authenticated(..)));
+    assert!(matches!(service.verify_mfa(&challenge(), &user_id, 0, Some("123456"), None).await, Err(AuthError::MfaNotEnrolled)));
+}
+
+#[tokio::test]
+async fn challenge_is_invalidated_after_too_many_wrong_codes() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+    let (secret, recovery_codes) = enrolled(&harness, &user_id).await;
+    let mfa_challenge = challenge();
+
+    // `test_settings` permite 3 códigos por reto; los de recuperación también cuentan
+    for _ in 0..2 {
+        let result = service.verify_mfa(&mfa_challenge, &user_id, 0, Some("000000"), None).await;
+        assert!(matches!(result, Err(AuthError::InvalidMfaCode)));
+    }
+    let result = service.verify_mfa(&mfa_challenge, &user_id, 0, None, Some("0000-0000-0000")).await;
+    assert!(matches!(result, Err(AuthError::InvalidMfaCode)));
+
+    // Ni un código correcto sirve ya con este reto, y no se gasta
+    let result = service.verify_mfa(&mfa_challenge, &user_id, 0, None, Some(&recovery_codes[0])).await;
+    assert!(matches!(result, Err(AuthError::MfaChallengeExhausted)));
+    assert!(harness.mfa.recovery_codes.lock().unwrap().iter().all(|code| !code.used));
+
+    // Un login nuevo trae un reto con sus propios intentos
+    assert!(service.verify_mfa(&challenge(), &user_id, 0, Some(&next_code(&secret)), None).await.is_ok());
+}
+
+#[tokio::test]
+async fn challenge_from_before_a_password_change_spends_nothing() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+    let (_, recovery_codes) = enrolled(&harness, &user_id).await;
+    service.change_password(&user_id, "secret123", "another-secret456").await.unwrap();
+
+    let result = service.verify_mfa(&challenge(), &user_id, 0, None, Some(&recovery_codes[0])).await;
+    assert!(matches!(result, Err(AuthError::InvalidToken(_))));
+    assert!(harness.mfa.recovery_codes.lock().unwrap().iter().all(|code| !code.used));
+    assert!(harness.mfa.challenge_attempts.lock().unwrap().is_empty());
+
+    // Con el reto de un login posterior el código sigue valiendo
+    assert!(service.verify_mfa(&challenge(), &user_id, 1, None, Some(&recovery_codes[0])).await.is_ok());
+}
+
+#[tokio::test]
+async fn confirming_an_enabled_totp_keeps_its_recovery_codes() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+    let (secret, _) = enrolled(&harness, &user_id).await;
+    let stored: Vec<String> = harness.mfa.recovery_codes.lock().unwrap().iter().map(|code| code.code_hash.clone()).collect();
+
+    let result = service.confirm_totp_enrollment(&user_id, &next_code(&secret)).await;
+    assert!(matches!(result, Err(AuthError::MfaAlreadyEnabled)));
+    let after: Vec<String> = harness.mfa.recovery_codes.lock().unwrap().iter().map(|code| code.code_hash.clone()).collect();
+    assert_eq!(after, stored);
+}
@@ -0,0 +1,423 @@
+mod support;
+
+use std::{collections::HashMap, sync::Arc};
+
+use api::{routes::create_router, AppState};
+use auth::{error::AuthError, oauth::GRANT_AUTHORIZATION_CODE};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use chrono::Utc;
+use common::{jwt::JwtConfig, keys::JwtKeys, utils::generate_secure_token};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use repository::{InMemoryRevocationStore, OAuthClient};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use shared::{oauth::CreateOAuthClientSchema, user::User};
+use tower::ServiceExt;
+
+use support::{
+    test_settings, AuthService, EmptyPermissionRepository, InMemoryUserRepository, TestHarness, TEST_ED25519_PRIVATE_KEY,
+    TEST_ED25519_PUBLIC_KEY, TEST_ISSUER,
+};
+
+const REDIRECT_URI: &str = "https://client.example.com/callback";
+
+/// Proveedor completo: el router de la API sobre el servicio real, firmando con Ed25519
+struct Provider {
+    harness: TestHarness,
+    service: Arc<AuthService<InMemoryUserRepository>>,
+    router: Router,
+}
+
+impl Provider {
+    fn new() -> Self {
+        let harness = TestHarness::default();
+        let keys = JwtKeys::from_pem("oidc-test", Algorithm::EdDSA, TEST_ED25519_PRIVATE_KEY, TEST_ED25519_PUBLIC_KEY).unwrap();
+        let jwt_config = Arc::new(JwtConfig::new(keys));
+        let service = Arc::new(harness.auth_service_with_jwt(test_settings(), jwt_config.clone()));
+        let state = AppState::new(
+            service.clone(),
+            jwt_config,
+            Arc::new(InMemoryRevocationStore::new()),
+            Arc::new(EmptyPermissionRepository),
+        );
+        Self { harness, service, router: create_router(Arc::new(state)) }
+    }
+
+    async fn client(&self, public: bool, scopes: &[&str]) -> Client<'_> {
+        let (client, client_secret) = self
+            .service
+            .register_oauth_client(&CreateOAuthClientSchema {
+                name: "Example App".into(),
+                redirect_uris: vec![REDIRECT_URI.into()],
+                scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+                public,
+                grant_types: vec![GRANT_AUTHORIZATION_CODE.into()],
+                first_party: false,
+            })
+            .await
+            .unwrap();
+        Client {
+            provider: self,
+            client_id: client.client_id,
+            client_secret,
+            code_verifier: generate_secure_token(32),
+        }
+    }
+
+    async fn register(&self, redirect_uri: &str, scope: &str) -> Result<(OAuthClient, Option<String>), AuthError> {
+        self.service
+            .register_oauth_client(&CreateOAuthClientSchema {
+                name: "Example App".into(),
+                redirect_uris: vec![redirect_uri.to_string()],
+                scopes: vec![scope.to_string()],
+                public: true,
+                grant_types: vec![GRANT_AUTHORIZATION_CODE.into()],
+                first_party: false,
+            })
+            .await
+    }
+
+    /// Usuario con sesión iniciada en el frontend y su access token
+    fn signed_in_user(&self) -> (User, String) {
+        let user = self.harness.users.insert("ana@example.com", None);
+        for stored in self.harness.users.users.lock().unwrap().iter_mut().filter(|stored| stored.id == user.id) {
+            stored.name = Some("Ana".into());
+            stored.email_verified_at = Some(Utc::now());
+        }
+        let user = self.harness.users.get(&user.id);
+        let session = self.service.generate_access_token(&user).unwrap();
+        (user, session)
+    }
+
+    async fn send(&self, request: Request<Body>) -> Response {
+        self.router.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn get(&self, uri: &str, bearer: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(uri);
+        if let Some(token) = bearer {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        self.send(request.body(Body::empty()).unwrap()).await
+    }
+}
+
+/// Cliente OAuth en el mismo proceso: recorre el flujo como una aplicación real
+struct Client<'a> {
+    provider: &'a Provider,
+    client_id: String,
+    client_secret: Option<String>,
+    code_verifier: String,
+}
+
+impl Client<'_> {
+    fn authorize_uri(&self, overrides: &[(&str, &str)]) -> String {
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(self.code_verifier.as_bytes()));
+        let mut params: Vec<(&str, &str)> = vec![
+            ("response_type", "code"),
+            ("client_id", &self.client_id),
+            ("redirect_uri", REDIRECT_URI),
+            ("scope", "openid profile email"),
+            ("state", "xyz"),
+            ("nonce", "n-0S6_WzA2Mj"),
+            ("code_challenge", &challenge),
+            ("code_challenge_method", "S256"),
+        ];
+        for (key, value) in overrides {
+            params.retain(|(existing, _)| existing != key);
+            if !value.is_empty() {
+                params.push((key, value));
+            }
+        }
+        let query = form_urlencoded::Serializer::new(String::new()).extend_pairs(params).finish();
+        format!("/oauth/authorize?{}", query)
+    }
+
+    /// `/oauth/authorize` y la pantalla de consentimiento; devuelve los parámetros con los
+    /// que el proveedor vuelve a la redirect_uri
+    async fn authorize(&self, session: &str, approve: bool) -> HashMap<String, String> {
+        let response = self.provider.get(&self.authorize_uri(&[]), None).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let consent_url = location(&response);
+        assert!(consent_url.starts_with("http://localhost:3000/oauth/consent?"), "{}", consent_url);
+        let request_id = query_params(&consent_url)["request_id"].clone();
+
+        let response = self.provider.get(&format!("/api/oauth/authorizations/{}", request_id), Some(session)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let request = json_body(response).await["authorization_request"].clone();
+        assert_eq!(request["client_name"], "Example App");
+        assert_eq!(request["scopes"], json!(["openid", "profile", "email"]));
+
+        let response = self
+            .provider
+            .send(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/oauth/authorizations/{}", request_id))
+                    .header(header::AUTHORIZATION, format!("Bearer {}", session))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(json!({ "approve": approve }).to_string()))
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let redirect_to = json_body(response).await["redirect_to"].as_str().unwrap().to_string();
+        assert!(redirect_to.starts_with(REDIRECT_URI), "{}", redirect_to);
+        query_params(&redirect_to)
+    }
+
+    async fn token(&self, code: &str, code_verifier: &str, credentials: Credentials<'_>) -> Response {
+        let mut form = form_urlencoded::Serializer::new(String::new());
+        form.append_pair("grant_type", "authorization_code")
+            .append_pair("code", code)
+            .append_pair("redirect_uri", REDIRECT_URI)
+            .append_pair("code_verifier", code_verifier);
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/oauth/token")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+        match credentials {
+            Credentials::Basic(secret) => {
+                let basic = STANDARD.encode(format!("{}:{}", self.client_id, secret));
+                request = request.header(header::AUTHORIZATION, format!("Basic {}", basic));
+            },
+            Credentials::Post(secret) => {
+                form.append_pair("client_id", &self.client_id).append_pair("client_secret", secret);
+            },
+            Credentials::None => {
+                form.append_pair("client_id", &self.client_id);
+            },
+        }
+        self.provider.send(request.body(Body::from(form.finish())).unwrap()).await
+    }
+
+    /// Verifica el ID token con las claves publicadas en el `jwks_uri` de la discovery
+    async fn verify_id_token(&self, id_token: &str) -> Value {
+        let discovery = json_body(self.provider.get("/.well-known/openid-configuration", None).await).await;
+        let jwks_path = discovery["jwks_uri"].as_str().unwrap().strip_prefix(TEST_ISSUER).unwrap().to_string();
+        let jwks: JwkSet = serde_json::from_value(json_body(self.provider.get(&jwks_path, None).await).await).unwrap();
+
+        let kid = decode_header(id_token).unwrap().kid.unwrap();
+        let jwk = jwks.find(&kid).expect("la clave del ID token se publica en el JWKS");
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_issuer(&[TEST_ISSUER]);
+        decode::<Value>(id_token, &DecodingKey::from_jwk(jwk).unwrap(), &validation).unwrap().claims
+    }
+}
+
+enum Credentials<'a> {
+    Basic(&'a str),
+    Post(&'a str),
+    None,
+}
+
+fn location(response: &Response) -> String {
+    response.headers()[header::LOCATION].to_str().unwrap().to_string()
+}
+
+fn query_params(url: &str) -> HashMap<String, String> {
+    let query = url.split_once('?').map(|(_, query)| query).unwrap_or_default();
+    form_urlencoded::parse(query.as_bytes()).into_owned().collect()
+}
+
+async fn json_body(response: Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn discovery_document_describes_the_provider() {
+    let provider = Provider::new();
+    let response = provider.get("/.well-known/openid-configuration", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let discovery = json_body(response).await;
+    assert_eq!(discovery["issuer"], TEST_ISSUER);
+    assert_eq!(discovery["authorization_endpoint"], format!("{}/oauth/authorize", TEST_ISSUER));
+    assert_eq!(discovery["token_endpoint"], format!("{}/oauth/token", TEST_ISSUER));
+    assert_eq!(discovery["userinfo_endpoint"], format!("{}/oauth/userinfo", TEST_ISSUER));
+    assert_eq!(discovery["response_types_supported"], json!(["code"]));
+    assert_eq!(discovery["code_challenge_methods_supported"], json!(["S256"]));
+    assert_eq!(discovery["id_token_signing_alg_values_supported"], json!(["EdDSA"]));
+}
+
+#[tokio::test]
+async fn authorization_code_flow_issues_verifiable_tokens() {
+    let provider = Provider::new();
+    let (user, session) = provider.signed_in_user();
+    let client = provider.client(true, &["openid", "profile", "email"]).await;
+
+    let callback = client.authorize(&session, true).await;
+    assert_eq!(callback["state"], "xyz");
+    assert_eq!(callback["iss"], TEST_ISSUER);
+
+    let response = client.token(&callback["code"], &client.code_verifier, Credentials::None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+    let tokens = json_body(response).await;
+    assert_eq!(tokens["token_type"], "Bearer");
+    assert_eq!(tokens["scope"], "openid profile email");
+    assert_eq!(tokens["expires_in"], 15 * 60);
+
+    let claims = client.verify_id_token(tokens["id_token"].as_str().unwrap()).await;
+    assert_eq!(claims["sub"], user.id.to_string());
+    assert_eq!(claims["nonce"], "n-0S6_WzA2Mj");
+    assert_eq!(claims["email"], "ana@example.com");
+    assert_eq!(claims["email_verified"], true);
+    assert_eq!(claims["name"], "Ana");
+    assert!(claims["auth_time"].as_i64().unwrap() <= Utc::now().timestamp());
+
+    let access_token = tokens["access_token"].as_str().unwrap();
+    let response = provider.get("/oauth/userinfo", Some(access_token)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(response).await,
+        json!({ "sub": user.id.to_string(), "name": "Ana", "email": "ana@example.com", "email_verified": true })
+    );
+}
+
+#[tokio::test]
+async fn confidential_clients_must_authenticate() {
+    let provider = Provider::new();
+    let (_, session) = provider.signed_in_user();
+    let client = provider.client(false, &["openid", "profile", "email"]).await;
+    let secret = client.client_secret.clone().unwrap();
+
+    // La autenticación del cliente se comprueba antes de gastar el código
+    let code = client.authorize(&session, true).await["code"].clone();
+    for credentials in [Credentials::None, Credentials::Basic("wrong-secret"), Credentials::Post("wrong-secret")] {
+        let response = client.token(&code, &client.code_verifier, credentials).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(json_body(response).await["error"], "invalid_client");
+    }
+    let response = client.token(&code, &client.code_verifier, Credentials::Basic(&secret)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let code = client.authorize(&session, true).await["code"].clone();
+    let response = client.token(&code, &client.code_verifier, Credentials::Post(&secret)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn codes_are_single_use_and_bound_to_the_code_verifier() {
+    let provider = Provider::new();
+    let (_, session) = provider.signed_in_user();
+    let client = provider.client(true, &["openid", "profile", "email"]).await;
+
+    let code = client.authorize(&session, true).await["code"].clone();
+    let response = client.token(&code, &generate_secure_token(32), Credentials::None).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json_body(response).await["error"], "invalid_grant");
+    // Tras un intento fallido el código ya no vale
+    let response = client.token(&code, &client.code_verifier, Credentials::None).await;
+    assert_eq!(json_body(response).await["error"], "invalid_grant");
+
+    let code = client.authorize(&session, true).await["code"].clone();
+    let response = client.token(&code, &client.code_verifier, Credentials::None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.token(&code, &client.code_verifier, Credentials::None).await;
+    assert_eq!(json_body(response).await["error"], "invalid_grant");
+
+    // Un código emitido a otro cliente no sirve
+    let other = provider.client(true, &["openid"]).await;
+    let code = client.authorize(&session, true).await["code"].clone();
+    let response = other.token(&code, &client.code_verifier, Credentials::None).await;
+    assert_eq!(json_body(response).await["error"], "invalid_grant");
+}
+
+#[tokio::test]
+async fn invalid_authorization_requests_never_reach_unregistered_uris() {
+    let provider = Provider::new();
+    let client = provider.client(true, &["openid", "profile", "email"]).await;
+
+    let response = provider.get(&client.authorize_uri(&[("client_id", "unknown")]), None).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.headers().get(header::LOCATION).is_none());
+
+    let response = provider.get(&client.authorize_uri(&[("redirect_uri", "https://evil.example.com/cb")]), None).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json_body(response).await["error"], "invalid_request");
+
+    // Con la redirect_uri validada, los errores vuelven al cliente con su state
+    let cases = [
+        (vec![("code_challenge", "")], "invalid_request"),
+        (vec![("code_challenge_method", "plain")], "invalid_request"),
+        (vec![("response_type", "token")], "unsupported_response_type"),
+        (vec![("scope", "openid offline_access")], "invalid_scope"),
+    ];
+    for (overrides, error) in cases {
+        let response = provider.get(&client.authorize_uri(&overrides), None).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let redirect = location(&response);
+        assert!(redirect.starts_with(REDIRECT_URI), "{}", redirect);
+        let params = query_params(&redirect);
+        assert_eq!(params["error"], error, "{:?}", overrides);
+        assert_eq!(params["state"], "xyz");
+    }
+    assert!(provider.harness.oauth.authorizations.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn denied_consent_returns_access_denied() {
+    let provider = Provider::new();
+    let (_, session) = provider.signed_in_user();
+    let client = provider.client(true, &["openid", "profile", "email"]).await;
+
+    let callback = client.authorize(&session, false).await;
+    assert_eq!(callback["error"], "access_denied");
+    assert_eq!(callback["state"], "xyz");
+    assert!(!callback.contains_key("code"));
+    assert!(provider.harness.oauth.authorizations.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn oauth_access_tokens_are_only_valid_at_userinfo() {
+    let provider = Provider::new();
+    let (user, session) = provider.signed_in_user();
+    let client = provider.client(true, &["openid", "profile", "email"]).await;
+    let code = client.authorize(&session, true).await["code"].clone();
+    let tokens = json_body(client.token(&code, &client.code_verifier, Credentials::None).await).await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    // No da acceso a la API del servicio como si fuera una sesión
+    let response = provider.get("/api/users/me", Some(access_token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Ni el token de sesión sirve en userinfo
+    let response = provider.get("/oauth/userinfo", Some(&session)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response.headers()[header::WWW_AUTHENTICATE].to_str().unwrap().contains("invalid_token"));
+
+    // Cambiar la contraseña invalida también los tokens emitidos a clientes
+    for stored in provider.harness.users.users.lock().unwrap().iter_mut().filter(|stored| stored.id == user.id) {
+        stored.token_version += 1;
+    }
+    let response = provider.get("/oauth/userinfo", Some(access_token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn client_registration_rejects_unsafe_redirect_uris_and_unknown_scopes() {
+    let provider = Provider::new();
+    for redirect_uri in ["http://client.example.com/cb", "https://client.example.com/cb#frag", "javascript:alert(1)", "/cb"] {
+        let result = provider.register(redirect_uri, "openid").await;
+        assert!(matches!(result, Err(AuthError::InvalidOAuthClient(_))), "{}", redirect_uri);
+    }
+    assert!(matches!(provider.register(REDIRECT_URI, "users:write").await, Err(AuthError::InvalidOAuthClient(_))));
+
+    for redirect_uri in ["http://127.0.0.1:8765/cb", "http://localhost/cb", "com.example.app:/oauth2redirect"] {
+        let (client, secret) = provider.register(redirect_uri, "openid").await.unwrap();
+        assert!(secret.is_none());
+        assert_eq!(client.redirect_uris, vec![redirect_uri.to_string()]);
+    }
+}
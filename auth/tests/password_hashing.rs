@@ -0,0 +1,127 @@
+mod support;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use auth::password::{hash_password, needs_rehash, verify_password};
+use common::password::{Argon2Params, HashAlgorithm};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+use support::{test_settings, AuthSettings, TestHarness};
+
+const PASSWORD: &str = "Lantern5-meadow-copper";
+
+// Parámetros bajos para que los tests no tarden
+const CHEAP: Argon2Params = Argon2Params { memory_kib: 1024, iterations: 1, parallelism: 1 };
+
+fn argon2_hash(algorithm: Algorithm, params: Argon2Params) -> String {
+    let params = Params::new(params.memory_kib, params.iterations, params.parallelism, None).unwrap();
+    Argon2::new(algorithm, Version::V0x13, params)
+        .hash_password(PASSWORD.as_bytes(), &SaltString::generate(&mut OsRng))
+        .unwrap()
+        .to_string()
+}
+
+fn legacy_hashes() -> Vec<(HashAlgorithm, String)> {
+    let salt = SaltString::generate(&mut OsRng);
+    let bcrypt = bcrypt::hash(PASSWORD, 4).unwrap();
+    let scrypt_params = scrypt::Params::new(10, 8, 1, 32).unwrap();
+    let pbkdf2_params = pbkdf2::Params { rounds: 1000, output_length: 32 };
+
+    vec![
+        (HashAlgorithm::Argon2i, argon2_hash(Algorithm::Argon2i, CHEAP)),
+        (HashAlgorithm::Argon2d, argon2_hash(Algorithm::Argon2d, CHEAP)),
+        (HashAlgorithm::Bcrypt, bcrypt.replacen("$2b$", "$2y$", 1)),
+        (HashAlgorithm::Bcrypt, bcrypt),
+        (
+            HashAlgorithm::Scrypt,
+            Scrypt.hash_password_customized(PASSWORD.as_bytes(), None, None, scrypt_params, &salt).unwrap().to_string(),
+        ),
+        (
+            HashAlgorithm::Pbkdf2,
+            Pbkdf2
+                .hash_password_customized(PASSWORD.as_bytes(), Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()), None, pbkdf2_params, &salt)
+                .unwrap()
+                .to_string(),
+        ),
+        (
+            HashAlgorithm::Pbkdf2,
+            Pbkdf2
+                .hash_password_customized(PASSWORD.as_bytes(), Some(pbkdf2::Algorithm::Pbkdf2Sha512.ident()), None, pbkdf2_params, &salt)
+                .unwrap()
+                .to_string(),
+        ),
+    ]
+}
+
+#[test]
+fn verifies_hashes_from_every_supported_algorithm() {
+    let current = hash_password(PASSWORD, &CHEAP).unwrap();
+    assert_eq!(HashAlgorithm::identify(&current), Some(HashAlgorithm::Argon2id));
+    assert!(verify_password(PASSWORD, &current).unwrap());
+    assert!(!verify_password("wrong-password", &current).unwrap());
+
+    for (algorithm, hash) in legacy_hashes() {
+        assert_eq!(HashAlgorithm::identify(&hash), Some(algorithm), "{}", hash);
+        assert!(verify_password(PASSWORD, &hash).unwrap(), "{}", hash);
+        assert!(!verify_password("wrong-password", &hash).unwrap(), "{}", hash);
+    }
+}
+
+#[test]
+fn rejects_unknown_hash_formats() {
+    assert_eq!(HashAlgorithm::identify("5f4dcc3b5aa765d61d8327deb882cf99"), None);
+    assert!(verify_password(PASSWORD, "5f4dcc3b5aa765d61d8327deb882cf99").is_err());
+    assert!(verify_password(PASSWORD, "$md5$abc").is_err());
+    assert!(verify_password(PASSWORD, "").is_err());
+}
+
+#[test]
+fn rehashes_legacy_and_weaker_hashes_only() {
+    let stronger = Argon2Params { memory_kib: 2048, iterations: 2, parallelism: 1 };
+
+    assert!(!needs_rehash(&argon2_hash(Algorithm::Argon2id, CHEAP), &CHEAP));
+    assert!(needs_rehash(&argon2_hash(Algorithm::Argon2id, CHEAP), &stronger));
+    // Un coste mayor que el configurado se conserva
+    assert!(!needs_rehash(&argon2_hash(Algorithm::Argon2id, stronger), &CHEAP));
+
+    for (_, hash) in legacy_hashes() {
+        assert!(needs_rehash(&hash, &CHEAP), "{}", hash);
+    }
+}
+
+#[tokio::test]
+async fn login_upgrades_legacy_hashes_transparently() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service_with(AuthSettings { argon2_params: CHEAP, ..test_settings() });
+
+    for (_, hash) in legacy_hashes() {
+        let user = harness.users.insert(&format!("{}@example.com", uuid::Uuid::new_v4()), None);
+        harness.users.users.lock().unwrap().iter_mut().find(|u| u.id == user.id).unwrap().password = hash.clone();
+
+        let authenticated = service.authenticate_by_email(&user.email, PASSWORD).await.unwrap();
+        let stored = harness.users.get(&user.id);
+        assert_eq!(HashAlgorithm::identify(&stored.password), Some(HashAlgorithm::Argon2id), "{}", hash);
+        assert!(!needs_rehash(&stored.password, &CHEAP));
+        assert_eq!(authenticated.password, stored.password);
+        // Las sesiones abiertas siguen valiendo
+        assert_eq!(stored.token_version, user.token_version);
+
+        // El hash nuevo sirve para el siguiente login y ya no se regenera
+        service.authenticate_by_email(&user.email, PASSWORD).await.unwrap();
+        assert_eq!(harness.users.get(&user.id).password, stored.password);
+    }
+}
+
+#[tokio::test]
+async fn failed_login_keeps_the_legacy_hash() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service_with(AuthSettings { argon2_params: CHEAP, ..test_settings() });
+    let hash = bcrypt::hash(PASSWORD, 4).unwrap();
+    let user = harness.users.insert("ana@example.com", None);
+    harness.users.users.lock().unwrap().iter_mut().find(|u| u.id == user.id).unwrap().password = hash.clone();
+
+    assert!(service.authenticate_by_email(&user.email, "wrong-password").await.is_err());
+    assert_eq!(harness.users.get(&user.id).password, hash);
+}
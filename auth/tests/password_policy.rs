@@ -0,0 +1,111 @@
+mod support;
+
+use auth::{
+    error::AuthError,
+    password_policy::{strength_score, CharacterClass, PasswordPolicy, PasswordViolation},
+};
+use shared::user::CreateUserSchema;
+use support::{test_settings, AuthSettings, TestHarness};
+
+fn strict_settings() -> AuthSettings {
+    AuthSettings {
+        password_policy: PasswordPolicy {
+            required_classes: vec![CharacterClass::Uppercase, CharacterClass::Digit],
+            ..PasswordPolicy::default()
+        },
+        ..test_settings()
+    }
+}
+
+fn new_user(password: &str) -> CreateUserSchema {
+    CreateUserSchema {
+        email: "ana.garcia@example.com".into(),
+        password: password.into(),
+        name: Some("Ana García".into()),
+    }
+}
+
+#[test]
+fn lists_every_failed_rule() {
+    let policy = PasswordPolicy {
+        max_length: 12,
+        required_classes: vec![CharacterClass::Uppercase, CharacterClass::Digit, CharacterClass::Symbol],
+        ..PasswordPolicy::default()
+    };
+
+    let violations = policy.check("ana", &["ana@example.com"]).unwrap_err();
+    assert_eq!(
+        violations,
+        vec![
+            PasswordViolation::TooShort { min: 8 },
+            PasswordViolation::MissingClass(CharacterClass::Uppercase),
+            PasswordViolation::MissingClass(CharacterClass::Digit),
+            PasswordViolation::MissingClass(CharacterClass::Symbol),
+            PasswordViolation::ContainsUserInfo,
+            PasswordViolation::TooWeak { score: 0, min: 2 },
+        ]
+    );
+
+    let violations = policy.check("Ab1!Ab1!Ab1!Ab1!", &[]).unwrap_err();
+    assert_eq!(violations[0], PasswordViolation::TooLong { max: 12 });
+}
+
+#[test]
+fn rejects_the_users_email_and_name() {
+    let policy = PasswordPolicy { min_strength: 0, ..PasswordPolicy::default() };
+    let user_inputs = ["ana.garcia@example.com", "Ana García"];
+
+    for password in ["ana.garcia-2024", "xx-GARCIA-xx", "mi nombre es ana"] {
+        assert_eq!(policy.check(password, &user_inputs), Err(vec![PasswordViolation::ContainsUserInfo]), "{}", password);
+    }
+    assert!(policy.check("river-orbit-kettle", &user_inputs).is_ok());
+    // El dominio del email no cuenta como dato personal
+    assert!(policy.check("example-of-river", &user_inputs).is_ok());
+}
+
+#[test]
+fn strength_score_penalizes_guessable_patterns() {
+    for weak in ["password", "P@ssw0rd", "secret123", "qwertyuiop", "aaaaaaaaaa", "abcdefgh", "12345678"] {
+        assert!(strength_score(weak, &[]) <= 1, "{} should be weak", weak);
+    }
+    assert!(strength_score("Garcia1990", &["ana.garcia@example.com"]) < strength_score("Garcia1990", &[]));
+    for strong in ["vX9#qL2!mR7z", "correct horse battery staple pickle", "Tr0ub4dor&3xyz"] {
+        assert!(strength_score(strong, &[]) >= 3, "{} should be strong", strong);
+    }
+}
+
+#[tokio::test]
+async fn registration_applies_the_policy() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service_with(strict_settings());
+
+    let error = service.register_user(&new_user("password")).await.unwrap_err();
+    let Ok(AuthError::WeakPassword(violations)) = error.downcast::<AuthError>() else {
+        panic!("expected a password policy rejection");
+    };
+    assert!(violations.contains(&PasswordViolation::MissingClass(CharacterClass::Uppercase)));
+    assert!(violations.contains(&PasswordViolation::MissingClass(CharacterClass::Digit)));
+    assert!(violations.contains(&PasswordViolation::TooWeak { score: 0, min: 2 }));
+    assert!(harness.users.users.lock().unwrap().is_empty());
+
+    assert!(service.register_user(&new_user("Orbit7-kettle-river")).await.is_ok());
+}
+
+#[tokio::test]
+async fn change_and_reset_apply_the_policy() {
+    let harness = TestHarness::default();
+    let user_id = harness.auth_service().register_user(&new_user("Orbit7-kettle-river")).await.unwrap().id;
+    let service = harness.auth_service_with(strict_settings());
+
+    let result = service.change_password(&user_id, "Orbit7-kettle-river", "AnaGarcia2024").await;
+    assert!(matches!(result, Err(AuthError::WeakPassword(ref violations)) if violations == &[PasswordViolation::ContainsUserInfo]));
+
+    service.request_password_reset("ana.garcia@example.com").await.unwrap();
+    let token = harness.mailer.last_token();
+    let result = service.reset_password(&token, "short").await;
+    assert!(matches!(result, Err(AuthError::WeakPassword(_))));
+
+    // El token no se consume con una contraseña rechazada
+    service.reset_password(&token, "Lantern5-meadow-copper").await.unwrap();
+    assert!(service.authenticate_by_email("ana.garcia@example.com", "Lantern5-meadow-copper").await.is_ok());
+}
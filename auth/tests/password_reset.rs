@@ -0,0 +1,90 @@
+mod support;
+
+use auth::error::AuthError;
+use chrono::{Duration, Utc};
+use shared::user::CreateUserSchema;
+use support::TestHarness;
+
+async fn registered(harness: &TestHarness) -> uuid::Uuid {
+    let user = CreateUserSchema {
+        email: "ana@example.com".into(),
+        password: "old-secret".into(),
+        name: None,
+    };
+    let user = harness.auth_service().register_user(&user).await.unwrap();
+    // Descarta el correo de verificación del registro
+    harness.mailer.sent.lock().unwrap().clear();
+    user.id
+}
+
+#[tokio::test]
+async fn reset_link_sets_a_new_password() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness).await;
+
+    service.request_password_reset("ana@example.com").await.unwrap();
+    service.reset_password(&harness.mailer.last_token(), "new-secret").await.unwrap();
+
+    assert!(service.authenticate_by_email("ana@example.com", "new-secret").await.is_ok());
+    assert!(matches!(
+        service.authenticate_by_email("ana@example.com", "old-secret").await,
+        Err(AuthError::InvalidCredentials)
+    ));
+    // Las sesiones abiertas se cierran
+    assert_eq!(*harness.refresh_tokens.revoked_users.lock().unwrap(), vec![user_id]);
+}
+
+#[tokio::test]
+async fn unknown_email_gets_the_same_answer_and_no_mail() {
+    let harness = TestHarness::default();
+    registered(&harness).await;
+
+    let result = harness.auth_service().request_password_reset("nobody@example.com").await;
+
+    assert!(result.is_ok());
+    assert_eq!(harness.mailer.sent_count(), 0);
+}
+
+#[tokio::test]
+async fn reset_token_is_single_use() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    registered(&harness).await;
+    service.request_password_reset("ana@example.com").await.unwrap();
+    let token = harness.mailer.last_token();
+
+    service.reset_password(&token, "new-secret").await.unwrap();
+    let second = service.reset_password(&token, "another-secret").await;
+
+    assert!(matches!(second, Err(AuthError::InvalidResetToken)));
+}
+
+#[tokio::test]
+async fn expired_reset_token_is_rejected() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    registered(&harness).await;
+    service.request_password_reset("ana@example.com").await.unwrap();
+    for token in harness.password_resets.tokens.lock().unwrap().iter_mut() {
+        token.expires_at = Utc::now() - Duration::minutes(1);
+    }
+
+    let result = service.reset_password(&harness.mailer.last_token(), "new-secret").await;
+
+    assert!(matches!(result, Err(AuthError::InvalidResetToken)));
+}
+
+#[tokio::test]
+async fn a_new_request_invalidates_previous_links() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    registered(&harness).await;
+    service.request_password_reset("ana@example.com").await.unwrap();
+    let first_token = harness.mailer.last_token();
+
+    service.request_password_reset("ana@example.com").await.unwrap();
+
+    assert!(matches!(service.reset_password(&first_token, "new-secret").await, Err(AuthError::InvalidResetToken)));
+    service.reset_password(&harness.mailer.last_token(), "new-secret").await.unwrap();
+}
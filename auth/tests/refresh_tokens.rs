@@ -0,0 +1,79 @@
+mod support;
+
+use auth::error::AuthError;
+use common::error::AppError;
+use support::TestHarness;
+
+#[tokio::test]
+async fn refresh_rotates_the_token_within_its_family() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+    let (record, refresh_token) = service.issue_refresh_token(&user.id, None).await.unwrap();
+
+    let (_token, rotated) = service.refresh_session(&refresh_token).await.unwrap();
+
+    assert_ne!(rotated, refresh_token);
+    let family = harness.refresh_tokens.family(&record.family_id);
+    assert_eq!(family.len(), 2);
+    let consumed = family.iter().find(|token| token.id == record.id).unwrap();
+    let replacement = family.iter().find(|token| token.id != record.id).unwrap();
+    assert!(consumed.revoked_at.is_some());
+    assert_eq!(consumed.replaced_by, Some(replacement.id));
+    assert!(replacement.revoked_at.is_none());
+}
+
+#[tokio::test]
+async fn reusing_a_rotated_token_revokes_the_whole_family() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+    let (record, refresh_token) = service.issue_refresh_token(&user.id, None).await.unwrap();
+    let (_token, rotated) = service.refresh_session(&refresh_token).await.unwrap();
+
+    let reused = service.refresh_session(&refresh_token).await;
+
+    assert!(matches!(reused, Err(AuthError::RefreshTokenReused)));
+    assert!(harness.refresh_tokens.family(&record.family_id).iter().all(|token| token.revoked_at.is_some()));
+    // El token legítimo más reciente también queda invalidado
+    assert!(matches!(service.refresh_session(&rotated).await, Err(AuthError::RefreshTokenReused)));
+}
+
+#[tokio::test]
+async fn unknown_refresh_tokens_are_rejected() {
+    let harness = TestHarness::default();
+
+    let result = harness.auth_service().refresh_session("not-a-token").await;
+
+    assert!(matches!(result, Err(AuthError::InvalidRefreshToken)));
+}
+
+#[tokio::test]
+async fn logout_revokes_the_family_of_the_users_token() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user = harness.users.insert("ana@example.com", None);
+    let (record, refresh_token) = service.issue_refresh_token(&user.id, None).await.unwrap();
+    let (_token, rotated) = service.refresh_session(&refresh_token).await.unwrap();
+
+    service.revoke_refresh_token(&user.id, &rotated).await.unwrap();
+
+    assert!(harness.refresh_tokens.family(&record.family_id).iter().all(|token| token.revoked_at.is_some()));
+    assert!(service.refresh_session(&rotated).await.is_err());
+}
+
+#[tokio::test]
+async fn logout_cannot_revoke_another_users_session() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let victim = harness.users.insert("ana@example.com", None);
+    let attacker = harness.users.insert("eve@example.com", None);
+    let (record, refresh_token) = service.issue_refresh_token(&victim.id, None).await.unwrap();
+
+    let result = service.revoke_refresh_token(&attacker.id, &refresh_token).await;
+
+    assert!(matches!(result, Err(AuthError::RefreshTokenNotOwned)));
+    assert!(matches!(AppError::from(result.unwrap_err()), AppError::Forbidden(_)));
+    assert!(harness.refresh_tokens.family(&record.family_id).iter().all(|token| token.revoked_at.is_none()));
+    assert!(service.refresh_session(&refresh_token).await.is_ok());
+}
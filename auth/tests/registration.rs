@@ -0,0 +1,51 @@
+mod support;
+
+use std::sync::Arc;
+
+use api::{routes::create_router, AppState};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use common::{jwt::JwtConfig, keys::JwtKeys};
+use repository::InMemoryRevocationStore;
+use serde_json::{json, Value};
+use support::{test_settings, EmptyPermissionRepository, TestHarness};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn self_registration_ignores_the_requested_role() {
+    let harness = TestHarness::default();
+    let jwt_config = Arc::new(JwtConfig::new(JwtKeys::hmac("test-secret")));
+    let state = AppState::new(
+        Arc::new(harness.auth_service_with_jwt(test_settings(), jwt_config.clone())),
+        jwt_config,
+        Arc::new(InMemoryRevocationStore::new()),
+        Arc::new(EmptyPermissionRepository),
+    );
+
+    let response = create_router(Arc::new(state))
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/auth/register")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    json!({ "email": "eve@example.com", "password": "secret123", "role": "admin" }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(response).await["user"]["role"], "user");
+    let stored = harness.users.users.lock().unwrap()[0].clone();
+    assert_eq!(stored.email, "eve@example.com");
+    assert_eq!(stored.role, "user");
+}
+
+async fn json_body(response: axum::response::Response) -> Value {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
@@ -0,0 +1,144 @@
+//! Autenticador WebAuthn por software para probar las ceremonias de passkeys sin hardware.
+//! Genera una clave ES256 y produce las respuestas que devolvería el navegador con `toJSON()`.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use minicbor::Encoder;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey, VerifyingKey};
+use rand::RngCore;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use shared::user::{
+    AssertionCredentialSchema, AssertionResponseSchema, AttestationResponseSchema, PasskeyLoginSchema, PasskeyOptions,
+    PasskeyRegistrationSchema, RegistrationCredentialSchema,
+};
+
+use super::{TEST_ORIGIN, TEST_RP_ID};
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+pub struct SoftwareAuthenticator {
+    pub credential_id: Vec<u8>,
+    pub signing_key: SigningKey,
+    /// `user.id` recibido al registrar la passkey
+    pub user_handle: Option<Vec<u8>>,
+    pub sign_count: u32,
+    /// Permiten simular respuestas de otro sitio o sin verificación de usuario
+    pub rp_id: String,
+    pub origin: String,
+    pub user_verified: bool,
+}
+
+impl Default for SoftwareAuthenticator {
+    fn default() -> Self {
+        let mut credential_id = vec![0u8; 16];
+        rand::thread_rng().fill_bytes(&mut credential_id);
+        Self {
+            credential_id,
+            signing_key: SigningKey::random(&mut rand::thread_rng()),
+            user_handle: None,
+            sign_count: 0,
+            rp_id: TEST_RP_ID.into(),
+            origin: TEST_ORIGIN.into(),
+            user_verified: true,
+        }
+    }
+}
+
+impl SoftwareAuthenticator {
+    pub fn credential_id_b64(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    /// Responde a las opciones de `navigator.credentials.create()` con atestación `none`
+    pub fn register(&mut self, options: &PasskeyOptions) -> PasskeyRegistrationSchema {
+        let user_id = options.public_key["user"]["id"].as_str().expect("user.id en las opciones");
+        self.user_handle = Some(URL_SAFE_NO_PAD.decode(user_id).unwrap());
+
+        let client_data_json = self.client_data("webauthn.create", options);
+        let mut auth_data = self.authenticator_data(FLAG_ATTESTED_CREDENTIAL_DATA);
+        // AAGUID a cero, longitud del credential ID, credential ID y clave COSE
+        auth_data.extend_from_slice(&[0u8; 16]);
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        auth_data.extend_from_slice(&self.cose_public_key());
+
+        let mut attestation_object = Encoder::new(Vec::new());
+        attestation_object
+            .map(3).unwrap()
+            .str("fmt").unwrap().str("none").unwrap()
+            .str("attStmt").unwrap().map(0).unwrap()
+            .str("authData").unwrap().bytes(&auth_data).unwrap();
+
+        PasskeyRegistrationSchema {
+            challenge_id: options.challenge_id,
+            name: Some("Software authenticator".into()),
+            credential: RegistrationCredentialSchema {
+                id: self.credential_id_b64(),
+                response: AttestationResponseSchema {
+                    client_data_json: URL_SAFE_NO_PAD.encode(client_data_json),
+                    attestation_object: URL_SAFE_NO_PAD.encode(attestation_object.into_writer()),
+                },
+            },
+        }
+    }
+
+    /// Responde a las opciones de `navigator.credentials.get()` firmando con la passkey
+    pub fn login(&mut self, options: &PasskeyOptions) -> PasskeyLoginSchema {
+        self.sign_count += 1;
+        let client_data_json = self.client_data("webauthn.get", options);
+        let auth_data = self.authenticator_data(0);
+
+        let mut signed_data = auth_data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature: Signature = self.signing_key.sign(&signed_data);
+
+        PasskeyLoginSchema {
+            challenge_id: options.challenge_id,
+            credential: AssertionCredentialSchema {
+                id: self.credential_id_b64(),
+                response: AssertionResponseSchema {
+                    client_data_json: URL_SAFE_NO_PAD.encode(client_data_json),
+                    authenticator_data: URL_SAFE_NO_PAD.encode(auth_data),
+                    signature: URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+                    user_handle: self.user_handle.as_ref().map(|handle| URL_SAFE_NO_PAD.encode(handle)),
+                },
+            },
+        }
+    }
+
+    fn client_data(&self, ceremony_type: &str, options: &PasskeyOptions) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "type": ceremony_type,
+            "challenge": options.public_key["challenge"],
+            "origin": self.origin,
+            "crossOrigin": false,
+        }))
+        .unwrap()
+    }
+
+    fn authenticator_data(&self, extra_flags: u8) -> Vec<u8> {
+        let mut flags = FLAG_USER_PRESENT | extra_flags;
+        if self.user_verified {
+            flags |= FLAG_USER_VERIFIED;
+        }
+        let mut auth_data = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+        auth_data.push(flags);
+        auth_data.extend_from_slice(&self.sign_count.to_be_bytes());
+        auth_data
+    }
+
+    // Clave EC2 P-256 en COSE: {1: 2, 3: -7, -1: 1, -2: x, -3: y}
+    fn cose_public_key(&self) -> Vec<u8> {
+        let point = VerifyingKey::from(&self.signing_key).to_encoded_point(false);
+        let mut key = Encoder::new(Vec::new());
+        key.map(5).unwrap()
+            .i64(1).unwrap().i64(2).unwrap()
+            .i64(3).unwrap().i64(-7).unwrap()
+            .i64(-1).unwrap().i64(1).unwrap()
+            .i64(-2).unwrap().bytes(point.x().unwrap()).unwrap()
+            .i64(-3).unwrap().bytes(point.y().unwrap()).unwrap();
+        key.into_writer()
+    }
+}
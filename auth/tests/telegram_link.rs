@@ -0,0 +1,111 @@
+mod support;
+
+use auth::error::AuthError;
+use repository::{UserRepository, IDENTITY_PROVIDER_TELEGRAM};
+use serde_json::json;
+use shared::user::LinkTelegramSchema;
+use support::{auth_service, signed_login_data, InMemoryUserRepository, TEST_BOT_TOKEN};
+
+fn link_with(telegram_id: i64, bot_token: &str) -> LinkTelegramSchema {
+    LinkTelegramSchema {
+        telegram: Some(signed_login_data(telegram_id, bot_token)),
+        init_data: None,
+    }
+}
+
+#[tokio::test]
+async fn links_a_verified_telegram_account() {
+    let users = InMemoryUserRepository::default();
+    let user = users.insert("ana@example.com", None);
+
+    let linked = auth_service(users.clone()).link_telegram(&user.id, &link_with(42, TEST_BOT_TOKEN)).await.unwrap();
+
+    assert_eq!(linked, "42");
+    assert_eq!(users.get(&user.id).telegram_user_id.as_deref(), Some("42"));
+    let identities = users.identities.lock().unwrap().clone();
+    assert_eq!(identities.len(), 1);
+    assert_eq!((identities[0].provider.as_str(), identities[0].subject.as_str()), (IDENTITY_PROVIDER_TELEGRAM, "42"));
+    assert_eq!(users.find_by_telegram_user_id("42").await.unwrap().id, user.id);
+}
+
+#[tokio::test]
+async fn rejects_unsigned_telegram_data() {
+    let users = InMemoryUserRepository::default();
+    let user = users.insert("ana@example.com", None);
+
+    let result = auth_service(users.clone()).link_telegram(&user.id, &link_with(42, "654321:OTHER-bot-token")).await;
+
+    assert!(matches!(result, Err(AuthError::InvalidTelegramData(_))));
+    assert!(users.get(&user.id).telegram_user_id.is_none());
+}
+
+#[tokio::test]
+async fn telegram_account_linked_to_another_user_is_a_conflict() {
+    let users = InMemoryUserRepository::default();
+    users.insert("owner@example.com", Some("42"));
+    let user = users.insert("ana@example.com", None);
+
+    let result = auth_service(users.clone()).link_telegram(&user.id, &link_with(42, TEST_BOT_TOKEN)).await;
+
+    assert!(matches!(result, Err(AuthError::TelegramAlreadyLinked)));
+    assert!(users.get(&user.id).telegram_user_id.is_none());
+}
+
+#[tokio::test]
+async fn linking_the_same_account_twice_is_idempotent() {
+    let users = InMemoryUserRepository::default();
+    let user = users.insert("ana@example.com", Some("42"));
+
+    let linked = auth_service(users).link_telegram(&user.id, &link_with(42, TEST_BOT_TOKEN)).await.unwrap();
+
+    assert_eq!(linked, "42");
+}
+
+#[tokio::test]
+async fn user_with_another_linked_account_must_unlink_first() {
+    let users = InMemoryUserRepository::default();
+    let user = users.insert("ana@example.com", Some("7"));
+
+    let result = auth_service(users).link_telegram(&user.id, &link_with(42, TEST_BOT_TOKEN)).await;
+
+    assert!(matches!(result, Err(AuthError::TelegramLinkExists)));
+}
+
+#[tokio::test]
+async fn unlinks_the_telegram_account() {
+    let users = InMemoryUserRepository::default();
+    let user = users.insert("ana@example.com", Some("42"));
+
+    users
+        .link_identity(&user.id, "https://idp.example.com", "corp-0001", json!({}))
+        .await
+        .unwrap();
+
+    auth_service(users.clone()).unlink_telegram(&user.id).await.unwrap();
+
+    assert!(users.get(&user.id).telegram_user_id.is_none());
+    assert!(users.find_by_telegram_user_id("42").await.is_err());
+    // Las cuentas de otros proveedores siguen vinculadas
+    assert_eq!(users.find_by_identity("https://idp.example.com", "corp-0001").await.unwrap().id, user.id);
+}
+
+#[tokio::test]
+async fn unlinking_without_a_linked_account_fails() {
+    let users = InMemoryUserRepository::default();
+    let user = users.insert("ana@example.com", None);
+
+    let result = auth_service(users).unlink_telegram(&user.id).await;
+
+    assert!(matches!(result, Err(AuthError::TelegramNotLinked)));
+}
+
+#[tokio::test]
+async fn telegram_only_users_cannot_unlink() {
+    let users = InMemoryUserRepository::default();
+    let user = users.insert("telegram-42@users.telegram.invalid", Some("42"));
+
+    let result = auth_service(users.clone()).unlink_telegram(&user.id).await;
+
+    assert!(matches!(result, Err(AuthError::TelegramOnlyLoginMethod)));
+    assert_eq!(users.get(&user.id).telegram_user_id.as_deref(), Some("42"));
+}
@@ -0,0 +1,116 @@
+mod support;
+
+use auth::error::AuthError;
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use support::{sign_init_data, signed_init_data, test_settings, AuthSettings, TestHarness, TEST_BOT_TOKEN};
+
+const USER_JSON: &str = r#"{"id":987654321,"first_name":"Ana","last_name":"Pérez","username":"ana","language_code":"es"}"#;
+
+fn init_data(auth_date: i64) -> String {
+    signed_init_data(&[("user", USER_JSON), ("auth_date", &auth_date.to_string())], TEST_BOT_TOKEN)
+}
+
+#[tokio::test]
+async fn unknown_telegram_user_is_provisioned() {
+    let harness = TestHarness::default();
+
+    let user = harness.auth_service().authenticate_by_telegram_web_app(&init_data(Utc::now().timestamp())).await.unwrap();
+
+    assert_eq!(user.email, "telegram-987654321@users.telegram.invalid");
+    assert_eq!(user.name.as_deref(), Some("Ana Pérez"));
+    assert_eq!(user.role, "user");
+    assert_eq!(user.telegram_user_id.as_deref(), Some("987654321"));
+    assert_eq!(harness.users.users.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn returning_telegram_user_gets_the_same_account() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+
+    let first = service.authenticate_by_telegram_web_app(&init_data(Utc::now().timestamp())).await.unwrap();
+    let second = service.authenticate_by_telegram_web_app(&init_data(Utc::now().timestamp())).await.unwrap();
+
+    assert_eq!(first.id, second.id);
+    assert_eq!(harness.users.users.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn linked_telegram_account_logs_in_as_its_user() {
+    let harness = TestHarness::default();
+    let existing = harness.users.insert("ana@example.com", Some("987654321"));
+
+    let user = harness.auth_service().authenticate_by_telegram_web_app(&init_data(Utc::now().timestamp())).await.unwrap();
+
+    assert_eq!(user.id, existing.id);
+    assert_eq!(user.email, "ana@example.com");
+    assert_eq!(harness.users.users.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn login_widget_key_is_not_accepted_for_init_data() {
+    let harness = TestHarness::default();
+    // Firmado como el Login Widget (SHA-256 del token) en lugar de con la clave `WebAppData`
+    let init_data = sign_init_data(
+        &[("user", USER_JSON), ("auth_date", &Utc::now().timestamp().to_string())],
+        &Sha256::digest(TEST_BOT_TOKEN.as_bytes()),
+    );
+
+    let result = harness.auth_service().authenticate_by_telegram_web_app(&init_data).await;
+
+    assert!(matches!(result, Err(AuthError::InvalidTelegramData(_))));
+    assert!(harness.users.users.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn init_data_signed_with_another_bot_token_is_rejected() {
+    let harness = TestHarness::default();
+    let init_data = signed_init_data(&[("user", USER_JSON), ("auth_date", &Utc::now().timestamp().to_string())], "654321:OTHER-bot-token");
+
+    let result = harness.auth_service().authenticate_by_telegram_web_app(&init_data).await;
+
+    assert!(matches!(result, Err(AuthError::InvalidTelegramData(_))));
+}
+
+#[tokio::test]
+async fn tampered_user_is_rejected() {
+    let harness = TestHarness::default();
+    let init_data = init_data(Utc::now().timestamp()).replace("987654321", "111111111");
+
+    let result = harness.auth_service().authenticate_by_telegram_web_app(&init_data).await;
+
+    assert!(matches!(result, Err(AuthError::InvalidTelegramData(_))));
+    assert!(harness.users.users.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn stale_init_data_is_rejected_without_provisioning() {
+    let harness = TestHarness::default();
+    let two_days_ago = (Utc::now() - Duration::days(2)).timestamp();
+
+    let result = harness.auth_service().authenticate_by_telegram_web_app(&init_data(two_days_ago)).await;
+
+    assert!(matches!(result, Err(AuthError::InvalidTelegramData(message)) if message.contains("too old")));
+    assert!(harness.users.users.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn init_data_without_user_is_rejected() {
+    let harness = TestHarness::default();
+    let init_data = signed_init_data(&[("auth_date", &Utc::now().timestamp().to_string())], TEST_BOT_TOKEN);
+
+    let result = harness.auth_service().authenticate_by_telegram_web_app(&init_data).await;
+
+    assert!(matches!(result, Err(AuthError::InvalidTelegramData(_))));
+}
+
+#[tokio::test]
+async fn mini_app_login_requires_a_bot_token() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service_with(AuthSettings { telegram_bot_token: None, ..test_settings() });
+
+    let result = service.authenticate_by_telegram_web_app(&init_data(Utc::now().timestamp())).await;
+
+    assert!(matches!(result, Err(AuthError::TelegramNotConfigured)));
+}
@@ -0,0 +1,42 @@
+use auth::totp::{generate_code, generate_secret, otpauth_uri, verify_code, TOTP_STEP_SECS};
+
+// Secreto ASCII "12345678901234567890" de los vectores de prueba del RFC 6238 (SHA-1)
+const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+#[test]
+fn codes_match_rfc_6238_test_vectors() {
+    let vectors = [
+        (59, "287082"),
+        (1_111_111_109, "081804"),
+        (1_111_111_111, "050471"),
+        (1_234_567_890, "005924"),
+        (2_000_000_000, "279037"),
+        (20_000_000_000, "353130"),
+    ];
+
+    for (timestamp, expected) in vectors {
+        assert_eq!(generate_code(RFC_SECRET, timestamp).as_deref(), Some(expected), "t = {}", timestamp);
+    }
+}
+
+#[test]
+fn verification_tolerates_one_step_of_drift() {
+    let now = 1_234_567_890;
+    let previous = generate_code(RFC_SECRET, now - TOTP_STEP_SECS).unwrap();
+    let too_old = generate_code(RFC_SECRET, now - 2 * TOTP_STEP_SECS).unwrap();
+
+    assert_eq!(verify_code(RFC_SECRET, &previous, now), Some(now / TOTP_STEP_SECS - 1));
+    assert_eq!(verify_code(RFC_SECRET, &too_old, now), None);
+    assert_eq!(verify_code(RFC_SECRET, "12345", now), None);
+}
+
+#[test]
+fn generated_secret_round_trips_through_the_otpauth_uri() {
+    let secret = generate_secret();
+    let uri = otpauth_uri(&secret, "Rust Base Service", "ana@example.com");
+
+    assert_eq!(secret.len(), 32);
+    assert!(uri.starts_with("otpauth://totp/Rust%20Base%20Service:ana%40example.com?"));
+    assert!(uri.contains(&format!("secret={}", secret)));
+    assert!(generate_code(&secret, 0).is_some());
+}
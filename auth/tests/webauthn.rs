@@ -0,0 +1,161 @@
+mod support;
+
+use auth::error::AuthError;
+use shared::user::CreateUserSchema;
+use support::{authenticator::SoftwareAuthenticator, TestHarness};
+use uuid::Uuid;
+
+async fn registered(harness: &TestHarness, email: &str) -> Uuid {
+    let user = CreateUserSchema {
+        email: email.into(),
+        password: "secret123".into(),
+        name: None,
+    };
+    harness.auth_service().register_user(&user).await.unwrap().id
+}
+
+// Registra una passkey nueva para el usuario y devuelve el autenticador que la guarda
+async fn with_passkey(harness: &TestHarness, user_id: &Uuid) -> SoftwareAuthenticator {
+    let service = harness.auth_service();
+    let mut authenticator = SoftwareAuthenticator::default();
+    let options = service.start_passkey_registration(user_id).await.unwrap();
+    let registration = authenticator.register(&options);
+    service.finish_passkey_registration(user_id, &registration).await.unwrap();
+    authenticator
+}
+
+#[tokio::test]
+async fn registered_passkey_logs_the_user_in() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness, "ana@example.com").await;
+
+    let mut authenticator = SoftwareAuthenticator::default();
+    let options = service.start_passkey_registration(&user_id).await.unwrap();
+    assert_eq!(options.public_key["rp"]["id"], "localhost");
+    let credential_id = service
+        .finish_passkey_registration(&user_id, &authenticator.register(&options))
+        .await
+        .unwrap();
+    assert_eq!(credential_id, authenticator.credential_id_b64());
+
+    let options = service.start_passkey_login().await.unwrap();
+    let user = service.finish_passkey_login(&authenticator.login(&options)).await.unwrap();
+    assert_eq!(user.id, user_id);
+
+    let credentials = harness.webauthn.credentials.lock().unwrap();
+    assert_eq!(credentials[0].sign_count, 1);
+    assert!(credentials[0].last_used_at.is_some());
+}
+
+#[tokio::test]
+async fn challenges_work_only_once() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness, "ana@example.com").await;
+    let mut authenticator = with_passkey(&harness, &user_id).await;
+
+    let options = service.start_passkey_login().await.unwrap();
+    assert!(service.finish_passkey_login(&authenticator.login(&options)).await.is_ok());
+
+    let replayed = service.finish_passkey_login(&authenticator.login(&options)).await;
+    assert!(matches!(replayed, Err(AuthError::InvalidPasskey(_))));
+}
+
+#[tokio::test]
+async fn registration_challenge_belongs_to_its_user() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let ana = registered(&harness, "ana@example.com").await;
+    let eve = registered(&harness, "eve@example.com").await;
+
+    let options = service.start_passkey_registration(&ana).await.unwrap();
+    let registration = SoftwareAuthenticator::default().register(&options);
+
+    let result = service.finish_passkey_registration(&eve, &registration).await;
+    assert!(matches!(result, Err(AuthError::InvalidPasskey(_))));
+    assert!(harness.webauthn.credentials.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn responses_for_another_origin_or_rp_are_rejected() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness, "ana@example.com").await;
+
+    let mut phishing = SoftwareAuthenticator { origin: "https://evil.example".into(), ..Default::default() };
+    let options = service.start_passkey_registration(&user_id).await.unwrap();
+    let result = service.finish_passkey_registration(&user_id, &phishing.register(&options)).await;
+    assert!(matches!(result, Err(AuthError::InvalidPasskey(_))));
+
+    let mut other_rp = SoftwareAuthenticator { rp_id: "evil.example".into(), ..Default::default() };
+    let options = service.start_passkey_registration(&user_id).await.unwrap();
+    let result = service.finish_passkey_registration(&user_id, &other_rp.register(&options)).await;
+    assert!(matches!(result, Err(AuthError::InvalidPasskey(_))));
+}
+
+#[tokio::test]
+async fn user_verification_is_required() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness, "ana@example.com").await;
+    let mut authenticator = with_passkey(&harness, &user_id).await;
+
+    authenticator.user_verified = false;
+    let options = service.start_passkey_login().await.unwrap();
+    let result = service.finish_passkey_login(&authenticator.login(&options)).await;
+    assert!(matches!(result, Err(AuthError::InvalidPasskey(_))));
+}
+
+#[tokio::test]
+async fn signature_from_another_key_is_rejected() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness, "ana@example.com").await;
+    let authenticator = with_passkey(&harness, &user_id).await;
+
+    // Mismo credential ID y user handle, pero otra clave privada
+    let mut forged = SoftwareAuthenticator {
+        credential_id: authenticator.credential_id.clone(),
+        user_handle: authenticator.user_handle.clone(),
+        ..Default::default()
+    };
+    let options = service.start_passkey_login().await.unwrap();
+    let result = service.finish_passkey_login(&forged.login(&options)).await;
+    assert!(matches!(result, Err(AuthError::InvalidPasskey(_))));
+}
+
+#[tokio::test]
+async fn sign_counter_that_does_not_advance_is_rejected() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness, "ana@example.com").await;
+    let mut authenticator = with_passkey(&harness, &user_id).await;
+
+    let options = service.start_passkey_login().await.unwrap();
+    service.finish_passkey_login(&authenticator.login(&options)).await.unwrap();
+
+    // Un clon del autenticador repite el mismo contador
+    authenticator.sign_count -= 1;
+    let options = service.start_passkey_login().await.unwrap();
+    let result = service.finish_passkey_login(&authenticator.login(&options)).await;
+    assert!(matches!(result, Err(AuthError::InvalidPasskey(_))));
+}
+
+#[tokio::test]
+async fn concurrent_assertions_with_the_same_counter_succeed_only_once() {
+    let harness = TestHarness::default();
+    let service = harness.auth_service();
+    let user_id = registered(&harness, "ana@example.com").await;
+    let mut authenticator = with_passkey(&harness, &user_id).await;
+
+    // Dos aserciones firmadas con el mismo contador antes de que se guarde ninguna
+    let (first, second) = (service.start_passkey_login().await.unwrap(), service.start_passkey_login().await.unwrap());
+    let first = authenticator.login(&first);
+    authenticator.sign_count -= 1;
+    let second = authenticator.login(&second);
+
+    let (first, second) = tokio::join!(service.finish_passkey_login(&first), service.finish_passkey_login(&second));
+    assert_eq!([first.is_ok(), second.is_ok()].iter().filter(|ok| **ok).count(), 1);
+    assert_eq!(harness.webauthn.credentials.lock().unwrap()[0].sign_count, 1);
+}
@@ -4,8 +4,58 @@ use serde::Deserialize;
 pub struct AppConfig {
     pub database_url: String,
     pub jwt_secret: String,
+    pub jwt_algorithm: String,
+    pub jwt_key_id: Option<String>,
+    pub jwt_private_key_path: Option<String>,
+    pub jwt_public_key_path: Option<String>,
+    pub jwt_previous_public_keys: Option<String>,
+    pub jwt_issuer: Option<String>,
+    pub jwt_audience: Option<String>,
+    pub jwt_leeway_secs: u64,
+    pub jwt_validate_nbf: bool,
     pub jwt_expires_in: String,
     pub jwt_maxage: i32,
+    pub refresh_token_expires_in: String,
+    pub telegram_bot_token: Option<String>,
+    pub telegram_auth_max_age: String,
+    pub frontend_url: String,
+    pub email_verification_required: bool,
+    pub email_verification_expires_in: String,
+    pub email_verification_resend_interval: String,
+    pub password_reset_expires_in: String,
+    pub totp_issuer: String,
+    pub mfa_token_expires_in: String,
+    pub mfa_max_attempts: u32,
+    pub webauthn_rp_id: String,
+    pub webauthn_rp_name: String,
+    pub webauthn_origin: Option<String>,
+    pub webauthn_challenge_expires_in: String,
+    pub login_max_failed_attempts: u32,
+    pub login_backoff_base: String,
+    pub login_lockout_duration: String,
+    pub rate_limits: String,
+    pub rate_limit_backend: String,
+    pub rate_limit_trust_proxy: bool,
+    pub password_min_length: usize,
+    pub password_max_length: usize,
+    pub password_required_classes: String,
+    pub password_forbid_user_info: bool,
+    pub password_min_strength: u8,
+    pub password_breach_list_path: Option<String>,
+    pub password_argon2_memory_kib: u32,
+    pub password_argon2_iterations: u32,
+    pub password_argon2_parallelism: u32,
+    pub oauth_issuer: String,
+    pub oauth_consent_expires_in: String,
+    pub oauth_code_expires_in: String,
+    pub oauth_device_code_expires_in: String,
+    pub oauth_device_poll_interval: String,
+    pub oidc_discovery_url: Option<String>,
+    pub oidc_client_id: Option<String>,
+    pub oidc_client_secret: Option<String>,
+    pub oidc_redirect_uri: Option<String>,
+    pub oidc_scopes: String,
+    pub oidc_login_expires_in: String,
     pub port: u16,
 }
 
@@ -17,8 +67,54 @@ impl AppConfig {
         // Crear una configuración con valores predeterminados y fuentes
         let config = config::Config::builder()
             .set_default("port", 8000)?
-            .set_default("jwt_expires_in", "60m")?
+            .set_default("jwt_secret", "")?
+            .set_default("jwt_algorithm", "HS256")?
+            .set_default("jwt_leeway_secs", 60)?
+            .set_default("jwt_validate_nbf", true)?
+            .set_default("jwt_expires_in", "15m")?
             .set_default("jwt_maxage", 60)?
+            .set_default("refresh_token_expires_in", "30d")?
+            .set_default("telegram_auth_max_age", "1d")?
+            .set_default("frontend_url", "http://localhost:3000")?
+            .set_default("email_verification_required", false)?
+            .set_default("email_verification_expires_in", "24h")?
+            .set_default("email_verification_resend_interval", "60s")?
+            .set_default("password_reset_expires_in", "1h")?
+            .set_default("totp_issuer", "Rust Base Service")?
+            .set_default("mfa_token_expires_in", "5m")?
+            .set_default("mfa_max_attempts", 5)?
+            .set_default("webauthn_rp_id", "localhost")?
+            .set_default("webauthn_rp_name", "Rust Base Service")?
+            .set_default("webauthn_challenge_expires_in", "5m")?
+            .set_default("login_max_failed_attempts", 5)?
+            .set_default("login_backoff_base", "1s")?
+            .set_default("login_lockout_duration", "15m")?
+            .set_default(
+                "rate_limits",
+                "/api/auth/login=ip:sliding_window:20/1m,\
+                 /api/auth/login=email:sliding_window:10/15m,\
+                 /api/auth/register=ip:token_bucket:5/1h,\
+                 /api/auth/forgot-password=email:token_bucket:3/1h,\
+                 /api/auth/resend-verification=email:token_bucket:3/1h,\
+                 /api/auth/mfa/verify=ip:sliding_window:10/5m",
+            )?
+            .set_default("rate_limit_backend", "memory")?
+            .set_default("rate_limit_trust_proxy", false)?
+            .set_default("password_min_length", 8)?
+            .set_default("password_max_length", 128)?
+            .set_default("password_required_classes", "")?
+            .set_default("password_forbid_user_info", true)?
+            .set_default("password_min_strength", 2)?
+            .set_default("password_argon2_memory_kib", 19456)?
+            .set_default("password_argon2_iterations", 2)?
+            .set_default("password_argon2_parallelism", 1)?
+            .set_default("oauth_issuer", "http://localhost:8000")?
+            .set_default("oauth_consent_expires_in", "10m")?
+            .set_default("oauth_code_expires_in", "1m")?
+            .set_default("oauth_device_code_expires_in", "10m")?
+            .set_default("oauth_device_poll_interval", "5s")?
+            .set_default("oidc_scopes", "openid email profile")?
+            .set_default("oidc_login_expires_in", "10m")?
             .add_source(config::Environment::default())
             .build()?;
         
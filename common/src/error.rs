@@ -23,8 +23,21 @@ pub enum AppError {
   #[error("Validation error: {0}")]
   Validation(String),
   
+  #[error("Forbidden: {0}")]
+  Forbidden(String),
+
   #[error("Not found: {0}")]
   NotFound(String),
+
+  #[error("Conflict: {0}")]
+  Conflict(String),
+
+  #[error("Account locked, retry after {retry_after} seconds")]
+  AccountLocked { retry_after: i64 },
+
+  /// Reglas de la política de contraseñas que no se cumplen
+  #[error("Password does not meet the policy: {}", .0.join("; "))]
+  PasswordPolicy(Vec<String>),
   
   #[error("Internal server error: {0}")]
   Internal(String),
@@ -32,6 +45,15 @@ pub enum AppError {
 
 impl IntoResponse for AppError {
   fn into_response(self) -> Response {
+      // Se devuelven todas las reglas incumplidas para que el cliente pueda mostrarlas
+      if let AppError::PasswordPolicy(violations) = self {
+          let body = Json(json!({
+              "error": "Password does not meet the policy",
+              "violations": violations,
+          }));
+          return (StatusCode::BAD_REQUEST, body).into_response();
+      }
+
       let (status, error_message) = match self {
           AppError::Auth(msg) => (StatusCode::UNAUTHORIZED, msg),
           AppError::TokenGenerationError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
@@ -39,7 +61,11 @@ impl IntoResponse for AppError {
           AppError::TokenExpired => (StatusCode::UNAUTHORIZED, "Token expired".to_string()),
           AppError::Database(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
           AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+          AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
           AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+          AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+          AppError::AccountLocked { .. } => (StatusCode::LOCKED, self.to_string()),
+          AppError::PasswordPolicy(_) => (StatusCode::BAD_REQUEST, self.to_string()),
           AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
       };
 
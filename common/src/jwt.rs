@@ -1,22 +1,122 @@
 use anyhow::Result;
-use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use jsonwebtoken::{decode, decode_header, encode, errors::ErrorKind, Header, Validation};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 //use crate::error::AppError;
+use crate::config::AppConfig;
 use crate::error::AppError;
+use crate::keys::JwtKeys;
 
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Valor de `token_use` de los tokens emitidos tras la contraseña cuando falta el segundo factor
+pub const MFA_PENDING_TOKEN_USE: &str = "mfa_pending";
+
+/// Valor de `token_use` de los claims que construye `auth_middleware` para una clave de API
+pub const API_KEY_TOKEN_USE: &str = "api_key";
+
+/// Valor de `token_use` de los access tokens emitidos a clientes OAuth; sólo valen en `/oauth/userinfo`
+pub const OAUTH_ACCESS_TOKEN_USE: &str = "oauth_access";
+
+/// Valor de `token_use` de los tokens `client_credentials`: `sub` es el client_id del servicio
+pub const SERVICE_TOKEN_USE: &str = "service";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
-    pub sub: String,     // subject (user id)
+    pub sub: String,     // subject (user id, o client_id en tokens de servicio)
     pub exp: usize,      // expiration time
     pub iat: usize,      // issued at
+    pub nbf: usize,      // not before
+    pub jti: String,     // JWT ID, permite revocar el token antes de que expire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,   // issuer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,   // audience
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,  // rol del usuario, evita consultar la base de datos
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ver: Option<i32>,      // token_version del usuario al emitir el token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_use: Option<String>, // uso restringido del token (p. ej. `mfa_pending`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>, // permisos a los que se limita el acceso, separados por espacios
+}
+
+/// Claims propios de la aplicación que se incluyen en el token
+#[derive(Debug, Clone, Default)]
+pub struct CustomClaims {
+    pub role: Option<String>,
+    pub email: Option<String>,
+    pub token_version: Option<i32>,
+    pub token_use: Option<String>,
+    pub scope: Option<String>,
+}
+
+/// Claves y reglas de emisión/validación de tokens (iss, aud, nbf, leeway)
+pub struct JwtConfig {
+    pub keys: JwtKeys,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    pub leeway: u64,
+    pub validate_nbf: bool,
+}
+
+impl JwtConfig {
+    pub fn new(keys: JwtKeys) -> Self {
+        Self {
+            keys,
+            issuer: None,
+            audience: None,
+            leeway: 60,
+            validate_nbf: true,
+        }
+    }
+
+    pub fn from_config(config: &AppConfig) -> Result<Self, AppError> {
+        Ok(Self {
+            keys: JwtKeys::from_config(config)?,
+            issuer: config.jwt_issuer.clone().filter(|iss| !iss.is_empty()),
+            audience: config.jwt_audience.clone().filter(|aud| !aud.is_empty()),
+            leeway: config.jwt_leeway_secs,
+            validate_nbf: config.jwt_validate_nbf,
+        })
+    }
+
+    /// Instante a partir del cual `verify_jwt` rechaza el token por caducado: `exp` más el
+    /// margen. Una revocación tiene que durar hasta entonces, no sólo hasta `exp`.
+    pub fn expires_at(&self, claims: &Claims) -> Option<DateTime<Utc>> {
+        let exp = Utc.timestamp_opt(claims.exp as i64, 0).single()?;
+        Some(exp + Duration::seconds(self.leeway as i64 + 1))
+    }
+
+    // Con iss/aud configurados, los tokens sin esos claims o con otros valores se rechazan
+    fn validation(&self, algorithm: jsonwebtoken::Algorithm) -> Validation {
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = self.leeway;
+        validation.validate_nbf = self.validate_nbf;
+
+        let mut required = vec!["exp"];
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+            required.push("iss");
+        }
+        if let Some(audience) = &self.audience {
+            validation.set_audience(&[audience]);
+            required.push("aud");
+        }
+        validation.set_required_spec_claims(&required);
+
+        validation
+    }
 }
 
 pub fn generate_jwt(
     user_id: &str,
-    jwt_secret: &str,
+    custom_claims: CustomClaims,
+    jwt_config: &JwtConfig,
     expiration: &str,
 ) -> Result<String, AppError> {
     let now = Utc::now();
@@ -29,37 +129,60 @@ pub fn generate_jwt(
         sub: user_id.to_string(),
         exp,
         iat,
+        nbf: iat,
+        jti: Uuid::new_v4().to_string(),
+        iss: jwt_config.issuer.clone(),
+        aud: jwt_config.audience.clone(),
+        role: custom_claims.role,
+        email: custom_claims.email,
+        ver: custom_claims.token_version,
+        token_use: custom_claims.token_use,
+        scope: custom_claims.scope,
     };
     
+    sign_jwt(&claims, jwt_config)
+}
+
+/// Firma unos claims cualesquiera con la clave activa (p. ej. los ID tokens de OpenID Connect)
+pub fn sign_jwt<T: Serialize>(claims: &T, jwt_config: &JwtConfig) -> Result<String, AppError> {
+    // La cabecera lleva el `kid` para que los verificadores elijan la clave correcta
+    let signing_key = jwt_config.keys.signing_key();
+    let mut header = Header::new(signing_key.algorithm);
+    header.kid = signing_key.kid.clone();
+
     let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(jwt_secret.as_bytes()),
+        &header,
+        claims,
+        signing_key.encoding_key(),
     )
     .map_err(|e| AppError::TokenGenerationError(e.to_string()))?;
     
     Ok(token)
 }
 
-pub fn verify_jwt(token: &str, jwt_secret: &str) -> Result<Claims, AppError> {
+pub fn verify_jwt(token: &str, jwt_config: &JwtConfig) -> Result<Claims, AppError> {
+    let header = decode_header(token).map_err(|e| AppError::InvalidToken(e.to_string()))?;
+    let verification_key = jwt_config
+        .keys
+        .find_verification_key(header.kid.as_deref())
+        .ok_or_else(|| AppError::InvalidToken("Unknown signing key".into()))?;
+
+    // El algoritmo lo fija la clave, nunca la cabecera del token
     let token_data = decode::<Claims>(
         token,
-        &DecodingKey::from_secret(jwt_secret.as_bytes()),
-        &Validation::default(),
+        verification_key.decoding_key(),
+        &jwt_config.validation(verification_key.algorithm),
     )
-    .map_err(|e| {
-        if e.to_string().contains("expired") {
-            AppError::TokenExpired
-        } else {
-            AppError::InvalidToken(e.to_string())
-        }
+    .map_err(|e| match e.kind() {
+        ErrorKind::ExpiredSignature => AppError::TokenExpired,
+        _ => AppError::InvalidToken(e.to_string()),
     })?;
     
     Ok(token_data.claims)
 }
 
 // Helper para convertir strings como "60m" a Duration
-fn parse_duration(duration_str: &str) -> Result<Duration> {
+pub fn parse_duration(duration_str: &str) -> Result<Duration> {
     let last_char = duration_str.chars().last().unwrap_or('s');
     let value = duration_str[0..duration_str.len() - 1]
         .parse::<i64>()
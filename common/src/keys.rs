@@ -0,0 +1,211 @@
+use std::fs;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use rsa::{pkcs1::DecodeRsaPublicKey, pkcs8::DecodePublicKey, traits::PublicKeyParts, RsaPublicKey};
+
+use crate::{config::AppConfig, error::AppError};
+
+// Cabecera DER de un SubjectPublicKeyInfo Ed25519; le siguen los 32 bytes de la clave
+const ED25519_SPKI_PREFIX: [u8; 12] = [0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+
+/// Clave con la que se firman los tokens nuevos.
+pub struct SigningKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    key: EncodingKey,
+}
+
+impl SigningKey {
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.key
+    }
+}
+
+/// Clave aceptada para verificar tokens. Las claves asimétricas llevan su JWK
+/// pública para publicarla en `/.well-known/jwks.json`.
+pub struct VerificationKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    key: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl VerificationKey {
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.key
+    }
+}
+
+/// Conjunto de claves JWT: una clave de firma activa y varias de verificación,
+/// de modo que los tokens firmados con claves anteriores sigan siendo válidos
+/// durante una rotación.
+pub struct JwtKeys {
+    signing: SigningKey,
+    verification: Vec<VerificationKey>,
+}
+
+impl JwtKeys {
+    /// Claves HS256 basadas en un secreto compartido (sin `kid`).
+    pub fn hmac(secret: &str) -> Self {
+        Self {
+            signing: SigningKey {
+                kid: None,
+                algorithm: Algorithm::HS256,
+                key: EncodingKey::from_secret(secret.as_bytes()),
+            },
+            verification: vec![VerificationKey {
+                kid: None,
+                algorithm: Algorithm::HS256,
+                key: DecodingKey::from_secret(secret.as_bytes()),
+                jwk: None,
+            }],
+        }
+    }
+
+    /// Claves asimétricas (RS256 o EdDSA) a partir de un par de claves en formato PEM.
+    pub fn from_pem(kid: &str, algorithm: Algorithm, private_pem: &str, public_pem: &str) -> Result<Self, AppError> {
+        let key = match algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(private_pem.as_bytes()),
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(private_pem.as_bytes()),
+            other => return Err(AppError::Internal(format!("Unsupported JWT algorithm: {:?}", other))),
+        }
+        .map_err(|e| AppError::Internal(format!("Invalid private key '{}': {}", kid, e)))?;
+
+        let public_key = parse_public_key(kid, public_pem)?;
+        if public_key.algorithm != algorithm {
+            return Err(AppError::Internal(format!("Public key '{}' does not match algorithm {:?}", kid, algorithm)));
+        }
+
+        Ok(Self {
+            signing: SigningKey {
+                kid: Some(kid.to_string()),
+                algorithm,
+                key,
+            },
+            verification: vec![public_key],
+        })
+    }
+
+    /// Añade una clave pública adicional aceptada para verificar (p. ej. la clave anterior a una rotación).
+    pub fn with_verification_key(mut self, kid: &str, public_pem: &str) -> Result<Self, AppError> {
+        if self.find_verification_key(Some(kid)).is_some() {
+            return Err(AppError::Internal(format!("Duplicate JWT key id: {}", kid)));
+        }
+        self.verification.push(parse_public_key(kid, public_pem)?);
+        Ok(self)
+    }
+
+    /// Construye las claves según `AppConfig`: HS256 con `jwt_secret`, o RS256/EdDSA
+    /// con `jwt_private_key_path`, `jwt_public_key_path` y `jwt_key_id`. Las claves
+    /// antiguas se declaran en `jwt_previous_public_keys` como `kid=ruta,kid=ruta`.
+    pub fn from_config(config: &AppConfig) -> Result<Self, AppError> {
+        let algorithm = match config.jwt_algorithm.to_uppercase().as_str() {
+            "HS256" => {
+                if config.jwt_secret.is_empty() {
+                    return Err(AppError::Internal("JWT_SECRET is required for HS256".into()));
+                }
+                return Ok(Self::hmac(&config.jwt_secret));
+            }
+            "RS256" => Algorithm::RS256,
+            "EDDSA" => Algorithm::EdDSA,
+            other => return Err(AppError::Internal(format!("Unsupported JWT algorithm: {}", other))),
+        };
+
+        let kid = required(&config.jwt_key_id, "JWT_KEY_ID")?;
+        let private_pem = read_pem(required(&config.jwt_private_key_path, "JWT_PRIVATE_KEY_PATH")?)?;
+        let public_pem = read_pem(required(&config.jwt_public_key_path, "JWT_PUBLIC_KEY_PATH")?)?;
+        let mut keys = Self::from_pem(kid, algorithm, &private_pem, &public_pem)?;
+
+        if let Some(previous) = &config.jwt_previous_public_keys {
+            for entry in previous.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let (kid, path) = entry
+                    .split_once('=')
+                    .ok_or_else(|| AppError::Internal(format!("Invalid entry in JWT_PREVIOUS_PUBLIC_KEYS: {}", entry)))?;
+                keys = keys.with_verification_key(kid.trim(), &read_pem(path.trim())?)?;
+            }
+        }
+
+        Ok(keys)
+    }
+
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.signing
+    }
+
+    pub fn find_verification_key(&self, kid: Option<&str>) -> Option<&VerificationKey> {
+        self.verification.iter().find(|key| key.kid.as_deref() == kid)
+    }
+
+    /// JWK Set con las claves públicas de verificación. Los secretos HMAC nunca se publican.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.verification.iter().filter_map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+fn required<'a>(value: &'a Option<String>, name: &str) -> Result<&'a str, AppError> {
+    value
+        .as_deref()
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| AppError::Internal(format!("{} is required for asymmetric JWT signing", name)))
+}
+
+fn read_pem(path: &str) -> Result<String, AppError> {
+    fs::read_to_string(path).map_err(|e| AppError::Internal(format!("Unable to read key file {}: {}", path, e)))
+}
+
+// Detecta el tipo de clave pública (RSA o Ed25519) y construye su JWK
+fn parse_public_key(kid: &str, public_pem: &str) -> Result<VerificationKey, AppError> {
+    let rsa_key = RsaPublicKey::from_public_key_pem(public_pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(public_pem));
+
+    let (algorithm, key_algorithm, parameters) = if let Ok(rsa_key) = rsa_key {
+        let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n: URL_SAFE_NO_PAD.encode(rsa_key.n().to_bytes_be()),
+            e: URL_SAFE_NO_PAD.encode(rsa_key.e().to_bytes_be()),
+        });
+        (Algorithm::RS256, KeyAlgorithm::RS256, parameters)
+    } else {
+        let der = pem::parse(public_pem)
+            .map_err(|e| AppError::Internal(format!("Invalid public key '{}': {}", kid, e)))?;
+        let raw = der
+            .contents()
+            .strip_prefix(&ED25519_SPKI_PREFIX[..])
+            .filter(|raw| raw.len() == 32)
+            .ok_or_else(|| AppError::Internal(format!("Public key '{}' is neither RSA nor Ed25519", kid)))?;
+        let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(raw),
+        });
+        (Algorithm::EdDSA, KeyAlgorithm::EdDSA, parameters)
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+    let key = DecodingKey::from_jwk(&jwk)
+        .map_err(|e| AppError::Internal(format!("Invalid public key '{}': {}", kid, e)))?;
+
+    Ok(VerificationKey {
+        kid: Some(kid.to_string()),
+        algorithm,
+        key,
+        jwk: Some(jwk),
+    })
+}
@@ -2,4 +2,8 @@ pub mod config;
 pub mod error;
 pub mod models;
 pub mod utils;
-pub mod jwt;
\ No newline at end of file
+pub mod jwt;
+pub mod keys;
+pub mod mailer;
+pub mod password;
+pub mod telegram;
\ No newline at end of file
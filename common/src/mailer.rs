@@ -0,0 +1,30 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use crate::error::AppError;
+
+/// Correo saliente en texto plano
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Envío de correos transaccionales (verificación de email, recuperación de contraseña...)
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: EmailMessage) -> Result<(), AppError>;
+}
+
+/// Mailer para desarrollo: escribe los correos en el log en lugar de enviarlos
+#[derive(Debug, Default)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: EmailMessage) -> Result<(), AppError> {
+        info!("Correo para {}: {}\n{}", message.to, message.subject, message.body);
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Result};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+
+use crate::config::AppConfig;
+
+/// Algoritmos de hash de contraseñas que se reconocen al verificar.
+/// Los hashes nuevos siempre son Argon2id; el resto se aceptan para no dejar fuera a
+/// usuarios creados por otras vías o importados de otros sistemas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Argon2id,
+    Argon2i,
+    Argon2d,
+    Bcrypt,
+    Scrypt,
+    Pbkdf2,
+}
+
+impl HashAlgorithm {
+    /// Identifica el algoritmo por el prefijo PHC (`$argon2id$`, `$scrypt$`, `$pbkdf2-sha256$`...)
+    /// o MCF (`$2a$`, `$2b$`, `$2y$` de bcrypt).
+    pub fn identify(stored_hash: &str) -> Option<Self> {
+        let id = stored_hash.strip_prefix('$')?.split('$').next()?;
+        match id {
+            "argon2id" => Some(HashAlgorithm::Argon2id),
+            "argon2i" => Some(HashAlgorithm::Argon2i),
+            "argon2d" => Some(HashAlgorithm::Argon2d),
+            "2a" | "2b" | "2x" | "2y" => Some(HashAlgorithm::Bcrypt),
+            "scrypt" => Some(HashAlgorithm::Scrypt),
+            "pbkdf2" | "pbkdf2-sha256" | "pbkdf2-sha512" => Some(HashAlgorithm::Pbkdf2),
+            _ => None,
+        }
+    }
+}
+
+/// Coste de Argon2id para los hashes nuevos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// Memoria en KiB
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Params {
+    // Los valores por defecto del crate argon2 (recomendación de OWASP)
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl Argon2Params {
+    pub fn from_config(config: &AppConfig) -> Result<Self> {
+        let params = Self {
+            memory_kib: config.password_argon2_memory_kib,
+            iterations: config.password_argon2_iterations,
+            parallelism: config.password_argon2_parallelism,
+        };
+        // Falla al arrancar en lugar de al primer registro
+        params.hasher()?;
+        Ok(params)
+    }
+
+    fn hasher(&self) -> Result<Argon2<'static>> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+/// Genera un hash Argon2id con los parámetros indicados.
+pub fn hash_password(password: &str, params: &Argon2Params) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = params
+        .hasher()?
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("Error hashing password: {}", e))?;
+    Ok(hash.to_string())
+}
+
+/// Comprueba la contraseña contra un hash de cualquiera de los algoritmos de `HashAlgorithm`.
+/// Devuelve error si el hash no se reconoce o está mal formado.
+pub fn verify_password(password: &str, stored_hash: &str) -> Result<bool> {
+    let algorithm = HashAlgorithm::identify(stored_hash).ok_or_else(|| anyhow!("Unrecognized password hash format"))?;
+
+    if algorithm == HashAlgorithm::Bcrypt {
+        return bcrypt::verify(password, stored_hash).map_err(|e| anyhow!("Invalid bcrypt hash: {}", e));
+    }
+
+    let parsed = PasswordHash::new(stored_hash).map_err(|e| anyhow!("Invalid password hash: {}", e))?;
+    let result = match algorithm {
+        // Argon2 toma el algoritmo, la versión y el coste del propio hash
+        HashAlgorithm::Argon2id | HashAlgorithm::Argon2i | HashAlgorithm::Argon2d => {
+            Argon2::default().verify_password(password.as_bytes(), &parsed)
+        }
+        HashAlgorithm::Scrypt => Scrypt.verify_password(password.as_bytes(), &parsed),
+        HashAlgorithm::Pbkdf2 => Pbkdf2.verify_password(password.as_bytes(), &parsed),
+        HashAlgorithm::Bcrypt => unreachable!(),
+    };
+
+    match result {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(anyhow!("Error verifying password: {}", e)),
+    }
+}
+
+/// Indica si el hash debe regenerarse: cuando no es Argon2id o cuando su coste es menor
+/// que el configurado. Un coste mayor se conserva.
+pub fn needs_rehash(stored_hash: &str, params: &Argon2Params) -> bool {
+    if HashAlgorithm::identify(stored_hash) != Some(HashAlgorithm::Argon2id) {
+        return true;
+    }
+    let Ok(parsed) = PasswordHash::new(stored_hash) else {
+        return true;
+    };
+    let Ok(current) = Params::try_from(&parsed) else {
+        return true;
+    };
+
+    parsed.version != Some(Version::V0x13.into())
+        || current.m_cost() < params.memory_kib
+        || current.t_cost() < params.iterations
+        || current.p_cost() < params.parallelism
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Tolerancia para relojes desincronizados cuando `auth_date` llega en el futuro
+const MAX_CLOCK_SKEW_SECS: i64 = 60;
+
+/// Datos que entrega el Telegram Login Widget tras la autorización del usuario.
+/// Los campos desconocidos se conservan en `extra` porque también forman parte de la firma.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramLoginData {
+    pub id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub photo_url: Option<String>,
+    pub auth_date: i64,
+    pub hash: String,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+impl TelegramLoginData {
+    /// data-check-string: pares `clave=valor` ordenados por clave, sin `hash`, separados por `\n`
+    pub fn data_check_string(&self) -> String {
+        let mut fields: BTreeMap<String, String> = self
+            .extra
+            .iter()
+            .map(|(key, value)| (key.clone(), value_to_string(value)))
+            .collect();
+        fields.insert("id".into(), self.id.to_string());
+        fields.insert("auth_date".into(), self.auth_date.to_string());
+        let optional = [
+            ("first_name", &self.first_name),
+            ("last_name", &self.last_name),
+            ("username", &self.username),
+            ("photo_url", &self.photo_url),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                fields.insert(key.into(), value.clone());
+            }
+        }
+
+        join_check_string(&fields)
+    }
+}
+
+/// Verifica la firma del Login Widget: HMAC-SHA256 del data-check-string usando
+/// como clave el SHA-256 del token del bot, y rechaza `auth_date` antiguos.
+pub fn verify_login_widget(data: &TelegramLoginData, bot_token: &str, max_age: Duration) -> Result<(), AppError> {
+    let secret_key = Sha256::digest(bot_token.as_bytes());
+    verify_hmac(&secret_key, &data.data_check_string(), &data.hash)?;
+    check_auth_date(data.auth_date, max_age)
+}
+
+/// Usuario incluido por Telegram en el `initData` de una Mini App
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramWebAppUser {
+    pub id: i64,
+    #[serde(default)]
+    pub first_name: Option<String>,
+    #[serde(default)]
+    pub last_name: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub language_code: Option<String>,
+    #[serde(default)]
+    pub photo_url: Option<String>,
+}
+
+impl TelegramWebAppUser {
+    /// Nombre completo tal como lo muestra Telegram
+    pub fn display_name(&self) -> Option<String> {
+        let parts: Vec<&str> = [&self.first_name, &self.last_name]
+            .into_iter()
+            .filter_map(|part| part.as_deref())
+            .filter(|part| !part.is_empty())
+            .collect();
+        if parts.is_empty() {
+            self.username.clone()
+        } else {
+            Some(parts.join(" "))
+        }
+    }
+}
+
+/// `initData` de una Mini App cuya firma ya fue verificada
+#[derive(Debug, Clone)]
+pub struct TelegramWebAppData {
+    pub user: Option<TelegramWebAppUser>,
+    pub auth_date: i64,
+    pub query_id: Option<String>,
+    pub start_param: Option<String>,
+}
+
+/// Clave de las Mini Apps: HMAC-SHA256 del token del bot usando "WebAppData" como clave
+pub fn web_app_secret_key(bot_token: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(b"WebAppData").expect("HMAC acepta claves de cualquier tamaño");
+    mac.update(bot_token.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Verifica el `initData` (query string) que envía una Mini App y devuelve sus datos.
+/// Todos los campos salvo `hash` forman el data-check-string, ya decodificados.
+pub fn verify_web_app_init_data(init_data: &str, bot_token: &str, max_age: Duration) -> Result<TelegramWebAppData, AppError> {
+    let mut fields = BTreeMap::new();
+    let mut hash = None;
+    for (key, value) in form_urlencoded::parse(init_data.as_bytes()) {
+        let key = key.into_owned();
+        let value = value.into_owned();
+        if key == "hash" {
+            hash = Some(value);
+        } else if fields.insert(key, value).is_some() {
+            return Err(AppError::Auth("Duplicated field in Telegram init data".into()));
+        }
+    }
+    let hash = hash.ok_or_else(|| AppError::Auth("Missing hash in Telegram init data".into()))?;
+
+    verify_hmac(&web_app_secret_key(bot_token), &join_check_string(&fields), &hash)?;
+
+    let auth_date = fields
+        .get("auth_date")
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or_else(|| AppError::Auth("Invalid auth_date in Telegram init data".into()))?;
+    check_auth_date(auth_date, max_age)?;
+
+    let user = fields
+        .get("user")
+        .map(|value| serde_json::from_str::<TelegramWebAppUser>(value))
+        .transpose()
+        .map_err(|_| AppError::Auth("Invalid user in Telegram init data".into()))?;
+
+    Ok(TelegramWebAppData {
+        user,
+        auth_date,
+        query_id: fields.remove("query_id"),
+        start_param: fields.remove("start_param"),
+    })
+}
+
+pub(crate) fn join_check_string(fields: &BTreeMap<String, String>) -> String {
+    fields
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub(crate) fn verify_hmac(secret_key: &[u8], data_check_string: &str, hash: &str) -> Result<(), AppError> {
+    let expected = hex::decode(hash).map_err(|_| AppError::Auth("Invalid Telegram hash".into()))?;
+    let mut mac = HmacSha256::new_from_slice(secret_key)
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    mac.update(data_check_string.as_bytes());
+    // Comparación en tiempo constante
+    mac.verify_slice(&expected)
+        .map_err(|_| AppError::Auth("Invalid Telegram signature".into()))
+}
+
+pub(crate) fn check_auth_date(auth_date: i64, max_age: Duration) -> Result<(), AppError> {
+    let age = Utc::now().timestamp() - auth_date;
+    if age > max_age.num_seconds() {
+        return Err(AppError::Auth("Telegram authentication data is too old".into()));
+    }
+    if age < -MAX_CLOCK_SKEW_SECS {
+        return Err(AppError::Auth("Telegram authentication date is in the future".into()));
+    }
+    Ok(())
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -1,4 +1,19 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
 // Aquí pueden ir funciones útiles reutilizables
 pub fn sanitize_string(s: &str) -> String {
   s.trim().to_string()
 }
+
+/// Genera un token aleatorio criptográficamente seguro, codificado en hexadecimal
+pub fn generate_secure_token(num_bytes: usize) -> String {
+  let mut bytes = vec![0u8; num_bytes];
+  rand::thread_rng().fill_bytes(&mut bytes);
+  hex::encode(bytes)
+}
+
+/// Calcula el hash SHA-256 (hex) de un token opaco para guardarlo en la base de datos
+pub fn hash_token(token: &str) -> String {
+  hex::encode(Sha256::digest(token.as_bytes()))
+}
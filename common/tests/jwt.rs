@@ -0,0 +1,101 @@
+use chrono::Utc;
+use common::error::AppError;
+use common::jwt::{generate_jwt, sign_jwt, verify_jwt, Claims, CustomClaims, JwtConfig};
+use common::keys::JwtKeys;
+
+fn config(issuer: Option<&str>, audience: Option<&str>) -> JwtConfig {
+    JwtConfig {
+        issuer: issuer.map(str::to_string),
+        audience: audience.map(str::to_string),
+        ..JwtConfig::new(JwtKeys::hmac("test-secret"))
+    }
+}
+
+fn claims(nbf_offset: i64) -> Claims {
+    let now = Utc::now().timestamp();
+    Claims {
+        sub: "user".into(),
+        exp: (now + 900) as usize,
+        iat: now as usize,
+        nbf: (now + nbf_offset) as usize,
+        jti: "jti".into(),
+        iss: None,
+        aud: None,
+        role: None,
+        email: None,
+        ver: None,
+        token_use: None,
+        scope: None,
+    }
+}
+
+#[test]
+fn issued_tokens_carry_the_configured_issuer_and_audience() {
+    let config = config(Some("https://auth.example.com"), Some("api"));
+
+    let token = generate_jwt("user", CustomClaims::default(), &config, "15m").unwrap();
+    let claims = verify_jwt(&token, &config).unwrap();
+
+    assert_eq!(claims.iss.as_deref(), Some("https://auth.example.com"));
+    assert_eq!(claims.aud.as_deref(), Some("api"));
+}
+
+#[test]
+fn tokens_for_another_issuer_or_audience_are_rejected() {
+    let config = config(Some("https://auth.example.com"), Some("api"));
+    let other_issuer = generate_jwt("user", CustomClaims::default(), &self::config(Some("https://evil.example.com"), Some("api")), "15m").unwrap();
+    let other_audience = generate_jwt("user", CustomClaims::default(), &self::config(Some("https://auth.example.com"), Some("admin")), "15m").unwrap();
+
+    assert!(matches!(verify_jwt(&other_issuer, &config), Err(AppError::InvalidToken(_))));
+    assert!(matches!(verify_jwt(&other_audience, &config), Err(AppError::InvalidToken(_))));
+}
+
+#[test]
+fn tokens_without_issuer_or_audience_are_rejected_once_configured() {
+    let token = generate_jwt("user", CustomClaims::default(), &config(None, None), "15m").unwrap();
+
+    assert!(verify_jwt(&token, &config(None, None)).is_ok());
+    assert!(matches!(verify_jwt(&token, &config(Some("https://auth.example.com"), None)), Err(AppError::InvalidToken(_))));
+    assert!(matches!(verify_jwt(&token, &config(None, Some("api"))), Err(AppError::InvalidToken(_))));
+}
+
+#[test]
+fn nbf_in_the_future_is_rejected_beyond_the_leeway() {
+    let config = config(None, None);
+
+    let within_leeway = sign_jwt(&claims(30), &config).unwrap();
+    let too_early = sign_jwt(&claims(600), &config).unwrap();
+
+    assert!(verify_jwt(&within_leeway, &config).is_ok());
+    assert!(matches!(verify_jwt(&too_early, &config), Err(AppError::InvalidToken(_))));
+}
+
+#[test]
+fn nbf_is_ignored_when_validation_is_disabled() {
+    let config = JwtConfig { validate_nbf: false, ..config(None, None) };
+
+    let too_early = sign_jwt(&claims(600), &config).unwrap();
+
+    assert!(verify_jwt(&too_early, &config).is_ok());
+}
+
+#[test]
+fn expired_tokens_are_reported_as_expired() {
+    let config = JwtConfig { leeway: 0, ..config(None, None) };
+    let mut expired = claims(-600);
+    expired.exp = (Utc::now().timestamp() - 60) as usize;
+
+    let token = sign_jwt(&expired, &config).unwrap();
+
+    assert!(matches!(verify_jwt(&token, &config), Err(AppError::TokenExpired)));
+}
+
+#[test]
+fn tokens_expire_for_revocation_after_the_leeway() {
+    let config = config(None, None);
+    let claims = claims(0);
+
+    let expires_at = config.expires_at(&claims).unwrap();
+
+    assert_eq!(expires_at.timestamp(), claims.exp as i64 + 61);
+}
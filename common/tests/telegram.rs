@@ -0,0 +1,99 @@
+use chrono::{Duration, Utc};
+use common::error::AppError;
+use common::telegram::{verify_login_widget, TelegramLoginData};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+const BOT_TOKEN: &str = "123456:TEST-bot-token";
+
+fn signed(auth_date: i64) -> TelegramLoginData {
+    let mut data: TelegramLoginData = serde_json::from_value(serde_json::json!({
+        "id": 424242,
+        "first_name": "Ana",
+        "username": "ana",
+        "auth_date": auth_date,
+        "hash": "",
+    }))
+    .unwrap();
+    sign(&mut data, BOT_TOKEN);
+    data
+}
+
+fn sign(data: &mut TelegramLoginData, bot_token: &str) {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&Sha256::digest(bot_token.as_bytes())).unwrap();
+    mac.update(data.data_check_string().as_bytes());
+    data.hash = hex::encode(mac.finalize().into_bytes());
+}
+
+fn verify(data: &TelegramLoginData) -> Result<(), AppError> {
+    verify_login_widget(data, BOT_TOKEN, Duration::days(1))
+}
+
+#[test]
+fn valid_signature_is_accepted() {
+    assert!(verify(&signed(Utc::now().timestamp())).is_ok());
+}
+
+#[test]
+fn data_signed_with_another_bot_token_is_rejected() {
+    let mut data = signed(Utc::now().timestamp());
+    sign(&mut data, "654321:OTHER-bot-token");
+
+    assert!(matches!(verify(&data), Err(AppError::Auth(message)) if message == "Invalid Telegram signature"));
+}
+
+#[test]
+fn malformed_hash_is_rejected() {
+    let mut data = signed(Utc::now().timestamp());
+    data.hash = "not-hex".into();
+
+    assert!(matches!(verify(&data), Err(AppError::Auth(message)) if message == "Invalid Telegram hash"));
+}
+
+#[test]
+fn tampered_fields_invalidate_the_signature() {
+    let now = Utc::now().timestamp();
+
+    let mut other_id = signed(now);
+    other_id.id = 1;
+    let mut other_name = signed(now);
+    other_name.username = Some("admin".into());
+    let mut extra_field = signed(now);
+    extra_field.extra.insert("is_admin".into(), serde_json::json!(true));
+    let mut removed_field = signed(now);
+    removed_field.first_name = None;
+
+    for data in [other_id, other_name, extra_field, removed_field] {
+        assert!(matches!(verify(&data), Err(AppError::Auth(message)) if message == "Invalid Telegram signature"));
+    }
+}
+
+#[test]
+fn unknown_fields_are_part_of_the_signature() {
+    let mut data: TelegramLoginData = serde_json::from_value(serde_json::json!({
+        "id": 424242,
+        "auth_date": Utc::now().timestamp(),
+        "allows_write_to_pm": true,
+        "hash": "",
+    }))
+    .unwrap();
+    sign(&mut data, BOT_TOKEN);
+
+    assert!(verify(&data).is_ok());
+}
+
+#[test]
+fn stale_auth_date_is_rejected() {
+    let data = signed((Utc::now() - Duration::days(2)).timestamp());
+
+    assert!(matches!(verify(&data), Err(AppError::Auth(message)) if message == "Telegram authentication data is too old"));
+}
+
+#[test]
+fn auth_date_in_the_future_is_rejected_beyond_the_clock_skew() {
+    let within_skew = signed(Utc::now().timestamp() + 30);
+    let future = signed((Utc::now() + Duration::hours(1)).timestamp());
+
+    assert!(verify(&within_skew).is_ok());
+    assert!(matches!(verify(&future), Err(AppError::Auth(message)) if message == "Telegram authentication date is in the future"));
+}
@@ -0,0 +1,15 @@
+-- Migration: 00003_create_revoked_tokens_table
+-- Description: Crea la tabla de access tokens revocados (por jti) antes de su expiración
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti VARCHAR(64) PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
+
+-- Down Migration
+-- DROP TABLE IF EXISTS revoked_tokens;
@@ -0,0 +1,52 @@
+-- Migration: 00005_audit_duplicate_telegram_links
+-- Description: Retira y registra los Telegram ID vinculados a varios usuarios antes de pasarlos a user_identities
+-- Created: 2026-10-17
+
+-- Up Migration
+-- Vínculos retirados por estar duplicados, para poder revisarlos y restaurarlos a mano
+CREATE TABLE IF NOT EXISTS telegram_link_conflicts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Usuario que pierde el vínculo
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    telegram_user_id VARCHAR(255) NOT NULL,
+    -- Usuario más antiguo con el mismo Telegram ID, que lo conserva
+    kept_by_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    detached_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Sólo en las bases de datos que aún tienen users.telegram_user_id, antes de que 00018 la
+-- pase a user_identities: si hay duplicados, el vínculo se conserva sólo en el usuario más
+-- antiguo; los demás se copian a telegram_link_conflicts y se avisa en el log antes de retirarlos.
+-- La unicidad la garantiza después user_identities.
+DO $$
+DECLARE
+    conflict RECORD;
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = 'users' AND column_name = 'telegram_user_id'
+    ) THEN
+        RETURN;
+    END IF;
+
+    FOR conflict IN
+        SELECT u.id AS user_id, u.telegram_user_id, kept.id AS kept_by_user_id
+        FROM users u
+        JOIN LATERAL (
+            SELECT older.id FROM users older
+            WHERE older.telegram_user_id = u.telegram_user_id
+            ORDER BY older.created_at, older.id
+            LIMIT 1
+        ) kept ON kept.id <> u.id
+        WHERE u.telegram_user_id IS NOT NULL
+    LOOP
+        RAISE WARNING 'Telegram ID % desvinculado del usuario % (lo conserva el usuario %); copiado a telegram_link_conflicts',
+            conflict.telegram_user_id, conflict.user_id, conflict.kept_by_user_id;
+        INSERT INTO telegram_link_conflicts (user_id, telegram_user_id, kept_by_user_id)
+        VALUES (conflict.user_id, conflict.telegram_user_id, conflict.kept_by_user_id);
+        UPDATE users SET telegram_user_id = NULL WHERE id = conflict.user_id;
+    END LOOP;
+END $$;
+
+-- Down Migration
+-- DROP TABLE IF EXISTS telegram_link_conflicts;
@@ -0,0 +1,24 @@
+-- Migration: 00006_create_email_verification_tokens_table
+-- Description: Añade users.email_verified_at y los tokens de verificación de email (sólo el hash)
+-- Created: 2026-10-17
+
+-- Up Migration
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMPTZ;
+
+-- Los usuarios existentes se consideran verificados para no bloquearlos al activar la verificación
+UPDATE users SET email_verified_at = created_at WHERE email_verified_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS email_verification_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_verification_tokens_user_id ON email_verification_tokens(user_id);
+
+-- Down Migration
+-- DROP TABLE IF EXISTS email_verification_tokens;
+-- ALTER TABLE users DROP COLUMN IF EXISTS email_verified_at;
@@ -0,0 +1,18 @@
+-- Migration: 00007_create_password_reset_tokens_table
+-- Description: Crea la tabla de tokens de recuperación de contraseña (sólo el hash)
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_user_id ON password_reset_tokens(user_id);
+
+-- Down Migration
+-- DROP TABLE IF EXISTS password_reset_tokens;
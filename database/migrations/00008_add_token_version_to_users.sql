@@ -0,0 +1,9 @@
+-- Migration: 00008_add_token_version_to_users
+-- Description: Añade users.token_version para invalidar los tokens emitidos antes de un cambio de contraseña
+-- Created: 2026-10-17
+
+-- Up Migration
+ALTER TABLE users ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0;
+
+-- Down Migration
+-- ALTER TABLE users DROP COLUMN IF EXISTS token_version;
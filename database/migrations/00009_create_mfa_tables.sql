@@ -0,0 +1,28 @@
+-- Migration: 00009_create_mfa_tables
+-- Description: Crea las tablas de TOTP y de códigos de recuperación (sólo el hash) para 2FA
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS user_totp (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    secret VARCHAR(64) NOT NULL,
+    -- NULL mientras la inscripción no se confirme con un primer código válido
+    enabled_at TIMESTAMPTZ,
+    -- Último paso de 30 s aceptado, para impedir reutilizar un código
+    last_used_step BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS mfa_recovery_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_mfa_recovery_codes_user_id ON mfa_recovery_codes(user_id);
+
+-- Down Migration
+-- DROP TABLE IF EXISTS mfa_recovery_codes;
+-- DROP TABLE IF EXISTS user_totp;
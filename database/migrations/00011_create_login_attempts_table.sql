@@ -0,0 +1,14 @@
+-- Migration: 00011_create_login_attempts_table
+-- Description: Crea la tabla de intentos de login fallidos por cuenta (backoff y bloqueo temporal)
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS login_attempts (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    -- Fallos consecutivos desde el último login correcto o desbloqueo
+    failed_count INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Down Migration
+-- DROP TABLE IF EXISTS login_attempts;
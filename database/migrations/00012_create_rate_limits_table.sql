@@ -0,0 +1,20 @@
+-- Migration: 00012_create_rate_limits_table
+-- Description: Crea la tabla con el estado de los límites de peticiones (token bucket y ventana deslizante)
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS rate_limits (
+    -- ruta:tipo de clave:cliente
+    key TEXT PRIMARY KEY,
+    -- Fichas disponibles (token bucket) o peticiones de la ventana actual (ventana deslizante)
+    value DOUBLE PRECISION NOT NULL,
+    -- Peticiones de la ventana anterior (sólo ventana deslizante)
+    previous DOUBLE PRECISION NOT NULL DEFAULT 0,
+    started_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_rate_limits_expires_at ON rate_limits(expires_at);
+
+-- Down Migration
+-- DROP TABLE IF EXISTS rate_limits;
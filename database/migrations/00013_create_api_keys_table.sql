@@ -0,0 +1,25 @@
+-- Migration: 00013_create_api_keys_table
+-- Description: Crea la tabla de claves de API de los usuarios (llamadas entre servicios)
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    -- Parte pública de la clave con la que se localiza
+    prefix VARCHAR(32) NOT NULL UNIQUE,
+    -- SHA-256 (hex) de la clave completa
+    key_hash VARCHAR(64) NOT NULL,
+    -- Permisos a los que se limita la clave
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_user_id ON api_keys(user_id);
+
+-- Down Migration
+-- DROP TABLE IF EXISTS api_keys;
@@ -0,0 +1,54 @@
+-- Migration: 00014_create_oauth_tables
+-- Description: Crea el registro de clientes OAuth y las autorizaciones (consentimiento y códigos) del proveedor OpenID Connect
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS oauth_clients (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    client_id VARCHAR(64) NOT NULL UNIQUE,
+    -- SHA-256 (hex) del secreto; NULL en clientes públicos, que sólo se protegen con PKCE
+    client_secret_hash VARCHAR(64),
+    name VARCHAR(100) NOT NULL,
+    -- URIs de redirección registradas; se comparan de forma exacta
+    redirect_uris TEXT[] NOT NULL,
+    -- Scopes que puede pedir el cliente
+    scopes TEXT[] NOT NULL DEFAULT '{openid}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS oauth_authorizations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    client_id VARCHAR(64) NOT NULL REFERENCES oauth_clients(client_id) ON DELETE CASCADE,
+    redirect_uri TEXT NOT NULL,
+    scopes TEXT[] NOT NULL,
+    state TEXT,
+    nonce TEXT,
+    -- Challenge PKCE (S256) que debe corresponder al code_verifier al canjear el código
+    code_challenge VARCHAR(128) NOT NULL,
+    -- Se rellenan al aprobar el consentimiento
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    -- SHA-256 (hex) del código de autorización
+    code_hash VARCHAR(64) UNIQUE,
+    approved_at TIMESTAMPTZ,
+    -- Plazo para dar el consentimiento y, una vez aprobado, para canjear el código
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_oauth_authorizations_expires_at ON oauth_authorizations(expires_at);
+
+-- Permisos para gestionar el registro de clientes
+INSERT INTO permissions (name, description) VALUES
+    ('oauth_clients:read', 'Consultar clientes OAuth'),
+    ('oauth_clients:write', 'Registrar clientes OAuth')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id FROM roles r, permissions p
+WHERE r.name = 'admin' AND p.name IN ('oauth_clients:read', 'oauth_clients:write')
+ON CONFLICT DO NOTHING;
+
+-- Down Migration
+-- DELETE FROM permissions WHERE name IN ('oauth_clients:read', 'oauth_clients:write');
+-- DROP TABLE IF EXISTS oauth_authorizations;
+-- DROP TABLE IF EXISTS oauth_clients;
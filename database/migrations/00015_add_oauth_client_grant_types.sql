@@ -0,0 +1,10 @@
+-- Migration: 00015_add_oauth_client_grant_types
+-- Description: Añade a los clientes OAuth los grants que pueden usar (authorization_code, client_credentials)
+-- Created: 2026-10-17
+
+-- Up Migration
+-- Los clientes existentes sólo usaban el flujo authorization code
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS grant_types TEXT[] NOT NULL DEFAULT '{authorization_code}';
+
+-- Down Migration
+-- ALTER TABLE oauth_clients DROP COLUMN IF EXISTS grant_types;
@@ -0,0 +1,28 @@
+-- Migration: 00016_create_oauth_device_authorizations_table
+-- Description: Crea las autorizaciones del flujo de dispositivo (RFC 8628) para herramientas sin navegador
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS oauth_device_authorizations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    client_id VARCHAR(64) NOT NULL REFERENCES oauth_clients(client_id) ON DELETE CASCADE,
+    -- SHA-256 (hex) del device_code con el que consulta el dispositivo
+    device_code_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- Código que introduce el usuario, normalizado (mayúsculas y sin guion)
+    user_code VARCHAR(16) NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    -- pending, approved o denied
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    -- Intervalo mínimo entre consultas; crece con cada slow_down
+    interval_secs INTEGER NOT NULL,
+    last_polled_at TIMESTAMPTZ,
+    approved_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_oauth_device_authorizations_expires_at ON oauth_device_authorizations(expires_at);
+
+-- Down Migration
+-- DROP TABLE IF EXISTS oauth_device_authorizations;
@@ -0,0 +1,22 @@
+-- Migration: 00017_create_federated_login_tables
+-- Description: Crea las solicitudes de login con un proveedor OpenID Connect externo
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS federated_login_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- SHA-256 (hex) del state que vuelve del proveedor
+    state_hash VARCHAR(64) NOT NULL UNIQUE,
+    nonce TEXT NOT NULL,
+    -- Verificador PKCE con el que se canjea el código
+    code_verifier TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_federated_login_requests_expires_at ON federated_login_requests(expires_at);
+
+-- Las identidades vinculadas se guardan en user_identities (00018)
+
+-- Down Migration
+-- DROP TABLE IF EXISTS federated_login_requests;
@@ -0,0 +1,55 @@
+-- Migration: 00018_create_user_identities_table
+-- Description: Identidades externas genéricas (Telegram, proveedores OpenID Connect...) en lugar de una columna por método de login
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS user_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- `telegram` o el `iss` del proveedor OpenID Connect
+    provider VARCHAR(255) NOT NULL,
+    -- Identificador de la cuenta en el proveedor (Telegram ID, `sub`)
+    subject TEXT NOT NULL,
+    -- Datos informativos del proveedor (username, email con el que se vinculó...)
+    metadata JSONB NOT NULL DEFAULT '{}',
+    linked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, subject),
+    -- Como mucho una cuenta de cada proveedor por usuario
+    UNIQUE (user_id, provider)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_identities_user_id ON user_identities(user_id);
+
+-- Copia única de las bases de datos anteriores: 00005 y 00017 ya no crean la columna
+-- users.telegram_user_id ni la tabla federated_identities, así que una vez borradas no se
+-- vuelve a tocar la tabla users en cada arranque
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = 'users' AND column_name = 'telegram_user_id'
+    ) THEN
+        INSERT INTO user_identities (user_id, provider, subject, linked_at)
+        SELECT id, 'telegram', telegram_user_id, updated_at
+        FROM users
+        WHERE telegram_user_id IS NOT NULL
+        ON CONFLICT DO NOTHING;
+
+        ALTER TABLE users DROP COLUMN telegram_user_id;
+    END IF;
+
+    IF to_regclass('federated_identities') IS NOT NULL THEN
+        INSERT INTO user_identities (user_id, provider, subject, metadata, linked_at)
+        SELECT user_id, issuer, subject, jsonb_strip_nulls(jsonb_build_object('email', email)), linked_at
+        FROM federated_identities
+        ORDER BY linked_at
+        ON CONFLICT DO NOTHING;
+
+        DROP TABLE federated_identities;
+    END IF;
+END $$;
+
+-- Down Migration
+-- ALTER TABLE users ADD COLUMN IF NOT EXISTS telegram_user_id VARCHAR(255);
+-- UPDATE users u SET telegram_user_id = i.subject FROM user_identities i WHERE i.user_id = u.id AND i.provider = 'telegram';
+-- DROP TABLE IF EXISTS user_identities;
@@ -0,0 +1,18 @@
+-- Migration: 00019_create_mfa_challenge_attempts_table
+-- Description: Cuenta los códigos probados con cada token mfa_pending para invalidar el reto tras varios fallos
+-- Created: 2026-10-17
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS mfa_challenge_attempts (
+    -- `jti` del token mfa_pending
+    challenge_id VARCHAR(64) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    attempts INTEGER NOT NULL,
+    -- Caducidad del token; a partir de ahí la fila se puede borrar
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_mfa_challenge_attempts_expires_at ON mfa_challenge_attempts(expires_at);
+
+-- Down Migration
+-- DROP TABLE IF EXISTS mfa_challenge_attempts;
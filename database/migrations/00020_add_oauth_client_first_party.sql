@@ -0,0 +1,10 @@
+-- Migration: 00020_add_oauth_client_first_party
+-- Description: Marca los clientes OAuth propios, los únicos que pueden usar el flujo de dispositivo
+-- Created: 2026-10-17
+
+-- Up Migration
+-- El flujo de dispositivo emite una sesión completa del usuario: sólo para aplicaciones propias
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS first_party BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Down Migration
+-- ALTER TABLE oauth_clients DROP COLUMN IF EXISTS first_party;
@@ -1,5 +1,4 @@
 pub mod error;
 pub mod pool;
-pub mod repository;
 
 pub mod migrations;
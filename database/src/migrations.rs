@@ -6,20 +6,84 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
     info!("Running database migrations");
     
     // Crear tabla de usuarios si no existe
-    pool.execute(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-            email VARCHAR(255) NOT NULL UNIQUE,
-            password VARCHAR(255) NOT NULL,
-            name VARCHAR(255),
-            role VARCHAR(50) NOT NULL DEFAULT 'user',
-            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        );
-        "#,
-    )
-    .await?;
+    pool.execute(include_str!("../migrations/00001_create_users_table.sql"))
+        .await?;
+
+    // Crear tabla de refresh tokens
+    pool.execute(include_str!("../migrations/00002_create_refresh_tokens_table.sql"))
+        .await?;
+
+    // Crear tabla de access tokens revocados
+    pool.execute(include_str!("../migrations/00003_create_revoked_tokens_table.sql"))
+        .await?;
+
+    // Crear tablas de roles y permisos
+    pool.execute(include_str!("../migrations/00004_create_permissions_tables.sql"))
+        .await?;
+
+    // Vínculos de Telegram duplicados de las bases de datos anteriores a user_identities
+    pool.execute(include_str!("../migrations/00005_audit_duplicate_telegram_links.sql"))
+        .await?;
+
+    // Verificación de email
+    pool.execute(include_str!("../migrations/00006_create_email_verification_tokens_table.sql"))
+        .await?;
+
+    // Recuperación de contraseña
+    pool.execute(include_str!("../migrations/00007_create_password_reset_tokens_table.sql"))
+        .await?;
+
+    // Versión de sesión de cada usuario
+    pool.execute(include_str!("../migrations/00008_add_token_version_to_users.sql"))
+        .await?;
+
+    // Autenticación en dos pasos (TOTP y códigos de recuperación)
+    pool.execute(include_str!("../migrations/00009_create_mfa_tables.sql"))
+        .await?;
+
+    // Passkeys (WebAuthn)
+    pool.execute(include_str!("../migrations/00010_create_webauthn_tables.sql"))
+        .await?;
+
+    // Intentos de login fallidos (backoff y bloqueo de cuentas)
+    pool.execute(include_str!("../migrations/00011_create_login_attempts_table.sql"))
+        .await?;
+
+    // Límites de peticiones compartidos entre instancias
+    pool.execute(include_str!("../migrations/00012_create_rate_limits_table.sql"))
+        .await?;
+
+    // Claves de API de los usuarios
+    pool.execute(include_str!("../migrations/00013_create_api_keys_table.sql"))
+        .await?;
+
+    // Clientes y autorizaciones del proveedor OAuth / OpenID Connect
+    pool.execute(include_str!("../migrations/00014_create_oauth_tables.sql"))
+        .await?;
+
+    // Grants por cliente OAuth (client credentials para servicios)
+    pool.execute(include_str!("../migrations/00015_add_oauth_client_grant_types.sql"))
+        .await?;
+
+    // Autorizaciones del flujo de dispositivo para CLIs
+    pool.execute(include_str!("../migrations/00016_create_oauth_device_authorizations_table.sql"))
+        .await?;
+
+    // Solicitudes de login con un proveedor OpenID Connect externo
+    pool.execute(include_str!("../migrations/00017_create_federated_login_tables.sql"))
+        .await?;
+
+    // Identidades externas de los usuarios (sustituye a users.telegram_user_id)
+    pool.execute(include_str!("../migrations/00018_create_user_identities_table.sql"))
+        .await?;
+
+    // Intentos de segundo factor por reto MFA
+    pool.execute(include_str!("../migrations/00019_create_mfa_challenge_attempts_table.sql"))
+        .await?;
+
+    // Clientes OAuth propios (flujo de dispositivo)
+    pool.execute(include_str!("../migrations/00020_add_oauth_client_first_party.sql"))
+        .await?;
     
     info!("Migrations completed successfully");
     
@@ -38,7 +102,7 @@ pub async fn ensure_database_exists(database_url: &str) -> Result<()> {
     
     if !Postgres::database_exists(&server_url).await? {
         info!("Creating database {}", db_name);
-        Postgres::create_database(&database_url).await?;
+        Postgres::create_database(database_url).await?;
     }
     
     Ok(())
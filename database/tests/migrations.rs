@@ -0,0 +1,43 @@
+use database::{migrations::run_migrations, pool::init_pool};
+use sqlx::Row;
+
+// Necesita una base de datos PostgreSQL vacía, p. ej.:
+// TEST_DATABASE_URL=postgres://postgres@localhost/auth_test cargo test -p database -- --ignored
+#[tokio::test]
+#[ignore = "necesita PostgreSQL (TEST_DATABASE_URL)"]
+async fn migrations_create_the_schema_and_can_run_again() {
+    let database_url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL");
+    let pool = init_pool(&database_url).await.unwrap();
+
+    // Se ejecutan en cada arranque, así que la segunda pasada tampoco debe fallar
+    run_migrations(&pool).await.unwrap();
+    run_migrations(&pool).await.unwrap();
+
+    for table in [
+        "users",
+        "refresh_tokens",
+        "revoked_tokens",
+        "roles",
+        "user_roles",
+        "login_attempts",
+        "api_keys",
+        "oauth_clients",
+        "oauth_device_authorizations",
+        "federated_login_requests",
+        "user_identities",
+        "mfa_challenge_attempts",
+    ] {
+        let row = sqlx::query("SELECT to_regclass($1)::text AS name").bind(table).fetch_one(&pool).await.unwrap();
+        assert_eq!(row.get::<Option<String>, _>("name").as_deref(), Some(table));
+    }
+
+    // La columna antigua de Telegram no vuelve a aparecer
+    let row = sqlx::query(
+        "SELECT COUNT(*) AS count FROM information_schema.columns
+         WHERE table_schema = current_schema() AND table_name = 'users' AND column_name = 'telegram_user_id'",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(row.get::<i64, _>("count"), 0);
+}
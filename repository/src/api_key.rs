@@ -0,0 +1,124 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Clave de API de un usuario. Sólo se guarda el hash SHA-256 de la clave completa;
+/// `prefix` es la parte pública con la que se localiza.
+#[derive(Debug, Clone, FromRow)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create_api_key(
+        &self,
+        user_id: &Uuid,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[String],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<ApiKey>;
+    /// Busca la clave por su prefijo, aunque esté revocada o caducada.
+    async fn find_api_key_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>>;
+    /// Claves no revocadas del usuario, de la más reciente a la más antigua.
+    async fn find_api_keys_for_user(&self, user_id: &Uuid) -> Result<Vec<ApiKey>>;
+    /// Revoca la clave si es del usuario y no estaba revocada. Devuelve si se revocó.
+    async fn revoke_api_key(&self, user_id: &Uuid, id: &Uuid) -> Result<bool>;
+    /// Actualiza `last_used_at`, como mucho una vez por minuto para no escribir en cada petición.
+    async fn touch_api_key(&self, id: &Uuid) -> Result<()>;
+}
+
+pub struct ApiKeyRepositoryImpl {
+    pool: PgPool,
+}
+
+impl ApiKeyRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for ApiKeyRepositoryImpl {
+    async fn create_api_key(
+        &self,
+        user_id: &Uuid,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[String],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<ApiKey> {
+        let api_key = sqlx::query_as::<_, ApiKey>(
+            "INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at) \
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+        )
+            .bind(user_id)
+            .bind(name)
+            .bind(prefix)
+            .bind(key_hash)
+            .bind(scopes)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(api_key)
+    }
+
+    async fn find_api_key_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>> {
+        let api_key = sqlx::query_as::<_, ApiKey>("SELECT * FROM api_keys WHERE prefix = $1")
+            .bind(prefix)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(api_key)
+    }
+
+    async fn find_api_keys_for_user(&self, user_id: &Uuid) -> Result<Vec<ApiKey>> {
+        let api_keys = sqlx::query_as::<_, ApiKey>(
+            "SELECT * FROM api_keys WHERE user_id = $1 AND revoked_at IS NULL ORDER BY created_at DESC",
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(api_keys)
+    }
+
+    async fn revoke_api_key(&self, user_id: &Uuid, id: &Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        )
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn touch_api_key(&self, id: &Uuid) -> Result<()> {
+        sqlx::query(
+            "UPDATE api_keys SET last_used_at = NOW() \
+             WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')",
+        )
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Token de verificación de email. Sólo se guarda el hash SHA-256 del valor enviado por correo.
+#[derive(Debug, Clone, FromRow)]
+pub struct EmailVerificationToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait EmailVerificationRepository: Send + Sync {
+    async fn create_verification_token(&self, user_id: &Uuid, token_hash: &str, expires_at: DateTime<Utc>) -> Result<EmailVerificationToken>;
+    /// Marca el token como usado si sigue vigente. Devuelve `None` si no existe, ya se usó o expiró.
+    async fn consume_verification_token(&self, token_hash: &str) -> Result<Option<EmailVerificationToken>>;
+    /// Último token emitido para el usuario, usado para limitar los reenvíos.
+    async fn find_latest_verification_token(&self, user_id: &Uuid) -> Result<Option<EmailVerificationToken>>;
+    /// Invalida los tokens pendientes del usuario y devuelve cuántos se invalidaron.
+    async fn invalidate_verification_tokens(&self, user_id: &Uuid) -> Result<u64>;
+}
+
+pub struct EmailVerificationRepositoryImpl {
+    pool: PgPool,
+}
+
+impl EmailVerificationRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EmailVerificationRepository for EmailVerificationRepositoryImpl {
+    async fn create_verification_token(&self, user_id: &Uuid, token_hash: &str, expires_at: DateTime<Utc>) -> Result<EmailVerificationToken> {
+        let token = sqlx::query_as::<_, EmailVerificationToken>(
+            "INSERT INTO email_verification_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3) RETURNING *",
+        )
+            .bind(user_id)
+            .bind(token_hash)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn consume_verification_token(&self, token_hash: &str) -> Result<Option<EmailVerificationToken>> {
+        // Un único UPDATE para que dos peticiones concurrentes no consuman el mismo token
+        let token = sqlx::query_as::<_, EmailVerificationToken>(
+            "UPDATE email_verification_tokens SET used_at = NOW() \
+             WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW() RETURNING *",
+        )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn find_latest_verification_token(&self, user_id: &Uuid) -> Result<Option<EmailVerificationToken>> {
+        let token = sqlx::query_as::<_, EmailVerificationToken>(
+            "SELECT * FROM email_verification_tokens WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1",
+        )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn invalidate_verification_tokens(&self, user_id: &Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE email_verification_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL",
+        )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
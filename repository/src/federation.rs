@@ -0,0 +1,76 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Login iniciado hacia el proveedor OpenID Connect externo, a la espera de que vuelva el usuario
+#[derive(Debug, Clone, FromRow)]
+pub struct FederatedLoginRequest {
+    pub id: Uuid,
+    pub state_hash: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait FederationRepository: Send + Sync {
+    async fn create_login_request(
+        &self,
+        state_hash: &str,
+        nonce: &str,
+        code_verifier: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<FederatedLoginRequest>;
+    /// Elimina y devuelve la solicitud si existe y no ha caducado (un solo uso).
+    async fn consume_login_request(&self, state_hash: &str) -> Result<Option<FederatedLoginRequest>>;
+}
+
+pub struct FederationRepositoryImpl {
+    pool: PgPool,
+}
+
+impl FederationRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl FederationRepository for FederationRepositoryImpl {
+    async fn create_login_request(
+        &self,
+        state_hash: &str,
+        nonce: &str,
+        code_verifier: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<FederatedLoginRequest> {
+        let request = sqlx::query_as::<_, FederatedLoginRequest>(
+            "INSERT INTO federated_login_requests (state_hash, nonce, code_verifier, expires_at) \
+             VALUES ($1, $2, $3, $4) RETURNING *",
+        )
+            .bind(state_hash)
+            .bind(nonce)
+            .bind(code_verifier)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(request)
+    }
+
+    async fn consume_login_request(&self, state_hash: &str) -> Result<Option<FederatedLoginRequest>> {
+        let request = sqlx::query_as::<_, FederatedLoginRequest>(
+            "DELETE FROM federated_login_requests \
+             WHERE state_hash = $1 AND expires_at > NOW() \
+             RETURNING *",
+        )
+            .bind(state_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(request)
+    }
+}
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Proveedor de las cuentas de Telegram; los proveedores OpenID Connect se identifican por su `iss`
+pub const IDENTITY_PROVIDER_TELEGRAM: &str = "telegram";
+
+/// Cuenta de un proveedor externo vinculada a un usuario local
+#[derive(Debug, Clone, FromRow)]
+pub struct UserIdentity {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub subject: String,
+    /// Datos informativos del proveedor (username, email con el que se vinculó...)
+    pub metadata: serde_json::Value,
+    pub linked_at: DateTime<Utc>,
+}
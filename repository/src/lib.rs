@@ -5,6 +5,36 @@ use chrono::{DateTime, Utc};
 use shared::user::{User, CreateUserSchema};
 use std::future::Future;
 
+pub mod api_key;
+pub mod email_verification;
+pub mod federation;
+pub mod identity;
+pub mod login_attempt;
+pub mod mfa;
+pub mod oauth;
+pub mod password_reset;
+pub mod permission;
+pub mod rate_limit;
+pub mod refresh_token;
+pub mod revocation;
+pub mod webauthn;
+pub use api_key::{ApiKey, ApiKeyRepository, ApiKeyRepositoryImpl};
+pub use email_verification::{EmailVerificationRepository, EmailVerificationRepositoryImpl, EmailVerificationToken};
+pub use federation::{FederatedLoginRequest, FederationRepository, FederationRepositoryImpl};
+pub use identity::{UserIdentity, IDENTITY_PROVIDER_TELEGRAM};
+pub use login_attempt::{LoginAttemptRepository, LoginAttemptRepositoryImpl, LoginAttempts};
+pub use mfa::{MfaRepository, MfaRepositoryImpl, TotpCredential};
+pub use oauth::{
+    DeviceAuthorization, NewDeviceAuthorization, NewOAuthAuthorization, NewOAuthClient, OAuthAuthorization, OAuthClient, OAuthRepository,
+    OAuthRepositoryImpl, DEVICE_STATUS_APPROVED, DEVICE_STATUS_DENIED, DEVICE_STATUS_PENDING,
+};
+pub use password_reset::{PasswordResetRepository, PasswordResetRepositoryImpl, PasswordResetToken};
+pub use permission::{PermissionRepository, PermissionRepositoryImpl};
+pub use rate_limit::{InMemoryRateLimitStore, PgRateLimitStore, RateLimitDecision, RateLimitPolicy, RateLimitState, RateLimitStore};
+pub use refresh_token::{RefreshToken, RefreshTokenRepository, RefreshTokenRepositoryImpl};
+pub use revocation::{InMemoryRevocationStore, PgRevocationStore, RevocationStore};
+pub use webauthn::{WebAuthnChallenge, WebAuthnCredential, WebAuthnRepository, WebAuthnRepositoryImpl};
+
 #[derive(FromRow)]
 struct UserRow {
     id: Uuid,
@@ -13,14 +43,45 @@ struct UserRow {
     name: String,
     role: String,
     telegram_user_id: Option<String>,
+    email_verified_at: Option<DateTime<Utc>>,
+    token_version: i32,
     created_at: Option<DateTime<Utc>>,
     updated_at: Option<DateTime<Utc>>,
 }
 
+// Columnas de `users` más el Telegram ID, que se guarda como identidad externa
+macro_rules! user_columns {
+    () => {
+        "users.*, (SELECT subject FROM user_identities \
+         WHERE user_identities.user_id = users.id AND provider = 'telegram') AS telegram_user_id"
+    };
+}
+
 pub trait UserRepository {
     fn find_user_by_id<'a>(&'a self, user_id: &'a Uuid) -> impl Future<Output = Result<User>> + Send + 'a; 
     fn find_user_by_email<'a>(&'a self, email: &'a str) -> impl Future<Output = Result<User>> + Send + 'a; 
     fn create_user<'a>(&'a self, user_data: &'a CreateUserSchema, hashed_password: &'a str, telegram_user_id: Option<String>) -> impl Future<Output = Result<User>> + Send + 'a; 
+    fn find_by_telegram_user_id<'a>(&'a self, telegram_user_id: &'a str) -> impl Future<Output = Result<User>> + Send + 'a {
+        self.find_by_identity(IDENTITY_PROVIDER_TELEGRAM, telegram_user_id)
+    }
+    /// Usuario vinculado a la cuenta `subject` del proveedor externo
+    fn find_by_identity<'a>(&'a self, provider: &'a str, subject: &'a str) -> impl Future<Output = Result<User>> + Send + 'a;
+    /// Falla con una violación de unicidad si la cuenta ya está vinculada o el usuario ya
+    /// tiene otra del mismo proveedor
+    fn link_identity<'a>(
+        &'a self,
+        user_id: &'a Uuid,
+        provider: &'a str,
+        subject: &'a str,
+        metadata: serde_json::Value,
+    ) -> impl Future<Output = Result<UserIdentity>> + Send + 'a;
+    /// Devuelve `false` si el usuario no tenía ninguna cuenta de ese proveedor
+    fn unlink_identity<'a>(&'a self, user_id: &'a Uuid, provider: &'a str) -> impl Future<Output = Result<bool>> + Send + 'a;
+    fn mark_email_verified<'a>(&'a self, user_id: &'a Uuid) -> impl Future<Output = Result<User>> + Send + 'a;
+    /// Guarda la contraseña nueva e incrementa `token_version`, invalidando los tokens emitidos
+    fn update_password<'a>(&'a self, user_id: &'a Uuid, hashed_password: &'a str) -> impl Future<Output = Result<User>> + Send + 'a;
+    /// Sustituye el hash de la misma contraseña (rehash) sin tocar `token_version`
+    fn update_password_hash<'a>(&'a self, user_id: &'a Uuid, hashed_password: &'a str) -> impl Future<Output = Result<User>> + Send + 'a;
 }
 
 pub struct UserRepositoryImpl {
@@ -34,74 +95,205 @@ impl UserRepositoryImpl {
 }
 
 impl UserRepository for UserRepositoryImpl {
-    fn find_user_by_id<'a>(&'a self, user_id: &'a Uuid) -> impl Future<Output = Result<User>> + Send + 'a {
-        async move {
-            let user = sqlx::query_as::<_, UserRow>("SELECT * FROM users WHERE id = $1")
-                .bind(user_id)
-                .fetch_one(&self.pool)
-                .await?;
-
-            Ok(User {
-                id: user.id,
-                email: user.email,
-                password: user.password,
-                name: Some(user.name),
-                telegram_user_id: user.telegram_user_id,
-                role: user.role,
-                created_at: user.created_at,
-                updated_at: user.updated_at,
-            })
-        }
+    async fn find_user_by_id<'a>(&'a self, user_id: &'a Uuid) -> Result<User> {
+        let user = sqlx::query_as::<_, UserRow>(concat!("SELECT ", user_columns!(), " FROM users WHERE id = $1"))
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(User {
+            id: user.id,
+            email: user.email,
+            password: user.password,
+            name: Some(user.name),
+            telegram_user_id: user.telegram_user_id,
+            role: user.role,
+            email_verified_at: user.email_verified_at,
+            token_version: user.token_version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        })
+    }
+
+    async fn find_user_by_email<'a>(&'a self, email: &'a str) -> Result<User> {
+        let user = sqlx::query_as::<_, UserRow>(concat!("SELECT ", user_columns!(), " FROM users WHERE email = $1"))
+            .bind(email)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(User {
+            id: user.id,
+            email: user.email,
+            password: user.password,
+            name: Some(user.name),
+            telegram_user_id: user.telegram_user_id,
+            role: user.role,
+            email_verified_at: user.email_verified_at,
+            token_version: user.token_version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        })
     }
 
-    fn find_user_by_email<'a>(&'a self, email: &'a str) -> impl Future<Output = Result<User>> + Send + 'a {
-        async move {
-            let user = sqlx::query_as::<_, UserRow>("SELECT * FROM users WHERE email = $1")
-                .bind(email)
-                .fetch_one(&self.pool)
-                .await?;
-
-            Ok(User {
-                id: user.id,
-                email: user.email,
-                password: user.password,
-                name: Some(user.name),
-                telegram_user_id: user.telegram_user_id,
-                role: user.role,
-                created_at: user.created_at,
-                updated_at: user.updated_at,
-            })
-        }
+    async fn find_by_identity<'a>(&'a self, provider: &'a str, subject: &'a str) -> Result<User> {
+        let user = sqlx::query_as::<_, UserRow>(concat!(
+            "SELECT ", user_columns!(), " FROM users \
+             JOIN user_identities identity ON identity.user_id = users.id \
+             WHERE identity.provider = $1 AND identity.subject = $2",
+        ))
+            .bind(provider)
+            .bind(subject)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(User {
+            id: user.id,
+            email: user.email,
+            password: user.password,
+            name: Some(user.name),
+            telegram_user_id: user.telegram_user_id,
+            role: user.role,
+            email_verified_at: user.email_verified_at,
+            token_version: user.token_version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        })
     }
 
-    fn create_user<'a>(
+    async fn link_identity<'a>(
+        &'a self,
+        user_id: &'a Uuid,
+        provider: &'a str,
+        subject: &'a str,
+        metadata: serde_json::Value,
+    ) -> Result<UserIdentity> {
+        let identity = sqlx::query_as::<_, UserIdentity>(
+            "INSERT INTO user_identities (user_id, provider, subject, metadata) \
+             VALUES ($1, $2, $3, $4) RETURNING *",
+        )
+            .bind(user_id)
+            .bind(provider)
+            .bind(subject)
+            .bind(metadata)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(identity)
+    }
+
+    async fn unlink_identity<'a>(&'a self, user_id: &'a Uuid, provider: &'a str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM user_identities WHERE user_id = $1 AND provider = $2")
+            .bind(user_id)
+            .bind(provider)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_email_verified<'a>(&'a self, user_id: &'a Uuid) -> Result<User> {
+        let user = sqlx::query_as::<_, UserRow>(
+            concat!("UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()), updated_at = NOW() WHERE id = $1 RETURNING ", user_columns!()),
+        )
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(User {
+            id: user.id,
+            email: user.email,
+            password: user.password,
+            name: Some(user.name),
+            telegram_user_id: user.telegram_user_id,
+            role: user.role,
+            email_verified_at: user.email_verified_at,
+            token_version: user.token_version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        })
+    }
+
+    async fn update_password<'a>(&'a self, user_id: &'a Uuid, hashed_password: &'a str) -> Result<User> {
+        let user = sqlx::query_as::<_, UserRow>(
+            concat!("UPDATE users SET password = $2, token_version = token_version + 1, updated_at = NOW() WHERE id = $1 RETURNING ", user_columns!()),
+        )
+            .bind(user_id)
+            .bind(hashed_password)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(User {
+            id: user.id,
+            email: user.email,
+            password: user.password,
+            name: Some(user.name),
+            telegram_user_id: user.telegram_user_id,
+            role: user.role,
+            email_verified_at: user.email_verified_at,
+            token_version: user.token_version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        })
+    }
+
+    async fn update_password_hash<'a>(&'a self, user_id: &'a Uuid, hashed_password: &'a str) -> Result<User> {
+        let user = sqlx::query_as::<_, UserRow>(
+            concat!("UPDATE users SET password = $2, updated_at = NOW() WHERE id = $1 RETURNING ", user_columns!()),
+        )
+            .bind(user_id)
+            .bind(hashed_password)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(User {
+            id: user.id,
+            email: user.email,
+            password: user.password,
+            name: Some(user.name),
+            telegram_user_id: user.telegram_user_id,
+            role: user.role,
+            email_verified_at: user.email_verified_at,
+            token_version: user.token_version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        })
+    }
+
+    async fn create_user<'a>(
         &'a self,
         user_data: &'a CreateUserSchema,
         hashed_password: &'a str,
         telegram_user_id: Option<String>,
-    ) -> impl Future<Output = Result<User>> + Send + 'a {
-        async move {
-            let user = sqlx::query_as::<_, UserRow>(
-                "INSERT INTO users (email, password, name, role, telegram_user_id) VALUES ($1, $2, $3, $4, $5) RETURNING *",
-            )
-                .bind(&user_data.email)
-                .bind(hashed_password)
-                .bind(&user_data.name)
-                .bind(&user_data.role)
-                .bind(&telegram_user_id)
-                .fetch_one(&self.pool)
-                .await?;
-
-            Ok(User {
-                id: user.id,
-                email: user.email,
-                password: user.password,
-                name: Some(user.name),
-                telegram_user_id: user.telegram_user_id,
-                role: user.role,
-                created_at: user.created_at,
-                updated_at: user.updated_at,
-            })
-        }
+    ) -> Result<User> {
+        // El usuario y su cuenta de Telegram se crean en la misma sentencia. Los usuarios nuevos
+        // siempre tienen el rol `user`; otros roles sólo los asigna un administrador.
+        let user = sqlx::query_as::<_, UserRow>(
+            "WITH new_user AS ( \
+                 INSERT INTO users (email, password, name, role) VALUES ($1, $2, $3, 'user') RETURNING * \
+             ), identity AS ( \
+                 INSERT INTO user_identities (user_id, provider, subject) \
+                 SELECT id, 'telegram', $4 FROM new_user WHERE $4::TEXT IS NOT NULL RETURNING subject \
+             ) \
+             SELECT new_user.*, (SELECT subject FROM identity) AS telegram_user_id FROM new_user",
+        )
+            .bind(&user_data.email)
+            .bind(hashed_password)
+            .bind(&user_data.name)
+            .bind(&telegram_user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(User {
+            id: user.id,
+            email: user.email,
+            password: user.password,
+            name: Some(user.name),
+            telegram_user_id: user.telegram_user_id,
+            role: user.role,
+            email_verified_at: user.email_verified_at,
+            token_version: user.token_version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        })
     }
 }
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Fallos de login consecutivos de una cuenta
+#[derive(Debug, Clone, FromRow)]
+pub struct LoginAttempts {
+    pub user_id: Uuid,
+    pub failed_count: i32,
+    pub last_failed_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait LoginAttemptRepository: Send + Sync {
+    async fn find_login_attempts(&self, user_id: &Uuid) -> Result<Option<LoginAttempts>>;
+    /// Cuenta un intento como fallo antes de comprobar la contraseña, sólo si el recuento sigue
+    /// siendo `seen_failed_count` (0 si no había fila). Devuelve `None` si otra petición
+    /// cambió el recuento entre medias.
+    async fn reserve_login_attempt(&self, user_id: &Uuid, seen_failed_count: i32) -> Result<Option<LoginAttempts>>;
+    /// Olvida los fallos de la cuenta (login correcto o desbloqueo manual).
+    async fn reset_login_attempts(&self, user_id: &Uuid) -> Result<()>;
+}
+
+pub struct LoginAttemptRepositoryImpl {
+    pool: PgPool,
+}
+
+impl LoginAttemptRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl LoginAttemptRepository for LoginAttemptRepositoryImpl {
+    async fn find_login_attempts(&self, user_id: &Uuid) -> Result<Option<LoginAttempts>> {
+        let attempts = sqlx::query_as::<_, LoginAttempts>("SELECT * FROM login_attempts WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(attempts)
+    }
+
+    async fn reserve_login_attempt(&self, user_id: &Uuid, seen_failed_count: i32) -> Result<Option<LoginAttempts>> {
+        let attempts = sqlx::query_as::<_, LoginAttempts>(
+            "INSERT INTO login_attempts (user_id, failed_count, last_failed_at) VALUES ($1, 1, NOW()) \
+             ON CONFLICT (user_id) DO UPDATE \
+             SET failed_count = login_attempts.failed_count + 1, last_failed_at = NOW() \
+             WHERE login_attempts.failed_count = $2 \
+             RETURNING *",
+        )
+            .bind(user_id)
+            .bind(seen_failed_count)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(attempts)
+    }
+
+    async fn reset_login_attempts(&self, user_id: &Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM login_attempts WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Secreto TOTP de un usuario. `enabled_at` es `None` hasta que se confirma la inscripción.
+#[derive(Debug, Clone, FromRow)]
+pub struct TotpCredential {
+    pub user_id: Uuid,
+    pub secret: String,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait MfaRepository: Send + Sync {
+    async fn find_totp(&self, user_id: &Uuid) -> Result<Option<TotpCredential>>;
+    /// Guarda un secreto pendiente de confirmar, sustituyendo cualquier inscripción sin confirmar.
+    async fn save_pending_totp(&self, user_id: &Uuid, secret: &str) -> Result<TotpCredential>;
+    /// Activa el TOTP pendiente y guarda los hashes de los códigos de recuperación en una
+    /// misma transacción. Devuelve `false` si no había inscripción pendiente.
+    async fn enable_totp(&self, user_id: &Uuid, used_step: i64, code_hashes: &[String]) -> Result<bool>;
+    /// Registra el paso usado. Devuelve `false` si ya se había usado ese paso o uno posterior.
+    async fn record_totp_step(&self, user_id: &Uuid, step: i64) -> Result<bool>;
+    /// Elimina el TOTP y los códigos de recuperación del usuario.
+    async fn delete_mfa(&self, user_id: &Uuid) -> Result<()>;
+    /// Marca un código de recuperación como usado. Devuelve `false` si no existe o ya se usó.
+    async fn consume_recovery_code(&self, user_id: &Uuid, code_hash: &str) -> Result<bool>;
+    /// Reserva un intento del reto `mfa_pending` identificado por `challenge_id` (el `jti`).
+    /// Devuelve `false` si el reto ya agotó sus `max_attempts` intentos.
+    async fn reserve_challenge_attempt(
+        &self,
+        challenge_id: &str,
+        user_id: &Uuid,
+        max_attempts: i32,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool>;
+}
+
+pub struct MfaRepositoryImpl {
+    pool: PgPool,
+}
+
+impl MfaRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl MfaRepository for MfaRepositoryImpl {
+    async fn find_totp(&self, user_id: &Uuid) -> Result<Option<TotpCredential>> {
+        let credential = sqlx::query_as::<_, TotpCredential>("SELECT * FROM user_totp WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(credential)
+    }
+
+    async fn save_pending_totp(&self, user_id: &Uuid, secret: &str) -> Result<TotpCredential> {
+        // Nunca sobrescribe un TOTP ya activado
+        let credential = sqlx::query_as::<_, TotpCredential>(
+            "INSERT INTO user_totp (user_id, secret) VALUES ($1, $2) \
+             ON CONFLICT (user_id) DO UPDATE SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW() \
+             WHERE user_totp.enabled_at IS NULL \
+             RETURNING *",
+        )
+            .bind(user_id)
+            .bind(secret)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(credential)
+    }
+
+    async fn enable_totp(&self, user_id: &Uuid, used_step: i64, code_hashes: &[String]) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE user_totp SET enabled_at = NOW(), last_used_step = $2 \
+             WHERE user_id = $1 AND enabled_at IS NULL",
+        )
+            .bind(user_id)
+            .bind(used_step)
+            .execute(&mut *tx)
+            .await?;
+        // Otra petición lo activó antes: se conservan sus códigos de recuperación
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        for code_hash in code_hashes {
+            sqlx::query("INSERT INTO mfa_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+                .bind(user_id)
+                .bind(code_hash)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn record_totp_step(&self, user_id: &Uuid, step: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE user_totp SET last_used_step = $2 \
+             WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)",
+        )
+            .bind(user_id)
+            .bind(step)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn delete_mfa(&self, user_id: &Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM user_totp WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn consume_recovery_code(&self, user_id: &Uuid, code_hash: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE mfa_recovery_codes SET used_at = NOW() \
+             WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+        )
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() >= 1)
+    }
+
+    async fn reserve_challenge_attempt(
+        &self,
+        challenge_id: &str,
+        user_id: &Uuid,
+        max_attempts: i32,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        // Los contadores de retos caducados se limpian al reservar otros intentos
+        sqlx::query("DELETE FROM mfa_challenge_attempts WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        // Sin fila devuelta el reto ya agotó sus intentos
+        let reserved = sqlx::query(
+            "INSERT INTO mfa_challenge_attempts (challenge_id, user_id, attempts, expires_at) \
+             VALUES ($1, $2, 1, $4) \
+             ON CONFLICT (challenge_id) DO UPDATE SET attempts = mfa_challenge_attempts.attempts + 1 \
+             WHERE mfa_challenge_attempts.attempts < $3 \
+             RETURNING attempts",
+        )
+            .bind(challenge_id)
+            .bind(user_id)
+            .bind(max_attempts)
+            .bind(expires_at)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(reserved.is_some())
+    }
+}
@@ -0,0 +1,339 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Cliente registrado en el proveedor OAuth. Los clientes públicos no tienen secreto.
+/// Con el grant `client_credentials`, los `scopes` son los permisos que puede pedir el servicio.
+#[derive(Debug, Clone, FromRow)]
+pub struct OAuthClient {
+    pub id: Uuid,
+    pub client_id: String,
+    pub client_secret_hash: Option<String>,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    /// Aplicación propia; sólo éstas pueden usar el flujo de dispositivo
+    pub first_party: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Solicitud de autorización. Mientras espera el consentimiento no tiene usuario ni código;
+/// al aprobarse guarda el hash del código y `expires_at` pasa a ser la caducidad del código.
+#[derive(Debug, Clone, FromRow)]
+pub struct OAuthAuthorization {
+    pub id: Uuid,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: String,
+    pub user_id: Option<Uuid>,
+    pub code_hash: Option<String>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Cliente ya validado, con el hash del secreto si es confidencial
+#[derive(Debug, Clone)]
+pub struct NewOAuthClient {
+    pub client_id: String,
+    pub client_secret_hash: Option<String>,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub first_party: bool,
+}
+
+/// Datos de `/oauth/authorize` ya validados, pendientes del consentimiento del usuario
+#[derive(Debug, Clone)]
+pub struct NewOAuthAuthorization {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Estados de una autorización de dispositivo
+pub const DEVICE_STATUS_PENDING: &str = "pending";
+pub const DEVICE_STATUS_APPROVED: &str = "approved";
+pub const DEVICE_STATUS_DENIED: &str = "denied";
+
+/// Autorización del flujo de dispositivo (RFC 8628): el dispositivo consulta con el
+/// `device_code` mientras el usuario aprueba el `user_code` desde otro navegador.
+#[derive(Debug, Clone, FromRow)]
+pub struct DeviceAuthorization {
+    pub id: Uuid,
+    pub client_id: String,
+    pub device_code_hash: String,
+    pub user_code: String,
+    pub scopes: Vec<String>,
+    pub status: String,
+    pub user_id: Option<Uuid>,
+    pub interval_secs: i32,
+    pub last_polled_at: Option<DateTime<Utc>>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewDeviceAuthorization {
+    pub client_id: String,
+    pub device_code_hash: String,
+    pub user_code: String,
+    pub scopes: Vec<String>,
+    pub interval_secs: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait OAuthRepository: Send + Sync {
+    async fn create_client(&self, client: &NewOAuthClient) -> Result<OAuthClient>;
+    async fn find_client(&self, client_id: &str) -> Result<Option<OAuthClient>>;
+    /// Clientes registrados, del más reciente al más antiguo.
+    async fn find_clients(&self) -> Result<Vec<OAuthClient>>;
+    async fn create_authorization(&self, authorization: &NewOAuthAuthorization) -> Result<OAuthAuthorization>;
+    /// Solicitud pendiente de consentimiento y no caducada.
+    async fn find_pending_authorization(&self, id: &Uuid) -> Result<Option<OAuthAuthorization>>;
+    /// Aprueba una solicitud pendiente para el usuario y guarda el hash del código.
+    /// Devuelve `None` si ya no estaba pendiente.
+    async fn approve_authorization(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+        code_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<OAuthAuthorization>>;
+    /// Borra una solicitud pendiente (consentimiento denegado) y la devuelve.
+    async fn delete_pending_authorization(&self, id: &Uuid) -> Result<Option<OAuthAuthorization>>;
+    /// Canjea un código: lo borra y lo devuelve si no había caducado, así que sólo vale una vez.
+    async fn consume_authorization_code(&self, code_hash: &str) -> Result<Option<OAuthAuthorization>>;
+    async fn create_device_authorization(&self, authorization: &NewDeviceAuthorization) -> Result<DeviceAuthorization>;
+    /// Autorización de dispositivo pendiente y no caducada con ese user_code.
+    async fn find_pending_device_authorization(&self, user_code: &str) -> Result<Option<DeviceAuthorization>>;
+    /// Aprueba (con el usuario) o deniega una autorización pendiente.
+    /// Devuelve `None` si ya no estaba pendiente.
+    async fn decide_device_authorization(
+        &self,
+        user_code: &str,
+        user_id: &Uuid,
+        status: &str,
+    ) -> Result<Option<DeviceAuthorization>>;
+    /// Autorización del device_code, caducada o no, para responder a la consulta del dispositivo.
+    async fn find_device_authorization(&self, device_code_hash: &str) -> Result<Option<DeviceAuthorization>>;
+    /// Registra una consulta del dispositivo y el intervalo que debe respetar desde ahora.
+    async fn record_device_poll(&self, id: &Uuid, interval_secs: i32) -> Result<()>;
+    /// Borra la autorización y la devuelve si seguía en ese estado, así que sólo se canjea una vez.
+    async fn delete_device_authorization(&self, id: &Uuid, status: &str) -> Result<Option<DeviceAuthorization>>;
+}
+
+pub struct OAuthRepositoryImpl {
+    pool: PgPool,
+}
+
+impl OAuthRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OAuthRepository for OAuthRepositoryImpl {
+    async fn create_client(&self, client: &NewOAuthClient) -> Result<OAuthClient> {
+        let client = sqlx::query_as::<_, OAuthClient>(
+            "INSERT INTO oauth_clients (client_id, client_secret_hash, name, redirect_uris, scopes, grant_types, first_party) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *",
+        )
+            .bind(&client.client_id)
+            .bind(&client.client_secret_hash)
+            .bind(&client.name)
+            .bind(&client.redirect_uris)
+            .bind(&client.scopes)
+            .bind(&client.grant_types)
+            .bind(client.first_party)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(client)
+    }
+
+    async fn find_client(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+        let client = sqlx::query_as::<_, OAuthClient>("SELECT * FROM oauth_clients WHERE client_id = $1")
+            .bind(client_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(client)
+    }
+
+    async fn find_clients(&self) -> Result<Vec<OAuthClient>> {
+        let clients = sqlx::query_as::<_, OAuthClient>("SELECT * FROM oauth_clients ORDER BY created_at DESC")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(clients)
+    }
+
+    async fn create_authorization(&self, authorization: &NewOAuthAuthorization) -> Result<OAuthAuthorization> {
+        let authorization = sqlx::query_as::<_, OAuthAuthorization>(
+            "INSERT INTO oauth_authorizations (client_id, redirect_uri, scopes, state, nonce, code_challenge, expires_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *",
+        )
+            .bind(&authorization.client_id)
+            .bind(&authorization.redirect_uri)
+            .bind(&authorization.scopes)
+            .bind(&authorization.state)
+            .bind(&authorization.nonce)
+            .bind(&authorization.code_challenge)
+            .bind(authorization.expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+
+    async fn find_pending_authorization(&self, id: &Uuid) -> Result<Option<OAuthAuthorization>> {
+        let authorization = sqlx::query_as::<_, OAuthAuthorization>(
+            "SELECT * FROM oauth_authorizations WHERE id = $1 AND code_hash IS NULL AND expires_at > NOW()",
+        )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+
+    async fn approve_authorization(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+        code_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<OAuthAuthorization>> {
+        // Un único UPDATE para que la misma solicitud no se apruebe dos veces
+        let authorization = sqlx::query_as::<_, OAuthAuthorization>(
+            "UPDATE oauth_authorizations SET user_id = $2, code_hash = $3, approved_at = NOW(), expires_at = $4 \
+             WHERE id = $1 AND code_hash IS NULL AND expires_at > NOW() RETURNING *",
+        )
+            .bind(id)
+            .bind(user_id)
+            .bind(code_hash)
+            .bind(expires_at)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+
+    async fn delete_pending_authorization(&self, id: &Uuid) -> Result<Option<OAuthAuthorization>> {
+        let authorization = sqlx::query_as::<_, OAuthAuthorization>(
+            "DELETE FROM oauth_authorizations WHERE id = $1 AND code_hash IS NULL AND expires_at > NOW() RETURNING *",
+        )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+
+    async fn consume_authorization_code(&self, code_hash: &str) -> Result<Option<OAuthAuthorization>> {
+        let authorization = sqlx::query_as::<_, OAuthAuthorization>(
+            "DELETE FROM oauth_authorizations WHERE code_hash = $1 AND expires_at > NOW() RETURNING *",
+        )
+            .bind(code_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+
+    async fn create_device_authorization(&self, authorization: &NewDeviceAuthorization) -> Result<DeviceAuthorization> {
+        let authorization = sqlx::query_as::<_, DeviceAuthorization>(
+            "INSERT INTO oauth_device_authorizations (client_id, device_code_hash, user_code, scopes, interval_secs, expires_at) \
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+        )
+            .bind(&authorization.client_id)
+            .bind(&authorization.device_code_hash)
+            .bind(&authorization.user_code)
+            .bind(&authorization.scopes)
+            .bind(authorization.interval_secs)
+            .bind(authorization.expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+
+    async fn find_pending_device_authorization(&self, user_code: &str) -> Result<Option<DeviceAuthorization>> {
+        let authorization = sqlx::query_as::<_, DeviceAuthorization>(
+            "SELECT * FROM oauth_device_authorizations WHERE user_code = $1 AND status = 'pending' AND expires_at > NOW()",
+        )
+            .bind(user_code)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+
+    async fn decide_device_authorization(
+        &self,
+        user_code: &str,
+        user_id: &Uuid,
+        status: &str,
+    ) -> Result<Option<DeviceAuthorization>> {
+        let authorization = sqlx::query_as::<_, DeviceAuthorization>(
+            "UPDATE oauth_device_authorizations SET status = $3, user_id = $2, \
+             approved_at = CASE WHEN $3 = 'approved' THEN NOW() END \
+             WHERE user_code = $1 AND status = 'pending' AND expires_at > NOW() RETURNING *",
+        )
+            .bind(user_code)
+            .bind(user_id)
+            .bind(status)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+
+    async fn find_device_authorization(&self, device_code_hash: &str) -> Result<Option<DeviceAuthorization>> {
+        let authorization = sqlx::query_as::<_, DeviceAuthorization>(
+            "SELECT * FROM oauth_device_authorizations WHERE device_code_hash = $1",
+        )
+            .bind(device_code_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+
+    async fn record_device_poll(&self, id: &Uuid, interval_secs: i32) -> Result<()> {
+        sqlx::query("UPDATE oauth_device_authorizations SET last_polled_at = NOW(), interval_secs = $2 WHERE id = $1")
+            .bind(id)
+            .bind(interval_secs)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_device_authorization(&self, id: &Uuid, status: &str) -> Result<Option<DeviceAuthorization>> {
+        let authorization = sqlx::query_as::<_, DeviceAuthorization>(
+            "DELETE FROM oauth_device_authorizations WHERE id = $1 AND status = $2 RETURNING *",
+        )
+            .bind(id)
+            .bind(status)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(authorization)
+    }
+}
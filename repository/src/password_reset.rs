@@ -0,0 +1,88 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Token de recuperación de contraseña. Sólo se guarda el hash SHA-256 del valor enviado por correo.
+#[derive(Debug, Clone, FromRow)]
+pub struct PasswordResetToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait PasswordResetRepository: Send + Sync {
+    async fn create_reset_token(&self, user_id: &Uuid, token_hash: &str, expires_at: DateTime<Utc>) -> Result<PasswordResetToken>;
+    /// Busca un token vigente sin consumirlo.
+    async fn find_reset_token(&self, token_hash: &str) -> Result<Option<PasswordResetToken>>;
+    /// Marca el token como usado si sigue vigente. Devuelve `None` si no existe, ya se usó o expiró.
+    async fn consume_reset_token(&self, token_hash: &str) -> Result<Option<PasswordResetToken>>;
+    /// Invalida los tokens pendientes del usuario y devuelve cuántos se invalidaron.
+    async fn invalidate_reset_tokens(&self, user_id: &Uuid) -> Result<u64>;
+}
+
+pub struct PasswordResetRepositoryImpl {
+    pool: PgPool,
+}
+
+impl PasswordResetRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PasswordResetRepository for PasswordResetRepositoryImpl {
+    async fn create_reset_token(&self, user_id: &Uuid, token_hash: &str, expires_at: DateTime<Utc>) -> Result<PasswordResetToken> {
+        let token = sqlx::query_as::<_, PasswordResetToken>(
+            "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3) RETURNING *",
+        )
+            .bind(user_id)
+            .bind(token_hash)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn find_reset_token(&self, token_hash: &str) -> Result<Option<PasswordResetToken>> {
+        let token = sqlx::query_as::<_, PasswordResetToken>(
+            "SELECT * FROM password_reset_tokens WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()",
+        )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn consume_reset_token(&self, token_hash: &str) -> Result<Option<PasswordResetToken>> {
+        // Un único UPDATE para que dos peticiones concurrentes no consuman el mismo token
+        let token = sqlx::query_as::<_, PasswordResetToken>(
+            "UPDATE password_reset_tokens SET used_at = NOW() \
+             WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW() RETURNING *",
+        )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn invalidate_reset_tokens(&self, user_id: &Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL",
+        )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
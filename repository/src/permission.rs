@@ -0,0 +1,90 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[async_trait]
+pub trait PermissionRepository: Send + Sync {
+    /// Nombres de los permisos efectivos del usuario: los de sus roles en `user_roles`
+    /// más los del rol indicado en la columna `users.role`.
+    async fn find_permissions_for_user(&self, user_id: &Uuid) -> Result<Vec<String>>;
+    async fn find_roles_for_user(&self, user_id: &Uuid) -> Result<Vec<String>>;
+    async fn assign_role_to_user(&self, user_id: &Uuid, role_name: &str) -> Result<()>;
+    async fn remove_role_from_user(&self, user_id: &Uuid, role_name: &str) -> Result<()>;
+}
+
+pub struct PermissionRepositoryImpl {
+    pool: PgPool,
+}
+
+impl PermissionRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PermissionRepository for PermissionRepositoryImpl {
+    async fn find_permissions_for_user(&self, user_id: &Uuid) -> Result<Vec<String>> {
+        let permissions = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT DISTINCT p.name
+            FROM permissions p
+            JOIN role_permissions rp ON rp.permission_id = p.id
+            JOIN roles r ON r.id = rp.role_id
+            WHERE r.id IN (SELECT role_id FROM user_roles WHERE user_id = $1)
+               OR r.name = (SELECT role FROM users WHERE id = $1)
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(permissions)
+    }
+
+    async fn find_roles_for_user(&self, user_id: &Uuid) -> Result<Vec<String>> {
+        let roles = sqlx::query_scalar::<_, String>(
+            "SELECT r.name FROM roles r JOIN user_roles ur ON ur.role_id = r.id WHERE ur.user_id = $1 ORDER BY r.name",
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(roles)
+    }
+
+    async fn assign_role_to_user(&self, user_id: &Uuid, role_name: &str) -> Result<()> {
+        let result = sqlx::query(
+            "INSERT INTO user_roles (user_id, role_id) SELECT $1, id FROM roles WHERE name = $2 ON CONFLICT DO NOTHING",
+        )
+            .bind(user_id)
+            .bind(role_name)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1)")
+                .bind(role_name)
+                .fetch_one(&self.pool)
+                .await?;
+            if !exists {
+                return Err(anyhow::anyhow!("Role not found: {}", role_name));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn remove_role_from_user(&self, user_id: &Uuid, role_name: &str) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM user_roles WHERE user_id = $1 AND role_id = (SELECT id FROM roles WHERE name = $2)",
+        )
+            .bind(user_id)
+            .bind(role_name)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{FromRow, PgPool};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+/// Política de un límite de peticiones. Ambas se expresan como "N peticiones por periodo".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitPolicy {
+    /// Admite ráfagas de hasta `capacity` peticiones; se recupera una ficha cada `period / capacity`.
+    TokenBucket { capacity: u32, period: Duration },
+    /// Como mucho `limit` peticiones en cualquier ventana de `window`, estimadas con el
+    /// contador de la ventana actual y el de la anterior ponderado por su solapamiento.
+    SlidingWindow { limit: u32, window: Duration },
+}
+
+/// Estado guardado de una clave. El significado de los campos depende de la política:
+/// en el token bucket `value` son las fichas disponibles y `started_at` la última recarga;
+/// en la ventana deslizante `value` y `previous` son las peticiones de la ventana actual
+/// y de la anterior, y `started_at` el inicio de la actual.
+#[derive(Debug, Clone, FromRow)]
+pub struct RateLimitState {
+    pub value: f64,
+    pub previous: f64,
+    pub started_at: DateTime<Utc>,
+    /// A partir de este momento el estado equivale a no tener peticiones registradas
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Resultado de registrar una petición, con lo necesario para las cabeceras `RateLimit-*`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Segundos hasta que el límite se recupera por completo
+    pub reset_after: i64,
+    /// Segundos hasta que se admitirá otra petición, si ésta se rechazó
+    pub retry_after: Option<i64>,
+}
+
+impl RateLimitPolicy {
+    pub fn limit(&self) -> u32 {
+        match *self {
+            RateLimitPolicy::TokenBucket { capacity, .. } => capacity,
+            RateLimitPolicy::SlidingWindow { limit, .. } => limit,
+        }
+    }
+
+    /// Registra una petición en `now` sobre el estado anterior y devuelve el estado nuevo
+    /// y la decisión. Es la misma lógica para todos los almacenes.
+    pub fn apply(&self, state: Option<&RateLimitState>, now: DateTime<Utc>) -> (RateLimitState, RateLimitDecision) {
+        let state = state.filter(|state| state.expires_at > now);
+        match *self {
+            RateLimitPolicy::TokenBucket { capacity, period } => token_bucket(capacity, period, state, now),
+            RateLimitPolicy::SlidingWindow { limit, window } => sliding_window(limit, window, state, now),
+        }
+    }
+}
+
+fn token_bucket(capacity: u32, period: Duration, state: Option<&RateLimitState>, now: DateTime<Utc>) -> (RateLimitState, RateLimitDecision) {
+    let capacity_f = f64::from(capacity);
+    // Fichas por milisegundo
+    let rate = capacity_f / period.num_milliseconds().max(1) as f64;
+
+    let mut tokens = match state {
+        Some(state) => {
+            let elapsed = (now - state.started_at).num_milliseconds().max(0) as f64;
+            (state.value + elapsed * rate).min(capacity_f)
+        }
+        None => capacity_f,
+    };
+
+    let allowed = tokens >= 1.0;
+    let retry_after = if allowed {
+        tokens -= 1.0;
+        None
+    } else {
+        Some(ms_to_secs((1.0 - tokens) / rate))
+    };
+    let until_full = (capacity_f - tokens) / rate;
+
+    let new_state = RateLimitState {
+        value: tokens,
+        previous: 0.0,
+        started_at: now,
+        expires_at: now + Duration::milliseconds(until_full.ceil() as i64),
+    };
+    let decision = RateLimitDecision {
+        allowed,
+        limit: capacity,
+        remaining: tokens.floor() as u32,
+        reset_after: ms_to_secs(until_full),
+        retry_after,
+    };
+    (new_state, decision)
+}
+
+fn sliding_window(limit: u32, window: Duration, state: Option<&RateLimitState>, now: DateTime<Utc>) -> (RateLimitState, RateLimitDecision) {
+    let limit_f = f64::from(limit);
+    let window_ms = window.num_milliseconds().max(1) as f64;
+
+    let (mut current, mut previous, mut started_at) = match state {
+        Some(state) => (state.value, state.previous, state.started_at),
+        None => (0.0, 0.0, now),
+    };
+    let elapsed = (now - started_at).num_milliseconds().max(0) as f64;
+    if elapsed >= 2.0 * window_ms {
+        (current, previous, started_at) = (0.0, 0.0, now);
+    } else if elapsed >= window_ms {
+        (current, previous, started_at) = (0.0, current, started_at + window);
+    }
+
+    let into_window = (now - started_at).num_milliseconds().max(0) as f64;
+    let estimated = previous * (1.0 - into_window / window_ms) + current;
+
+    let allowed = estimated + 1.0 <= limit_f;
+    let retry_after = if allowed {
+        current += 1.0;
+        None
+    } else if current + 1.0 <= limit_f {
+        // Basta con que pese menos la ventana anterior
+        let weight = (limit_f - current - 1.0) / previous;
+        Some(ms_to_secs((1.0 - weight) * window_ms - into_window))
+    } else {
+        // Hay que esperar a la ventana siguiente, en la que la actual pasa a ser la anterior
+        let weight = (limit_f - 1.0).max(0.0) / current;
+        Some(ms_to_secs(window_ms - into_window + (1.0 - weight) * window_ms))
+    };
+    let remaining = (limit_f - previous * (1.0 - into_window / window_ms) - current).max(0.0);
+
+    let new_state = RateLimitState {
+        value: current,
+        previous,
+        started_at,
+        expires_at: started_at + window + window,
+    };
+    let decision = RateLimitDecision {
+        allowed,
+        limit,
+        remaining: remaining.floor() as u32,
+        reset_after: ms_to_secs(2.0 * window_ms - into_window),
+        retry_after,
+    };
+    (new_state, decision)
+}
+
+// Redondea hacia arriba para no invitar a reintentar antes de tiempo
+fn ms_to_secs(ms: f64) -> i64 {
+    ((ms / 1000.0).ceil() as i64).max(1)
+}
+
+/// Almacén del estado de los límites de peticiones.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Registra una petición para la clave según la política y devuelve si se admite.
+    async fn check(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision>;
+    /// Elimina los estados ya expirados y devuelve cuántos se borraron.
+    async fn prune_expired(&self) -> Result<u64>;
+}
+
+/// Implementación en PostgreSQL, compartida por todas las instancias del servicio.
+pub struct PgRateLimitStore {
+    pool: PgPool,
+}
+
+impl PgRateLimitStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for PgRateLimitStore {
+    async fn check(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision> {
+        let mut tx = self.pool.begin().await?;
+
+        // Serializa las peticiones de la misma clave, incluida la primera (aún sin fila)
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(key)
+            .execute(&mut *tx)
+            .await?;
+        // La hora de la base de datos evita depender del reloj de cada instancia
+        let now: DateTime<Utc> = sqlx::query_scalar("SELECT NOW()")
+            .fetch_one(&mut *tx)
+            .await?;
+        let state = sqlx::query_as::<_, RateLimitState>(
+            "SELECT value, previous, started_at, expires_at FROM rate_limits WHERE key = $1",
+        )
+            .bind(key)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let (state, decision) = policy.apply(state.as_ref(), now);
+
+        sqlx::query(
+            "INSERT INTO rate_limits (key, value, previous, started_at, expires_at) VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value, previous = EXCLUDED.previous, \
+             started_at = EXCLUDED.started_at, expires_at = EXCLUDED.expires_at",
+        )
+            .bind(key)
+            .bind(state.value)
+            .bind(state.previous)
+            .bind(state.started_at)
+            .bind(state.expires_at)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(decision)
+    }
+
+    async fn prune_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM rate_limits WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Implementación en memoria, válida para una sola instancia o para pruebas.
+#[derive(Default)]
+pub struct InMemoryRateLimitStore {
+    entries: Mutex<HashMap<String, RateLimitState>>,
+}
+
+impl InMemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn check(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision> {
+        let mut entries = self.entries.lock().unwrap();
+        let (state, decision) = policy.apply(entries.get(key), Utc::now());
+        entries.insert(key.to_string(), state);
+        Ok(decision)
+    }
+
+    async fn prune_expired(&self) -> Result<u64> {
+        let now = Utc::now();
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|_, state| state.expires_at > now);
+        Ok((before - entries.len()) as u64)
+    }
+}
+
+/// Lanza una tarea en segundo plano que purga periódicamente los estados expirados.
+pub fn spawn_pruning_task(store: Arc<dyn RateLimitStore>, every: std::time::Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            match store.prune_expired().await {
+                Ok(removed) => debug!("Límites de peticiones expirados eliminados: {}", removed),
+                Err(e) => error!("Error al purgar límites de peticiones expirados: {}", e),
+            }
+        }
+    })
+}
@@ -0,0 +1,101 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Refresh token persistido. Sólo se guarda el hash SHA-256 del valor entregado al cliente.
+/// Todos los tokens obtenidos a partir de un mismo login comparten `family_id`.
+#[derive(Debug, Clone, FromRow)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub family_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub replaced_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait RefreshTokenRepository: Send + Sync {
+    async fn create_refresh_token(&self, user_id: &Uuid, family_id: &Uuid, token_hash: &str, expires_at: DateTime<Utc>) -> Result<RefreshToken>;
+    async fn find_refresh_token_by_hash(&self, token_hash: &str) -> Result<Option<RefreshToken>>;
+    /// Revoca un token concreto. Devuelve `false` si ya estaba revocado.
+    async fn revoke_refresh_token(&self, id: &Uuid, replaced_by: Option<&Uuid>) -> Result<bool>;
+    /// Revoca todos los tokens aún activos de una familia y devuelve cuántos se revocaron.
+    async fn revoke_refresh_token_family(&self, family_id: &Uuid) -> Result<u64>;
+    /// Revoca todos los tokens aún activos del usuario (todas sus sesiones).
+    async fn revoke_user_refresh_tokens(&self, user_id: &Uuid) -> Result<u64>;
+}
+
+pub struct RefreshTokenRepositoryImpl {
+    pool: PgPool,
+}
+
+impl RefreshTokenRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for RefreshTokenRepositoryImpl {
+    async fn create_refresh_token(&self, user_id: &Uuid, family_id: &Uuid, token_hash: &str, expires_at: DateTime<Utc>) -> Result<RefreshToken> {
+        let token = sqlx::query_as::<_, RefreshToken>(
+            "INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at) VALUES ($1, $2, $3, $4) RETURNING *",
+        )
+            .bind(user_id)
+            .bind(family_id)
+            .bind(token_hash)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn find_refresh_token_by_hash(&self, token_hash: &str) -> Result<Option<RefreshToken>> {
+        let token = sqlx::query_as::<_, RefreshToken>("SELECT * FROM refresh_tokens WHERE token_hash = $1")
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn revoke_refresh_token(&self, id: &Uuid, replaced_by: Option<&Uuid>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = NOW(), replaced_by = $2 WHERE id = $1 AND revoked_at IS NULL",
+        )
+            .bind(id)
+            .bind(replaced_by)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: &Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+        )
+            .bind(family_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: &Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+/// Almacén de identificadores (`jti`) de access tokens revocados antes de su expiración.
+/// Cada entrada se conserva sólo hasta `expires_at`: pasado ese momento el token
+/// ya es rechazado por su propia expiración y la entrada puede eliminarse.
+#[async_trait]
+pub trait RevocationStore: Send + Sync {
+    async fn revoke(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<()>;
+    async fn is_revoked(&self, jti: &str) -> Result<bool>;
+    /// Elimina las entradas ya expiradas y devuelve cuántas se borraron.
+    async fn prune_expired(&self) -> Result<u64>;
+}
+
+pub struct PgRevocationStore {
+    pool: PgPool,
+}
+
+impl PgRevocationStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RevocationStore for PgRevocationStore {
+    async fn revoke(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO revoked_tokens (jti, expires_at) VALUES ($1, $2) ON CONFLICT (jti) DO NOTHING",
+        )
+            .bind(jti)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool> {
+        let revoked: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM revoked_tokens WHERE jti = $1 AND expires_at > NOW())",
+        )
+            .bind(jti)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(revoked)
+    }
+
+    async fn prune_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Implementación en memoria, útil para una sola instancia o para pruebas.
+#[derive(Default)]
+pub struct InMemoryRevocationStore {
+    entries: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl InMemoryRevocationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RevocationStore for InMemoryRevocationStore {
+    async fn revoke(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<()> {
+        self.entries.lock().unwrap().insert(jti.to_string(), expires_at);
+        Ok(())
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool> {
+        let entries = self.entries.lock().unwrap();
+        Ok(entries.get(jti).is_some_and(|expires_at| *expires_at > Utc::now()))
+    }
+
+    async fn prune_expired(&self) -> Result<u64> {
+        let now = Utc::now();
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|_, expires_at| *expires_at > now);
+        Ok((before - entries.len()) as u64)
+    }
+}
+
+/// Lanza una tarea en segundo plano que purga periódicamente las revocaciones expiradas.
+pub fn spawn_pruning_task(store: Arc<dyn RevocationStore>, every: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            match store.prune_expired().await {
+                Ok(removed) => debug!("Revocaciones expiradas eliminadas: {}", removed),
+                Err(e) => error!("Error al purgar revocaciones expiradas: {}", e),
+            }
+        }
+    })
+}
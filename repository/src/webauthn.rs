@@ -0,0 +1,168 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Passkey registrada por un usuario
+#[derive(Debug, Clone, FromRow)]
+pub struct WebAuthnCredential {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub credential_id: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+    pub name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Challenge emitido para una ceremonia de registro (`registration`) o de login (`authentication`)
+#[derive(Debug, Clone, FromRow)]
+pub struct WebAuthnChallenge {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub ceremony: String,
+    pub challenge: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait WebAuthnRepository: Send + Sync {
+    async fn create_challenge(
+        &self,
+        user_id: Option<&Uuid>,
+        ceremony: &str,
+        challenge: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<WebAuthnChallenge>;
+    /// Elimina y devuelve el challenge si existe, es de esa ceremonia y no ha caducado (un solo uso).
+    async fn consume_challenge(&self, id: &Uuid, ceremony: &str) -> Result<Option<WebAuthnChallenge>>;
+    async fn create_credential(
+        &self,
+        user_id: &Uuid,
+        credential_id: &str,
+        public_key: &[u8],
+        sign_count: i64,
+        name: Option<&str>,
+    ) -> Result<WebAuthnCredential>;
+    async fn find_credential(&self, credential_id: &str) -> Result<Option<WebAuthnCredential>>;
+    async fn find_credentials_for_user(&self, user_id: &Uuid) -> Result<Vec<WebAuthnCredential>>;
+    /// Guarda el contador de firmas si avanza (o si sigue en 0, como en las passkeys
+    /// sincronizadas). Devuelve `false` si el contador no avanzó.
+    async fn update_sign_count(&self, id: &Uuid, sign_count: i64) -> Result<bool>;
+}
+
+pub struct WebAuthnRepositoryImpl {
+    pool: PgPool,
+}
+
+impl WebAuthnRepositoryImpl {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl WebAuthnRepository for WebAuthnRepositoryImpl {
+    async fn create_challenge(
+        &self,
+        user_id: Option<&Uuid>,
+        ceremony: &str,
+        challenge: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<WebAuthnChallenge> {
+        // Los challenges abandonados se limpian al crear otros nuevos
+        sqlx::query("DELETE FROM webauthn_challenges WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        let challenge = sqlx::query_as::<_, WebAuthnChallenge>(
+            "INSERT INTO webauthn_challenges (user_id, ceremony, challenge, expires_at) \
+             VALUES ($1, $2, $3, $4) RETURNING *",
+        )
+            .bind(user_id)
+            .bind(ceremony)
+            .bind(challenge)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(challenge)
+    }
+
+    async fn consume_challenge(&self, id: &Uuid, ceremony: &str) -> Result<Option<WebAuthnChallenge>> {
+        let challenge = sqlx::query_as::<_, WebAuthnChallenge>(
+            "DELETE FROM webauthn_challenges \
+             WHERE id = $1 AND ceremony = $2 AND expires_at > NOW() \
+             RETURNING *",
+        )
+            .bind(id)
+            .bind(ceremony)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(challenge)
+    }
+
+    async fn create_credential(
+        &self,
+        user_id: &Uuid,
+        credential_id: &str,
+        public_key: &[u8],
+        sign_count: i64,
+        name: Option<&str>,
+    ) -> Result<WebAuthnCredential> {
+        let credential = sqlx::query_as::<_, WebAuthnCredential>(
+            "INSERT INTO webauthn_credentials (user_id, credential_id, public_key, sign_count, name) \
+             VALUES ($1, $2, $3, $4, $5) RETURNING *",
+        )
+            .bind(user_id)
+            .bind(credential_id)
+            .bind(public_key)
+            .bind(sign_count)
+            .bind(name)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(credential)
+    }
+
+    async fn find_credential(&self, credential_id: &str) -> Result<Option<WebAuthnCredential>> {
+        let credential = sqlx::query_as::<_, WebAuthnCredential>(
+            "SELECT * FROM webauthn_credentials WHERE credential_id = $1",
+        )
+            .bind(credential_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(credential)
+    }
+
+    async fn find_credentials_for_user(&self, user_id: &Uuid) -> Result<Vec<WebAuthnCredential>> {
+        let credentials = sqlx::query_as::<_, WebAuthnCredential>(
+            "SELECT * FROM webauthn_credentials WHERE user_id = $1 ORDER BY created_at",
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(credentials)
+    }
+
+    async fn update_sign_count(&self, id: &Uuid, sign_count: i64) -> Result<bool> {
+        // La comparación va en el UPDATE para que dos aserciones simultáneas con el mismo
+        // contador no pasen las dos
+        let result = sqlx::query(
+            "UPDATE webauthn_credentials SET sign_count = $2, last_used_at = NOW() \
+             WHERE id = $1 AND (sign_count < $2 OR (sign_count = 0 AND $2 = 0))",
+        )
+            .bind(id)
+            .bind(sign_count)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -1,11 +1,19 @@
 // src/main.rs
 use std::sync::Arc;
 use api::routes::create_router;
-use auth::service::AuthService as AuthServiceImpl;
+use auth::service::{AuthService as AuthServiceImpl, AuthSettings, AuthStores};
 use common::config::AppConfig;
-use api::AppState;
+use common::jwt::JwtConfig;
+use common::mailer::LogMailer;
+use api::{middleware::rate_limit::RateLimiter, AppState};
 use database::pool;
-use database::repository::PgUserRepository;
+use repository::{
+    rate_limit, revocation::spawn_pruning_task, ApiKeyRepositoryImpl, EmailVerificationRepositoryImpl, FederationRepositoryImpl, InMemoryRateLimitStore,
+    LoginAttemptRepositoryImpl, MfaRepositoryImpl, OAuthRepositoryImpl, PasswordResetRepositoryImpl, PermissionRepositoryImpl, PgRateLimitStore,
+    PgRevocationStore, RateLimitStore, RefreshTokenRepositoryImpl, RevocationStore, UserRepositoryImpl, WebAuthnRepositoryImpl,
+};
+use std::net::SocketAddr;
+use std::time::Duration;
 use tracing::{info, Level};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 use tower_http::trace::{self, TraceLayer};
@@ -28,26 +36,66 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = AppConfig::init()?;
     info!("Configuración cargada correctamente");
 
+    // Cargar las claves de firma/verificación y las reglas de validación de JWT
+    let jwt_config = Arc::new(JwtConfig::from_config(&config)?);
+    info!("Claves JWT cargadas ({:?})", jwt_config.keys.signing_key().algorithm);
+
     // Inicializar el pool de conexiones
     let db_pool = pool::init_pool(&config.database_url).await?;
     info!("Conexión a la base de datos establecida");
 
-    // Crear el repositorio de usuarios
-    let user_repo = PgUserRepository::new(db_pool);
+    // Crear o actualizar el esquema antes de que lo usen los repositorios
+    database::migrations::run_migrations(&db_pool).await?;
+
+    // Crear los repositorios
+    let user_repo = UserRepositoryImpl::new(db_pool.clone());
+    let stores = AuthStores {
+        refresh_tokens: Arc::new(RefreshTokenRepositoryImpl::new(db_pool.clone())),
+        email_verifications: Arc::new(EmailVerificationRepositoryImpl::new(db_pool.clone())),
+        password_resets: Arc::new(PasswordResetRepositoryImpl::new(db_pool.clone())),
+        mfa: Arc::new(MfaRepositoryImpl::new(db_pool.clone())),
+        webauthn: Arc::new(WebAuthnRepositoryImpl::new(db_pool.clone())),
+        login_attempts: Arc::new(LoginAttemptRepositoryImpl::new(db_pool.clone())),
+        api_keys: Arc::new(ApiKeyRepositoryImpl::new(db_pool.clone())),
+        oauth: Arc::new(OAuthRepositoryImpl::new(db_pool.clone())),
+        federation: Arc::new(FederationRepositoryImpl::new(db_pool.clone())),
+    };
 
     // Crear el servicio de autenticación
     let auth_service = AuthServiceImpl::new(
         user_repo,
-        config.jwt_secret.clone(),
-        config.jwt_expires_in.clone(),
+        stores,
+        // Sin proveedor de correo configurado, los correos se escriben en el log
+        Arc::new(LogMailer),
+        jwt_config.clone(),
+        AuthSettings::from_config(&config)?,
     );
     info!("Servicio de autenticación inicializado");
 
+    // Almacén de tokens revocados, con purga periódica de entradas expiradas
+    let revocation_store: Arc<dyn RevocationStore> = Arc::new(PgRevocationStore::new(db_pool.clone()));
+    spawn_pruning_task(revocation_store.clone(), Duration::from_secs(600));
+
+    // Límites de peticiones; con varias instancias el estado debe vivir en PostgreSQL
+    let rate_limit_store: Arc<dyn RateLimitStore> = match config.rate_limit_backend.as_str() {
+        "postgres" => Arc::new(PgRateLimitStore::new(db_pool.clone())),
+        "memory" => Arc::new(InMemoryRateLimitStore::new()),
+        other => return Err(format!("Unsupported RATE_LIMIT_BACKEND: {}", other).into()),
+    };
+    rate_limit::spawn_pruning_task(rate_limit_store.clone(), Duration::from_secs(600));
+    let rate_limiter = RateLimiter::from_config(&config, rate_limit_store)?;
+    info!("Límites de peticiones configurados ({} reglas, backend {})", rate_limiter.rules().len(), config.rate_limit_backend);
+
     // Crear el estado de la aplicación
-    let app_state = Arc::new(AppState {
-        auth_service: Arc::new(auth_service),
-        jwt_secret: config.jwt_secret.clone(),
-    });
+    let app_state = Arc::new(
+        AppState::new(
+            Arc::new(auth_service),
+            jwt_config,
+            revocation_store,
+            Arc::new(PermissionRepositoryImpl::new(db_pool.clone())),
+        )
+        .with_rate_limiter(rate_limiter),
+    );
 
     // Crear el enrutador con capa de logging
     let router = create_router(app_state)
@@ -65,7 +113,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let addr = format!("0.0.0.0:{}", config.port);
     info!("Servidor escuchando en http://{}", addr);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, router).await?;
+    // La IP del cliente la necesitan los límites de peticiones por IP
+    axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
\ No newline at end of file
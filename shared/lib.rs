@@ -2,8 +2,12 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 use anyhow::Result;
-use bcrypt::{hash, verify, DEFAULT_COST};
+use common::jwt::JwtConfig;
+use common::password::{self, Argon2Params};
+use common::telegram::{verify_login_widget, TelegramLoginData};
+use std::sync::Arc;
 
+pub mod oauth;
 pub mod user;
 use user::{
     User, 
@@ -49,21 +53,37 @@ impl From<common::error::AppError> for AuthError {
 }
 
 pub trait UserRepository: Send + Sync {
-    fn find_user_by_id<'a>(&'a self, user_id: &Uuid) -> impl std::future::Future<Output = Result<User, AuthError>> + Send;
-    fn find_user_by_email<'a>(&'a self, email: &str) -> impl std::future::Future<Output = Result<User, AuthError>> + Send;
+    fn find_user_by_id(&self, user_id: &Uuid) -> impl std::future::Future<Output = Result<User, AuthError>> + Send;
+    fn find_user_by_email(&self, email: &str) -> impl std::future::Future<Output = Result<User, AuthError>> + Send;
     fn create_user<'a>(&'a self, user_data: &'a CreateUserSchema, hashed_password: &str, telegram_user_id: Option<String>) -> impl std::future::Future<Output = Result<User, AuthError>> + Send;
-    fn find_by_telegram_user_id<'a>(&'a self, telegram_id: &str) -> impl std::future::Future<Output = Result<User, AuthError>> + Send;
+    fn find_by_telegram_user_id(&self, telegram_id: &str) -> impl std::future::Future<Output = Result<User, AuthError>> + Send;
 }
 
 pub struct AuthServiceImpl<T: UserRepository> {
     repository: T,
-    jwt_secret: String,
+    jwt_config: Arc<JwtConfig>,
     jwt_expires_in: String,
+    telegram_bot_token: Option<String>,
+    telegram_auth_max_age: chrono::Duration,
 }
 
 impl<T: UserRepository> AuthServiceImpl<T> {
-    pub fn new(repository: T, jwt_secret: String, jwt_expires_in: String) -> Self {
-        Self { repository, jwt_secret, jwt_expires_in }
+    pub fn new(
+        repository: T,
+        jwt_config: Arc<JwtConfig>,
+        jwt_expires_in: String,
+        telegram_bot_token: Option<String>,
+        telegram_auth_max_age: chrono::Duration,
+    ) -> Self {
+        Self { repository, jwt_config, jwt_expires_in, telegram_bot_token, telegram_auth_max_age }
+    }
+
+    // Sólo se confía en el Telegram ID si la firma del Login Widget es válida
+    async fn find_verified_telegram_user(&self, data: &TelegramLoginData) -> Result<User, AuthError> {
+        let bot_token = self.telegram_bot_token.as_deref().ok_or(AuthError::InternalServerError)?;
+        verify_login_widget(data, bot_token, self.telegram_auth_max_age)
+            .map_err(|_| AuthError::InvalidCredentials)?;
+        self.repository.find_by_telegram_user_id(&data.id.to_string()).await
     }
 }
 
@@ -74,7 +94,7 @@ pub struct LoginResponse {
 
 pub trait AuthService: Send + Sync {
     fn authenticate_by_email<'a>(&'a self, email: &'a str, password: &'a str) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a>;
-    fn authenticate_by_telegram<'a>(&'a self, telegram_id: &'a str) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a>;
+    fn authenticate_by_telegram<'a>(&'a self, data: &'a TelegramLoginData) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a>;
 }
 
 pub trait AuthServiceAsync: Send + Sync {
@@ -95,9 +115,9 @@ impl<T: UserRepository + Send + Sync> AuthService for AuthServiceImpl<T> {
         })
     }
 
-    fn authenticate_by_telegram<'a>(&'a self, telegram_id: &'a str) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a> {
+    fn authenticate_by_telegram<'a>(&'a self, data: &'a TelegramLoginData) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a> {
         Box::new(async move {
-            self.repository.find_by_telegram_user_id(telegram_id).await
+            self.find_verified_telegram_user(data).await
         })
     }
 }
@@ -106,7 +126,14 @@ impl<T: UserRepository + Send + Sync> AuthServiceAsync for AuthServiceImpl<T> {
     fn generate_token<'a>(&'a self, user: &'a User) -> Box<dyn std::future::Future<Output = Result<String, AuthError>> + Send + 'a> {
         Box::new(async move {
             let user_id_str = user.id.to_string();
-            let token = common::jwt::generate_jwt(&user_id_str, &self.jwt_secret, &self.jwt_expires_in)?;
+            let custom_claims = common::jwt::CustomClaims {
+                role: Some(user.role.clone()),
+                email: Some(user.email.clone()),
+                token_version: Some(user.token_version),
+                token_use: None,
+                scope: None,
+            };
+            let token = common::jwt::generate_jwt(&user_id_str, custom_claims, &self.jwt_config, &self.jwt_expires_in)?;
             Ok(token)
         })
     }
@@ -133,7 +160,7 @@ impl<T: UserRepository + Send + Sync> AuthServiceAsync for AuthServiceImpl<T> {
 
 pub trait AuthServiceImplTrait: AuthService + AuthServiceAsync {
     fn authenticate_by_email<'a>(&'a self, email: &'a str, password: &'a str) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a>;
-    fn authenticate_by_telegram<'a>(&'a self, telegram_id: &'a str) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a>;
+    fn authenticate_by_telegram<'a>(&'a self, data: &'a TelegramLoginData) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a>;
     fn generate_token<'a>(&'a self, user: &'a User) -> Box<dyn std::future::Future<Output = Result<String, AuthError>> + Send + 'a>;
     fn get_user<'a>(&'a self, user_id: &'a Uuid) -> Box<dyn std::future::Future<Output = Result<FilteredUser, AuthError>> + Send + 'a>;
     fn register_user<'a>(&'a self, user_data: &'a CreateUserSchema, telegram_user_id: Option<String>) -> Box<dyn std::future::Future<Output = Result<FilteredUser, AuthError>> + Send + 'a>;
@@ -151,16 +178,23 @@ impl<T: UserRepository + Send + Sync> AuthServiceImplTrait for AuthServiceImpl<T
         })
     }
 
-    fn authenticate_by_telegram<'a>(&'a self, telegram_id: &'a str) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a> {
+    fn authenticate_by_telegram<'a>(&'a self, data: &'a TelegramLoginData) -> Box<dyn std::future::Future<Output = Result<User, AuthError>> + Send + 'a> {
         Box::new(async move {
-            self.repository.find_by_telegram_user_id(telegram_id).await
+            self.find_verified_telegram_user(data).await
         })
     }
 
     fn generate_token<'a>(&'a self, user: &'a User) -> Box<dyn std::future::Future<Output = Result<String, AuthError>> + Send + 'a> {
         Box::new(async move {
             let user_id_str = user.id.to_string();
-            let token = common::jwt::generate_jwt(&user_id_str, &self.jwt_secret, &self.jwt_expires_in)?;
+            let custom_claims = common::jwt::CustomClaims {
+                role: Some(user.role.clone()),
+                email: Some(user.email.clone()),
+                token_version: Some(user.token_version),
+                token_use: None,
+                scope: None,
+            };
+            let token = common::jwt::generate_jwt(&user_id_str, custom_claims, &self.jwt_config, &self.jwt_expires_in)?;
             Ok(token)
         })
     }
@@ -185,10 +219,11 @@ impl<T: UserRepository + Send + Sync> AuthServiceImplTrait for AuthServiceImpl<T
     }
 }
 
+// Mismo formato que el crate auth (Argon2id), y se siguen aceptando los hashes bcrypt anteriores
 fn verify_password(password: &str, hash: &str) -> Result<bool, AuthError> {
-    verify(password, hash).map_err(|e| AuthError::PasswordVerifyError(e.to_string()))
+    password::verify_password(password, hash).map_err(|e| AuthError::PasswordVerifyError(e.to_string()))
 }
 
 fn hash_password(password: &str) -> Result<String, AuthError> {
-    hash(password, DEFAULT_COST).map_err(|e| AuthError::PasswordVerifyError(e.to_string()))
+    password::hash_password(password, &Argon2Params::default()).map_err(|e| AuthError::PasswordVerifyError(e.to_string()))
 }
\ No newline at end of file
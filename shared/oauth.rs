@@ -0,0 +1,237 @@
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Error del protocolo OAuth 2.1 con su código estándar (`invalid_grant`, `invalid_client`...)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OAuthError {
+    pub error: String,
+    pub error_description: String,
+}
+
+impl OAuthError {
+    pub fn new(error: &str, description: impl Into<String>) -> Self {
+        Self {
+            error: error.to_string(),
+            error_description: description.into(),
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self.error.as_str() {
+            "invalid_client" | "invalid_token" => StatusCode::UNAUTHORIZED,
+            "insufficient_scope" => StatusCode::FORBIDDEN,
+            "server_error" => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl std::fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.error, self.error_description)
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let challenge = matches!(self.error.as_str(), "invalid_token" | "insufficient_scope")
+            .then(|| format!("Bearer error=\"{}\", error_description=\"{}\"", self.error, self.error_description.replace('"', "'")));
+
+        let mut response = (status, Json(self)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        // Los errores del recurso protegido (RFC 6750) se indican también en WWW-Authenticate
+        if let Some(value) = challenge.and_then(|challenge| HeaderValue::from_str(&challenge).ok()) {
+            headers.insert(header::WWW_AUTHENTICATE, value);
+        }
+        response
+    }
+}
+
+/// Error de `/oauth/authorize`
+#[derive(Debug)]
+pub enum AuthorizeError {
+    /// El cliente o la `redirect_uri` no son válidos: se responde al navegador sin redirigir
+    Direct(OAuthError),
+    /// URL de la `redirect_uri` del cliente con el error y el `state`
+    Redirect(String),
+}
+
+/// Parámetros de `/oauth/authorize` (flujo authorization code con PKCE)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizeParams {
+    #[serde(default)]
+    pub response_type: Option<String>,
+    pub client_id: String,
+    /// Opcional si el cliente sólo tiene una URI registrada
+    #[serde(default)]
+    pub redirect_uri: Option<String>,
+    /// Scopes separados por espacios; sin indicar se pide `openid`
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    /// Se copia en el ID token para que el cliente detecte respuestas repetidas
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(default)]
+    pub code_challenge: Option<String>,
+    #[serde(default)]
+    pub code_challenge_method: Option<String>,
+}
+
+/// Cuerpo de `/oauth/token` (`application/x-www-form-urlencoded`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    /// Permisos que pide un servicio con `client_credentials`; por defecto todos los del cliente
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub redirect_uri: Option<String>,
+    #[serde(default)]
+    pub code_verifier: Option<String>,
+    /// Código con el que consulta el dispositivo en el flujo de dispositivo
+    #[serde(default)]
+    pub device_code: Option<String>,
+    /// Credenciales del cliente en el cuerpo (`client_secret_post`) o sólo `client_id` si es público
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    /// Scopes a los que se limita el access token; no aparece en el flujo de dispositivo,
+    /// cuyo access token es una sesión completa del usuario
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Sólo en el flujo de dispositivo, donde el access token es una sesión del usuario
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Sólo si se pidió el scope `openid`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+/// Cuerpo de `/oauth/device_authorization` (`application/x-www-form-urlencoded`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeviceAuthorizationRequest {
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+/// Lo que muestra el dispositivo al usuario y con qué frecuencia puede consultar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    pub interval: i64,
+}
+
+/// Lo que necesita mostrar la pantalla de aprobación del dispositivo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceAuthorizationInfo {
+    pub user_code: String,
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Claims del usuario según los scopes concedidos (`/oauth/userinfo` y el ID token)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserInfo {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+/// Lo que necesita mostrar la pantalla de consentimiento del frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizationRequestInfo {
+    pub id: Uuid,
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+    pub redirect_uri: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsentSchema {
+    pub approve: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateOAuthClientSchema {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
+    pub name: String,
+    /// Obligatorias con el grant `authorization_code`
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    /// Scopes que puede pedir el cliente: los de OpenID Connect con `authorization_code`
+    /// (por defecto todos) y permisos (`users:read`) con `client_credentials`
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Cliente público (SPA, app móvil): no recibe secreto y se protege sólo con PKCE
+    #[serde(default)]
+    pub public: bool,
+    #[serde(default = "default_grant_types")]
+    pub grant_types: Vec<String>,
+    /// Aplicación propia (p. ej. la CLI interna). Obligatorio con el grant de dispositivo,
+    /// que emite una sesión completa del usuario
+    #[serde(default)]
+    pub first_party: bool,
+}
+
+fn default_grant_types() -> Vec<String> {
+    vec!["authorization_code".to_string()]
+}
+
+/// Datos visibles de un cliente OAuth; el secreto sólo se entrega al registrarlo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthClientInfo {
+    pub client_id: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<String>,
+    pub public: bool,
+    pub grant_types: Vec<String>,
+    pub first_party: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Cliente recién registrado, con el secreto en claro si es confidencial
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedOAuthClient {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    #[serde(flatten)]
+    pub info: OAuthClientInfo,
+}
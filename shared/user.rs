@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use chrono::{Utc, DateTime};
 use uuid::Uuid;
 use validator::Validate;
+use common::telegram::TelegramLoginData;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -11,6 +12,11 @@ pub struct User {
     pub telegram_user_id: Option<String>,
     pub name: Option<String>,
     pub role: String,
+    #[serde(default)]
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// Se incrementa al cambiar la contraseña; los tokens con otra versión dejan de valer
+    #[serde(default)]
+    pub token_version: i32,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -19,15 +25,9 @@ pub struct User {
 pub struct CreateUserSchema {
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
-    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
+    /// Se valida con la política de contraseñas del servicio de autenticación
     pub password: String,
     pub name: Option<String>,
-    #[serde(default = "default_role")]
-    pub role: String,
-}
-
-fn default_role() -> String {
-    "user".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -36,15 +36,211 @@ pub struct LoginUserSchema {
     pub email: Option<String>,
     #[serde(default)]
     pub telegram_user_id: Option<String>,
+    /// Datos firmados del Telegram Login Widget
+    #[serde(default)]
+    pub telegram: Option<TelegramLoginData>,
+    #[serde(default)]
     pub password: String,
 }
 
+/// `initData` sin modificar, tal como lo expone `Telegram.WebApp.initData`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TelegramWebAppSchema {
+    pub init_data: String,
+}
+
+/// Prueba de propiedad de una cuenta de Telegram: datos del Login Widget o `initData` de la Mini App
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkTelegramSchema {
+    #[serde(default)]
+    pub telegram: Option<TelegramLoginData>,
+    #[serde(default)]
+    pub init_data: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyEmailSchema {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ResendVerificationSchema {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ForgotPasswordSchema {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ResetPasswordSchema {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangePasswordSchema {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpCodeSchema {
+    pub code: String,
+}
+
+/// Segundo factor: un código TOTP o, en su defecto, un código de recuperación
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaCodeSchema {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaVerifySchema {
+    pub mfa_token: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub recovery_code: Option<String>,
+}
+
+/// Datos para dar de alta el TOTP en una app de autenticación
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Opciones para `navigator.credentials.create()` o `.get()`; `challenge_id` se
+/// devuelve junto con la respuesta del autenticador.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyOptions {
+    pub challenge_id: Uuid,
+    pub public_key: serde_json::Value,
+}
+
+/// Respuesta de `navigator.credentials.create()` serializada con `toJSON()` (base64url)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponseSchema {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistrationCredentialSchema {
+    pub id: String,
+    pub response: AttestationResponseSchema,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PasskeyRegistrationSchema {
+    pub challenge_id: Uuid,
+    #[serde(default)]
+    #[validate(length(max = 100, message = "Name must be at most 100 characters"))]
+    pub name: Option<String>,
+    pub credential: RegistrationCredentialSchema,
+}
+
+/// Respuesta de `navigator.credentials.get()` serializada con `toJSON()` (base64url)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponseSchema {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(default)]
+    pub user_handle: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssertionCredentialSchema {
+    pub id: String,
+    pub response: AssertionResponseSchema,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyLoginSchema {
+    pub challenge_id: Uuid,
+    pub credential: AssertionCredentialSchema,
+}
+
+/// Login federado recién iniciado
+#[derive(Debug, Clone)]
+pub struct FederatedLoginStart {
+    /// URL del proveedor a la que enviar al usuario
+    pub authorization_url: String,
+    /// Hash del `state`, que ata el login al navegador que lo inició (cookie HttpOnly)
+    pub state_binding: String,
+    /// Segundos que sigue vigente la solicitud
+    pub expires_in: i64,
+}
+
+/// `code` y `state` con los que el proveedor externo devuelve al usuario al frontend
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FederatedCallbackSchema {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateApiKeySchema {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
+    pub name: String,
+    /// Permisos a los que se limita la clave; deben ser permisos del usuario
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Validez de la clave (`90d`, `12h`...); sin indicar no caduca
+    #[serde(default)]
+    pub expires_in: Option<String>,
+}
+
+/// Datos visibles de una clave de API; el secreto sólo se entrega al crearla
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Clave de API recién creada, con el secreto en claro
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub info: ApiKeyInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshTokenSchema {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogoutSchema {
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FilteredUser {
     pub id: Uuid,
     pub email: String,
     pub name: Option<String>,
     pub role: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -56,6 +252,7 @@ impl User {
             email: self.email.clone(),
             name: self.name.clone(),
             role: self.role.clone(),
+            email_verified_at: self.email_verified_at,
             created_at: self.created_at.unwrap_or_else(Utc::now),
             updated_at: self.updated_at.unwrap_or_else(Utc::now),
         }